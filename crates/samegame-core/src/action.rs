@@ -0,0 +1,410 @@
+//! 着手関連。
+
+use anyhow::{anyhow, ensure};
+use arrayvec::ArrayVec;
+
+use crate::board::{Board, MaskBoard};
+use crate::hint::{assert_unchecked, unchecked_fn};
+use crate::piece::Piece;
+use crate::score::{calc_score_erase, Score};
+use crate::square::Square;
+
+/// 着手。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Action {
+    piece: Piece,
+    mb: MaskBoard,
+}
+
+impl Action {
+    /// 駒種と盤面マスクを指定して着手を作る。
+    ///
+    /// `mb` は 2 つ以上のマスを含んでいなければならない。
+    pub fn new(piece: Piece, mb: MaskBoard) -> Self {
+        unsafe { assert_unchecked!(mb.square_count() >= 2) }
+
+        Self { piece, mb }
+    }
+
+    /// 盤面とマスを指定して着手を作る。合法手でない場合、エラーを返す。
+    ///
+    /// `board` のマス `sq` には駒があり、かつ同種の駒が繋がっていなければならない。
+    pub fn from_board_square(board: &Board, sq: Square) -> anyhow::Result<Self> {
+        let piece = board
+            .get(sq)
+            .ok_or_else(|| anyhow!("盤面のマス {sq} に駒がない"))?;
+
+        let mb = board.piece_mask(piece).flood_fill(sq);
+        ensure!(
+            mb.square_count() >= 2,
+            "盤面のマス {sq} から同種の駒が 2 個以上繋がっていなければならない"
+        );
+
+        Ok(Self::new(piece, mb))
+    }
+
+    unchecked_fn! {
+        /// 盤面とマスを指定して着手を作る。
+        ///
+        /// # Safety
+        ///
+        /// `board` のマス `sq` には駒があり、かつ同種の駒が繋がっていなければならない。
+        unsafe fn from_board_square_unchecked(board: &Board, sq: Square) -> Self {
+            assert_unchecked!(board.get(sq).is_some());
+
+            let piece = board.get(sq).unwrap_unchecked();
+            let mb = board.piece_mask(piece).flood_fill(sq);
+            debug_assert!(
+                mb.square_count() >= 2,
+                "盤面のマス {sq} から同種の駒が 2 個以上繋がっていない"
+            );
+
+            Self::new(piece, mb)
+        }
+    }
+
+    /// 駒種を返す。
+    pub fn piece(&self) -> Piece {
+        self.piece
+    }
+
+    /// 盤面マスクを返す。
+    pub fn mask(&self) -> &MaskBoard {
+        &self.mb
+    }
+
+    /// この着手により消える駒数を返す。
+    pub fn square_count(&self) -> u32 {
+        self.mb.square_count()
+    }
+
+    /// この着手により消える駒を含む最小のマスを返す。
+    pub fn least_square(&self) -> Square {
+        unsafe { self.mb.least_square_unchecked() }
+    }
+
+    /// この着手により得られる駒消しスコアを返す。パーフェクトボーナスは含まないことに注意。
+    pub fn gain(&self) -> Score {
+        calc_score_erase(self.square_count())
+    }
+}
+
+/// 着手のリスト。合法手を呼び出し側のバッファに集め、並べ替えて使うためのもの。
+///
+/// `Position::collect_actions()` で合法手を詰める。1 局面の合法手は高々 `Square::NUM / 2` 個なので、容量は固定。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MoveList(ArrayVec<Action, MOVE_LIST_CAP>);
+
+const MOVE_LIST_CAP: usize = Square::NUM / 2;
+
+impl MoveList {
+    pub const CAPACITY: usize = MOVE_LIST_CAP;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Action] {
+        self.0.as_slice()
+    }
+
+    pub fn iter(&self) -> <&Self as IntoIterator>::IntoIter {
+        self.into_iter()
+    }
+
+    /// 全ての着手を取り除く。容量は保つ。
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// 末尾に着手を追加する。容量オーバーの場合、panic する。
+    pub fn push(&mut self, action: Action) {
+        self.0.push(action);
+    }
+
+    /// `key` の昇順に安定ソートする。
+    pub fn sort_by_key<K: Ord>(&mut self, key: impl FnMut(&Action) -> K) {
+        self.0.sort_by_key(key);
+    }
+
+    /// 消す駒数の降順 (獲得スコアの降順と同じ) に安定ソートする。
+    pub fn sort_largest_first(&mut self) {
+        self.sort_by_key(|action| std::cmp::Reverse(action.square_count()));
+    }
+}
+
+impl std::iter::Extend<Action> for MoveList {
+    /// 容量オーバーの場合、panic する。
+    fn extend<I: IntoIterator<Item = Action>>(&mut self, actions: I) {
+        self.0.extend(actions);
+    }
+}
+
+impl std::iter::IntoIterator for MoveList {
+    type Item = Action;
+    type IntoIter = <ArrayVec<Action, MOVE_LIST_CAP> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> std::iter::IntoIterator for &'a MoveList {
+    type Item = &'a Action;
+    type IntoIter = std::slice::Iter<'a, Action>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+const HISTORY_CAP: usize = Square::NUM / 2;
+
+/// 着手履歴。
+#[repr(transparent)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ActionHistory(ArrayVec<Square, HISTORY_CAP>);
+
+impl ActionHistory {
+    pub const CAPACITY: usize = HISTORY_CAP;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// スライスから着手履歴を作る。容量オーバーの場合、エラーを返す。
+    pub fn from_slice(sqs: &[Square]) -> anyhow::Result<Self> {
+        let mut this = Self::new();
+        this.try_extend_from_slice(sqs)?;
+        Ok(this)
+    }
+
+    pub fn as_slice(&self) -> &[Square] {
+        self.0.as_slice()
+    }
+
+    /// 最初の `n` 手を返す。
+    ///
+    /// `n <= self.len()` でなければならない。
+    pub fn prefix(&self, n: usize) -> &[Square] {
+        &self.as_slice()[..n]
+    }
+
+    /// 最初の `n` 手を除いた残りの手を返す。
+    ///
+    /// `n <= self.len()` でなければならない。
+    pub fn suffix_from(&self, n: usize) -> &[Square] {
+        &self.as_slice()[n..]
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(&mut self, sq: Square) {
+        self.0.push(sq);
+    }
+
+    unchecked_fn! {
+        /// # Safety
+        ///
+        /// 容量オーバーしてはならない。
+        #[cfg_attr(any(test, debug_assertions), track_caller)]
+        unsafe fn push_unchecked(&mut self, sq: Square) {
+            // テスト時はリリースビルドでも検査する (容量オーバーは未定義動作なので、確実に panic させる)。
+            if cfg!(any(test, debug_assertions)) {
+                assert!(
+                    self.len() < HISTORY_CAP,
+                    "着手履歴の容量 ({HISTORY_CAP} 手) を超えて {sq} を追加しようとした: {self}"
+                );
+            }
+
+            self.0.push_unchecked(sq);
+        }
+    }
+
+    /// 末尾に `other` の全ての手を追加する。
+    ///
+    /// 容量オーバーする場合、panic する。
+    pub fn extend_from(&mut self, other: &Self) {
+        self.try_extend_from_slice(other.as_slice())
+            .expect("着手履歴の容量オーバー");
+    }
+
+    /// 末尾に `sqs` の全ての手を追加する。容量オーバーする場合、何もせずにエラーを返す。
+    pub fn try_extend_from_slice(&mut self, sqs: &[Square]) -> anyhow::Result<()> {
+        ensure!(
+            self.len() + sqs.len() <= HISTORY_CAP,
+            "着手履歴は {HISTORY_CAP} 手以下でなければならない"
+        );
+        self.0.try_extend_from_slice(sqs).unwrap();
+        Ok(())
+    }
+
+    /// `self` と `other` を連結した着手履歴を返す。容量オーバーする場合、エラーを返す。
+    pub fn concat(&self, other: &Self) -> anyhow::Result<Self> {
+        let mut res = self.clone();
+        res.try_extend_from_slice(other.as_slice())?;
+        Ok(res)
+    }
+
+    /// 最初の `n` 手のみを残す。`n >= self.len()` の場合、何もしない。
+    pub fn truncate(&mut self, n: usize) {
+        self.0.truncate(n);
+    }
+
+    /// 最初の `n` 手とそれ以降の手に分割する。
+    ///
+    /// `n <= self.len()` でなければならない。
+    pub fn split_at(&self, n: usize) -> (Self, Self) {
+        let (former, latter) = self.as_slice().split_at(n);
+        (
+            former.iter().copied().collect(),
+            latter.iter().copied().collect(),
+        )
+    }
+
+    pub fn remove_last(&mut self) {
+        self.0.pop();
+    }
+
+    unchecked_fn! {
+        /// # Safety
+        ///
+        /// `self` は空であってはならない。
+        #[cfg_attr(debug_assertions, track_caller)]
+        unsafe fn remove_last_unchecked(&mut self) {
+            debug_assert!(!self.is_empty(), "空の着手履歴から手を取り除こうとした");
+
+            self.0.set_len(self.len() - 1)
+        }
+    }
+
+    pub fn iter(&self) -> <&Self as IntoIterator>::IntoIter {
+        self.into_iter()
+    }
+
+    /// 連続する着手の最小マス間のマンハッタン距離の総和 (カーソルの移動量の見積もり) を返す。
+    ///
+    /// 最初の着手へのカーソル移動は数えない。`Solver::set_tie_break_cost()` に渡せる。
+    pub fn cursor_travel(&self) -> u32 {
+        self.as_slice()
+            .windows(2)
+            .map(|w| {
+                let (from, to) = (w[0], w[1]);
+                u32::from(from.col().to_inner().abs_diff(to.col().to_inner()))
+                    + u32::from(from.row().to_inner().abs_diff(to.row().to_inner()))
+            })
+            .sum()
+    }
+}
+
+impl std::iter::FromIterator<Square> for ActionHistory {
+    fn from_iter<I: IntoIterator<Item = Square>>(sqs: I) -> Self {
+        Self(ArrayVec::from_iter(sqs))
+    }
+}
+
+impl std::iter::IntoIterator for ActionHistory {
+    type Item = Square;
+    type IntoIter = <ArrayVec<Square, HISTORY_CAP> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> std::iter::IntoIterator for &'a ActionHistory {
+    type Item = &'a Square;
+    type IntoIter = std::slice::Iter<'a, Square>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl std::str::FromStr for ActionHistory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<_> = s.split_ascii_whitespace().collect();
+        ensure!(
+            tokens.len() <= HISTORY_CAP,
+            "着手履歴は {HISTORY_CAP} 手以下でなければならない"
+        );
+
+        tokens.into_iter().map(str::parse).collect()
+    }
+}
+
+impl std::fmt::Display for ActionHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, &sq) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            sq.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_history(s: impl AsRef<str>) -> ActionHistory {
+        s.as_ref().parse().unwrap()
+    }
+
+    #[test]
+    fn test_history_concat_split() {
+        let former = parse_history("1,1 2,3 8,6");
+        let latter = parse_history("4,2 5,5");
+        let all = parse_history("1,1 2,3 8,6 4,2 5,5");
+
+        let mut extended = former.clone();
+        extended.extend_from(&latter);
+        assert_eq!(extended, all);
+        assert_eq!(former.concat(&latter).unwrap(), all);
+
+        assert_eq!(all.split_at(3), (former.clone(), latter.clone()));
+        assert_eq!(all.prefix(3), former.as_slice());
+        assert_eq!(all.suffix_from(3), latter.as_slice());
+
+        assert_eq!(former.cursor_travel(), (1 + 2) + (6 + 3));
+        assert_eq!(ActionHistory::new().cursor_travel(), 0);
+
+        let mut truncated = all.clone();
+        truncated.truncate(3);
+        assert_eq!(truncated, former);
+
+        let full: ActionHistory =
+            std::iter::repeat_n(Square::MIN, ActionHistory::CAPACITY).collect();
+        assert!(full.concat(&latter).is_err());
+        assert!(ActionHistory::from_slice(full.as_slice()).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "容量")]
+    fn test_history_push_unchecked_overflow() {
+        let mut history = ActionHistory::new();
+        let sq: Square = "1,1".parse().unwrap();
+        for _ in 0..=ActionHistory::CAPACITY {
+            unsafe { history.push_unchecked(sq) }
+        }
+    }
+}
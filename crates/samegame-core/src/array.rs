@@ -34,6 +34,10 @@ macro_rules! array_newtype {
                 &self.0
             }
 
+            pub fn as_array_mut(&mut self) -> &mut [T; <$ty_idx>::NUM] {
+                &mut self.0
+            }
+
             pub fn enumerate(
                 &self,
             ) -> impl ::std::iter::DoubleEndedIterator<Item = ($ty_idx, &T)>
@@ -0,0 +1,2373 @@
+//! 盤面関連。
+
+use std::fmt::Write as _;
+
+use anyhow::{bail, ensure};
+use arrayvec::ArrayVec;
+
+use crate::bitop;
+use crate::hint::{assert_unchecked, unchecked_fn};
+use crate::piece::{Piece, PieceArray};
+use crate::prng::FastRng;
+use crate::rules;
+use crate::square::{Col, ColArray, Row, RowArray, Square};
+
+type BitColT = u32;
+
+/// bitboard の列。
+///
+/// 1 マス 3bit。
+/// メソッドに渡すマスの値は `0b111` 以下でなければならない。
+#[repr(transparent)]
+#[derive(Clone, Copy, Eq, PartialEq)]
+struct BitCol(BitColT);
+
+impl Default for BitCol {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl BitCol {
+    const fn zero() -> Self {
+        Self(0)
+    }
+
+    const fn new(inner: BitColT) -> Self {
+        Self(inner)
+    }
+
+    /// 全マスの値が `value` であるような `BitCol` を返す。
+    const fn broadcast(value: u8) -> Self {
+        const UNIT: BitColT = 0b001_001_001_001_001_001;
+
+        unsafe { assert_unchecked!(Self::value_is_ok(value)) }
+
+        Self::new(UNIT * value as BitColT)
+    }
+
+    fn from_pieces(pieces: &RowArray<Piece>) -> Self {
+        let mut bc = Self::zero();
+
+        for (row, piece) in pieces.enumerate() {
+            bc.set(row, piece.to_inner());
+        }
+
+        bc
+    }
+
+    const fn inner(self) -> BitColT {
+        self.0
+    }
+
+    const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// 指定した行のマスの値を返す。
+    const fn get(self, row: Row) -> u8 {
+        ((self.0 >> (3 * row.to_index())) & 0b111) as u8
+    }
+
+    /// 指定した行のマスの値を設定する。
+    fn set(&mut self, row: Row, value: u8) {
+        unsafe { assert_unchecked!(Self::value_is_ok(value)) }
+
+        self.0 &= !(0b111 << (3 * row.to_index()));
+        self.0 |= BitColT::from(value) << (3 * row.to_index());
+    }
+
+    fn iter(
+        self,
+    ) -> impl DoubleEndedIterator<Item = u8> + ExactSizeIterator + std::iter::FusedIterator + Clone
+    {
+        self.enumerate().map(|(_, value)| value)
+    }
+
+    fn enumerate(
+        self,
+    ) -> impl DoubleEndedIterator<Item = (Row, u8)> + ExactSizeIterator + std::iter::FusedIterator + Clone
+    {
+        Row::all().map(move |row| (row, self.get(row)))
+    }
+
+    const fn value_is_ok(value: u8) -> bool {
+        value <= 0b111
+    }
+}
+
+impl std::ops::BitAnd for BitCol {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl std::ops::BitOr for BitCol {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitXor for BitCol {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl std::ops::BitAndAssign for BitCol {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = *self & rhs;
+    }
+}
+
+impl std::ops::BitOrAssign for BitCol {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl std::ops::BitXorAssign for BitCol {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        *self = *self ^ rhs;
+    }
+}
+
+impl std::fmt::Debug for BitCol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("0b")?;
+
+        for row in Row::all().rev() {
+            let value = self.get(row);
+            write!(f, "_{value:03b}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 列 `bc` のうち、列マスク `mask_bc` (各マスの値は 0b000 か 0b001) 内にある駒の数を返す。
+fn erased_count_col(bc: BitCol, mask_bc: BitCol) -> u32 {
+    let bc = bc.0;
+    let occupied = (bc | (bc >> 1) | (bc >> 2)) & BitCol::broadcast(0b001).0;
+    (occupied & mask_bc.0).count_ones()
+}
+
+/// 盤面。
+///
+/// `BitCol` を `Col::NUM` 個持っており、常に左詰めされている。
+/// ただし列の詰め直しを行わない変種ルール用の `erase_without_compaction()` の結果は途中に空の列を含みうる
+/// (このとき `width_remain()` は右端の空でない列の番号となる)。
+///
+/// `BitCol` のマスの値は 0 が空白、`1..=5` が各駒種を表す。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Board {
+    bcs: ColArray<BitCol>,
+    width_remain: u32,
+}
+
+impl Board {
+    pub(crate) const CHAR_BLANK: char = '.';
+
+    /// `Board` を生成する。デバッグモードでは不変条件のチェックも行う。
+    #[cfg_attr(test, track_caller)]
+    fn new(bcs: ColArray<BitCol>, width_remain: u32) -> Self {
+        let this = Self { bcs, width_remain };
+        this.check_invariants(true);
+        this
+    }
+
+    /// デバッグモードで盤面の不変条件をチェックする。`compacted` なら左詰めも要求する。
+    ///
+    /// `compacted == false` は途中に空の列を含みうる盤面 (`erase_without_compaction()` の結果) 用。
+    #[cfg_attr(test, track_caller)]
+    fn check_invariants(&self, compacted: bool) {
+        let Self { bcs, width_remain } = self;
+        let width_remain = *width_remain;
+
+        #[cfg(test)]
+        invariants::check_board(bcs, width_remain, compacted);
+
+        unsafe { assert_unchecked!(width_remain as usize <= Col::NUM) }
+
+        debug_assert!(
+            bcs.as_array()
+                .iter()
+                .copied()
+                .all(|bc| bc.iter().all(|value| value <= Piece::MAX_VALUE)),
+            "Board のマスの値が正しくない"
+        );
+
+        if compacted {
+            debug_assert!(
+                bcs.as_array()[..width_remain as usize]
+                    .iter()
+                    .copied()
+                    .all(|bc| !bc.is_zero()),
+                "Board の左端 width_remain 列に空の列がある"
+            );
+        } else {
+            debug_assert!(
+                width_remain == 0 || !bcs.as_array()[width_remain as usize - 1].is_zero(),
+                "Board の列 width_remain が空"
+            );
+        }
+
+        debug_assert!(
+            bcs.as_array()[width_remain as usize..]
+                .iter()
+                .copied()
+                .all(BitCol::is_zero),
+            "Board の左端 width_remain 列以外に空でない列がある"
+        );
+    }
+
+    /// 空の盤面を返す。
+    pub fn empty() -> Self {
+        Self::new(ColArray::default(), 0)
+    }
+
+    /// Piece の 2 次元配列 (column-major) から盤面を生成する。
+    /// ゲーム内の再生成判定は考慮しない。
+    pub fn from_piece_arrays(arrays: &ColArray<RowArray<Piece>>) -> Self {
+        let bcs = ColArray::from_fn(|col| BitCol::from_pieces(&arrays[col]));
+
+        Self::new(bcs, Col::NUM as u32)
+    }
+
+    /// `rules::Grid` 形式の 2 次元配列から盤面を生成する。
+    ///
+    /// 盤面が下詰めかつ左詰めになっていなければエラーを返す。
+    pub fn from_grid(grid: &rules::Grid) -> anyhow::Result<Self> {
+        let mut bcs = ColArray::<BitCol>::default();
+
+        for col in Col::all() {
+            let values = &grid[col.to_index()];
+            for row in Row::all() {
+                let value = values[row.to_index()];
+                ensure!(
+                    value <= Piece::MAX_VALUE,
+                    "盤面 {} の値が無効: {value}",
+                    Square::new(col, row)
+                );
+                bcs[col].set(row, value);
+            }
+            ensure!(
+                values.windows(2).all(|w| w[0] != 0 || w[1] == 0),
+                "盤面の列 {col} が下詰めになっていない"
+            );
+        }
+
+        let width_remain = bcs
+            .as_array()
+            .iter()
+            .copied()
+            .position(BitCol::is_zero)
+            .unwrap_or(Col::NUM);
+        ensure!(
+            bcs.as_array()[width_remain..]
+                .iter()
+                .copied()
+                .all(BitCol::is_zero),
+            "盤面が左詰めになっていない"
+        );
+
+        Ok(Self::new(bcs, width_remain as u32))
+    }
+
+    /// 乱数生成器 `rng` を用いてランダムな盤面を生成する。駒は値 `1..=piece_kind_count` の駒種から選ぶ。
+    ///
+    /// ゲーム内の乱数生成器とは無関係で、幅、各列の高さ、各マスの駒をそれぞれ一様に選ぶ
+    /// (空の盤面や一部の列のみの盤面も生成しうる)。ゲーム内の盤面は偏った部分集合に限られるので、
+    /// 一般の盤面操作のファジングや差分テスト、ベンチマークにはこちらを用いる。
+    /// 同じ状態の `rng` からは常に同じ盤面を生成する。ゲーム内の再生成判定は考慮しない。
+    ///
+    /// `piece_kind_count` は `1..=Piece::NUM` でなければならない。
+    pub fn random_with(rng: &mut FastRng, piece_kind_count: u8) -> Self {
+        assert!(
+            (1..=Piece::NUM as u8).contains(&piece_kind_count),
+            "駒種数が無効: {piece_kind_count}"
+        );
+
+        let width_remain = rng.gen_below(Col::NUM as u64 + 1) as usize;
+
+        let mut bcs = ColArray::<BitCol>::default();
+        for col in Col::all().take(width_remain) {
+            let height = 1 + rng.gen_below(Row::NUM as u64) as usize;
+            for row in Row::all().take(height) {
+                let value = 1 + rng.gen_below(u64::from(piece_kind_count)) as u8;
+                bcs[col].set(row, value);
+            }
+        }
+
+        Self::new(bcs, width_remain as u32)
+    }
+
+    /// 盤面を `rules::Grid` 形式の 2 次元配列に変換する。
+    pub fn to_grid(&self) -> rules::Grid {
+        std::array::from_fn(|c| {
+            std::array::from_fn(|r| {
+                let sq = Square::new(Col::from_index(c).unwrap(), Row::from_index(r).unwrap());
+                self.get(sq).map_or(0, Piece::to_inner)
+            })
+        })
+    }
+
+    /// バイナリ形式のバイト数。
+    pub const BYTES_LEN: usize = (3 * Square::NUM).div_ceil(8);
+
+    /// バイナリ形式のバージョン。
+    ///
+    /// バイナリ形式自体はバージョンを含まないので、盤面たちをファイルなどに保存する際はヘッダにこれを記録すること。
+    pub const BYTES_VERSION: u32 = 1;
+
+    /// 盤面をバイナリ形式 (`Board::BYTES_LEN` バイト) に変換する。
+    ///
+    /// 各マスの値 (0 が空白、`1..=5` が駒種) を 3 bit ずつ、`Square::to_index()` 順 (左下のマスから列ごとに下から) に
+    /// リトルエンディアンで詰めて並べる。
+    pub fn to_bytes(&self) -> [u8; Self::BYTES_LEN] {
+        let mut bytes = [0; Self::BYTES_LEN];
+
+        for (i, value) in self.to_grid().as_flattened().iter().copied().enumerate() {
+            let bit = 3 * i;
+            let chunk = u16::from(value) << (bit % 8);
+            bytes[bit / 8] |= chunk as u8;
+            if let Some(byte) = bytes.get_mut(bit / 8 + 1) {
+                *byte |= (chunk >> 8) as u8;
+            }
+        }
+
+        bytes
+    }
+
+    /// バイナリ形式 (`Board::to_bytes()` を参照) から盤面を生成する。
+    ///
+    /// バイト数が `Board::BYTES_LEN` でない場合、マスの値が無効な場合、
+    /// 盤面が下詰めかつ左詰めになっていない場合はエラーを返す。
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        ensure!(
+            bytes.len() == Self::BYTES_LEN,
+            "盤面のバイナリ形式のバイト数が {} でない: {}",
+            Self::BYTES_LEN,
+            bytes.len()
+        );
+
+        let mut grid: rules::Grid = Default::default();
+        for (i, value) in grid.as_flattened_mut().iter_mut().enumerate() {
+            let bit = 3 * i;
+            let lo = u16::from(bytes[bit / 8]);
+            let hi = u16::from(bytes.get(bit / 8 + 1).copied().unwrap_or(0));
+            *value = (((hi << 8 | lo) >> (bit % 8)) & 0b111) as u8;
+        }
+
+        Self::from_grid(&grid)
+    }
+
+    /// 指定したマスの駒を返す。
+    pub fn get(&self, sq: Square) -> Option<Piece> {
+        let value = self.bcs[sq.col()].get(sq.row());
+        unsafe { assert_unchecked!(value <= Piece::MAX_VALUE) }
+
+        Piece::from_inner(value)
+    }
+
+    /// 盤面が空かどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.width_remain == 0
+    }
+
+    /// 空でない列数を返す。
+    ///
+    /// 途中に空の列を含む盤面 (`erase_without_compaction()` を参照) の場合、右端の空でない列の番号を返す。
+    pub fn width_remain(&self) -> u32 {
+        self.width_remain
+    }
+
+    /// 空でない列を昇順で列挙する。
+    ///
+    /// 途中に空の列を含む盤面 (`erase_without_compaction()` を参照) の場合、その空の列も列挙する。
+    pub fn nonempty_cols(
+        &self,
+    ) -> impl ExactSizeIterator<Item = Col> + std::iter::FusedIterator + Clone {
+        (0..self.width_remain as u8).map(|i| unsafe { Col::from_inner_unchecked(1 + i) })
+    }
+
+    /// 指定した駒種の数を返す。
+    pub fn piece_count(&self, piece: Piece) -> u32 {
+        self.piece_mask(piece).square_count()
+    }
+
+    /// 駒の総数を返す。
+    pub fn piece_count_total(&self) -> u32 {
+        self.nonempty_cols()
+            .map(|col| {
+                let bc = self.bcs[col].0;
+                let bc = (bc | (bc >> 1) | (bc >> 2)) & BitCol::broadcast(0b001).0;
+                bc.count_ones()
+            })
+            .sum()
+    }
+
+    /// 指定した駒のみからなる盤面マスクを返す。
+    pub fn piece_mask(&self, piece: Piece) -> MaskBoard {
+        // まず全体を piece の内部値で埋めた盤面との XOR をとる。
+        // すると、指定した駒のあるマスのみが 0b000 である盤面が得られる。
+        //
+        // 他のマスは 0b000 でない値になっているので、適当にシフトと AND を用いて値を 0b001 に統一する。
+        // そして、全マスに対して 0b001 を XOR すれば求めるマスクが得られる。
+        //
+        // 実際には各列について上記を個別に行う。
+
+        let filled = BitCol::broadcast(piece.to_inner());
+
+        let mut bcs = ColArray::<BitCol>::default();
+        let mut col_mask = 0;
+
+        for col in self.nonempty_cols() {
+            let bc = (self.bcs[col] ^ filled).0;
+            let bc = (bc | (bc >> 1) | (bc >> 2)) & BitCol::broadcast(0b001).0;
+            let bc = BitCol::new(bc) ^ BitCol::broadcast(0b001);
+            bcs[col] = bc;
+            if !bc.is_zero() {
+                col_mask |= 1 << col.to_index();
+            }
+        }
+
+        MaskBoard::new(bcs, col_mask)
+    }
+
+    /// 全駒種の盤面マスクを返す。
+    ///
+    /// 盤面を 1 回走査するだけで求めるので、`piece_mask()` を駒種ごとに呼ぶより軽い。
+    pub fn piece_masks(&self) -> PieceArray<MaskBoard> {
+        let mut bcs = PieceArray::<ColArray<BitCol>>::default();
+        let mut col_masks = PieceArray::<u32>::default();
+
+        for col in self.nonempty_cols() {
+            let bc_board = self.bcs[col];
+            for piece in Piece::all() {
+                // 手法は piece_mask() と同じ。
+                let bc = (bc_board ^ BitCol::broadcast(piece.to_inner())).0;
+                let bc = (bc | (bc >> 1) | (bc >> 2)) & BitCol::broadcast(0b001).0;
+                let bc = BitCol::new(bc) ^ BitCol::broadcast(0b001);
+                bcs[piece][col] = bc;
+                if !bc.is_zero() {
+                    col_masks[piece] |= 1 << col.to_index();
+                }
+            }
+        }
+
+        PieceArray::from_fn(|piece| MaskBoard::new(bcs[piece].clone(), col_masks[piece]))
+    }
+
+    /// 各駒種について連結成分を列挙する。孤立駒も含むことに注意。
+    ///
+    /// 列挙順は駒種の昇順、同じ駒種内では最小マスの昇順。
+    pub fn piece_components(
+        &self,
+    ) -> impl std::iter::FusedIterator<Item = (Piece, MaskBoard)> + Clone + '_ {
+        let masks = self.piece_masks();
+
+        Piece::all().flat_map(move |piece| masks[piece].components().map(move |comp| (piece, comp)))
+    }
+
+    /// 各駒種についてサイズ 2 以上の連結成分 (着手) を列挙する。列挙順は `piece_components()` と同じ。
+    pub fn action_components(
+        &self,
+    ) -> impl std::iter::FusedIterator<Item = (Piece, MaskBoard)> + Clone + '_ {
+        let masks = self.piece_masks();
+
+        Piece::all().flat_map(move |piece| {
+            masks[piece]
+                .non_isolated()
+                .components()
+                .map(move |comp| (piece, comp))
+        })
+    }
+
+    /// 各駒種の連結成分たちを返す。孤立駒も含むことに注意。
+    ///
+    /// 各駒種の連結成分は最小マスの昇順に並ぶ。
+    pub fn components_by_piece(&self) -> PieceArray<Vec<MaskBoard>> {
+        let masks = self.piece_masks();
+
+        PieceArray::from_fn(|piece| masks[piece].components().collect())
+    }
+
+    /// 合法手があるかどうかを返す。
+    pub fn has_action(&self) -> bool {
+        // 盤面が空なら明らかに合法手はない。
+        if self.is_empty() {
+            return false;
+        }
+
+        Piece::all().any(|piece| self.has_action_for(piece))
+    }
+
+    /// 指定した駒種について合法手があるかどうかを返す。
+    pub fn has_action_for(&self, piece: Piece) -> bool {
+        // 駒種のマスクを求め、それを上下方向/左右方向にずらしたとき重なる部分があるかどうかを見ればよい。
+
+        let mb = self.piece_mask(piece);
+
+        mb.nonempty_cols().any(|col| {
+            let bc = mb.bcs[col].0;
+            if (bc & (bc >> 3)) != 0 {
+                return true;
+            }
+            if let Some(col_prev) = col.prev() {
+                let bc_prev = mb.bcs[col_prev].0;
+                if (bc & bc_prev) != 0 {
+                    return true;
+                }
+            }
+            false
+        })
+    }
+
+    /// 合法手の数を返す。
+    ///
+    /// 各駒種について孤立駒を除いたマスクの連結成分数を数える。着手を生成するより軽い。
+    pub fn action_count(&self) -> u32 {
+        Piece::all()
+            .map(|piece| self.piece_mask(piece).non_isolated().components().count() as u32)
+            .sum()
+    }
+
+    /// 与えられた盤面マスク内の全ての駒を消し、その結果を返す。
+    pub fn erase(&self, mb: &MaskBoard) -> Self {
+        let mut res = self.clone();
+        res.erase_in_place(mb);
+        res
+    }
+
+    /// 与えられた盤面マスク内の全ての駒をその場で消し、消した駒数を返す。結果の盤面は `erase()` と同じ。
+    pub fn erase_in_place(&mut self, mb: &MaskBoard) -> u32 {
+        // mb の各マスの値は 0b000, 0b001 の 2 値だが、0b111 を掛けることで 0b000, 0b111 の 2 値に変換できる。
+        // これの NOT をマスクとして PEXT を行えばよい。
+        //
+        // 列の詰め直しは愚直に行う。この操作の頻度は低いのでさほど問題にはならないだろう。
+        //
+        // 大半の着手は 1 列のみに影響するので、その場合は他の列に触れない高速パスで処理する。
+
+        if let Some(col) = mb.single_col() {
+            return self.erase_single_col_in_place(mb, col);
+        }
+
+        let (erased_count, erased_col_mask) = self.erase_drop(mb);
+
+        if erased_col_mask != 0 {
+            let width_before = self.width_remain as usize;
+            let arr = self.bcs.as_array_mut();
+            let mut width_remain = 0;
+            for i in 0..width_before {
+                if (erased_col_mask & (1 << i)) != 0 {
+                    continue;
+                }
+                arr[width_remain] = arr[i];
+                width_remain += 1;
+            }
+            arr[width_remain..width_before].fill(BitCol::zero());
+            self.width_remain = width_remain as u32;
+        }
+
+        self.check_invariants(true);
+
+        erased_count
+    }
+
+    /// 与えられた盤面マスク内の全ての駒を消した結果を `out` に書き込み、消した駒数を返す。
+    ///
+    /// `out` の元の内容は捨てる。既存の盤面をバッファとして使い回したい場合に用いる。
+    pub fn erase_into(&self, mb: &MaskBoard, out: &mut Self) -> u32 {
+        out.clone_from(self);
+        out.erase_in_place(mb)
+    }
+
+    /// `erase_in_place()` の高速パス。`mb` は列 `col` のみを含まねばならない。
+    fn erase_single_col_in_place(&mut self, mb: &MaskBoard, col: Col) -> u32 {
+        let erased_count = erased_count_col(self.bcs[col], mb.bcs[col]);
+        let mask = !(mb.bcs[col].0 * 0b111);
+        self.bcs[col] = BitCol::new(bitop::u32_pext(self.bcs[col].0, mask));
+
+        // 列が空になったら、その右側の列たちを 1 つずつ左へずらす。
+        if self.bcs[col].is_zero() {
+            let width_remain = self.width_remain as usize;
+            let arr = self.bcs.as_array_mut();
+            arr.copy_within(col.to_index() + 1..width_remain, col.to_index());
+            arr[width_remain - 1] = BitCol::zero();
+            self.width_remain -= 1;
+        }
+
+        self.check_invariants(true);
+
+        erased_count
+    }
+
+    /// 与えられた盤面マスク内の全ての駒を消し、空になった列を詰め直さずにその結果を返す。
+    ///
+    /// 空の列を詰めない変種ルール用。結果の盤面は途中に空の列を含みうる。
+    pub fn erase_without_compaction(&self, mb: &MaskBoard) -> Self {
+        let mut res = self.clone();
+        res.erase_without_compaction_in_place(mb);
+        res
+    }
+
+    /// `erase_without_compaction()` のその場版。消した駒数を返す。
+    pub fn erase_without_compaction_in_place(&mut self, mb: &MaskBoard) -> u32 {
+        let (erased_count, _) = self.erase_drop(mb);
+
+        self.width_remain = self.bcs.as_array()[..self.width_remain as usize]
+            .iter()
+            .rposition(|bc| !bc.is_zero())
+            .map_or(0, |i| i as u32 + 1);
+
+        self.check_invariants(false);
+
+        erased_count
+    }
+
+    /// 与えられた盤面マスク内の全ての駒をその場で消して各列を下詰めし、(消した駒数, 空になった列の集合) を返す。
+    /// 列の集合は列インデックスのビットマスクで表す。`width_remain` は更新しない。
+    fn erase_drop(&mut self, mb: &MaskBoard) -> (u32, u32) {
+        let mut erased_count = 0;
+        let mut erased_col_mask = 0;
+        for col in mb.nonempty_cols() {
+            erased_count += erased_count_col(self.bcs[col], mb.bcs[col]);
+            let mask = !(mb.bcs[col].0 * 0b111);
+            self.bcs[col] = BitCol::new(bitop::u32_pext(self.bcs[col].0, mask));
+            if self.bcs[col].is_zero() {
+                erased_col_mask |= 1 << col.to_index();
+            }
+        }
+
+        (erased_count, erased_col_mask)
+    }
+
+    /// `self` と `other` で値が異なるマスの集合を表す盤面マスクを返す。
+    pub fn xor_mask(&self, other: &Self) -> MaskBoard {
+        let mut bcs = ColArray::<BitCol>::default();
+        let mut col_mask = 0;
+
+        let cols = if self.width_remain >= other.width_remain {
+            self.nonempty_cols()
+        } else {
+            other.nonempty_cols()
+        };
+        for col in cols {
+            let bc = (self.bcs[col] ^ other.bcs[col]).0;
+            let bc = (bc | (bc >> 1) | (bc >> 2)) & BitCol::broadcast(0b001).0;
+            bcs[col] = BitCol::new(bc);
+            if !bcs[col].is_zero() {
+                col_mask |= 1 << col.to_index();
+            }
+        }
+
+        MaskBoard::new(bcs, col_mask)
+    }
+
+    /// 列 `col` が空になったときに、その左右の列で同種駒同士が新たに隣接する行の集合を返す。
+    ///
+    /// 返り値の bit i が行 i+1 に対応する。`col` が端の列なら常に 0 を返す。
+    /// 左右の駒が既に (他の経路で) 同じ塊に属している場合も含む。
+    pub fn bridged_rows(&self, col: Col) -> u8 {
+        let (Some(left), Some(right)) = (col.prev(), col.next()) else {
+            return 0;
+        };
+
+        let unit = BitCol::broadcast(0b001).0;
+        let left = self.bcs[left].0;
+        let diff = left ^ self.bcs[right].0;
+        let same = !(diff | (diff >> 1) | (diff >> 2)) & unit;
+        let nonblank = (left | (left >> 1) | (left >> 2)) & unit;
+        let lanes = same & nonblank;
+
+        // 3bit おきのビットを下位 6bit に詰める。
+        let x = (lanes | (lanes >> 2)) & 0x30C3;
+        let x = (x | (x >> 4)) & 0x30F;
+        let x = (x | (x >> 4)) & 0x3F;
+
+        x as u8
+    }
+}
+
+/// 盤面文字列における行の並び順。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum BoardOrientation {
+    /// 上の行から順に並べる (画面上の見た目通り)。
+    #[default]
+    TopDown,
+    /// 下の行 (行 1) から順に並べる (メモリ上の順序)。
+    BottomUp,
+}
+
+impl Board {
+    /// 多少の表記揺れを許容して盤面文字列をパースする。
+    ///
+    /// `FromStr` の厳密な形式に加え、以下を受け付ける:
+    ///
+    /// * 行内の空白文字 (行末の空白を含む) は無視する。
+    /// * 空行は無視する (末尾の改行の有無も問わない)。
+    /// * 空白マスとして `.` の他に `0` も使える。
+    /// * 1 行のみからなる場合、それをカンマ区切りの各行とみなす。
+    ///
+    /// 行の並び順は `orientation` で指定する。
+    pub fn parse_flexible(s: &str, orientation: BoardOrientation) -> anyhow::Result<Self> {
+        let lines: Vec<String> = s
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .filter(|ch| !ch.is_whitespace())
+                    .collect::<String>()
+            })
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let mut rows: Vec<&str> = if let [line] = lines.as_slice() {
+            line.split(',').collect()
+        } else {
+            lines.iter().map(String::as_str).collect()
+        };
+        if orientation == BoardOrientation::BottomUp {
+            rows.reverse();
+        }
+
+        Self::parse_rows(&rows, &[Self::CHAR_BLANK, '0'])
+    }
+
+    /// 上から順に並んだ各行の文字列をパースする。`blanks` に含まれる文字は空白マスとみなす。
+    fn parse_rows(rows: &[&str], blanks: &[char]) -> anyhow::Result<Self> {
+        ensure!(
+            rows.len() == Row::NUM,
+            "盤面文字列はちょうど {} 行でなければならない",
+            Row::NUM
+        );
+
+        let mut bcs = ColArray::<BitCol>::default();
+
+        for (row, line) in Row::all().rev().zip(rows) {
+            let chars: Vec<_> = line.chars().collect();
+            ensure!(
+                chars.len() == Col::NUM,
+                "盤面の行 {row} がちょうど {} 文字でない",
+                Col::NUM
+            );
+
+            for (col, ch) in Col::all().zip(chars) {
+                let sq = Square::new(col, row);
+                let piece = match ch {
+                    _ if blanks.contains(&ch) => None,
+                    '1'..='5' => Some(Piece::from_inner(ch.to_digit(10).unwrap() as u8).unwrap()),
+                    _ => bail!("盤面 {sq} の文字が無効: {ch}",),
+                };
+                let value = piece.map_or(0, Piece::to_inner);
+                bcs[col].set(row, value);
+            }
+        }
+
+        let width_remain = bcs
+            .as_array()
+            .iter()
+            .copied()
+            .position(BitCol::is_zero)
+            .unwrap_or(Col::NUM);
+        ensure!(
+            bcs.as_array()[width_remain..]
+                .iter()
+                .copied()
+                .all(BitCol::is_zero),
+            "盤面が左詰めになっていない"
+        );
+        let width_remain = width_remain as u32;
+
+        Ok(Self::new(bcs, width_remain))
+    }
+}
+
+impl std::str::FromStr for Board {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<_> = s.lines().collect();
+
+        Self::parse_rows(&lines, &[Self::CHAR_BLANK])
+    }
+}
+
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in Row::all().rev() {
+            for col in Col::all() {
+                let sq = Square::new(col, row);
+                let piece = self.get(sq);
+                let ch = piece.map_or(Self::CHAR_BLANK, |piece| {
+                    char::from(b'0' + piece.to_inner())
+                });
+                f.write_char(ch)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 盤面のマスの集合を表すマスク。
+///
+/// `BitCol` を `Col::NUM` 個持っている。
+///
+/// `BitCol` の値は、マスが集合に含まれるなら `0b001`, さもなくば `0b000` となる。
+#[derive(Clone, Eq, PartialEq)]
+pub struct MaskBoard {
+    bcs: ColArray<BitCol>,
+
+    /// 空でない列たちを表すマスク。
+    col_mask: u32,
+}
+
+impl MaskBoard {
+    const CHAR_FALSE: char = '.';
+    const CHAR_TRUE: char = '*';
+
+    /// `MaskBoard` を生成する。デバッグモードでは不変条件のチェックも行う。
+    #[cfg_attr(test, track_caller)]
+    fn new(bcs: ColArray<BitCol>, col_mask: u32) -> Self {
+        #[cfg(test)]
+        invariants::check_mask_board(&bcs, col_mask);
+
+        unsafe { assert_unchecked!((col_mask & !((1 << Col::NUM) - 1)) == 0) }
+
+        debug_assert!(
+            bcs.as_array()
+                .iter()
+                .copied()
+                .all(|bc| bc.iter().all(|value| value <= 1)),
+            "MaskBoard のマスの値は 0 または 1 でなければならない"
+        );
+
+        debug_assert!(
+            Col::all().all(|col| {
+                let cond_bc = !bcs[col].is_zero();
+                let cond_mask = (col_mask & (1 << col.to_index())) != 0;
+                cond_bc == cond_mask
+            }),
+            "MaskBoard: bcs と col_mask が矛盾している"
+        );
+
+        Self { bcs, col_mask }
+    }
+
+    /// 空集合を表すマスクを返す。
+    pub fn empty() -> Self {
+        Self::new(ColArray::default(), 0)
+    }
+
+    /// 指定したマスのみを含むマスクを返す。
+    pub fn single(sq: Square) -> Self {
+        let mut bcs = ColArray::<BitCol>::default();
+        bcs[sq.col()].set(sq.row(), 0b001);
+
+        Self::new(bcs, 1 << sq.col().to_index())
+    }
+
+    /// 指定したマスが集合に含まれるかどうかを返す。
+    pub fn test(&self, sq: Square) -> bool {
+        self.bcs[sq.col()].get(sq.row()) != 0
+    }
+
+    /// 指定したマスが集合に含まれるかどうかを設定する。
+    pub fn set(&mut self, sq: Square, value: bool) {
+        let bc = &mut self.bcs[sq.col()];
+        let value = if value { 0b001 } else { 0b000 };
+
+        bc.set(sq.row(), value);
+
+        if bc.is_zero() {
+            self.col_mask &= !(1 << sq.col().to_index());
+        } else {
+            self.col_mask |= 1 << sq.col().to_index();
+        }
+
+        #[cfg(test)]
+        invariants::check_mask_board(&self.bcs, self.col_mask);
+    }
+
+    /// 空集合かどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.col_mask == 0
+    }
+
+    /// ちょうど 1 つのマスを含むかどうかを返す。
+    pub fn is_single(&self) -> bool {
+        if !self.col_mask.is_power_of_two() {
+            return false;
+        }
+
+        let bc = self.bcs[unsafe { self.least_nonempty_col_unchecked() }];
+        bc.inner().is_power_of_two()
+    }
+
+    /// 空でない列数を返す。
+    pub fn nonempty_col_count(&self) -> u32 {
+        self.col_mask.count_ones()
+    }
+
+    /// ちょうど 1 つの列のみにマスを含むなら、その列を返す。さもなくば `None` を返す。
+    pub fn single_col(&self) -> Option<Col> {
+        self.col_mask
+            .is_power_of_two()
+            .then(|| unsafe { self.least_nonempty_col_unchecked() })
+    }
+
+    /// 含まれるマス数を返す。
+    pub fn square_count(&self) -> u32 {
+        self.nonempty_cols()
+            .map(|col| self.bcs[col].0.count_ones())
+            .sum()
+    }
+
+    /// 空でない最小の列を返す。
+    pub fn least_nonempty_col(&self) -> Option<Col> {
+        (!self.is_empty()).then(|| unsafe { self.least_nonempty_col_unchecked() })
+    }
+
+    unchecked_fn! {
+        /// 空でない最小の列を返す。
+        ///
+        /// # Safety
+        ///
+        /// `self` は空であってはならない。
+        unsafe fn least_nonempty_col_unchecked(&self) -> Col {
+            assert_unchecked!(!self.is_empty());
+
+            let col = 1 + self.col_mask.trailing_zeros() as u8;
+
+            unsafe { Col::from_inner_unchecked(col) }
+        }
+    }
+
+    /// 空でない列を昇順で列挙する。
+    pub fn nonempty_cols(
+        &self,
+    ) -> impl ExactSizeIterator<Item = Col> + std::iter::FusedIterator + Clone {
+        bitop::u32_one_indexs(self.col_mask).map(|i| {
+            let col = 1 + i as u8;
+            unsafe { Col::from_inner_unchecked(col) }
+        })
+    }
+
+    /// 含まれる最小のマスを返す。
+    pub fn least_square(&self) -> Option<Square> {
+        (!self.is_empty()).then(|| unsafe { self.least_square_unchecked() })
+    }
+
+    unchecked_fn! {
+        /// 含まれる最小のマスを返す。
+        ///
+        /// # Safety
+        ///
+        /// `self` は空であってはならない。
+        unsafe fn least_square_unchecked(&self) -> Square {
+            assert_unchecked!(!self.is_empty());
+
+            let col = self.least_nonempty_col_unchecked();
+
+            let row = 1 + (self.bcs[col].0.trailing_zeros() / 3) as u8;
+            let row = Row::from_inner_unchecked(row);
+
+            Square::new(col, row)
+        }
+    }
+
+    /// 含まれるマスを昇順で列挙する。
+    pub fn squares(&self) -> impl std::iter::FusedIterator<Item = Square> + Clone + '_ {
+        self.nonempty_cols().flat_map(|col| {
+            let bc = self.bcs[col];
+            bitop::u32_one_indexs(bc.inner()).map(move |i| {
+                let row = 1 + (i / 3) as u8;
+                let row = unsafe { Row::from_inner_unchecked(row) };
+                Square::new(col, row)
+            })
+        })
+    }
+
+    /// 含まれるマスを昇順で `ArrayVec` に集めて返す。ヒープ割り当てを行わない。
+    pub fn squares_arrayvec(&self) -> ArrayVec<Square, { Square::NUM }> {
+        let mut res = ArrayVec::new();
+        for sq in self.squares() {
+            unsafe { res.push_unchecked(sq) }
+        }
+        res
+    }
+
+    /// `self` と `rhs` が共通のマスを含むかどうかを返す。
+    pub fn intersects(&self, rhs: &Self) -> bool {
+        (self.col_mask & rhs.col_mask) != 0
+            && Col::all().any(|col| (self.bcs[col].0 & rhs.bcs[col].0) != 0)
+    }
+
+    /// 差集合 `self` - `rhs` を返す。
+    pub fn subtract(&self, rhs: &Self) -> Self {
+        let mut res = self.clone();
+        res.subtract_assign(rhs);
+        res
+    }
+
+    /// `self` を差集合 `self` - `rhs` とする。
+    pub fn subtract_assign(&mut self, rhs: &Self) {
+        for col in rhs.nonempty_cols() {
+            let bc = &mut self.bcs[col];
+            bc.0 &= !rhs.bcs[col].0;
+            if bc.is_zero() {
+                self.col_mask &= !(1 << col.to_index());
+            }
+        }
+
+        #[cfg(test)]
+        invariants::check_mask_board(&self.bcs, self.col_mask);
+    }
+
+    /// 連結成分を列挙する (4 近傍)。
+    pub fn components(&self) -> impl std::iter::FusedIterator<Item = Self> + Clone {
+        let mut remain = self.clone();
+
+        std::iter::from_fn(move || {
+            if remain.is_empty() {
+                return None;
+            }
+
+            let comp = remain.flood_fill_impl(remain.blsi());
+            remain.subtract_assign(&comp);
+
+            Some(comp)
+        })
+        .fuse()
+    }
+
+    /// 4 近傍に隣接するマスを持たない (孤立した) マスを除いたマスクを返す。
+    ///
+    /// 結果の連結成分は、`self` のサイズ 2 以上の連結成分と一致する。
+    fn non_isolated(&self) -> Self {
+        let mut bcs = ColArray::<BitCol>::default();
+        let mut col_mask = 0;
+
+        for col in self.nonempty_cols() {
+            let bc = self.bcs[col].0;
+            let mut adj = (bc << 3) | (bc >> 3);
+            if let Some(col_prev) = col.prev() {
+                adj |= self.bcs[col_prev].0;
+            }
+            if let Some(col_next) = col.next() {
+                adj |= self.bcs[col_next].0;
+            }
+            let bc = BitCol::new(bc & adj);
+            bcs[col] = bc;
+            if !bc.is_zero() {
+                col_mask |= 1 << col.to_index();
+            }
+        }
+
+        Self::new(bcs, col_mask)
+    }
+
+    /// `self` に対して `sq` を始点として flood fill を行った結果を返す。
+    ///
+    /// `self` は `sq` を含んでいなければならない。
+    pub fn flood_fill(&self, sq: Square) -> Self {
+        unsafe { assert_unchecked!(self.test(sq)) }
+
+        self.flood_fill_impl(Self::single(sq))
+    }
+
+    fn flood_fill_impl(&self, seed: Self) -> Self {
+        unsafe { assert_unchecked!(seed.is_single()) }
+        unsafe { assert_unchecked!(self.test(seed.least_square_unchecked())) }
+
+        fn col(col: u8) -> Col {
+            unsafe { assert_unchecked!(matches!(col, Col::MIN_VALUE..=Col::MAX_VALUE)) }
+            unsafe { Col::from_inner_unchecked(col) }
+        }
+
+        macro_rules! update {
+            ($lhs:expr, $rhs:expr) => {{
+                if $lhs != $rhs {
+                    $lhs = $rhs;
+                    true
+                } else {
+                    false
+                }
+            }};
+        }
+
+        let MaskBoard {
+            mut bcs,
+            mut col_mask,
+        } = seed;
+        let mut c_min = 1 + col_mask.trailing_zeros() as u8;
+        let mut c_max = c_min;
+
+        loop {
+            let mut updated = false;
+
+            // 上下に伸ばす。
+            for c in c_min..=c_max {
+                let bc = bcs[col(c)].0;
+                let bc = (bc | (bc << 3) | (bc >> 3)) & self.bcs[col(c)].0;
+                updated |= update!(bcs[col(c)], BitCol::new(bc));
+            }
+            // 左に伸ばす (左端を除く)。
+            for c in c_min + 1..=c_max {
+                let bc = (bcs[col(c - 1)] | bcs[col(c)]) & self.bcs[col(c - 1)];
+                updated |= update!(bcs[col(c - 1)], bc);
+            }
+            // 右に伸ばす (右端を除く)。
+            for c in c_min + 1..=c_max {
+                let bc = (bcs[col(c - 1)] | bcs[col(c)]) & self.bcs[col(c)];
+                updated |= update!(bcs[col(c)], bc);
+            }
+            // 左端を左に伸ばす。
+            if c_min != Col::MIN_VALUE {
+                let bc = bcs[col(c_min)] & self.bcs[col(c_min - 1)];
+                if !bc.is_zero() {
+                    bcs[col(c_min - 1)] = bc;
+                    col_mask |= 1 << (c_min - 1 - 1);
+                    c_min -= 1;
+                    updated = true;
+                }
+            }
+            // 右端を右に伸ばす。
+            if c_max != Col::MAX_VALUE {
+                let bc = bcs[col(c_max)] & self.bcs[col(c_max + 1)];
+                if !bc.is_zero() {
+                    bcs[col(c_max + 1)] = bc;
+                    col_mask |= 1 << (c_max + 1 - 1);
+                    c_max += 1;
+                    updated = true;
+                }
+            }
+
+            if !updated {
+                break Self::new(bcs, col_mask);
+            }
+        }
+    }
+
+    /// `self` の最下位マスのみが含まれるマスクを返す。
+    ///
+    /// `self` は空であってはならない。
+    fn blsi(&self) -> Self {
+        unsafe { assert_unchecked!(!self.is_empty()) }
+
+        let mut bcs = ColArray::<BitCol>::default();
+
+        let col = unsafe { self.least_nonempty_col_unchecked() };
+        bcs[col].0 = bitop::u32_blsi(self.bcs[col].0);
+
+        Self::new(bcs, 1 << col.to_index())
+    }
+}
+
+impl std::fmt::Debug for MaskBoard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaskBoard")
+            .field("bcs", &self.bcs)
+            .field("col_mask", &ColMaskDebug(self.col_mask))
+            .finish()
+    }
+}
+
+struct ColMaskDebug(u32);
+
+impl std::fmt::Debug for ColMaskDebug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0b{:0width$b}", self.0, width = Col::NUM)
+    }
+}
+
+impl std::str::FromStr for MaskBoard {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<_> = s.lines().collect();
+        ensure!(
+            lines.len() == Row::NUM,
+            "盤面マスク文字列はちょうど {} 行でなければならない",
+            Row::NUM
+        );
+
+        let mut this = Self::empty();
+
+        for (row, line) in Row::all().rev().zip(lines) {
+            let chars: Vec<_> = line.chars().collect();
+            ensure!(
+                chars.len() == Col::NUM,
+                "盤面の行 {row} がちょうど {} 文字でない",
+                Col::NUM
+            );
+
+            for (col, ch) in Col::all().zip(chars) {
+                let sq = Square::new(col, row);
+                let value = match ch {
+                    Self::CHAR_FALSE => false,
+                    Self::CHAR_TRUE => true,
+                    _ => bail!("盤面マスク {sq} の文字が無効: {ch}",),
+                };
+                this.set(sq, value);
+            }
+        }
+
+        Ok(this)
+    }
+}
+
+impl std::fmt::Display for MaskBoard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in Row::all().rev() {
+            for col in Col::all() {
+                let sq = Square::new(col, row);
+                let ch = if self.test(sq) {
+                    Self::CHAR_TRUE
+                } else {
+                    Self::CHAR_FALSE
+                };
+                f.write_char(ch)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `MaskBoard` をマスのリスト形式 (`"1,2 3,4 ..."`) で入出力するためのラッパー。
+///
+/// 6 行のテキストより短く書けるので、コマンドライン引数などで使いやすい。
+/// 各マスは空白区切りで、`Display` はマスの昇順に出力する。空集合は空文字列となる。
+#[repr(transparent)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaskBoardSquares(pub MaskBoard);
+
+impl From<MaskBoard> for MaskBoardSquares {
+    fn from(mask: MaskBoard) -> Self {
+        Self(mask)
+    }
+}
+
+impl From<MaskBoardSquares> for MaskBoard {
+    fn from(squares: MaskBoardSquares) -> Self {
+        squares.0
+    }
+}
+
+impl std::str::FromStr for MaskBoardSquares {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mask = MaskBoard::empty();
+
+        for token in s.split_ascii_whitespace() {
+            let sq: Square = token.parse()?;
+            ensure!(!mask.test(sq), "盤面マスクのマスが重複している: {sq}");
+            mask.set(sq, true);
+        }
+
+        Ok(Self(mask))
+    }
+}
+
+impl std::fmt::Display for MaskBoardSquares {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, sq) in self.0.squares().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            sq.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 不変条件の詳細な検査 (テスト時のみ)。
+///
+/// `assert_unchecked!` などで仮定している不変条件 (マスの値の範囲、`width_remain` と列の内容の整合性など) を、盤面およびマスクの生成・変更のたびに全て検査し、
+/// 違反があれば違反箇所を含むメッセージで panic する。
+/// 新たな内部表現を導入する際も、テストを通すだけで不変条件の破れを検出できるようにするためのもの。
+#[cfg(test)]
+mod invariants {
+    use super::*;
+
+    /// 盤面の不変条件を検査する。`compacted` なら左詰めも要求する。
+    #[track_caller]
+    pub(super) fn check_board(bcs: &ColArray<BitCol>, width_remain: u32, compacted: bool) {
+        let width = width_remain as usize;
+        assert!(
+            width <= Col::NUM,
+            "Board: width_remain が範囲外: {width_remain} (列: {bcs:?})"
+        );
+
+        for (col, &bc) in bcs.enumerate() {
+            assert!(
+                bc.0 >> (3 * Row::NUM) == 0,
+                "Board: 列 {col} の盤面外のビットが立っている: {:#x}",
+                bc.0
+            );
+            for (row, value) in bc.enumerate() {
+                assert!(
+                    value <= Piece::MAX_VALUE,
+                    "Board: マス {} の値が範囲外: {value} (列 {col}: {bc:?})",
+                    Square::new(col, row)
+                );
+            }
+
+            let in_width = col.to_index() < width;
+            if !in_width {
+                assert!(
+                    bc.is_zero(),
+                    "Board: width_remain ({width_remain}) より右の列 {col} が空でない: {bc:?}"
+                );
+            } else if compacted || col.to_index() + 1 == width {
+                assert!(
+                    !bc.is_zero(),
+                    "Board: width_remain ({width_remain}) 以内の列 {col} が空"
+                );
+            }
+        }
+    }
+
+    /// マスクの不変条件を検査する。
+    #[track_caller]
+    pub(super) fn check_mask_board(bcs: &ColArray<BitCol>, col_mask: u32) {
+        assert!(
+            col_mask >> Col::NUM == 0,
+            "MaskBoard: col_mask の範囲外のビットが立っている: {col_mask:#b}"
+        );
+
+        for (col, &bc) in bcs.enumerate() {
+            for (row, value) in bc.enumerate() {
+                assert!(
+                    value <= 1,
+                    "MaskBoard: マス {} の値が 0, 1 以外: {value} (列 {col}: {bc:?})",
+                    Square::new(col, row)
+                );
+            }
+            assert!(
+                bc.0 >> (3 * Row::NUM) == 0,
+                "MaskBoard: 列 {col} の盤面外のビットが立っている: {:#x}",
+                bc.0
+            );
+            let in_mask = (col_mask & (1 << col.to_index())) != 0;
+            assert_eq!(
+                !bc.is_zero(),
+                in_mask,
+                "MaskBoard: 列 {col} の内容 ({bc:?}) と col_mask ({col_mask:#b}) が矛盾している"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use itertools::assert_equal;
+
+    use crate::square::*;
+
+    use super::*;
+
+    fn sq_new(col: Col, row: Row) -> Square {
+        Square::new(col, row)
+    }
+
+    fn parse_board(s: impl AsRef<str>) -> Board {
+        s.as_ref().parse().unwrap()
+    }
+
+    fn parse_mask_board(s: impl AsRef<str>) -> MaskBoard {
+        s.as_ref().parse().unwrap()
+    }
+
+    #[test]
+    fn test_bit_col() {
+        let mut bc = BitCol::new(0b111_110_101_100_011_010);
+        bc.set(ROW_2, 0b000);
+
+        assert_eq!(bc.get(ROW_1), 0b010);
+        assert_eq!(bc.get(ROW_2), 0b000);
+        assert_eq!(bc.get(ROW_3), 0b100);
+        assert_eq!(bc.get(ROW_4), 0b101);
+        assert_eq!(bc.get(ROW_5), 0b110);
+        assert_eq!(bc.get(ROW_6), 0b111);
+
+        assert_equal(
+            bc.enumerate(),
+            [
+                (ROW_1, 0b010),
+                (ROW_2, 0b000),
+                (ROW_3, 0b100),
+                (ROW_4, 0b101),
+                (ROW_5, 0b110),
+                (ROW_6, 0b111),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_board_io() {
+        let cases = [
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                ........
+                ........
+            "},
+            indoc! {"
+                ........
+                ........
+                .1......
+                121.....
+                1213....
+                1213....
+            "},
+            indoc! {"
+                12345123
+                51234512
+                45123451
+                34512345
+                23451234
+                12345123
+            "},
+        ];
+
+        for s in cases {
+            let board = parse_board(s);
+            assert_eq!(board.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_board_bytes() {
+        let mut rng = FastRng::new(1);
+        for _ in 0..100 {
+            let board = Board::random_with(&mut rng, 5);
+            let bytes = board.to_bytes();
+            assert_eq!(Board::from_bytes(&bytes).unwrap(), board);
+        }
+
+        // 左下のマスが最下位 bit から入る。
+        let board = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            2.......
+            13......
+        "});
+        let mut expect = [0; Board::BYTES_LEN];
+        expect[0] = 0b010_001;
+        expect[2] = 3 << 2;
+        assert_eq!(board.to_bytes(), expect);
+
+        // バイト数が違う。
+        assert!(Board::from_bytes(&expect[1..]).is_err());
+        // マスの値が無効。
+        let mut bytes = expect;
+        bytes[0] = 0b110;
+        assert!(Board::from_bytes(&bytes).is_err());
+        // 下詰めになっていない (列 0 の行 0 が空白)。
+        let mut bytes = expect;
+        bytes[0] &= !0b111;
+        assert!(Board::from_bytes(&bytes).is_err());
+        // 左詰めになっていない (列 0 が空)。
+        let mut bytes = expect;
+        bytes[0] = 0;
+        assert!(Board::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_board_parse_flexible() {
+        let expect = parse_board(indoc! {"
+            ........
+            ........
+            .1......
+            121.....
+            1213....
+            1213....
+        "});
+
+        let cases = [
+            (
+                "\n........  \n........\n.1......\n121.....\t\n1213....\n1213....",
+                BoardOrientation::TopDown,
+            ),
+            (
+                "00000000\n00000000\n01000000\n12100000\n12130000\n12130000\n",
+                BoardOrientation::TopDown,
+            ),
+            (
+                "........,........,.1......,121.....,1213....,1213....",
+                BoardOrientation::TopDown,
+            ),
+            (
+                "1213....,1213....,121.....,.1......,........,........",
+                BoardOrientation::BottomUp,
+            ),
+            (
+                "1 2 1 3 . . . .\n1 2 1 3 . . . .\n1 2 1 . . . . .\n. 1 . . . . . .\n. . . . . . . .\n. . . . . . . .\n",
+                BoardOrientation::BottomUp,
+            ),
+        ];
+
+        for (s, orientation) in cases {
+            assert_eq!(Board::parse_flexible(s, orientation).unwrap(), expect);
+        }
+
+        assert!(Board::parse_flexible("1......", BoardOrientation::TopDown).is_err());
+        assert!("0000000\n".repeat(6).parse::<Board>().is_err());
+    }
+
+    #[test]
+    fn test_board_random_with() {
+        let boards = |seed| {
+            let mut rng = FastRng::new(seed);
+            (0..200)
+                .map(|i| Board::random_with(&mut rng, 1 + (i % Piece::NUM) as u8))
+                .collect::<Vec<_>>()
+        };
+
+        // 同じシードからは同じ盤面列が得られる。
+        let boards_1 = boards(1262);
+        assert_eq!(boards(1262), boards_1);
+        assert_ne!(boards(1263), boards_1);
+
+        for (i, board) in boards_1.iter().enumerate() {
+            // 下詰めかつ左詰めになっている。
+            assert_eq!(&Board::from_grid(&board.to_grid()).unwrap(), board);
+            // 指定外の駒種は現れない。
+            let piece_kind_count = 1 + i % Piece::NUM;
+            assert!(Piece::all()
+                .skip(piece_kind_count)
+                .all(|piece| board.piece_count(piece) == 0));
+        }
+
+        // 全面埋まった盤面以外も生成する。
+        assert!(boards_1
+            .iter()
+            .any(|board| board.piece_count_total() < Square::NUM as u32));
+    }
+
+    #[test]
+    fn test_board_piece_count() {
+        for piece in Piece::all() {
+            assert_eq!(Board::empty().piece_count(piece), 0);
+        }
+        assert_eq!(Board::empty().piece_count_total(), 0);
+
+        let cases = [
+            (
+                indoc! {"
+                    ........
+                    ........
+                    ........
+                    ........
+                    ........
+                    12345...
+                "},
+                [1, 1, 1, 1, 1],
+            ),
+            (
+                indoc! {"
+                    .......4
+                    .......4
+                    .1...5.4
+                    .1.3.5.4
+                    1213.5.5
+                    12134555
+                "},
+                [6, 2, 3, 5, 7],
+            ),
+        ];
+
+        for (board, counts) in cases {
+            let board = parse_board(board);
+            for piece in Piece::all() {
+                assert_eq!(board.piece_count(piece), counts[piece.to_index()]);
+            }
+            assert_eq!(board.piece_count_total(), counts.into_iter().sum());
+        }
+    }
+
+    #[test]
+    fn test_board_has_action() {
+        assert!(!Board::empty().has_action());
+
+        let falses = [
+            indoc! {"
+                ........
+                ........
+                ........
+                ...543..
+                ..14213.
+                1232121.
+            "},
+            indoc! {"
+                12345123
+                51234512
+                45123451
+                34512345
+                23451234
+                12345123
+            "},
+        ];
+
+        let trues = [
+            indoc! {"
+                ........
+                ........
+                ........
+                1.......
+                1.5.....
+                234.....
+            "},
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                .34.....
+                2251....
+            "},
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                ........
+                12345133
+            "},
+            indoc! {"
+                .......5
+                .......5
+                .......3
+                .......2
+                .......1
+                12345123
+            "},
+            indoc! {"
+                1......2
+                155....2
+                111.4..2
+                12144..1
+                12133.51
+                12135551
+            "},
+        ];
+
+        for board in falses {
+            let board = parse_board(board);
+            assert!(!board.has_action());
+            assert!(Piece::all().all(|piece| !board.has_action_for(piece)));
+        }
+        for board in trues {
+            let board = parse_board(board);
+            assert!(board.has_action());
+        }
+
+        let board = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            .34.....
+            2251....
+        "});
+        let piece_2 = Piece::from_inner(2).unwrap();
+        for piece in Piece::all() {
+            assert_eq!(board.has_action_for(piece), piece == piece_2);
+        }
+    }
+
+    #[test]
+    fn test_board_action_count() {
+        assert_eq!(Board::empty().action_count(), 0);
+
+        let cases = [
+            (
+                indoc! {"
+                    12345123
+                    51234512
+                    45123451
+                    34512345
+                    23451234
+                    12345123
+                "},
+                0,
+            ),
+            (
+                indoc! {"
+                    ........
+                    ........
+                    ........
+                    ........
+                    .34.....
+                    2251....
+                "},
+                1,
+            ),
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                8,
+            ),
+        ];
+
+        for (board, count) in cases {
+            let board = parse_board(board);
+            assert_eq!(board.action_count(), count);
+            assert_eq!(board.action_components().count() as u32, count);
+        }
+    }
+
+    #[test]
+    fn test_board_components_by_piece() {
+        let board = parse_board(indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "});
+
+        let masks = board.piece_masks();
+        let comps = board.components_by_piece();
+        for piece in Piece::all() {
+            assert_eq!(masks[piece], board.piece_mask(piece));
+            assert_eq!(
+                comps[piece],
+                board.piece_mask(piece).components().collect::<Vec<_>>()
+            );
+        }
+
+        // 駒 1 の連結成分は左の大きな塊と (8,1)..(8,3) の塊の 2 つ。
+        let piece_1 = Piece::from_inner(1).unwrap();
+        assert_eq!(comps[piece_1].len(), 2);
+
+        assert_eq!(
+            comps
+                .enumerate()
+                .flat_map(|(piece, comps)| comps.iter().map(move |comp| (piece, comp.clone())))
+                .collect::<Vec<_>>(),
+            board.piece_components().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            board.action_components().collect::<Vec<_>>(),
+            board
+                .piece_components()
+                .filter(|(_, comp)| !comp.is_single())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_board_erase() {
+        let cases = [
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    *.......
+                    *.......
+                    ***.....
+                    *.*.....
+                    *.*.....
+                    *.*.....
+                "},
+                indoc! {"
+                    ......2.
+                    ......2.
+                    5..4..2.
+                    2.44..1.
+                    2.33.51.
+                    2535551.
+                "},
+            ),
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    ........
+                    .**.....
+                    ........
+                    ........
+                    ........
+                    ........
+                "},
+                indoc! {"
+                    1......2
+                    1......2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+            ),
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    ........
+                    ........
+                    ........
+                    ........
+                    ...**...
+                    ...*....
+                "},
+                indoc! {"
+                    1......2
+                    155....2
+                    111....2
+                    121.4..1
+                    121.4.51
+                    12145551
+                "},
+            ),
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    ........
+                    ........
+                    ........
+                    ........
+                    ......*.
+                    ....***.
+                "},
+                indoc! {"
+                    1....2..
+                    155..2..
+                    111..2..
+                    121441..
+                    121341..
+                    121331..
+                "},
+            ),
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    .......*
+                    .......*
+                    .......*
+                    ........
+                    ........
+                    ........
+                "},
+                indoc! {"
+                    1.......
+                    155.....
+                    111.4...
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+            ),
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    ........
+                    ........
+                    ........
+                    .......*
+                    .......*
+                    .......*
+                "},
+                indoc! {"
+                    1.......
+                    155.....
+                    111.4...
+                    12144..2
+                    12133.52
+                    12135552
+                "},
+            ),
+        ];
+
+        for (before, mb, after) in cases {
+            let before = parse_board(before);
+            let mb = parse_mask_board(mb);
+            let after = parse_board(after);
+            let erased_count = before.piece_count_total() - after.piece_count_total();
+
+            assert_eq!(before.erase(&mb), after);
+
+            // その場版とバッファ版も同じ結果になり、消した駒数を返す。
+            let mut board = before.clone();
+            assert_eq!(board.erase_in_place(&mb), erased_count);
+            assert_eq!(board, after);
+            let mut out = parse_board("11111111\n".repeat(Row::NUM));
+            assert_eq!(before.erase_into(&mb, &mut out), erased_count);
+            assert_eq!(out, after);
+
+            let mut board = before.clone();
+            assert_eq!(board.erase_without_compaction_in_place(&mb), erased_count);
+            assert_eq!(board, before.erase_without_compaction(&mb));
+        }
+
+        // 1 列のみの着手 (高速パス)。途中の列が空になると右側の列が左へずれる。
+        let before = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            .1......
+            21.3....
+            214335..
+        "});
+        let mb = parse_mask_board(indoc! {"
+            ........
+            ........
+            ........
+            .*......
+            .*......
+            .*......
+        "});
+        assert_eq!(mb.single_col(), Some(COL_2));
+        assert_eq!(
+            before.erase(&mb).to_string(),
+            indoc! {"
+            ........
+            ........
+            ........
+            ........
+            2.3.....
+            24335...
+        "}
+        );
+        let mb = parse_mask_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            ...*....
+            ..*.....
+        "});
+        assert_eq!(mb.single_col(), None);
+        assert_eq!(MaskBoard::empty().single_col(), None);
+    }
+
+    #[test]
+    fn test_board_xor_mask() {
+        assert_eq!(Board::empty().xor_mask(&Board::empty()), MaskBoard::empty());
+
+        {
+            let board = parse_board(indoc! {"
+                12345123
+                51234512
+                45123451
+                34512345
+                23451234
+                12345123
+            "});
+            assert_eq!(board.xor_mask(&board), MaskBoard::empty());
+        }
+
+        let cases = [
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    ......2.
+                    ......2.
+                    5..4..2.
+                    2.44..1.
+                    2.33.51.
+                    2535551.
+                "},
+                indoc! {"
+                    *.....**
+                    ***...**
+                    *****.**
+                    ***.*.**
+                    ***.****
+                    ****..**
+                "},
+            ),
+            (
+                indoc! {"
+                    1......2
+                    155....2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    1......2
+                    1......2
+                    111.4..2
+                    12144..1
+                    12133.51
+                    12135551
+                "},
+                indoc! {"
+                    ........
+                    .**.....
+                    ........
+                    ........
+                    ........
+                    ........
+                "},
+            ),
+        ];
+
+        for (before, after, mb) in cases {
+            let before = parse_board(before);
+            let after = parse_board(after);
+            let mb = parse_mask_board(mb);
+
+            assert_eq!(before.xor_mask(&after), mb);
+            assert_eq!(after.xor_mask(&before), mb);
+        }
+    }
+
+    #[test]
+    fn test_mask_board_io() {
+        let cases = [
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                ........
+                ........
+            "},
+            indoc! {"
+                .......*
+                .....*.*
+                .*...*..
+                *.*....*
+                *.*...*.
+                *.*.....
+            "},
+        ];
+
+        for s in cases {
+            let mb = parse_mask_board(s);
+            assert_eq!(mb.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_mask_board_squares_io() {
+        let mb = parse_mask_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            .*......
+            *......*
+        "});
+        let squares = MaskBoardSquares(mb.clone());
+        assert_eq!(squares.to_string(), "1,1 2,2 8,1");
+        assert_eq!(
+            "8,1  2,2\t1,1".parse::<MaskBoardSquares>().unwrap(),
+            squares
+        );
+
+        assert_eq!(MaskBoardSquares(MaskBoard::empty()).to_string(), "");
+        assert_eq!(
+            "".parse::<MaskBoardSquares>().unwrap(),
+            MaskBoardSquares(MaskBoard::empty())
+        );
+
+        assert!("1,1 1,1".parse::<MaskBoardSquares>().is_err());
+        assert!("1,1 9,1".parse::<MaskBoardSquares>().is_err());
+        assert!("1,1,".parse::<MaskBoardSquares>().is_err());
+
+        // ランダムなマスクで往復変換を確認する。
+        let mut rng = crate::prng::FastRng::new(1204);
+        for _ in 0..1000 {
+            let mut mb = MaskBoard::empty();
+            for sq in Square::all() {
+                mb.set(sq, rng.gen_bool(0.3));
+            }
+            let s = MaskBoardSquares(mb.clone()).to_string();
+            assert_eq!(s.parse::<MaskBoardSquares>().unwrap().0, mb);
+        }
+    }
+
+    #[test]
+    fn test_mask_board_squares() {
+        assert_eq!(MaskBoard::empty().squares().next(), None);
+
+        let cases = [
+            (
+                indoc! {"
+                    *......*
+                    ........
+                    ........
+                    ........
+                    ........
+                    *......*
+                "},
+                vec![
+                    sq_new(COL_1, ROW_1),
+                    sq_new(COL_1, ROW_6),
+                    sq_new(COL_8, ROW_1),
+                    sq_new(COL_8, ROW_6),
+                ],
+            ),
+            (
+                indoc! {"
+                    ........
+                    ...**...
+                    ..*..*..
+                    .*....*.
+                    *......*
+                    ........
+                "},
+                vec![
+                    sq_new(COL_1, ROW_2),
+                    sq_new(COL_2, ROW_3),
+                    sq_new(COL_3, ROW_4),
+                    sq_new(COL_4, ROW_5),
+                    sq_new(COL_5, ROW_5),
+                    sq_new(COL_6, ROW_4),
+                    sq_new(COL_7, ROW_3),
+                    sq_new(COL_8, ROW_2),
+                ],
+            ),
+        ];
+
+        for (mb, sqs) in cases {
+            let mb = parse_mask_board(mb);
+            assert_equal(mb.squares(), sqs.iter().copied());
+            assert_eq!(mb.squares_arrayvec().as_slice(), sqs);
+        }
+    }
+
+    #[test]
+    fn test_mask_board_components() {
+        assert_eq!(MaskBoard::empty().components().next(), None);
+
+        let mb = parse_mask_board(indoc! {"
+            ****...*
+            ...*....
+            .***....
+            .*...*..
+            *.*...*.
+            *.*...**
+        "});
+        let expect = [
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                *.......
+                *.......
+            "},
+            indoc! {"
+                ****....
+                ...*....
+                .***....
+                .*......
+                ........
+                ........
+            "},
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                ..*.....
+                ..*.....
+            "},
+            indoc! {"
+                ........
+                ........
+                ........
+                .....*..
+                ........
+                ........
+            "},
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                ......*.
+                ......**
+            "},
+            indoc! {"
+                .......*
+                ........
+                ........
+                ........
+                ........
+                ........
+            "},
+        ];
+
+        let expect = expect.map(parse_mask_board);
+        assert_equal(mb.components(), expect);
+    }
+
+    #[test]
+    fn test_board_erase_edge_cases() {
+        // 全マスが同じ駒の盤面を一度に消すと空になる。
+        let board = parse_board("11111111\n".repeat(Row::NUM));
+        let mb = board.piece_mask(Piece::MIN);
+        assert_eq!(mb.square_count(), Square::NUM as u32);
+        assert_eq!(mb.flood_fill(sq_new(COL_8, ROW_6)), mb);
+        let erased = board.erase(&mb);
+        assert!(erased.is_empty());
+        assert_eq!(erased.width_remain(), 0);
+
+        // 1 列のみの盤面。列が空になると盤面も空になる。
+        let board = parse_board(indoc! {"
+            1.......
+            1.......
+            2.......
+            2.......
+            1.......
+            1.......
+        "});
+        let mb = parse_mask_board(indoc! {"
+            ........
+            ........
+            *.......
+            *.......
+            ........
+            ........
+        "});
+        let erased = board.erase(&mb);
+        assert_eq!(
+            erased,
+            parse_board(indoc! {"
+                ........
+                ........
+                1.......
+                1.......
+                1.......
+                1.......
+            "})
+        );
+        let mb = erased.piece_mask(Piece::MIN);
+        assert_eq!(mb.components().count(), 1);
+        assert!(erased.erase(&mb).is_empty());
+
+        // 最上段のみを消す (下の駒は動かない)。
+        let board = parse_board(indoc! {"
+            33333333
+            12121212
+            21212121
+            12121212
+            21212121
+            12121212
+        "});
+        let mb = board.piece_mask(Piece::from_inner(3).unwrap());
+        assert_eq!(mb.components().count(), 1);
+        let erased = board.erase(&mb);
+        assert_eq!(erased.width_remain(), Col::NUM as u32);
+        assert_eq!(erased.piece_count_total(), (Square::NUM - Col::NUM) as u32);
+        assert!(!erased.has_action());
+
+        // 右端の列を消す。
+        let board = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            .......3
+            12121213
+        "});
+        let mb = board.piece_mask(Piece::from_inner(3).unwrap());
+        assert_eq!(board.erase(&mb).width_remain(), 7);
+        assert_eq!(board.erase_without_compaction(&mb).width_remain(), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "値が範囲外")]
+    fn test_board_invariants_piece_value() {
+        let mut bcs = ColArray::<BitCol>::default();
+        bcs[COL_1].set(ROW_1, 6);
+        let _ = Board::new(bcs, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "盤面外のビット")]
+    fn test_board_invariants_out_of_board_bits() {
+        let mut bcs = ColArray::<BitCol>::default();
+        bcs[COL_1] = BitCol::new(1 | (1 << (3 * Row::NUM)));
+        let _ = Board::new(bcs, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "width_remain (1) 以内の列 1 が空")]
+    fn test_board_invariants_width_remain() {
+        let _ = Board::new(ColArray::default(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "col_mask")]
+    fn test_mask_board_invariants_col_mask() {
+        let _ = MaskBoard::new(ColArray::default(), 1);
+    }
+}
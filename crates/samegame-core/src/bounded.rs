@@ -36,15 +36,17 @@ macro_rules! impl_bounded_uint {
                 }
             }
 
-            /// 内部値から値を作る。
-            ///
-            /// # Safety
-            ///
-            /// `inner` は有効値でなければならない。
-            pub const unsafe fn from_inner_unchecked(inner: $ty_inner) -> Self {
-                $crate::hint::assert_unchecked!(Self::inner_is_valid(inner));
-
-                Self(inner)
+            $crate::hint::unchecked_fn! {
+                /// 内部値から値を作る。
+                ///
+                /// # Safety
+                ///
+                /// `inner` は有効値でなければならない。
+                const unsafe fn from_inner_unchecked(inner: $ty_inner) -> Self {
+                    $crate::hint::assert_unchecked!(Self::inner_is_valid(inner));
+
+                    Self(inner)
+                }
             }
 
             const fn inner_is_valid(inner: $ty_inner) -> bool {
@@ -60,15 +62,17 @@ macro_rules! impl_bounded_uint {
                 }
             }
 
-            /// 0-based のインデックスから値を作る。
-            ///
-            /// # Safety
-            ///
-            /// `idx` は有効なインデックスでなければならない。
-            pub const unsafe fn from_index_unchecked(idx: usize) -> Self {
-                $crate::hint::assert_unchecked!(Self::index_is_valid(idx));
+            $crate::hint::unchecked_fn! {
+                /// 0-based のインデックスから値を作る。
+                ///
+                /// # Safety
+                ///
+                /// `idx` は有効なインデックスでなければならない。
+                const unsafe fn from_index_unchecked(idx: usize) -> Self {
+                    $crate::hint::assert_unchecked!(Self::index_is_valid(idx));
 
-                Self::from_inner_unchecked(idx as $ty_inner)
+                    Self::from_inner_unchecked(idx as $ty_inner)
+                }
             }
 
             const fn index_is_valid(idx: usize) -> bool {
@@ -140,15 +144,17 @@ macro_rules! impl_bounded_nonzero_uint {
                 }
             }
 
-            /// 内部値から値を作る。
-            ///
-            /// # Safety
-            ///
-            /// `inner` は有効値でなければならない。
-            pub const unsafe fn from_inner_unchecked(inner: $ty_inner) -> Self {
-                $crate::hint::assert_unchecked!(Self::inner_is_valid(inner));
+            $crate::hint::unchecked_fn! {
+                /// 内部値から値を作る。
+                ///
+                /// # Safety
+                ///
+                /// `inner` は有効値でなければならない。
+                const unsafe fn from_inner_unchecked(inner: $ty_inner) -> Self {
+                    $crate::hint::assert_unchecked!(Self::inner_is_valid(inner));
 
-                Self($crate::nonzero::NonZero::<$ty_inner>::new_unchecked(inner))
+                    Self($crate::nonzero::NonZero::<$ty_inner>::new_unchecked(inner))
+                }
             }
 
             const fn inner_is_valid(inner: $ty_inner) -> bool {
@@ -164,15 +170,17 @@ macro_rules! impl_bounded_nonzero_uint {
                 }
             }
 
-            /// 0-based のインデックスから値を作る。
-            ///
-            /// # Safety
-            ///
-            /// `idx` は有効なインデックスでなければならない。
-            pub const unsafe fn from_index_unchecked(idx: usize) -> Self {
-                $crate::hint::assert_unchecked!(Self::index_is_valid(idx));
-
-                Self::from_inner_unchecked((idx + $min as usize) as $ty_inner)
+            $crate::hint::unchecked_fn! {
+                /// 0-based のインデックスから値を作る。
+                ///
+                /// # Safety
+                ///
+                /// `idx` は有効なインデックスでなければならない。
+                const unsafe fn from_index_unchecked(idx: usize) -> Self {
+                    $crate::hint::assert_unchecked!(Self::index_is_valid(idx));
+
+                    Self::from_inner_unchecked((idx + $min as usize) as $ty_inner)
+                }
             }
 
             const fn index_is_valid(idx: usize) -> bool {
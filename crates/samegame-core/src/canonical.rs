@@ -0,0 +1,230 @@
+//! 盤面生成パラメータの重複除去関連。
+
+use std::collections::HashMap;
+use std::hash::{Hash as _, Hasher as _};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use crate::board::Board;
+use crate::rng::{EntropyPartition, GameEntropy, RandomBoardParam};
+
+/// 生成される盤面 (駒の列) が同一のパラメータたちを代表元にまとめるための索引。
+///
+/// 対象は乱数生成器の内部状態が指定範囲にある `RandomBoardParam::all()` のパラメータで、
+/// 各同値類の最小のパラメータを代表元とする。
+///
+/// 内部状態の bit15 (`all()` で除外済み) とエントロピー値の同値類 (`EntropyPartition`) に加え、
+/// 内部状態や NMI カウンタが異なるパラメータ間の一致もまとめる
+/// (全範囲では、エントロピー値の同値類の代表元の 2 割強がこれに当たる)。
+/// 後者は構築時に全パラメータの盤面のハッシュ値を求めて検出し、ハッシュ値が一致したものは盤面を比較して確かめる。
+/// 索引が保持するのは後者でまとめたパラメータのみ。
+///
+/// 全範囲では相異なる盤面は 11092510 個 (パラメータは 41943040 個)。
+/// 構築時に範囲内の全パラメータの盤面を生成するので、全範囲ではリリースビルドでも 20 秒ほどかかり、
+/// 一時的に数百 MB のメモリを使う。複製は内部のテーブルを共有するので安価。
+#[derive(Clone, Debug)]
+pub struct CanonicalIndex {
+    rng_states: RangeInclusive<u16>,
+    /// パラメータ (`pack()`) から同値なパラメータ数への写像 (代表元でなければ 0)。
+    merged: Arc<HashMap<u32, u32>>,
+}
+
+impl CanonicalIndex {
+    /// 乱数生成器の内部状態が `rng_states` の範囲にあるパラメータについて索引を作る。
+    ///
+    /// `rng_states` は `0..=0x7FFF` に含まれなければならない (さもなくば panic する)。
+    pub fn new(rng_states: RangeInclusive<u16>) -> Self {
+        let reps = partition_representatives(rng_states.clone());
+        Self::from_representatives(rng_states, reps)
+    }
+
+    /// エントロピー値の同値類の代表元たち `reps` (昇順) をまとめて索引を作る。
+    fn from_representatives(
+        rng_states: RangeInclusive<u16>,
+        reps: impl Iterator<Item = (RandomBoardParam, u32)>,
+    ) -> Self {
+        assert!(
+            *rng_states.end() <= 0x7FFF,
+            "rng_states は 0..=0x7FFF に含まれなければならない: {rng_states:?}"
+        );
+
+        // (盤面のハッシュ値, パラメータ, 同値なパラメータ数)。ハッシュ値が同じものはパラメータの昇順に並ぶ。
+        let mut entries: Vec<(u64, u32, u8)> = reps
+            .map(|(param, count)| (board_hash(&param.gen_board().0), pack(&param), count as u8))
+            .collect();
+        entries.sort_unstable();
+
+        let mut merged = HashMap::new();
+        for group in entries.chunk_by(|a, b| a.0 == b.0).filter(|g| g.len() >= 2) {
+            // ハッシュ値の衝突に備え、盤面ごとに分ける。
+            let mut classes: Vec<(Board, Vec<(u32, u8)>)> = Vec::new();
+            for &(_, packed, count) in group {
+                let board = unpack(packed).gen_board().0;
+                match classes.iter_mut().find(|(b, _)| *b == board) {
+                    Some((_, members)) => members.push((packed, count)),
+                    None => classes.push((board, vec![(packed, count)])),
+                }
+            }
+
+            for (_, members) in classes.into_iter().filter(|(_, m)| m.len() >= 2) {
+                let total = members.iter().map(|&(_, count)| u32::from(count)).sum();
+                merged.insert(members[0].0, total);
+                for &(packed, _) in &members[1..] {
+                    merged.insert(packed, 0);
+                }
+            }
+        }
+
+        Self {
+            rng_states,
+            merged: Arc::new(merged),
+        }
+    }
+
+    /// 索引の対象となる乱数生成器の内部状態の範囲を返す。
+    pub fn rng_states(&self) -> RangeInclusive<u16> {
+        self.rng_states.clone()
+    }
+
+    /// パラメータ `param` が代表元ならば、それと同値なパラメータ数 (自身を含む) を返す。
+    /// 代表元でないか、索引の対象外ならば 0 を返す。
+    pub fn multiplicity(&self, param: &RandomBoardParam) -> u32 {
+        if param.nmi_timing != 40 || !self.rng_states.contains(&param.rng_state) {
+            return 0;
+        }
+        if let Some(&count) = self.merged.get(&pack(param)) {
+            return count;
+        }
+
+        let partition = EntropyPartition::new(param.rng_state, param.nmi_counter, 40);
+        if partition.representative(param.entropy) == param.entropy {
+            partition.class_size(param.entropy) as u32
+        } else {
+            0
+        }
+    }
+
+    /// 全ての代表元について (代表元, 同値なパラメータ数) を昇順で列挙する。
+    pub fn params(&self) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, u32)> + Clone {
+        self.params_in(self.rng_states())
+    }
+
+    /// 乱数生成器の内部状態が `rng_states` の範囲にある代表元について、`params()` と同様に列挙する。
+    ///
+    /// 同値なパラメータ数は索引の対象全体で数える。`rng_states` のうち索引の対象外の部分は列挙しない。
+    pub fn params_in(
+        &self,
+        rng_states: RangeInclusive<u16>,
+    ) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, u32)> + Clone {
+        let start = (*rng_states.start()).max(*self.rng_states.start());
+        let end = (*rng_states.end()).min(*self.rng_states.end());
+        let merged = Arc::clone(&self.merged);
+
+        partition_representatives(start..=end)
+            .filter_map(move |(param, count)| match merged.get(&pack(&param)) {
+                Some(0) => None,
+                Some(&count) => Some((param, count)),
+                None => Some((param, count)),
+            })
+            .fuse()
+    }
+}
+
+/// エントロピー値の同値類の代表元について、(代表元, 同値なパラメータ数) を昇順で列挙する。
+fn partition_representatives(
+    rng_states: RangeInclusive<u16>,
+) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, u32)> + Clone {
+    rng_states
+        .flat_map(|rng_state| (0..=u8::MAX).map(move |nmi_counter| (rng_state, nmi_counter)))
+        .flat_map(|(rng_state, nmi_counter)| {
+            let partition = EntropyPartition::new(rng_state, nmi_counter, 40);
+            GameEntropy::all()
+                .filter(move |&entropy| partition.representative(entropy) == entropy)
+                .map(move |entropy| {
+                    let param = RandomBoardParam {
+                        rng_state,
+                        nmi_counter,
+                        nmi_timing: 40,
+                        entropy,
+                    };
+                    (param, partition.class_size(entropy) as u32)
+                })
+        })
+        .fuse()
+}
+
+/// NMI 発生タイミングが 40 のパラメータを、順序を保って 32bit に詰める。
+fn pack(param: &RandomBoardParam) -> u32 {
+    (u32::from(param.rng_state) << 11)
+        | (u32::from(param.nmi_counter) << 3)
+        | u32::from(param.entropy.to_inner())
+}
+
+/// `pack()` の逆。
+fn unpack(packed: u32) -> RandomBoardParam {
+    RandomBoardParam {
+        rng_state: (packed >> 11) as u16,
+        nmi_counter: (packed >> 3) as u8,
+        nmi_timing: 40,
+        entropy: GameEntropy::from_inner((packed & 7) as u8).unwrap(),
+    }
+}
+
+fn board_hash(board: &Board) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    board.to_bytes().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_index() {
+        // 内部状態も NMI カウンタも異なるが、同じ盤面を生成するパラメータ。
+        let a: RandomBoardParam = "0x0709,0x8D,40,3".parse().unwrap();
+        let b: RandomBoardParam = "0x0801,0x01,40,1".parse().unwrap();
+        assert_eq!(a.gen_board().0, b.gen_board().0);
+
+        // 全範囲で作ると重いので、2 つの内部状態のパラメータのみから作る。
+        let reps = partition_representatives(0x0709..=0x0709)
+            .chain(partition_representatives(0x0801..=0x0801));
+        let index = CanonicalIndex::from_representatives(0x0709..=0x0801, reps);
+
+        let class_size = |param: &RandomBoardParam| {
+            EntropyPartition::new(param.rng_state, param.nmi_counter, 40).class_size(param.entropy)
+                as u32
+        };
+        assert_eq!(index.multiplicity(&a), class_size(&a) + class_size(&b));
+        assert_eq!(index.multiplicity(&b), 0);
+        assert!(index
+            .params_in(0x0709..=0x0709)
+            .any(|(param, n)| param == a && n == index.multiplicity(&a)));
+        assert!(index
+            .params_in(0x0801..=0x0801)
+            .all(|(param, _)| param != b));
+
+        // 列挙したものは相異なる盤面を生成し、同値なパラメータ数の総和は元のパラメータ数と一致する。
+        let canonical: Vec<_> = index
+            .params_in(0x0709..=0x0709)
+            .chain(index.params_in(0x0801..=0x0801))
+            .collect();
+        let boards: std::collections::HashSet<[u8; Board::BYTES_LEN]> = canonical
+            .iter()
+            .map(|(param, _)| param.gen_board().0.to_bytes())
+            .collect();
+        assert_eq!(boards.len(), canonical.len());
+        assert_eq!(
+            canonical.iter().map(|&(_, n)| n as usize).sum::<usize>(),
+            2 * 0x100 * GameEntropy::NUM
+        );
+        assert!(canonical
+            .iter()
+            .all(|(param, n)| index.multiplicity(param) == *n));
+
+        // 対象外のパラメータ。
+        assert_eq!(index.multiplicity(&"0x0802,0x00,40,0".parse().unwrap()), 0);
+        assert_eq!(index.multiplicity(&"0x0709,0x8D,41,3".parse().unwrap()), 0);
+    }
+}
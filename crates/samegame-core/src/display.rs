@@ -0,0 +1,299 @@
+//! 盤面の表示オプション関連。
+
+use crate::board::{Board, BoardOrientation};
+use crate::piece::Piece;
+use crate::square::{Col, Row, Square};
+
+/// 盤面表示に用いる文字たち。
+///
+/// 各マスは 1 つの文字列で表す (絵文字のように複数のコードポイントからなるものでもよい)。
+/// `FromStr` が受け付けるのはデフォルト (`BoardCharset::digits()`) の形式のみであることに注意。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BoardCharset {
+    blank: String,
+    pieces: [String; Piece::NUM],
+}
+
+impl BoardCharset {
+    /// 空白マスおよび駒種 `1..=5` の文字列を指定して作る。
+    pub fn new(blank: impl Into<String>, pieces: [&str; Piece::NUM]) -> Self {
+        Self {
+            blank: blank.into(),
+            pieces: pieces.map(str::to_owned),
+        }
+    }
+
+    /// `Board` の `Display` 実装と同じ文字たち (`.`, `1`..`5`)。
+    pub fn digits() -> Self {
+        Self::new(Board::CHAR_BLANK, ["1", "2", "3", "4", "5"])
+    }
+
+    /// 英字 (`.`, `A`..`E`)。
+    pub fn letters() -> Self {
+        Self::new(Board::CHAR_BLANK, ["A", "B", "C", "D", "E"])
+    }
+
+    /// 色付きの四角の絵文字。
+    ///
+    /// 絵文字は多くの端末で全角幅となるので、欄外表示 (`BoardDisplayOptions::gutters()`) の列番号とは揃わない。
+    pub fn emoji() -> Self {
+        Self::new("⬛", ["🟥", "🟩", "🟦", "🟨", "🟪"])
+    }
+
+    /// 空白マスの文字列を返す。
+    pub fn blank(&self) -> &str {
+        &self.blank
+    }
+
+    /// 駒 `piece` の文字列を返す。
+    pub fn piece(&self, piece: Piece) -> &str {
+        &self.pieces[piece.to_index()]
+    }
+}
+
+impl Default for BoardCharset {
+    fn default() -> Self {
+        Self::digits()
+    }
+}
+
+/// 盤面の表示オプション。
+///
+/// デフォルトでは `Board` の `Display` 実装と同じ出力になる。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BoardDisplayOptions {
+    orientation: BoardOrientation,
+    gutters: bool,
+    charset: BoardCharset,
+}
+
+impl BoardDisplayOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 行の並び順を設定する。`BoardOrientation::BottomUp` ならメモリ上の順序 (行 1 が先頭) で表示する。
+    pub fn orientation(mut self, orientation: BoardOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// 座標の欄外表示の有無を設定する。
+    ///
+    /// 有効な場合、各行の左端に行番号を、行 1 側の端に列番号の行を表示する。
+    pub fn gutters(mut self, gutters: bool) -> Self {
+        self.gutters = gutters;
+        self
+    }
+
+    /// マスの表示に用いる文字たちを設定する。
+    pub fn charset(mut self, charset: BoardCharset) -> Self {
+        self.charset = charset;
+        self
+    }
+}
+
+/// オプションを指定して盤面を表示するためのアダプタ。`Board::display_with()` で作る。
+#[derive(Debug)]
+pub struct BoardDisplay<'a> {
+    board: &'a Board,
+    options: &'a BoardDisplayOptions,
+}
+
+impl Board {
+    /// オプションを指定して盤面を表示するためのアダプタを返す。
+    pub fn display_with<'a>(&'a self, options: &'a BoardDisplayOptions) -> BoardDisplay<'a> {
+        BoardDisplay {
+            board: self,
+            options,
+        }
+    }
+}
+
+impl BoardDisplay<'_> {
+    fn fmt_col_header(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("  ")?;
+        for col in Col::all() {
+            write!(f, "{col}")?;
+        }
+        writeln!(f)
+    }
+}
+
+impl std::fmt::Display for BoardDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows: Vec<Row> = match self.options.orientation {
+            BoardOrientation::TopDown => Row::all().rev().collect(),
+            BoardOrientation::BottomUp => Row::all().collect(),
+        };
+
+        if self.options.gutters && self.options.orientation == BoardOrientation::BottomUp {
+            self.fmt_col_header(f)?;
+        }
+
+        for row in rows {
+            if self.options.gutters {
+                write!(f, "{row} ")?;
+            }
+            for col in Col::all() {
+                let sq = Square::new(col, row);
+                let s = self
+                    .board
+                    .get(sq)
+                    .map_or(self.options.charset.blank(), |piece| {
+                        self.options.charset.piece(piece)
+                    });
+                f.write_str(s)?;
+            }
+            writeln!(f)?;
+        }
+
+        if self.options.gutters && self.options.orientation == BoardOrientation::TopDown {
+            self.fmt_col_header(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_board_display_options() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            .1......
+            121.....
+            1213....
+            1223....
+        "}
+        .parse()
+        .unwrap();
+
+        let cases = [
+            (BoardDisplayOptions::new(), board.to_string()),
+            (
+                BoardDisplayOptions::new().orientation(BoardOrientation::BottomUp),
+                indoc! {"
+                    1223....
+                    1213....
+                    121.....
+                    .1......
+                    ........
+                    ........
+                "}
+                .to_owned(),
+            ),
+            (
+                BoardDisplayOptions::new().gutters(true),
+                indoc! {"
+                    6 ........
+                    5 ........
+                    4 .1......
+                    3 121.....
+                    2 1213....
+                    1 1223....
+                      12345678
+                "}
+                .to_owned(),
+            ),
+            (
+                BoardDisplayOptions::new()
+                    .orientation(BoardOrientation::BottomUp)
+                    .gutters(true),
+                indoc! {"
+                      12345678
+                    1 1223....
+                    2 1213....
+                    3 121.....
+                    4 .1......
+                    5 ........
+                    6 ........
+                "}
+                .to_owned(),
+            ),
+        ];
+
+        for (options, expect) in cases {
+            assert_eq!(board.display_with(&options).to_string(), expect);
+        }
+    }
+
+    /// 出力を golden ファイル `testdata/display/{name}.txt` と照合する。
+    /// 環境変数 `UPDATE_GOLDEN` が設定されていれば、golden ファイルを更新する。
+    fn check_golden(name: &str, actual: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/display")
+            .join(format!("{name}.txt"));
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expect = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("golden ファイル '{}' を読めない: {e}", path.display()));
+        assert_eq!(
+            actual,
+            expect,
+            "golden ファイル '{}' と一致しない",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_board_charset_golden() {
+        let board: Board = indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "}
+        .parse()
+        .unwrap();
+
+        let cases = [
+            ("digits", BoardDisplayOptions::new()),
+            ("digits_gutters", BoardDisplayOptions::new().gutters(true)),
+            (
+                "letters",
+                BoardDisplayOptions::new().charset(BoardCharset::letters()),
+            ),
+            (
+                "letters_bottom_up",
+                BoardDisplayOptions::new()
+                    .orientation(BoardOrientation::BottomUp)
+                    .charset(BoardCharset::letters()),
+            ),
+            (
+                "emoji",
+                BoardDisplayOptions::new().charset(BoardCharset::emoji()),
+            ),
+            (
+                "custom",
+                BoardDisplayOptions::new()
+                    .charset(BoardCharset::new(" ", ["@", "#", "$", "%", "&"])),
+            ),
+        ];
+
+        for (name, options) in cases {
+            check_golden(name, &board.display_with(&options).to_string());
+        }
+
+        // FromStr は従来通り数字の形式のみを受け付ける。
+        let options = BoardDisplayOptions::new().charset(BoardCharset::letters());
+        assert!(board
+            .display_with(&options)
+            .to_string()
+            .parse::<Board>()
+            .is_err());
+        assert_eq!(board.to_string().parse::<Board>().unwrap(), board);
+    }
+}
@@ -44,3 +44,33 @@ macro_rules! unreachable_unchecked {
     }};
 }
 pub(crate) use unreachable_unchecked;
+
+/// 検査を省く unsafe な関数 (`*_unchecked`) を定義する。
+///
+/// feature `unchecked` が有効なら `pub`, さもなくば `pub(crate)` となる。
+/// 誤用しやすいので、下流クレートには明示的に要求した場合のみ公開する (通常は検査付きの安全な版を使えばよい)。
+/// 各関数は事前条件を `assert_unchecked!` などで表明し、デバッグビルドでは違反を検出すること。
+/// (feature 無効時にクレート内で使われない関数があっても警告しない)
+macro_rules! unchecked_fn {
+    ($(#[$attr:meta])* const unsafe fn $($rest:tt)*) => {
+        #[cfg(feature = "unchecked")]
+        $(#[$attr])*
+        pub const unsafe fn $($rest)*
+
+        #[cfg(not(feature = "unchecked"))]
+        #[allow(dead_code)]
+        $(#[$attr])*
+        pub(crate) const unsafe fn $($rest)*
+    };
+    ($(#[$attr:meta])* unsafe fn $($rest:tt)*) => {
+        #[cfg(feature = "unchecked")]
+        $(#[$attr])*
+        pub unsafe fn $($rest)*
+
+        #[cfg(not(feature = "unchecked"))]
+        #[allow(dead_code)]
+        $(#[$attr])*
+        pub(crate) unsafe fn $($rest)*
+    };
+}
+pub(crate) use unchecked_fn;
@@ -0,0 +1,36 @@
+//! SFC『鮫亀』: さめがめ「かんたん」の盤面、ゲームルール、乱数生成器。
+//!
+//! ソルバーやツール類を含まない最小限の部分。ソルバーは `samegame-solver` クレート、ツール類は `samegame-tools` クレートにある。
+//! `samegame-sfc-small-2` クレートはこれら全ての内容を再エクスポートしている。
+
+mod action;
+mod array;
+mod bitop;
+mod board;
+mod bounded;
+mod canonical;
+mod display;
+mod hint;
+mod nonzero;
+mod parse;
+mod piece;
+mod prng;
+mod rng;
+pub mod rules;
+mod ruleset;
+mod schema;
+mod score;
+mod square;
+
+pub use self::action::*;
+pub use self::board::*;
+pub use self::canonical::*;
+pub use self::display::*;
+pub use self::parse::*;
+pub use self::piece::*;
+pub use self::prng::*;
+pub use self::rng::*;
+pub use self::ruleset::*;
+pub use self::schema::*;
+pub use self::score::*;
+pub use self::square::*;
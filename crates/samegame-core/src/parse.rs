@@ -0,0 +1,45 @@
+//! 文字列パース用の補助関数。
+
+use anyhow::{anyhow, Context as _};
+
+/// 符号なし整数をパースする。
+///
+/// 10 進数の他、`0x`, `0o`, `0b` 接頭辞による 16/8/2 進数表記も受け付ける。
+/// 桁区切りの `_` は無視する。
+pub fn parse_uint<T: TryFrom<u64>>(s: &str) -> anyhow::Result<T> {
+    let (digits, radix) = if let Some(digits) = s.strip_prefix("0x") {
+        (digits, 16)
+    } else if let Some(digits) = s.strip_prefix("0o") {
+        (digits, 8)
+    } else if let Some(digits) = s.strip_prefix("0b") {
+        (digits, 2)
+    } else {
+        (s, 10)
+    };
+
+    let digits = digits.replace('_', "");
+    let value = u64::from_str_radix(&digits, radix)
+        .with_context(|| format!("整数のパースに失敗: '{s}'"))?;
+
+    T::try_from(value).map_err(|_| anyhow!("整数が範囲外: '{s}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_uint() {
+        assert_eq!(parse_uint::<u16>("1234").unwrap(), 1234);
+        assert_eq!(parse_uint::<u16>("0x7FFF").unwrap(), 0x7FFF);
+        assert_eq!(parse_uint::<u8>("0o17").unwrap(), 0o17);
+        assert_eq!(parse_uint::<u8>("0b1010").unwrap(), 0b1010);
+        assert_eq!(parse_uint::<u32>("0x0001_0000").unwrap(), 0x1_0000);
+
+        assert!(parse_uint::<u8>("256").is_err());
+        assert!(parse_uint::<u8>("").is_err());
+        assert!(parse_uint::<u8>("0x").is_err());
+        assert!(parse_uint::<u8>("-1").is_err());
+        assert!(parse_uint::<u8>("12a").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use std::num::NonZeroU8;
+
+use crate::array::array_newtype;
+use crate::bitop;
+use crate::bounded::impl_bounded_nonzero_uint;
+
+/// 駒種。
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Piece(NonZeroU8);
+
+impl_bounded_nonzero_uint!(Piece, u8, 1, 5);
+
+array_newtype!(PieceArray, Piece);
+
+/// 駒種の集合。
+#[repr(transparent)]
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct PieceSet(u8);
+
+impl PieceSet {
+    /// 空集合を返す。
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// 全ての駒種を含む集合を返す。
+    pub const fn full() -> Self {
+        Self((1 << Piece::NUM) - 1)
+    }
+
+    /// 指定した駒種のみを含む集合を返す。
+    pub const fn single(piece: Piece) -> Self {
+        Self(1 << piece.to_index())
+    }
+
+    /// 指定した駒種を含むかどうかを返す。
+    pub const fn contains(self, piece: Piece) -> bool {
+        (self.0 & (1 << piece.to_index())) != 0
+    }
+
+    /// 指定した駒種を追加する。
+    pub fn insert(&mut self, piece: Piece) {
+        self.0 |= 1 << piece.to_index();
+    }
+
+    /// 指定した駒種を取り除く。
+    pub fn remove(&mut self, piece: Piece) {
+        self.0 &= !(1 << piece.to_index());
+    }
+
+    /// 空集合かどうかを返す。
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// 要素数を返す。
+    pub const fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// 含まれる駒種を昇順で列挙する。
+    pub fn iter(self) -> impl ExactSizeIterator<Item = Piece> + std::iter::FusedIterator + Clone {
+        bitop::u32_one_indexs(u32::from(self.0))
+            .map(|i| unsafe { Piece::from_index_unchecked(i as usize) })
+    }
+}
+
+impl std::ops::BitAnd for PieceSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl std::ops::BitOr for PieceSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::Not for PieceSet {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0 & Self::full().0)
+    }
+}
+
+impl std::iter::FromIterator<Piece> for PieceSet {
+    fn from_iter<I: IntoIterator<Item = Piece>>(pieces: I) -> Self {
+        let mut this = Self::empty();
+        for piece in pieces {
+            this.insert(piece);
+        }
+        this
+    }
+}
+
+impl std::fmt::Debug for PieceSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(Piece::to_inner))
+            .finish()
+    }
+}
@@ -0,0 +1,126 @@
+//! プレイアウトやテストデータ生成用の擬似乱数生成器関連。
+//!
+//! ゲーム内の乱数生成器 (`GameRng`) とは無関係。
+
+/// 高速な擬似乱数生成器 (xoshiro256**)。
+///
+/// シードを明示的に与えて作るので、同じシードからは常に同じ乱数列が得られる (再現性がある)。
+/// 暗号学的な安全性はない。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FastRng {
+    s: [u64; 4],
+}
+
+impl FastRng {
+    /// シードを与えて乱数生成器を作る。内部状態は splitmix64 でシードから生成する。
+    pub fn new(seed: u64) -> Self {
+        let mut x = seed;
+        let s = std::array::from_fn(|_| splitmix64(&mut x));
+
+        Self { s }
+    }
+
+    /// 64bit の乱数を返す。
+    pub fn next_u64(&mut self) -> u64 {
+        let res = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+
+        res
+    }
+
+    /// 32bit の乱数を返す。
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// `0..n` の一様乱数を返す。`n` は正でなければならない。
+    pub fn gen_below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "gen_below: n は正でなければならない");
+
+        // 剰余の偏りを避けるため、端数の範囲の値は棄却する。
+        let zone = u64::MAX - (u64::MAX - n + 1) % n;
+        loop {
+            let x = self.next_u64();
+            if x <= zone {
+                return x % n;
+            }
+        }
+    }
+
+    /// 確率 `p` で `true` を返す。
+    pub fn gen_bool(&mut self, p: f64) -> bool {
+        // 上位 53bit から [0, 1) の一様乱数を作る。
+        let x = (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64;
+        x < p
+    }
+
+    /// スライスの要素を一様ランダムに選んで返す。空なら `None` を返す。
+    pub fn choose<'a, T>(&mut self, xs: &'a [T]) -> Option<&'a T> {
+        if xs.is_empty() {
+            return None;
+        }
+
+        Some(&xs[self.gen_below(xs.len() as u64) as usize])
+    }
+
+    /// スライスをシャッフルする (Fisher-Yates)。
+    pub fn shuffle<T>(&mut self, xs: &mut [T]) {
+        for i in (1..xs.len()).rev() {
+            let j = self.gen_below(i as u64 + 1) as usize;
+            xs.swap(i, j);
+        }
+    }
+}
+
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9E3779B97F4A7C15);
+
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fast_rng() {
+        // 同じシードからは同じ乱数列が得られる。
+        let xs: Vec<u64> = {
+            let mut rng = FastRng::new(42);
+            (0..10).map(|_| rng.next_u64()).collect()
+        };
+        let ys: Vec<u64> = {
+            let mut rng = FastRng::new(42);
+            (0..10).map(|_| rng.next_u64()).collect()
+        };
+        assert_eq!(xs, ys);
+        assert_ne!(FastRng::new(43).next_u64(), xs[0]);
+
+        let mut rng = FastRng::new(1);
+        let mut counts = [0_u32; 5];
+        for _ in 0..5000 {
+            counts[rng.gen_below(5) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| (800..1200).contains(&count)));
+
+        let mut xs: Vec<u32> = (0..20).collect();
+        rng.shuffle(&mut xs);
+        let mut sorted = xs.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+
+        assert_eq!(rng.choose::<u32>(&[]), None);
+        assert!(!rng.gen_bool(0.0));
+        assert!(rng.gen_bool(1.0));
+    }
+}
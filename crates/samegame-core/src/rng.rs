@@ -0,0 +1,1597 @@
+//! ゲーム内乱数関連。
+
+use anyhow::{anyhow, ensure, Context as _};
+
+use crate::board::Board;
+use crate::bounded::impl_bounded_uint;
+use crate::canonical::CanonicalIndex;
+use crate::parse::parse_uint;
+use crate::piece::{Piece, PieceArray};
+use crate::ruleset::RuleSet;
+use crate::square::{ColArray, RowArray, Square};
+
+/// 全ての盤面生成パラメータについて (生成パラメータ, 盤面, ゲーム内に出現しうるか, 生成後の乱数生成器) を列挙する。
+pub fn enumerate_all_board(
+) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, Board, bool, GameRng)> {
+    RandomBoardParam::all().map(|param| {
+        let (board, legal, rng_after) = param.gen_board();
+        (param, board, legal, rng_after)
+    })
+}
+
+/// ゲーム内に現れうる全ての盤面について (生成パラメータ, 盤面, 生成後の乱数生成器) を列挙する。
+///
+/// 内部では `enumerate_all_board_fast()` を用いる。
+pub fn enumerate_all_legal_board(
+) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, Board, GameRng)> {
+    enumerate_all_board_fast()
+        .filter_map(|(param, board, legal, rng_after)| legal.then_some((param, board, rng_after)))
+}
+
+/// `enumerate_all_board()` と同じものを同じ順序で、より高速に列挙する。
+///
+/// 乱数生成器の出力 (`GameRng::gen()`) はエントロピーに依存しないので、
+/// (内部状態, NMI カウンタ) ごとに 1 回だけ乱数列を生成し、5 通りのエントロピーで使い回す。
+/// (内部状態や NMI カウンタが異なると乱数列は初回の出力から異なるので、それらの間では使い回せない)
+pub fn enumerate_all_board_fast(
+) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, Board, bool, GameRng)> {
+    enumerate_all_board_fast_in(0..=0x7FFF)
+}
+
+/// 乱数生成器の内部状態が `rng_states` の範囲にあるパラメータについて、`enumerate_all_board_fast()` と同様に列挙する。
+pub fn enumerate_all_board_fast_in(
+    rng_states: std::ops::RangeInclusive<u16>,
+) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, Board, bool, GameRng)> {
+    rng_states
+        .flat_map(|rng_state| (0..=u8::MAX).map(move |nmi_counter| (rng_state, nmi_counter)))
+        .flat_map(|(rng_state, nmi_counter)| {
+            let mut rng = GameRng::new(rng_state);
+            let stream = rng.gen_stream(nmi_counter, 40);
+            GameEntropy::all().map(move |entropy| {
+                let param = RandomBoardParam {
+                    rng_state,
+                    nmi_counter,
+                    nmi_timing: 40,
+                    entropy,
+                };
+                let (board, piece_counts) = board_from_stream(&stream, entropy);
+                let legal = RuleSet::GAME.is_legal_piece_counts(&piece_counts);
+                (param, board, legal, rng)
+            })
+        })
+        .fuse()
+}
+
+/// 全ての (乱数生成器の内部状態, NMI カウンタ) について、エントロピー値たちの同値類分割を列挙する。
+///
+/// 内部状態の範囲および NMI 発生タイミングは `RandomBoardParam::all()` と同じ。
+pub fn enumerate_entropy_partitions(
+) -> impl std::iter::FusedIterator<Item = (u16, u8, EntropyPartition)> {
+    (0..=0x7FFF)
+        .flat_map(|rng_state| (0..=u8::MAX).map(move |nmi_counter| (rng_state, nmi_counter)))
+        .map(|(rng_state, nmi_counter)| {
+            let partition = EntropyPartition::new(rng_state, nmi_counter, 40);
+            (rng_state, nmi_counter, partition)
+        })
+        .fuse()
+}
+
+/// 全ての盤面生成パラメータについて、盤面が再生成される (ゲーム内に出現しえない) 割合を正確に数える。
+///
+/// 範囲は `RandomBoardParam::all()` と同じ。全パラメータの盤面を生成するので、リリースビルドでも十数秒かかる。
+/// (結果は 41943040 個中 47943 個、約 0.114%。)
+pub fn regeneration_probability() -> RegenerationReport {
+    regeneration_probability_in(0..=0x7FFF)
+}
+
+/// 乱数生成器の内部状態が `rng_states` の範囲にあるパラメータについて、`regeneration_probability()` と同様に数える。
+pub fn regeneration_probability_in(
+    rng_states: std::ops::RangeInclusive<u16>,
+) -> RegenerationReport {
+    let mut report = RegenerationReport::default();
+
+    for rng_state in rng_states {
+        for nmi_counter in 0..=u8::MAX {
+            for entropy in GameEntropy::all() {
+                let (_, legal) = GameRng::new(rng_state).gen_board(nmi_counter, 40, entropy);
+                for count in [
+                    &mut report.total,
+                    &mut report.by_entropy[entropy.to_index()],
+                    &mut report.by_nmi_counter[usize::from(nmi_counter)],
+                ] {
+                    count.add(legal);
+                }
+            }
+        }
+    }
+
+    report
+}
+
+/// 盤面の再生成の集計。`regeneration_probability()` の戻り値。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RegenerationReport {
+    /// 全体。
+    pub total: RegenerationCount,
+    /// ゲーム内エントロピーごと。
+    pub by_entropy: [RegenerationCount; GameEntropy::NUM],
+    /// NMI カウンタごと。
+    pub by_nmi_counter: [RegenerationCount; 0x100],
+}
+
+impl Default for RegenerationReport {
+    fn default() -> Self {
+        Self {
+            total: RegenerationCount::default(),
+            by_entropy: [RegenerationCount::default(); GameEntropy::NUM],
+            by_nmi_counter: [RegenerationCount::default(); 0x100],
+        }
+    }
+}
+
+/// パラメータ数と、そのうち盤面が再生成されるものの数。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct RegenerationCount {
+    /// パラメータ数。
+    pub params: u64,
+    /// 盤面が再生成されるパラメータ数。
+    pub regenerated: u64,
+}
+
+impl RegenerationCount {
+    /// 盤面が再生成される確率を返す。パラメータ数が 0 なら 0 を返す。
+    pub fn probability(&self) -> f64 {
+        if self.params == 0 {
+            return 0.0;
+        }
+        self.regenerated as f64 / self.params as f64
+    }
+
+    fn add(&mut self, legal: bool) {
+        self.params += 1;
+        if !legal {
+            self.regenerated += 1;
+        }
+    }
+}
+
+/// 2 つの乱数生成器の内部状態 `state_a`, `state_b` から、NMI カウンタ `nmi_counter`,
+/// ゲーム内エントロピー `entropy` の下でそれぞれ `n` 個の駒を生成し、駒の列の食い違いを調べる。
+///
+/// `n == Square::NUM` とすれば (NMI 発生タイミングを無視した) 盤面全体の食い違いとなる。
+/// リセットのタイミングをどの程度正確に合わせれば同じ盤面が得られるか、といった問いの検討に使う。
+pub fn rng_stream_divergence(
+    state_a: u16,
+    state_b: u16,
+    nmi_counter: u8,
+    entropy: GameEntropy,
+    n: usize,
+) -> StreamDivergence {
+    let mut rng_a = GameRng::new(state_a);
+    let mut rng_b = GameRng::new(state_b);
+
+    let mut res = StreamDivergence {
+        len: n,
+        first_diff: None,
+        diff_count: 0,
+    };
+    for i in 0..n {
+        let piece_a = rng_a.gen_piece(nmi_counter, entropy);
+        let piece_b = rng_b.gen_piece(nmi_counter, entropy);
+        if piece_a != piece_b {
+            res.first_diff.get_or_insert(i);
+            res.diff_count += 1;
+        }
+    }
+
+    res
+}
+
+/// 2 つの駒の列の食い違い。`rng_stream_divergence()` の戻り値。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StreamDivergence {
+    /// 比較した駒の個数。
+    pub len: usize,
+    /// 最初に駒が食い違うインデックス (0-based)。全て一致するなら `None`。
+    pub first_diff: Option<usize>,
+    /// 駒が食い違うインデックスの個数。
+    pub diff_count: usize,
+}
+
+impl StreamDivergence {
+    /// 全ての駒が一致するかどうかを返す。
+    pub fn is_identical(&self) -> bool {
+        self.first_diff.is_none()
+    }
+
+    /// 駒が食い違う割合を返す。比較した駒がなければ 0 を返す。
+    pub fn diff_ratio(&self) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.diff_count as f64 / self.len as f64
+    }
+}
+
+/// エントロピー値たちを「生成される盤面が一致する」という関係で分割したもの。
+///
+/// 同じ (乱数生成器の内部状態, NMI カウンタ, NMI 発生タイミング) に対して求める。
+/// 同値類が少ないほど、エントロピー (メニュー操作のタイミング) を正確に合わせる必要性は低い。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct EntropyPartition([u8; GameEntropy::NUM]);
+
+impl EntropyPartition {
+    /// 指定したパラメータで全エントロピー値について盤面を生成し、分割を求める。
+    pub fn new(rng_state: u16, nmi_counter: u8, nmi_timing: usize) -> Self {
+        let boards: Vec<Board> = GameEntropy::all()
+            .map(|entropy| {
+                GameRng::new(rng_state)
+                    .gen_board(nmi_counter, nmi_timing, entropy)
+                    .0
+            })
+            .collect();
+
+        // 各エントロピー値について、同じ盤面を生成する最小のエントロピー値を代表元とする。
+        Self(std::array::from_fn(|i| {
+            boards.iter().position(|board| *board == boards[i]).unwrap() as u8
+        }))
+    }
+
+    /// 指定したエントロピー値が属する同値類の代表元 (最小元) を返す。
+    pub fn representative(&self, entropy: GameEntropy) -> GameEntropy {
+        GameEntropy::from_inner(self.0[entropy.to_index()]).unwrap()
+    }
+
+    /// 指定したエントロピー値が属する同値類の要素数を返す。
+    pub fn class_size(&self, entropy: GameEntropy) -> usize {
+        let repr = self.representative(entropy);
+        GameEntropy::all()
+            .filter(|&other| self.representative(other) == repr)
+            .count()
+    }
+
+    /// 同値類の個数 (生成される相異なる盤面の個数) を返す。
+    pub fn class_count(&self) -> usize {
+        GameEntropy::all()
+            .filter(|&entropy| self.representative(entropy) == entropy)
+            .count()
+    }
+
+    /// 同値類たちを代表元の昇順で返す。各同値類内も昇順。
+    pub fn classes(&self) -> Vec<Vec<GameEntropy>> {
+        GameEntropy::all()
+            .filter(|&entropy| self.representative(entropy) == entropy)
+            .map(|repr| {
+                GameEntropy::all()
+                    .filter(|&entropy| self.representative(entropy) == repr)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for EntropyPartition {
+    /// `{0,1}{2}{3,4}` のような形式で出力する。
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for class in self.classes() {
+            f.write_str("{")?;
+            for (i, entropy) in class.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write!(f, "{entropy}")?;
+            }
+            f.write_str("}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 盤面生成にわずかな影響を与えるゲーム内エントロピー。値域は `0..=4`。
+///
+/// メインループカウンタ `$7F0046` から生成される (式は `(5 * counter) >> 8`)。
+///
+/// 値とメインループカウンタの対応は以下の通り:
+///
+/// | 値   | カウンタ範囲 |
+/// | --   | --           |
+/// | `0`  | `  0..= 51`  |
+/// | `1`  | ` 52..=102`  |
+/// | `2`  | `103..=153`  |
+/// | `3`  | `154..=204`  |
+/// | `4`  | `205..=255`  |
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GameEntropy(u8);
+
+impl_bounded_uint!(GameEntropy, u8, 4);
+
+impl std::str::FromStr for GameEntropy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entropy: u8 = s
+            .parse()
+            .with_context(|| format!("GameEntropy のパースに失敗: '{s}'"))?;
+
+        GameEntropy::from_inner(entropy).ok_or_else(|| anyhow!("GameEntropy の値が無効: {entropy}"))
+    }
+}
+
+impl std::fmt::Display for GameEntropy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// ランダムな盤面を生成するためのパラメータ。
+///
+/// 順序は `RandomBoardParam::all()` の列挙順と一致する。
+///
+/// 不正な値を避けるため、構築には `RandomBoardParam::new()` (または `FromStr`) を用いること。
+/// 各フィールドは次の破壊的変更のリリースで非公開とする予定なので、読み出しにはアクセサを用いること。
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RandomBoardParam {
+    /// 乱数生成器の内部状態。
+    pub rng_state: u16,
+    /// NMI カウンタ。
+    pub nmi_counter: u8,
+    /// 盤面生成中の NMI 発生タイミング (駒をこの個数だけ生成した直後に NMI カウンタをインクリメントする)。
+    /// ゲーム内では通常 40 になるようだ (盤面再生成時はまた別)。
+    pub nmi_timing: usize,
+    /// ゲーム内エントロピー。
+    pub entropy: GameEntropy,
+}
+
+impl RandomBoardParam {
+    /// 各値を検証してパラメータを作る。
+    ///
+    /// `nmi_timing` が `Square::NUM` を超える場合、エラーを返す。
+    pub fn new(
+        rng_state: u16,
+        nmi_counter: u8,
+        nmi_timing: usize,
+        entropy: GameEntropy,
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            nmi_timing <= Square::NUM,
+            "nmi_timing は {} 以下でなければならない: {nmi_timing}",
+            Square::NUM
+        );
+
+        Ok(Self {
+            rng_state,
+            nmi_counter,
+            nmi_timing,
+            entropy,
+        })
+    }
+
+    /// 乱数生成器の内部状態を返す。
+    pub fn rng_state(&self) -> u16 {
+        self.rng_state
+    }
+
+    /// NMI カウンタを返す。
+    pub fn nmi_counter(&self) -> u8 {
+        self.nmi_counter
+    }
+
+    /// 盤面生成中の NMI 発生タイミングを返す。
+    pub fn nmi_timing(&self) -> usize {
+        self.nmi_timing
+    }
+
+    /// ゲーム内エントロピーを返す。
+    pub fn entropy(&self) -> GameEntropy {
+        self.entropy
+    }
+
+    /// このパラメータで盤面を生成する。
+    /// (盤面, ゲーム内に出現しうるか, 生成後の乱数生成器) を返す。
+    pub fn gen_board(&self) -> (Board, bool, GameRng) {
+        self.gen_board_with_rules(&RuleSet::GAME)
+    }
+
+    /// ルール `rules` の下でこのパラメータで盤面を生成する。戻り値は `gen_board()` と同様。
+    pub fn gen_board_with_rules(&self, rules: &RuleSet) -> (Board, bool, GameRng) {
+        let mut rng = GameRng::new(self.rng_state);
+        let (board, legal) =
+            rng.gen_board_with_rules(self.nmi_counter, self.nmi_timing, self.entropy, rules);
+
+        (board, legal, rng)
+    }
+
+    /// このパラメータで有効な盤面を生成する。
+    /// 盤面がゲーム内に現れない場合、`None` を返す。
+    pub fn gen_legal_board(&self) -> Option<(Board, GameRng)> {
+        let (board, legal, rng_after) = self.gen_board();
+
+        legal.then_some((board, rng_after))
+    }
+
+    /// このパラメータでゲーム内の盤面再生成ループを再現して盤面を生成する (`GameRng::gen_board_with_regen()`)。
+    /// (ゲーム内に出現する盤面, 再生成の回数, 生成後の乱数生成器) を返す。
+    pub fn gen_board_with_regen(&self) -> (Board, u32, GameRng) {
+        let mut rng = GameRng::new(self.rng_state);
+        let (board, regen_count) =
+            rng.gen_board_with_regen(self.nmi_counter, self.nmi_timing, self.entropy);
+
+        (board, regen_count, rng)
+    }
+
+    /// このパラメータの識別子を返す。`SeedId` で表せないパラメータなら `None` を返す。
+    pub fn seed_id(&self) -> Option<SeedId> {
+        SeedId::try_from(self).ok()
+    }
+
+    /// 全パラメータを昇順で列挙する。
+    ///
+    /// 乱数生成器の内部状態の bit15 は実質無意味なので、範囲は `0..=0x7FFF` としている。
+    /// NMI 発生タイミングは 40 固定としている。
+    pub fn all() -> impl std::iter::FusedIterator<Item = Self> + Clone {
+        Self::all_in(0..=0x7FFF)
+    }
+
+    /// 乱数生成器の内部状態が `rng_states` の範囲にあるパラメータについて、`all()` と同様に列挙する。
+    pub fn all_in(
+        rng_states: std::ops::RangeInclusive<u16>,
+    ) -> impl std::iter::FusedIterator<Item = Self> + Clone {
+        rng_states
+            .flat_map(|rng_state| {
+                (0..=u8::MAX).flat_map(move |nmi_counter| {
+                    GameEntropy::all().map(move |entropy| Self {
+                        rng_state,
+                        nmi_counter,
+                        nmi_timing: 40,
+                        entropy,
+                    })
+                })
+            })
+            .fuse()
+    }
+
+    /// 生成される盤面 (駒の列) が同一のパラメータたちを代表元にまとめ、(代表元, 同値なパラメータ数) を昇順で列挙する。
+    ///
+    /// 範囲は `all()` と同じで、相異なる盤面がちょうど 1 回ずつ現れ、同値なパラメータ数の総和は `all()` の要素数と一致する。
+    /// 出現頻度で重み付けした統計と、重複を除いた全面探索とで同じ列挙を共有するためのもの。
+    ///
+    /// 内部状態や NMI カウンタが異なるパラメータ間の一致もまとめる (`CanonicalIndex` を参照)。
+    /// 最初に全パラメータの盤面を生成して索引を作るので、列挙開始までにリリースビルドでも 20 秒ほどかかる。
+    pub fn all_canonical() -> impl std::iter::FusedIterator<Item = (Self, u32)> + Clone {
+        Self::all_canonical_in(0..=0x7FFF)
+    }
+
+    /// 乱数生成器の内部状態が `rng_states` の範囲にあるパラメータについて、`all_canonical()` と同様に列挙する。
+    ///
+    /// 重複は範囲内でのみまとめる。範囲をまたいで一貫した代表元が必要なら、全範囲の `CanonicalIndex` を用いること。
+    pub fn all_canonical_in(
+        rng_states: std::ops::RangeInclusive<u16>,
+    ) -> impl std::iter::FusedIterator<Item = (Self, u32)> + Clone {
+        CanonicalIndex::new(rng_states).params()
+    }
+
+    /// 盤面 `board` を生成するパラメータを全て求め、昇順に返す。
+    ///
+    /// 範囲は `all()` と同じ (NMI 発生タイミングは 40 固定)。ゲーム内に現れうるか (再生成判定) は問わない。
+    /// `board` は生成直後の盤面 (全マスに駒がある) でなければならず、そうでなければ空の `Vec` を返す。
+    ///
+    /// 全パラメータを走査する代わりに、最初の駒 (左下のマス) と整合する「最初の駒を生成した直後の内部状態」を
+    /// 列挙し、そこから生成前の内部状態を逆算する。最初の駒で乱数出力の候補が約 1/5 に絞られ、
+    /// 逆算できる内部状態はさらにその半分なので、調べる候補は全パラメータの約 1/5 で済む。
+    pub fn find_for_board(board: &Board) -> Vec<Self> {
+        const NMI_TIMING: usize = 40;
+
+        // ゲーム内のマス順 (`gen_stream()` の順) に並べる。
+        let Some(pieces) = (0..Square::NUM)
+            .map(|idx| board.get(Square::from_game_index(idx).unwrap()))
+            .collect::<Option<Vec<Piece>>>()
+        else {
+            return Vec::new();
+        };
+
+        let mut res = Vec::new();
+        for nmi_counter in 0..=u8::MAX {
+            for entropy in GameEntropy::all() {
+                let outputs = (0..=u8::MAX).filter(|&r| piece_from_output(r, entropy) == pieces[0]);
+                for r in outputs {
+                    for hi in 0..=u8::MAX {
+                        // 出力は内部状態の上位バイトと下位バイトの xor。
+                        let mut rng = GameRng::new(u16::from_be_bytes([hi, r ^ hi]));
+                        // bit15 のみが異なる候補は同じ盤面を生成するので、bit15 が 0 のものだけ調べる。
+                        let Some(rng_state) = rng.prev(nmi_counter).next().map(GameRng::state)
+                        else {
+                            continue;
+                        };
+
+                        let found = pieces.iter().enumerate().skip(1).all(|(i, &piece)| {
+                            let nmi_counter = if i < NMI_TIMING {
+                                nmi_counter
+                            } else {
+                                nmi_counter.wrapping_add(1)
+                            };
+                            rng.gen_piece(nmi_counter, entropy) == piece
+                        });
+                        if found {
+                            res.push(Self {
+                                rng_state,
+                                nmi_counter,
+                                nmi_timing: NMI_TIMING,
+                                entropy,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        res.sort_unstable();
+        res
+    }
+
+    /// 全パラメータ (`all()`) のうち範囲 `range` に含まれるものを昇順で列挙する。
+    ///
+    /// 列挙は範囲の下限から始まるので、`all()` を読み飛ばすより速い。
+    /// 中断した列挙を再開するには、最後に処理したパラメータを `Bound::Excluded` で下限に与えればよい。
+    pub fn range(
+        range: impl std::ops::RangeBounds<Self>,
+    ) -> impl std::iter::FusedIterator<Item = Self> + Clone {
+        use std::ops::Bound;
+
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+        let rng_state_min = match &start {
+            Bound::Included(param) | Bound::Excluded(param) => param.rng_state,
+            Bound::Unbounded => 0,
+        };
+        let rng_state_max = match &end {
+            Bound::Included(param) | Bound::Excluded(param) => param.rng_state.min(0x7FFF),
+            Bound::Unbounded => 0x7FFF,
+        };
+
+        Self::all_in(rng_state_min..=rng_state_max)
+            .skip_while(move |param| match &start {
+                Bound::Included(start) => param < start,
+                Bound::Excluded(start) => param <= start,
+                Bound::Unbounded => false,
+            })
+            .take_while(move |param| match &end {
+                Bound::Included(end) => param <= end,
+                Bound::Excluded(end) => param < end,
+                Bound::Unbounded => true,
+            })
+            .fuse()
+    }
+}
+
+impl std::str::FromStr for RandomBoardParam {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split(',').collect();
+        ensure!(
+            fields.len() == 4,
+            "RandomBoardParam 文字列はカンマ区切りの 4 フィールドでなければならない"
+        );
+
+        let rng_state: u16 = parse_uint(fields[0])
+            .with_context(|| format!("rng_state のパースに失敗: '{}'", fields[0]))?;
+        let nmi_counter: u8 = parse_uint(fields[1])
+            .with_context(|| format!("nmi_counter のパースに失敗: '{}'", fields[1]))?;
+        let nmi_timing: usize = fields[2]
+            .parse()
+            .with_context(|| format!("nmi_timing のパースに失敗: '{}'", fields[2]))?;
+        let entropy: GameEntropy = fields[3]
+            .parse()
+            .with_context(|| format!("entropy のパースに失敗: '{}'", fields[3]))?;
+
+        Self::new(rng_state, nmi_counter, nmi_timing, entropy)
+    }
+}
+
+impl std::fmt::Display for RandomBoardParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "0x{:04X},0x{:02X},{},{}",
+            self.rng_state, self.nmi_counter, self.nmi_timing, self.entropy
+        )
+    }
+}
+
+/// `RandomBoardParam` を 32bit 整数に詰めた識別子。
+///
+/// ビット配置は上位から `rng_state` (15bit), `nmi_counter` (8bit), `nmi_timing` (6bit), `entropy` (3bit)。
+/// よって識別子の大小関係は `RandomBoardParam` の順序 (列挙順) と一致する。
+///
+/// 文字列形式は 8 桁の 16 進数 (`0x` 付き)。
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SeedId(u32);
+
+impl SeedId {
+    const SHIFT_RNG_STATE: u32 = 17;
+    const SHIFT_NMI_COUNTER: u32 = 9;
+    const SHIFT_NMI_TIMING: u32 = 3;
+
+    /// 内部値から識別子を作る。内部値が有効な `RandomBoardParam` を表さない場合、`None` を返す。
+    pub fn from_inner(inner: u32) -> Option<Self> {
+        let this = Self(inner);
+        (this.nmi_timing() <= Square::NUM
+            && GameEntropy::from_inner(this.entropy_inner()).is_some())
+        .then_some(this)
+    }
+
+    /// 内部値を返す。
+    pub const fn to_inner(self) -> u32 {
+        self.0
+    }
+
+    /// 盤面生成パラメータを返す。
+    pub fn to_param(self) -> RandomBoardParam {
+        RandomBoardParam {
+            rng_state: (self.0 >> Self::SHIFT_RNG_STATE) as u16,
+            nmi_counter: (self.0 >> Self::SHIFT_NMI_COUNTER) as u8,
+            nmi_timing: self.nmi_timing(),
+            entropy: GameEntropy::from_inner(self.entropy_inner()).unwrap(),
+        }
+    }
+
+    fn nmi_timing(self) -> usize {
+        ((self.0 >> Self::SHIFT_NMI_TIMING) & 0x3F) as usize
+    }
+
+    fn entropy_inner(self) -> u8 {
+        (self.0 & 0x7) as u8
+    }
+}
+
+impl TryFrom<&RandomBoardParam> for SeedId {
+    type Error = anyhow::Error;
+
+    /// `rng_state` が `0..=0x7FFF`, `nmi_timing` が `0..=48` の範囲になければ失敗する。
+    fn try_from(param: &RandomBoardParam) -> Result<Self, Self::Error> {
+        ensure!(
+            param.rng_state <= 0x7FFF,
+            "SeedId で表せない rng_state: 0x{:04X}",
+            param.rng_state
+        );
+        ensure!(
+            param.nmi_timing <= Square::NUM,
+            "SeedId で表せない nmi_timing: {}",
+            param.nmi_timing
+        );
+
+        Ok(Self(
+            (u32::from(param.rng_state) << Self::SHIFT_RNG_STATE)
+                | (u32::from(param.nmi_counter) << Self::SHIFT_NMI_COUNTER)
+                | ((param.nmi_timing as u32) << Self::SHIFT_NMI_TIMING)
+                | u32::from(param.entropy.to_inner()),
+        ))
+    }
+}
+
+impl TryFrom<RandomBoardParam> for SeedId {
+    type Error = anyhow::Error;
+
+    fn try_from(param: RandomBoardParam) -> Result<Self, Self::Error> {
+        Self::try_from(&param)
+    }
+}
+
+impl From<SeedId> for RandomBoardParam {
+    fn from(id: SeedId) -> Self {
+        id.to_param()
+    }
+}
+
+impl std::str::FromStr for SeedId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner: u32 = parse_uint(s).with_context(|| format!("SeedId のパースに失敗: '{s}'"))?;
+
+        Self::from_inner(inner).ok_or_else(|| anyhow!("SeedId の値が無効: 0x{inner:08X}"))
+    }
+}
+
+impl std::fmt::Display for SeedId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{:08X}", self.0)
+    }
+}
+
+/// 全パラメータ (`RandomBoardParam::all()`) を乱数生成器の内部状態で区切った区間 (チャンク)。
+///
+/// 長時間の全探索を複数マシンに分割したり、チャンク単位で再開したりするのに用いる。
+/// 文字列形式は内部状態の閉区間 `0x0000..=0x0FFF` (両端は 10 進でも可)。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ParamChunk {
+    start: u16,
+    end: u16,
+}
+
+impl ParamChunk {
+    /// 内部状態の最大値。
+    const RNG_STATE_MAX: u16 = 0x7FFF;
+
+    /// 全パラメータからなるチャンク。
+    pub const ALL: Self = Self {
+        start: 0,
+        end: Self::RNG_STATE_MAX,
+    };
+
+    /// 内部状態が `start..=end` のチャンクを作る。
+    ///
+    /// `start <= end <= 0x7FFF` でなければエラーを返す。
+    pub fn new(start: u16, end: u16) -> anyhow::Result<Self> {
+        ensure!(
+            start <= end && end <= Self::RNG_STATE_MAX,
+            "チャンクの範囲が無効: 0x{start:04X}..=0x{end:04X}"
+        );
+
+        Ok(Self { start, end })
+    }
+
+    /// 全パラメータを内部状態 `block_size` 個ずつのチャンクに区切り、昇順に列挙する (最後のチャンクは短くなりうる)。
+    ///
+    /// `block_size` は 1 以上でなければならない。
+    pub fn blocks(block_size: u16) -> impl std::iter::FusedIterator<Item = Self> + Clone {
+        assert!(block_size >= 1, "ブロックサイズは 1 以上でなければならない");
+
+        (0..=Self::RNG_STATE_MAX)
+            .step_by(usize::from(block_size))
+            .map(move |start| Self {
+                start,
+                end: start
+                    .saturating_add(block_size - 1)
+                    .min(Self::RNG_STATE_MAX),
+            })
+            .fuse()
+    }
+
+    /// 全パラメータをほぼ等しい大きさの `chunk_count` 個のチャンクに分割し、昇順に列挙する。
+    ///
+    /// `chunk_count` は `1..=0x8000` でなければならない。
+    pub fn split(chunk_count: usize) -> impl std::iter::FusedIterator<Item = Self> + Clone {
+        let total = usize::from(Self::RNG_STATE_MAX) + 1;
+        assert!(
+            (1..=total).contains(&chunk_count),
+            "チャンク数が無効: {chunk_count}"
+        );
+
+        (0..chunk_count)
+            .map(move |i| Self {
+                start: (total * i / chunk_count) as u16,
+                end: (total * (i + 1) / chunk_count - 1) as u16,
+            })
+            .fuse()
+    }
+
+    /// 内部状態の範囲を返す。
+    pub fn rng_states(&self) -> std::ops::RangeInclusive<u16> {
+        self.start..=self.end
+    }
+
+    /// パラメータ `param` がこのチャンクに含まれるかどうかを返す。
+    pub fn contains(&self, param: &RandomBoardParam) -> bool {
+        self.rng_states().contains(&param.rng_state)
+    }
+
+    /// チャンク内のパラメータを昇順で列挙する (`RandomBoardParam::all_in()`)。
+    pub fn params(&self) -> impl std::iter::FusedIterator<Item = RandomBoardParam> + Clone {
+        RandomBoardParam::all_in(self.rng_states())
+    }
+
+    /// チャンク内の代表元を (代表元, 同値なパラメータ数) の形で昇順に列挙する。
+    ///
+    /// 代表元と同値なパラメータ数は全範囲 (`RandomBoardParam::all_canonical()`) と同じなので、
+    /// 全チャンクを合わせると相異なる盤面がちょうど 1 回ずつ現れる。
+    /// 全範囲の `CanonicalIndex` を作るので、列挙開始までにリリースビルドでも 20 秒ほどかかる。
+    /// 複数のチャンクを処理するなら、索引を 1 度だけ作る `params_canonical_with()` を用いるとよい。
+    pub fn params_canonical(
+        &self,
+    ) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, u32)> + Clone {
+        self.params_canonical_with(&CanonicalIndex::new(Self::ALL.rng_states()))
+    }
+
+    /// 索引 `index` を用いて、チャンク内の代表元を `params_canonical()` と同様に列挙する。
+    ///
+    /// 同値なパラメータ数は索引の対象全体で数える。チャンクのうち索引の対象外の部分は列挙しない。
+    pub fn params_canonical_with(
+        &self,
+        index: &CanonicalIndex,
+    ) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, u32)> + Clone {
+        index.params_in(self.rng_states())
+    }
+
+    /// チャンク内のパラメータのうち `last` より後のものを昇順で列挙する。`last` まで処理済みのチャンクの再開用。
+    pub fn params_after(
+        &self,
+        last: &RandomBoardParam,
+    ) -> impl std::iter::FusedIterator<Item = RandomBoardParam> + Clone {
+        use std::ops::Bound;
+
+        let first = self.params().next().unwrap();
+        let start = if *last < first {
+            Bound::Included(first)
+        } else {
+            Bound::Excluded(last.clone())
+        };
+        let end = self.end;
+
+        RandomBoardParam::range((start, Bound::Unbounded))
+            .take_while(move |param| param.rng_state <= end)
+            .fuse()
+    }
+}
+
+impl std::str::FromStr for ParamChunk {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once("..=")
+            .with_context(|| format!("チャンク文字列は 'start..=end' でなければならない: '{s}'"))?;
+        let start: u16 =
+            parse_uint(start).with_context(|| format!("start のパースに失敗: '{start}'"))?;
+        let end: u16 = parse_uint(end).with_context(|| format!("end のパースに失敗: '{end}'"))?;
+
+        Self::new(start, end)
+    }
+}
+
+impl std::fmt::Display for ParamChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{:04X}..=0x{:04X}", self.start, self.end)
+    }
+}
+
+/// ゲーム内の乱数生成器。
+///
+/// 16bit シフトレジスタだが、NMI カウンタ `$7F0F52` およびゲーム内エントロピーの影響を受ける。
+///
+/// NOTE: 内部状態の最上位ビット (bit15) は意味を持たない。
+/// `gen()` の更新式を見ての通り、bit15 は単に捨てられている。
+#[repr(transparent)]
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct GameRng(u16);
+
+impl GameRng {
+    /// 盤面再生成時の NMI 発生タイミング (`gen_board()` の `nmi_timing`)。実機で確認した値。
+    pub const NMI_TIMING_REGEN: usize = 46;
+
+    /// 内部状態を与えて乱数生成器を作る。
+    pub const fn new(state: u16) -> Self {
+        Self(state)
+    }
+
+    /// 内部状態を返す。
+    pub const fn state(self) -> u16 {
+        self.0
+    }
+
+    /// 内部状態を更新し、`0..=0xFF` の乱数を返す。
+    /// NMI カウンタの影響を受ける。
+    pub fn gen(&mut self, nmi_counter: u8) -> u8 {
+        let bit = ((self.0 >> 14) ^ self.0) & 1;
+
+        self.0 = self.0 ^ ((self.0 << 8) | u16::from(nmi_counter));
+        self.0 = (self.0 << 1) | bit;
+
+        (self.0 ^ (self.0 >> 8)) as u8
+    }
+
+    /// `gen()` の逆操作。NMI カウンタ `nmi_counter` の下で `gen()` を呼ぶと現在の内部状態になる、
+    /// 呼ぶ前の内部状態の乱数生成器たちを昇順に列挙する。
+    ///
+    /// 呼ぶ前の内部状態の bit15 は捨てられるので、候補は bit15 のみが異なる 2 つか、存在しないかのどちらか。
+    pub fn prev(self, nmi_counter: u8) -> impl std::iter::FusedIterator<Item = Self> {
+        // gen() の更新式 t = s ^ ((s << 8) | nmi_counter), state = (t << 1) | bit を逆に辿る。
+        // t の bit15 は捨てられているので、s の bit15 は決まらない。
+        let t = self.0 >> 1;
+        let lo = (t as u8) ^ nmi_counter;
+        let hi = ((t >> 8) as u8 ^ lo) & 0x7F;
+        let prev = u16::from_be_bytes([hi, lo]);
+
+        let bit = ((prev >> 14) ^ prev) & 1;
+        (bit == self.0 & 1)
+            .then_some(prev)
+            .into_iter()
+            .flat_map(|prev| [Self(prev), Self(prev | 0x8000)])
+    }
+
+    /// 乱数列の観測値 `observations` (NMI カウンタ, `gen()` の出力) の列を順に生成する内部状態を全て求め、昇順に返す。
+    ///
+    /// 内部状態の bit15 は意味を持たないので、bit15 が 0 のもののみを返す。
+    /// `observations` が空なら `0..=0x7FFF` の全内部状態を返す。
+    ///
+    /// 最初の出力と整合する「最初の `gen()` 直後の内部状態」(256 通り) から `prev()` で逆算し、
+    /// 残りの観測値と照合する。
+    pub fn reconstruct(observations: &[(u8, u8)]) -> Vec<Self> {
+        let Some(&(nmi_counter, output)) = observations.first() else {
+            return (0..=0x7FFF).map(Self).collect();
+        };
+
+        let mut res: Vec<Self> = (0..=u8::MAX)
+            .filter_map(|hi| {
+                // 出力は内部状態の上位バイトと下位バイトの xor。
+                let after = Self(u16::from_be_bytes([hi, output ^ hi]));
+                after.prev(nmi_counter).next()
+            })
+            .filter(|&rng| {
+                let mut rng = rng;
+                observations
+                    .iter()
+                    .all(|&(nmi_counter, output)| rng.gen(nmi_counter) == output)
+            })
+            .collect();
+
+        res.sort_unstable_by_key(|rng| rng.0);
+        res
+    }
+
+    /// ランダムな駒を生成する。
+    /// NMI カウンタおよびゲーム内エントロピーの影響を受ける。
+    pub fn gen_piece(&mut self, nmi_counter: u8, entropy: GameEntropy) -> Piece {
+        piece_from_output(self.gen(nmi_counter), entropy)
+    }
+
+    /// ランダムな盤面を生成する。
+    /// NMI カウンタ、本体 CPU サイクル、ゲーム内エントロピーの影響を受ける。
+    ///
+    /// 戻り値の `bool` は、この盤面がゲーム中に出現しうるなら `true`,
+    /// 再生成判定に引っかかって出現しえないなら `false` となる。
+    ///
+    /// `nmi_timing` は、駒を何個生成した後に NMI カウンタをインクリメントするかのパラメータ。
+    /// `Square::NUM` 以下でなければならない (さもなくば panic する)。
+    /// (ゲーム内では盤面生成中に NMI が発生して NMI カウンタがインクリメントされる。
+    /// 通常は駒が 40 個生成された直後に NMI が発生するようだが、
+    /// 盤面再生成時はタイミングが異なる (46 個生成直後の NMI 発生を確認している)。
+    /// 再生成ループ全体は `gen_board_with_regen()` で再現できる。
+    pub fn gen_board(
+        &mut self,
+        nmi_counter: u8,
+        nmi_timing: usize,
+        entropy: GameEntropy,
+    ) -> (Board, bool) {
+        self.gen_board_with_rules(nmi_counter, nmi_timing, entropy, &RuleSet::GAME)
+    }
+
+    /// ルール `rules` の下でランダムな盤面を生成する。
+    /// 戻り値の `bool` は `rules` の再生成判定に引っかからないなら `true` となる。その他は `gen_board()` と同様。
+    pub fn gen_board_with_rules(
+        &mut self,
+        nmi_counter: u8,
+        nmi_timing: usize,
+        entropy: GameEntropy,
+        rules: &RuleSet,
+    ) -> (Board, bool) {
+        // 公開 API の引数なので、unchecked ではなく通常の assert とする。
+        assert!(
+            nmi_timing <= Square::NUM,
+            "nmi_timing は {} 以下でなければならない: {nmi_timing}",
+            Square::NUM
+        );
+
+        let stream = self.gen_stream(nmi_counter, nmi_timing);
+        let (board, piece_counts) = board_from_stream(&stream, entropy);
+
+        // ゲーム内では同種駒の個数が (マス数) / 2 以上の場合、盤面が再生成される。
+        let legal = rules.is_legal_piece_counts(&piece_counts);
+
+        (board, legal)
+    }
+
+    /// ゲーム内の盤面再生成ループを再現してランダムな盤面を生成する。
+    /// (ゲーム内に出現する盤面, 再生成の回数) を返す。
+    ///
+    /// 最初の盤面は `gen_board()` と同様に生成し、再生成判定に引っかかる限り盤面を生成し直す。
+    /// 再生成時の NMI 発生タイミングは `NMI_TIMING_REGEN` とする。
+    /// NMI カウンタは盤面生成中の NMI でのみインクリメントされ、エントロピーは変化しないものとする。
+    pub fn gen_board_with_regen(
+        &mut self,
+        nmi_counter: u8,
+        nmi_timing: usize,
+        entropy: GameEntropy,
+    ) -> (Board, u32) {
+        let (mut board, mut legal) = self.gen_board(nmi_counter, nmi_timing, entropy);
+        let mut nmi_counter = nmi_counter_after(nmi_counter, nmi_timing);
+
+        let mut regen_count = 0;
+        while !legal {
+            (board, legal) = self.gen_board(nmi_counter, Self::NMI_TIMING_REGEN, entropy);
+            nmi_counter = nmi_counter_after(nmi_counter, Self::NMI_TIMING_REGEN);
+            regen_count += 1;
+        }
+
+        (board, regen_count)
+    }
+}
+
+impl GameRng {
+    /// 盤面生成に使う乱数列 (`gen()` の出力をゲーム内のマス順 (下から上の row-major) に並べたもの) を生成する。
+    ///
+    /// `nmi_timing` は `gen_board()` と同様。
+    fn gen_stream(&mut self, nmi_counter: u8, nmi_timing: usize) -> [u8; Square::NUM] {
+        std::array::from_fn(|i| {
+            let nmi_counter = if i < nmi_timing {
+                nmi_counter
+            } else {
+                nmi_counter.wrapping_add(1)
+            };
+            self.gen(nmi_counter)
+        })
+    }
+}
+
+/// NMI 発生タイミング `nmi_timing` で盤面を生成した後の NMI カウンタを返す。
+fn nmi_counter_after(nmi_counter: u8, nmi_timing: usize) -> u8 {
+    if nmi_timing < Square::NUM {
+        nmi_counter.wrapping_add(1)
+    } else {
+        nmi_counter
+    }
+}
+
+/// `GameRng::gen()` の出力 `r` とエントロピー `entropy` から駒を得る。
+fn piece_from_output(r: u8, entropy: GameEntropy) -> Piece {
+    // 0..5 の乱数を発生。
+    let r = ((5 * u32::from(r) + u32::from(entropy.to_inner())) >> 8) as u8;
+
+    unsafe { Piece::from_inner_unchecked(1 + r) }
+}
+
+/// 乱数列 `stream` (`GameRng::gen_stream()` を参照) とエントロピー `entropy` から盤面を得る。
+/// (盤面, 各駒種の個数) を返す。
+///
+/// 再生成判定のために盤面から駒種ごとの個数を数え直すと盤面生成よりも重いので、生成時に数えておく。
+fn board_from_stream(stream: &[u8; Square::NUM], entropy: GameEntropy) -> (Board, PieceArray<u8>) {
+    let mut piece_counts = PieceArray::<u8>::default();
+    let arrays = ColArray::from_fn(|col| {
+        RowArray::from_fn(|row| {
+            let piece = piece_from_output(stream[Square::new(col, row).to_game_index()], entropy);
+            piece_counts[piece] += 1;
+            piece
+        })
+    });
+
+    (Board::from_piece_arrays(&arrays), piece_counts)
+}
+
+impl GameRng {
+    /// 実機やエミュレータで記録したトレース `trace` を再現できるかを検証する。
+    ///
+    /// 各エントリについて、内部状態 `state` の乱数生成器に NMI カウンタ `nmi_counter` を与えて `gen()` を呼び、
+    /// 出力が `output` と一致するかを調べる。最初に一致しなかったエントリを `Err` で返す。
+    ///
+    /// `gen()` の実装を最適化 (バッチ化、SIMD 化など) する際の回帰防止に使う。
+    pub fn verify_trace(trace: &[RngTraceEntry]) -> Result<(), RngTraceMismatch> {
+        for (index, entry) in trace.iter().enumerate() {
+            let actual = Self::new(entry.state).gen(entry.nmi_counter);
+            if actual != entry.output {
+                return Err(RngTraceMismatch {
+                    index,
+                    entry: entry.clone(),
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 乱数生成器のトレースの 1 エントリ。
+///
+/// 文字列形式は空白区切りの `state nmi_counter output` (各値は `0x` 接頭辞付きの 16 進数も可)。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RngTraceEntry {
+    /// `gen()` 呼び出し前の内部状態。
+    pub state: u16,
+    /// NMI カウンタ。
+    pub nmi_counter: u8,
+    /// `gen()` の出力。
+    pub output: u8,
+}
+
+impl std::str::FromStr for RngTraceEntry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split_whitespace().collect();
+        ensure!(
+            fields.len() == 3,
+            "トレースのエントリは空白区切りの 3 フィールドでなければならない: '{s}'"
+        );
+
+        Ok(Self {
+            state: parse_uint(fields[0])?,
+            nmi_counter: parse_uint(fields[1])?,
+            output: parse_uint(fields[2])?,
+        })
+    }
+}
+
+impl std::fmt::Display for RngTraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "0x{:04X} 0x{:02X} 0x{:02X}",
+            self.state, self.nmi_counter, self.output
+        )
+    }
+}
+
+/// 乱数生成器のトレースを読み込む。空行および `#` で始まる行は無視する。
+pub fn parse_rng_trace(rdr: impl std::io::BufRead) -> anyhow::Result<Vec<RngTraceEntry>> {
+    let mut trace = Vec::new();
+
+    for (i, line) in rdr.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = line
+            .parse()
+            .with_context(|| format!("トレースの {} 行目のパースに失敗", i + 1))?;
+        trace.push(entry);
+    }
+
+    Ok(trace)
+}
+
+/// `GameRng::verify_trace()` で見つかった不一致。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RngTraceMismatch {
+    /// トレース内の位置。
+    pub index: usize,
+    /// 記録されたエントリ。
+    pub entry: RngTraceEntry,
+    /// `GameRng` の実際の出力。
+    pub actual: u8,
+}
+
+impl std::fmt::Display for RngTraceMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "トレース #{} ({}) の出力が一致しない: 実際の出力 0x{:02X}",
+            self.index, self.entry, self.actual
+        )
+    }
+}
+
+impl std::error::Error for RngTraceMismatch {}
+
+impl std::fmt::Debug for GameRng {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GameRng(0x{:04X}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MaskBoard;
+    use crate::square::{COL_8, ROW_6};
+
+    #[test]
+    fn test_rng_trace() {
+        // testdata/rng/ 以下の全トレースを検証する。
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/rng");
+        let mut count = 0;
+        for dirent in std::fs::read_dir(dir).unwrap() {
+            let path = dirent.unwrap().path();
+            if path.extension() != Some("txt".as_ref()) {
+                continue;
+            }
+            let rdr = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+            let trace = parse_rng_trace(rdr).unwrap();
+            assert!(!trace.is_empty());
+            if let Err(e) = GameRng::verify_trace(&trace) {
+                panic!("{}: {e}", path.display());
+            }
+            count += 1;
+        }
+        assert!(count >= 1);
+
+        let mut trace = parse_rng_trace("# comment\n\n0x1234 0x56 0x88\n".as_bytes()).unwrap();
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].to_string(), "0x1234 0x56 0x88");
+        assert!(GameRng::verify_trace(&trace).is_ok());
+
+        trace[0].output ^= 1;
+        let mismatch = GameRng::verify_trace(&trace).unwrap_err();
+        assert_eq!(mismatch.index, 0);
+        assert_eq!(mismatch.actual, 0x88);
+
+        assert!(parse_rng_trace("0x1234 0x56".as_bytes()).is_err());
+        assert!(parse_rng_trace("0x1234 0x100 0x00".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_rng_prev() {
+        for nmi_counter in [0x00, 0x56, 0xFF] {
+            // 全ての内部状態について、前の内部状態は bit15 のみが異なる 2 つか、存在しないかのどちらか。
+            let mut pred_count = 0;
+            for state in 0..=u16::MAX {
+                let prevs: Vec<GameRng> = GameRng::new(state).prev(nmi_counter).collect();
+                assert!(matches!(prevs.len(), 0 | 2));
+                pred_count += prevs.len();
+                for prev in prevs {
+                    let mut rng = prev;
+                    rng.gen(nmi_counter);
+                    assert_eq!(rng.state(), state);
+                }
+            }
+            assert_eq!(pred_count, 0x10000);
+        }
+
+        let nmi_counters = [0x12, 0x12, 0x12, 0x13];
+        for state in [0x0000, 0x1234, 0x7FFF] {
+            let mut rng = GameRng::new(state);
+            let observations: Vec<(u8, u8)> = nmi_counters
+                .iter()
+                .map(|&nmi_counter| (nmi_counter, rng.gen(nmi_counter)))
+                .collect();
+
+            let found = GameRng::reconstruct(&observations);
+            assert!(found.contains(&GameRng::new(state)));
+            for rng in found {
+                assert!(rng.state() <= 0x7FFF);
+                let mut rng = rng;
+                assert!(observations
+                    .iter()
+                    .all(|&(nmi_counter, output)| rng.gen(nmi_counter) == output));
+            }
+        }
+        assert_eq!(GameRng::reconstruct(&[]).len(), 0x8000);
+    }
+
+    #[test]
+    fn test_entropy_partition() {
+        for rng_state in (0..=0x7FFF).step_by(0x1111) {
+            let partition = EntropyPartition::new(rng_state, 0x12, 40);
+
+            for entropy in GameEntropy::all() {
+                let repr = partition.representative(entropy);
+                assert!(repr <= entropy);
+                let board = |entropy| GameRng::new(rng_state).gen_board(0x12, 40, entropy).0;
+                assert_eq!(board(repr), board(entropy));
+                for other in GameEntropy::all().filter(|&other| other < repr) {
+                    assert_ne!(board(other), board(entropy));
+                }
+            }
+
+            let classes = partition.classes();
+            assert_eq!(classes.len(), partition.class_count());
+            for class in &classes {
+                assert!(class
+                    .iter()
+                    .all(|&entropy| partition.class_size(entropy) == class.len()));
+            }
+            assert_eq!(
+                classes.iter().map(Vec::len).sum::<usize>(),
+                GameEntropy::NUM
+            );
+        }
+
+        let partition = EntropyPartition([0, 0, 2, 2, 4]);
+        assert_eq!(partition.to_string(), "{0,1}{2,3}{4}");
+    }
+
+    #[test]
+    fn test_enumerate_all_board_fast() {
+        let rng_states = 0x1230..=0x1233;
+        let expect: Vec<_> = RandomBoardParam::all()
+            .skip_while(|param| param.rng_state < *rng_states.start())
+            .take_while(|param| param.rng_state <= *rng_states.end())
+            .map(|param| {
+                let (board, legal, rng_after) = param.gen_board();
+                (param, board, legal, rng_after)
+            })
+            .collect();
+        assert_eq!(
+            expect.len(),
+            rng_states.clone().count() * 0x100 * GameEntropy::NUM
+        );
+        assert!(expect.iter().any(|(_, _, legal, _)| !legal));
+
+        let actual: Vec<_> = enumerate_all_board_fast_in(rng_states).collect();
+        assert_eq!(actual, expect);
+
+        assert!(enumerate_all_board_fast()
+            .take(100)
+            .eq(enumerate_all_board().take(100)));
+    }
+
+    #[test]
+    fn test_all_canonical() {
+        let rng_states = 0x1230..=0x1233;
+        let canonical: Vec<_> = RandomBoardParam::all_canonical_in(rng_states.clone()).collect();
+
+        // 同値なパラメータ数の総和は元のパラメータ数と一致する。
+        let param_count = rng_states.clone().count() * 0x100 * GameEntropy::NUM;
+        assert_eq!(
+            canonical.iter().map(|&(_, n)| n as usize).sum::<usize>(),
+            param_count
+        );
+        assert!(canonical.len() < param_count);
+        assert!(canonical.windows(2).all(|w| w[0].0 < w[1].0));
+
+        // 各パラメータの盤面はちょうど 1 つの代表元の盤面と一致し、代表元はそれらの最小のもの。
+        let canonical_boards: std::collections::HashMap<_, _> = canonical
+            .iter()
+            .map(|(param, n)| (param.gen_board().0.to_bytes(), (param.clone(), *n)))
+            .collect();
+        assert_eq!(canonical_boards.len(), canonical.len());
+        let mut counts = std::collections::HashMap::<_, (RandomBoardParam, u32)>::new();
+        for param in RandomBoardParam::all_in(rng_states) {
+            let key = param.gen_board().0.to_bytes();
+            assert!(canonical_boards.contains_key(&key));
+            counts.entry(key).or_insert((param, 0)).1 += 1;
+        }
+        assert_eq!(counts, canonical_boards);
+    }
+
+    #[test]
+    fn test_find_for_board() {
+        for s in ["0x1234,0x56,40,2", "0x7FFF,0xFF,40,4", "0x0000,0x00,40,0"] {
+            let param: RandomBoardParam = s.parse().unwrap();
+            let (board, _, _) = param.gen_board();
+
+            let found = RandomBoardParam::find_for_board(&board);
+            assert!(found.windows(2).all(|w| w[0] < w[1]));
+            assert!(found.iter().all(|p| p.gen_board().0 == board));
+
+            // 同じ (内部状態, NMI カウンタ) からは、エントロピーの同値類がちょうど見つかる。
+            let partition = EntropyPartition::new(param.rng_state, param.nmi_counter, 40);
+            let entropies: Vec<GameEntropy> = found
+                .iter()
+                .filter(|p| (p.rng_state, p.nmi_counter) == (param.rng_state, param.nmi_counter))
+                .map(|p| p.entropy)
+                .collect();
+            let expect: Vec<GameEntropy> = GameEntropy::all()
+                .filter(|&e| partition.representative(e) == partition.representative(param.entropy))
+                .collect();
+            assert_eq!(entropies, expect);
+        }
+
+        // 駒が欠けた盤面は生成されない。
+        let board = "0x1234,0x56,40,2"
+            .parse::<RandomBoardParam>()
+            .unwrap()
+            .gen_board()
+            .0;
+        let board = board.erase(&MaskBoard::single(Square::new(COL_8, ROW_6)));
+        assert!(RandomBoardParam::find_for_board(&board).is_empty());
+    }
+
+    #[test]
+    fn test_param_range_chunk() {
+        use std::ops::{Bound, RangeBounds as _};
+
+        let param = |s: &str| s.parse::<RandomBoardParam>().unwrap();
+        let all: Vec<_> = RandomBoardParam::all_in(0x1233..=0x1235).collect();
+
+        // range() は all() から範囲内のものを取り出したものと一致する。
+        let start = param("0x1233,0xFF,40,3");
+        let end = param("0x1235,0x00,40,2");
+        let cases = [
+            (Bound::Included(start.clone()), Bound::Excluded(end.clone())),
+            (Bound::Excluded(start.clone()), Bound::Included(end.clone())),
+        ];
+        for (lo, hi) in cases {
+            let expect: Vec<_> = all
+                .iter()
+                .filter(|&p| (lo.as_ref(), hi.as_ref()).contains(p))
+                .cloned()
+                .collect();
+            assert_eq!(
+                RandomBoardParam::range((lo.clone(), hi.clone())).collect::<Vec<_>>(),
+                expect
+            );
+        }
+        assert_eq!(
+            RandomBoardParam::range(..).take(10).collect::<Vec<_>>(),
+            RandomBoardParam::all().take(10).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            RandomBoardParam::range(param("0x7FFF,0xFF,40,4")..).count(),
+            1
+        );
+
+        // チャンクは全体を隙間なく覆う。
+        for chunks in [
+            ParamChunk::split(3).collect::<Vec<_>>(),
+            ParamChunk::blocks(0x3000).collect(),
+        ] {
+            assert_eq!(chunks.len(), 3);
+            assert_eq!(*chunks[0].rng_states().start(), 0);
+            assert_eq!(*chunks[2].rng_states().end(), 0x7FFF);
+            assert!(chunks
+                .windows(2)
+                .all(|w| w[0].rng_states().end() + 1 == *w[1].rng_states().start()));
+        }
+        assert_eq!(
+            ParamChunk::split(1).collect::<Vec<_>>(),
+            [ParamChunk::new(0, 0x7FFF).unwrap()]
+        );
+
+        let chunk: ParamChunk = "0x1233..=0x1235".parse().unwrap();
+        assert_eq!(chunk.to_string(), "0x1233..=0x1235");
+        assert_eq!("4659..=4661".parse::<ParamChunk>().unwrap(), chunk);
+        assert!("0x1235..=0x1233".parse::<ParamChunk>().is_err());
+        assert!("0x1233..=0x8000".parse::<ParamChunk>().is_err());
+        assert!("0x1233".parse::<ParamChunk>().is_err());
+
+        assert_eq!(chunk.params().collect::<Vec<_>>(), all);
+        assert!(chunk.contains(&start));
+        assert!(!chunk.contains(&param("0x1236,0x00,40,0")));
+        // 再開時は最後に処理したパラメータの次から列挙する。
+        assert_eq!(
+            chunk.params_after(&start).collect::<Vec<_>>(),
+            all.iter()
+                .filter(|&p| *p > start)
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            chunk
+                .params_after(&param("0x0000,0x00,40,0"))
+                .collect::<Vec<_>>(),
+            all
+        );
+    }
+
+    #[test]
+    fn test_regeneration_probability() {
+        let rng_states = 0x0000..=0x0003;
+        let report = regeneration_probability_in(rng_states.clone());
+
+        let illegal = rng_states
+            .flat_map(|rng_state| {
+                (0..=u8::MAX).flat_map(move |nmi_counter| {
+                    GameEntropy::all().map(move |entropy| {
+                        RandomBoardParam::new(rng_state, nmi_counter, 40, entropy).unwrap()
+                    })
+                })
+            })
+            .filter(|param| param.gen_legal_board().is_none())
+            .count();
+        assert_eq!(report.total.params, 4 * 0x100 * GameEntropy::NUM as u64);
+        assert_eq!(report.total.regenerated, illegal as u64);
+        assert!(report.total.regenerated > 0);
+
+        // 内訳の総和は全体と一致する。
+        for counts in [&report.by_entropy[..], &report.by_nmi_counter[..]] {
+            let params: u64 = counts.iter().map(|count| count.params).sum();
+            let regenerated: u64 = counts.iter().map(|count| count.regenerated).sum();
+            assert_eq!(params, report.total.params);
+            assert_eq!(regenerated, report.total.regenerated);
+        }
+
+        let p = report.total.probability();
+        assert!(0.0 < p && p < 1.0);
+        assert_eq!(RegenerationCount::default().probability(), 0.0);
+    }
+
+    #[test]
+    fn test_gen_board_with_regen() {
+        // 再生成されない盤面はそのまま。
+        let param: RandomBoardParam = "0x1234,0x56,40,2".parse().unwrap();
+        let (board, legal, rng_after) = param.gen_board();
+        assert!(legal);
+        assert_eq!(param.gen_board_with_regen(), (board, 0, rng_after));
+
+        // 再生成される盤面。
+        let param: RandomBoardParam = "0x1233,0x16,40,0".parse().unwrap();
+        assert!(!param.gen_board().1);
+        let (board, regen_count, rng_after) = param.gen_board_with_regen();
+        assert!(regen_count >= 1);
+        assert!(RuleSet::GAME.is_legal_board(&board));
+
+        // 1 回ずつ手で再生成したものと一致する。
+        let mut rng = GameRng::new(param.rng_state());
+        let mut nmi_counter = param.nmi_counter();
+        let (mut board_expect, _) = rng.gen_board(nmi_counter, 40, param.entropy());
+        for _ in 0..regen_count {
+            nmi_counter = nmi_counter.wrapping_add(1);
+            board_expect = rng
+                .gen_board(nmi_counter, GameRng::NMI_TIMING_REGEN, param.entropy())
+                .0;
+        }
+        assert_eq!(board, board_expect);
+        assert_eq!(rng_after, rng);
+    }
+
+    #[test]
+    fn test_rng_stream_divergence() {
+        let entropy = GameEntropy::from_inner(2).unwrap();
+
+        let div = rng_stream_divergence(0x1234, 0x1234, 0x56, entropy, Square::NUM);
+        assert!(div.is_identical());
+        assert_eq!(div.diff_count, 0);
+
+        // bit15 は無意味なので、それのみ異なる内部状態からは同じ駒の列が得られる。
+        assert!(rng_stream_divergence(0x1234, 0x9234, 0x56, entropy, Square::NUM).is_identical());
+
+        let state_b = 0x1235;
+        let div = rng_stream_divergence(0x1234, state_b, 0x56, entropy, Square::NUM);
+        let first_diff = div.first_diff.unwrap();
+        assert!(div.diff_count >= 1);
+
+        // 盤面生成 (NMI なし) の結果と一致するはず。
+        let (board_a, _) = GameRng::new(0x1234).gen_board(0x56, Square::NUM, entropy);
+        let (board_b, _) = GameRng::new(state_b).gen_board(0x56, Square::NUM, entropy);
+        let mask = board_a.xor_mask(&board_b);
+        assert_eq!(mask.square_count() as usize, div.diff_count);
+        assert_eq!(
+            mask.squares().map(Square::to_game_index).min(),
+            Some(first_diff)
+        );
+
+        // 比較する駒がなければ食い違いはない。
+        let div = rng_stream_divergence(0x1234, state_b, 0x56, entropy, 0);
+        assert!(div.is_identical());
+        assert_eq!(div.diff_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_random_board_param_new() {
+        let entropy = GameEntropy::from_inner(2).unwrap();
+
+        let param = RandomBoardParam::new(0x1234, 0x56, 40, entropy).unwrap();
+        assert_eq!(param.rng_state(), 0x1234);
+        assert_eq!(param.nmi_counter(), 0x56);
+        assert_eq!(param.nmi_timing(), 40);
+        assert_eq!(param.entropy(), entropy);
+        assert_eq!(param, "0x1234,0x56,40,2".parse().unwrap());
+
+        assert!(RandomBoardParam::new(0, 0, Square::NUM, entropy).is_ok());
+        assert!(RandomBoardParam::new(0, 0, Square::NUM + 1, entropy).is_err());
+        assert!("0,0,1000,0".parse::<RandomBoardParam>().is_err());
+    }
+
+    #[test]
+    fn test_seed_id() {
+        let params: Vec<RandomBoardParam> = [
+            "0x0000,0x00,0,0",
+            "0x0000,0x00,40,4",
+            "0x0123,0x45,40,2",
+            "0x0123,0x46,40,0",
+            "0x7FFF,0xFF,48,4",
+        ]
+        .into_iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+
+        let ids: Vec<SeedId> = params
+            .iter()
+            .map(|param| param.seed_id().unwrap())
+            .collect();
+
+        for (param, id) in std::iter::zip(&params, &ids) {
+            assert_eq!(&id.to_param(), param);
+            assert_eq!(id.to_string().parse::<SeedId>().unwrap(), *id);
+        }
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(ids[2].to_string(), "0x02468B42");
+
+        assert_eq!(
+            "0x8000,0x00,40,0"
+                .parse::<RandomBoardParam>()
+                .unwrap()
+                .seed_id(),
+            None
+        );
+        // nmi_timing が範囲外のパラメータはそもそも作れない。
+        assert!("0x0000,0x00,49,0".parse::<RandomBoardParam>().is_err());
+        assert!("0x00000005".parse::<SeedId>().is_err());
+        assert!("0x000001F8".parse::<SeedId>().is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! ゲームルールのみを扱う最小限の API (`samegame-rules` クレートの再エクスポート)。
+//!
+//! GUI やボットなど、ソルバーを必要としないフロントエンドへの組み込み向け。
+//! `no_std` 環境では、このクレートではなく `samegame-rules` クレートを直接用いること。
+//!
+//! 盤面は単純な 2 次元配列 `Grid` で表す。`Board::to_grid()`, `Board::from_grid()` で相互変換できる。
+
+pub use samegame_rules::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::action::Action;
+    use crate::board::Board;
+    use crate::rng::RandomBoardParam;
+    use crate::square::{Col, Row, Square};
+
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        // 盤面ライブラリの実装と照合する。
+        for rng_state in (0..=0x7FFF).step_by(0x1000) {
+            let param: RandomBoardParam = format!("{rng_state},0x12,40,2").parse().unwrap();
+            let (mut board, _, _) = param.gen_board();
+
+            loop {
+                let grid = board.to_grid();
+                assert_eq!(is_game_over(&grid), !board.has_action());
+
+                for sq in Square::all() {
+                    let (c, r) = (sq.col().to_index(), sq.row().to_index());
+                    let action = Action::from_board_square(&board, sq).ok();
+                    assert_eq!(is_legal_click(&grid, c, r), action.is_some());
+                    assert_eq!(
+                        resulting_score(&grid, c, r),
+                        action.as_ref().map(|action| action.gain().to_inner())
+                    );
+                    assert_eq!(
+                        next_board(&grid, c, r),
+                        action
+                            .as_ref()
+                            .map(|action| board.erase(action.mask()).to_grid())
+                    );
+                }
+
+                let Some(action) = Square::all()
+                    .rev()
+                    .find_map(|sq| Action::from_board_square(&board, sq).ok())
+                else {
+                    break;
+                };
+                board = board.erase(action.mask());
+            }
+
+            assert_eq!(Board::from_grid(&board.to_grid()).unwrap(), board);
+        }
+
+        assert_eq!(final_bonus(&[[0; ROW_COUNT]; COL_COUNT]), PERFECT_BONUS);
+        assert!(!is_legal_click(
+            &[[0; ROW_COUNT]; COL_COUNT],
+            Col::NUM,
+            Row::NUM
+        ));
+    }
+}
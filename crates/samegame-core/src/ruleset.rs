@@ -0,0 +1,127 @@
+//! 調整可能なゲームルール関連。
+//!
+//! 「パーフェクトボーナスが 500 だったら最適手順はどう変わるか」といった仮定の分析に使う。
+//! デフォルトは実際のゲームのルール。
+
+use crate::action::Action;
+use crate::board::{Board, MaskBoard};
+use crate::piece::{Piece, PieceArray};
+use crate::score::{calc_score_erase, Score, SCORE_PERFECT};
+use crate::square::Square;
+
+/// ゲームルールの定数たち。
+///
+/// `Solver::set_rule_set()`, `Position::actions_with_rules()`, `GameRng::gen_board_with_rules()` などに渡して使う。
+///
+/// ソルバー側 (`samegame-solver`) では上界関数 (`BoundFn`) および終了局面評価 (`TerminalEval`) としても使える。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RuleSet {
+    /// パーフェクト達成時に得られるボーナススコア。
+    pub perfect_bonus: Score,
+    /// 着手可能な塊の最小駒数。
+    ///
+    /// 盤面ライブラリは 2 個以上の塊のみを着手として列挙するので、2 未満の値は 2 とみなす。
+    pub min_group_size: u8,
+    /// 盤面再生成の閾値。同種駒の個数がこの値以上の盤面は再生成される (ゲーム内に出現しない)。
+    pub regen_piece_count: u8,
+    /// 着手により空になった列を詰めるかどうか。
+    ///
+    /// `false` なら駒の落下のみを行い、空の列はそのまま残す変種ルールとなる (`Board::erase_without_compaction()` を参照)。
+    pub column_compaction: bool,
+}
+
+impl RuleSet {
+    /// 実際のゲームのルール。
+    pub const GAME: Self = Self {
+        perfect_bonus: SCORE_PERFECT,
+        min_group_size: 2,
+        regen_piece_count: (Square::NUM / 2) as u8,
+        column_compaction: true,
+    };
+
+    /// このルールにおける理論上の最大スコア (48 個を 1 手で全消しし、パーフェクトボーナスを得た場合) を返す。
+    pub const fn score_max(&self) -> Score {
+        Score::new(calc_score_erase(Square::NUM as u32).to_inner() + self.perfect_bonus.to_inner())
+    }
+
+    /// パーフェクトボーナスを差し替えたルールを返す。
+    pub const fn with_perfect_bonus(self, perfect_bonus: Score) -> Self {
+        Self {
+            perfect_bonus,
+            ..self
+        }
+    }
+
+    /// 着手可能な塊の最小駒数を差し替えたルールを返す。
+    pub const fn with_min_group_size(self, min_group_size: u8) -> Self {
+        Self {
+            min_group_size,
+            ..self
+        }
+    }
+
+    /// 盤面再生成の閾値を差し替えたルールを返す。
+    pub const fn with_regen_piece_count(self, regen_piece_count: u8) -> Self {
+        Self {
+            regen_piece_count,
+            ..self
+        }
+    }
+
+    /// 空になった列を詰めるかどうかを差し替えたルールを返す。
+    pub const fn with_column_compaction(self, column_compaction: bool) -> Self {
+        Self {
+            column_compaction,
+            ..self
+        }
+    }
+
+    /// 着手可能な塊の最小駒数が実際のゲームと同じ (よって全ての `Action` が合法手) かどうかを返す。
+    pub const fn is_default_group_rule(&self) -> bool {
+        self.min_group_size <= 2
+    }
+
+    /// `action` がこのルールの下で合法手かどうかを返す。
+    pub fn is_legal_action(&self, action: &Action) -> bool {
+        action.square_count() >= u32::from(self.min_group_size)
+    }
+
+    /// このルールの下で盤面 `board` からマスク `mb` 内の駒を消した結果を返す。
+    pub fn erase(&self, board: &Board, mb: &MaskBoard) -> Board {
+        if self.column_compaction {
+            board.erase(mb)
+        } else {
+            board.erase_without_compaction(mb)
+        }
+    }
+
+    /// このルールの下で盤面 `board` からマスク `mb` 内の駒をその場で消し、消した駒数を返す。
+    pub fn erase_in_place(&self, board: &mut Board, mb: &MaskBoard) -> u32 {
+        if self.column_compaction {
+            board.erase_in_place(mb)
+        } else {
+            board.erase_without_compaction_in_place(mb)
+        }
+    }
+
+    /// 盤面がこのルールの下でゲーム内に出現しうるか (再生成されないか) を返す。
+    pub fn is_legal_board(&self, board: &Board) -> bool {
+        Piece::all().all(|piece| board.piece_count(piece) < u32::from(self.regen_piece_count))
+    }
+
+    /// 各駒種の個数が `piece_counts` である盤面がこのルールの下でゲーム内に出現しうるかを返す。
+    ///
+    /// `is_legal_board()` と同じ判定を、盤面から個数を数え直さずに行う。
+    pub(crate) fn is_legal_piece_counts(&self, piece_counts: &PieceArray<u8>) -> bool {
+        piece_counts
+            .as_array()
+            .iter()
+            .all(|&count| count < self.regen_piece_count)
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::GAME
+    }
+}
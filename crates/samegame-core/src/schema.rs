@@ -0,0 +1,239 @@
+//! テキスト形式のスキーマ関連。
+//!
+//! 盤面文字列、着手履歴文字列、盤面生成パラメータ文字列の各形式について、
+//! バージョンとフィールド構成を機械可読な形で提供する。
+//! 外部ツールが入力を検証したり、形式の変更を検出したりするのに使う。
+//!
+//! serde には依存せず、JSON は `FormatSchema::to_json()` で自前で書き出す。
+
+use anyhow::ensure;
+
+use crate::action::ActionHistory;
+use crate::board::Board;
+use crate::rng::{GameEntropy, RandomBoardParam};
+use crate::square::{Col, Row, Square};
+
+/// フィールドの型。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FieldType {
+    /// 符号なし整数。`max` 以下の値をとる。
+    ///
+    /// `hex` が真なら `0x` 付き 16 進で出力する。
+    /// 入力は 10 進の他、`0x`/`0o`/`0b` 接頭辞と `_` 区切りも受け付けるものがある (各形式の説明を参照)。
+    Uint { max: u64, hex: bool },
+    /// 正規表現 `pattern` (先頭・末尾アンカー付き) に一致する文字列。
+    Pattern { pattern: &'static str },
+    /// 名前が `format` である別の形式の文字列。
+    Format { format: &'static str },
+}
+
+/// フィールドのスキーマ。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FieldSchema {
+    /// フィールド名。
+    pub name: &'static str,
+    /// 型。
+    pub ty: FieldType,
+    /// 説明。
+    pub description: &'static str,
+}
+
+/// テキスト形式のスキーマ。
+///
+/// 文字列は `fields` を `repeat` の範囲の回数だけ繰り返したものを `separator` で区切って並べたものとなる。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FormatSchema {
+    /// 形式名。
+    pub name: &'static str,
+    /// 形式のバージョン。互換性のない変更を加えたら上げる。
+    pub version: u32,
+    /// 説明。
+    pub description: &'static str,
+    /// フィールド間の区切り文字列。
+    pub separator: &'static str,
+    /// フィールドたち。
+    pub fields: &'static [FieldSchema],
+    /// `fields` の繰り返し回数の範囲 (最小, 最大)。
+    pub repeat: (usize, usize),
+}
+
+impl FormatSchema {
+    /// 外部から与えられた形式のバージョン `version` がこのスキーマと互換かどうかを確かめる。
+    pub fn ensure_version(&self, version: u32) -> anyhow::Result<()> {
+        ensure!(
+            version == self.version,
+            "形式 '{}' のバージョンが一致しない (対応: {}, 入力: {version})",
+            self.name,
+            self.version
+        );
+        Ok(())
+    }
+
+    /// スキーマを JSON 文字列として返す。
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|field| {
+                let ty = match field.ty {
+                    FieldType::Uint { max, hex } => {
+                        format!("\"type\":\"uint\",\"max\":{max},\"hex\":{hex}")
+                    }
+                    FieldType::Pattern { pattern } => {
+                        format!("\"type\":\"pattern\",\"pattern\":{}", json_str(pattern))
+                    }
+                    FieldType::Format { format } => {
+                        format!("\"type\":\"format\",\"format\":{}", json_str(format))
+                    }
+                };
+                format!(
+                    "{{\"name\":{},{ty},\"description\":{}}}",
+                    json_str(field.name),
+                    json_str(field.description)
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"name\":{},\"version\":{},\"description\":{},\"separator\":{},\"fields\":[{}],\"repeat\":[{},{}]}}",
+            json_str(self.name),
+            self.version,
+            json_str(self.description),
+            json_str(self.separator),
+            fields.join(","),
+            self.repeat.0,
+            self.repeat.1
+        )
+    }
+}
+
+const SQUARE_PATTERN: &str = "^[1-8],[1-6]$";
+
+const _: () = assert!(Col::NUM == 8 && Row::NUM == 6);
+
+static BOARD_SCHEMA: FormatSchema = FormatSchema {
+    name: "board",
+    version: Board::FORMAT_VERSION,
+    description: "盤面。上の行から順に 6 行を改行区切りで並べる (末尾の改行は任意)。\
+                  '.' は空白、'1'..='5' は駒種。列は下詰め、空列は右詰めでなければならない",
+    separator: "\n",
+    fields: &[FieldSchema {
+        name: "row",
+        ty: FieldType::Pattern {
+            pattern: "^[.1-5]{8}$",
+        },
+        description: "1 行分のマス (左の列から)",
+    }],
+    repeat: (Row::NUM, Row::NUM),
+};
+
+static HISTORY_SCHEMA: FormatSchema = FormatSchema {
+    name: "history",
+    version: ActionHistory::FORMAT_VERSION,
+    description: "着手履歴。各手を空白区切りで並べる",
+    separator: " ",
+    fields: &[FieldSchema {
+        name: "square",
+        ty: FieldType::Pattern {
+            pattern: SQUARE_PATTERN,
+        },
+        description:
+            "着手 (消去する駒群の最小マス)。'列,行' で、列は左から 1..=8、行は下から 1..=6",
+    }],
+    repeat: (0, ActionHistory::CAPACITY),
+};
+
+/// 盤面生成パラメータ形式のフィールドたち。
+///
+/// 結果レコード形式など、盤面生成パラメータを含む形式のスキーマを定義するのに使う。
+pub const PARAM_FIELDS: [FieldSchema; 4] = [
+    FieldSchema {
+        name: "rng_state",
+        ty: FieldType::Uint {
+            max: u16::MAX as u64,
+            hex: true,
+        },
+        description: "乱数生成器の内部状態 (入力は 0x/0o/0b 接頭辞と '_' 区切りも可)",
+    },
+    FieldSchema {
+        name: "nmi_counter",
+        ty: FieldType::Uint {
+            max: u8::MAX as u64,
+            hex: true,
+        },
+        description: "NMI カウンタ (入力は 0x/0o/0b 接頭辞と '_' 区切りも可)",
+    },
+    FieldSchema {
+        name: "nmi_timing",
+        ty: FieldType::Uint {
+            max: Square::NUM as u64,
+            hex: false,
+        },
+        description: "NMI 発生タイミング (盤面生成中の何駒目の後か)。10 進のみ",
+    },
+    FieldSchema {
+        name: "entropy",
+        ty: FieldType::Uint {
+            max: GameEntropy::MAX_VALUE as u64,
+            hex: false,
+        },
+        description: "ゲーム内エントロピー。10 進のみ",
+    },
+];
+
+static PARAM_SCHEMA: FormatSchema = FormatSchema {
+    name: "param",
+    version: RandomBoardParam::FORMAT_VERSION,
+    description: "盤面生成パラメータ。4 フィールドをカンマ区切りで並べる",
+    separator: ",",
+    fields: &PARAM_FIELDS,
+    repeat: (1, 1),
+};
+
+impl Board {
+    /// 盤面文字列形式のバージョン。
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// 盤面文字列形式のスキーマを返す。
+    pub fn schema() -> &'static FormatSchema {
+        &BOARD_SCHEMA
+    }
+}
+
+impl ActionHistory {
+    /// 着手履歴文字列形式のバージョン。
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// 着手履歴文字列形式のスキーマを返す。
+    pub fn schema() -> &'static FormatSchema {
+        &HISTORY_SCHEMA
+    }
+}
+
+impl RandomBoardParam {
+    /// 盤面生成パラメータ文字列形式のバージョン。
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// 盤面生成パラメータ文字列形式のスキーマを返す。
+    pub fn schema() -> &'static FormatSchema {
+        &PARAM_SCHEMA
+    }
+}
+
+/// 文字列 `s` を JSON 文字列リテラル (引用符を含む) にエスケープして返す。
+pub fn json_str(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            ch if ch.is_control() => res.push_str(&format!("\\u{:04x}", u32::from(ch))),
+            ch => res.push(ch),
+        }
+    }
+    res.push('"');
+    res
+}
@@ -0,0 +1,179 @@
+//! スコア関連。
+
+use anyhow::Context as _;
+
+use crate::hint::assert_unchecked;
+use crate::square::Square;
+
+/// スコア型。
+///
+/// 理論上の値域は `0..=2409` (最大値は 48 個全消し時)。
+///
+/// 駒数などとの取り違えを防ぐため newtype としている。
+/// 加減算は通常の整数と同様にオーバーフロー時の挙動が未規定なので、必要に応じて `checked_*`, `saturating_*` を使うこと。
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Score(u32);
+
+/// 移行期間中の互換用。旧来の生のスコア値の型。
+#[deprecated(note = "`Score` を使うこと (`Score::new()`, `Score::to_inner()` で相互変換できる)")]
+pub type RawScore = u32;
+
+impl Score {
+    pub const ZERO: Self = Self(0);
+
+    /// 内部値からスコアを作る。
+    pub const fn new(inner: u32) -> Self {
+        Self(inner)
+    }
+
+    /// 内部値を返す。
+    pub const fn to_inner(self) -> u32 {
+        self.0
+    }
+
+    pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(inner) => Some(Self(inner)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(inner) => Some(Self(inner)),
+            None => None,
+        }
+    }
+
+    pub const fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub const fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl From<u32> for Score {
+    fn from(inner: u32) -> Self {
+        Self::new(inner)
+    }
+}
+
+impl From<Score> for u32 {
+    fn from(score: Score) -> Self {
+        score.to_inner()
+    }
+}
+
+impl std::ops::Add for Score {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::Sub for Score {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl std::ops::AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl std::iter::Sum for Score {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, std::ops::Add::add)
+    }
+}
+
+impl std::str::FromStr for Score {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner: u32 = s
+            .parse()
+            .with_context(|| format!("Score のパースに失敗: '{s}'"))?;
+
+        Ok(Self::new(inner))
+    }
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// パーフェクト達成時に得られるボーナススコア。
+pub const SCORE_PERFECT: Score = Score::new(200);
+
+/// n 個の駒を消す着手による獲得スコアを返す。
+///
+/// `n >= 2` でなければならない。
+pub const fn calc_score_erase(n: u32) -> Score {
+    unsafe { assert_unchecked!(n >= 2) }
+
+    Score::new((n - 1).pow(2))
+}
+
+/// 実際のゲームのルールにおける理論上の最大スコア (48 個を 1 手で全消しし、パーフェクトボーナスを得た場合)。
+pub const SCORE_MAX: Score = Score::new(calc_score_erase(Square::NUM as u32).0 + SCORE_PERFECT.0);
+
+/// スコアの加算 `lhs + rhs` を行う。
+///
+/// 監査モード (デバッグビルド、または feature `score-audit` 有効時) では、結果が理論上の最大値 `max` を
+/// 超えたら `context` の返す文脈とともに panic する。スコア計算やルール変種の誤りを早期に検出するため。
+/// それ以外では検査は消え、通常の加算となる。
+#[inline(always)]
+#[allow(unused_variables)]
+pub fn audited_add(lhs: Score, rhs: Score, max: Score, context: impl FnOnce() -> String) -> Score {
+    #[cfg(any(debug_assertions, feature = "score-audit"))]
+    {
+        let sum = lhs.checked_add(rhs);
+        assert!(
+            sum.is_some_and(|sum| sum <= max),
+            "スコアが理論上の最大値 {max} を超えた: {lhs} + {rhs} ({})",
+            context()
+        );
+    }
+
+    lhs + rhs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audited_add() {
+        assert_eq!(SCORE_MAX, Score::new(2409));
+        assert_eq!(
+            audited_add(Score::new(2400), Score::new(9), SCORE_MAX, String::new),
+            SCORE_MAX
+        );
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "score-audit"))]
+    #[should_panic(expected = "理論上の最大値")]
+    fn test_audited_add_violation() {
+        audited_add(Score::new(2400), Score::new(10), SCORE_MAX, || {
+            "test".to_owned()
+        });
+    }
+}
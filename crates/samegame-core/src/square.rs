@@ -4,7 +4,7 @@ use anyhow::{anyhow, ensure, Context as _};
 
 use crate::array::array_newtype;
 use crate::bounded::impl_bounded_nonzero_uint;
-use crate::hint::assert_unchecked;
+use crate::hint::{assert_unchecked, unchecked_fn};
 
 const COL_NUM: u8 = 8;
 const ROW_NUM: u8 = 6;
@@ -35,15 +35,17 @@ impl Col {
         }
     }
 
-    /// 左隣の列を返す。
-    ///
-    /// # Safety
-    ///
-    /// `self != Self::MIN` でなければならない。
-    pub const unsafe fn prev_unchecked(self) -> Self {
-        assert_unchecked!(!matches!(self, Self::MIN));
+    unchecked_fn! {
+        /// 左隣の列を返す。
+        ///
+        /// # Safety
+        ///
+        /// `self != Self::MIN` でなければならない。
+        const unsafe fn prev_unchecked(self) -> Self {
+            assert_unchecked!(!matches!(self, Self::MIN));
 
-        Self::from_inner_unchecked(self.to_inner() - 1)
+            Self::from_inner_unchecked(self.to_inner() - 1)
+        }
     }
 
     /// 右隣の列を返す。
@@ -55,15 +57,17 @@ impl Col {
         }
     }
 
-    /// 右隣の列を返す。
-    ///
-    /// # Safety
-    ///
-    /// `self != Self::MAX` でなければならない。
-    pub const unsafe fn next_unchecked(self) -> Self {
-        assert_unchecked!(!matches!(self, Self::MAX));
+    unchecked_fn! {
+        /// 右隣の列を返す。
+        ///
+        /// # Safety
+        ///
+        /// `self != Self::MAX` でなければならない。
+        const unsafe fn next_unchecked(self) -> Self {
+            assert_unchecked!(!matches!(self, Self::MAX));
 
-        Self::from_inner_unchecked(self.to_inner() + 1)
+            Self::from_inner_unchecked(self.to_inner() + 1)
+        }
     }
 }
 
@@ -145,6 +149,38 @@ impl Square {
 
         unsafe { Row::from_inner_unchecked(row) }
     }
+
+    /// ゲーム内部の盤面インデックス (0-based, 下の行から row-major) からマスを作る。
+    pub const fn from_game_index(idx: usize) -> Option<Self> {
+        if idx < Self::NUM {
+            Some(unsafe { Self::from_game_index_unchecked(idx) })
+        } else {
+            None
+        }
+    }
+
+    unchecked_fn! {
+        /// ゲーム内部の盤面インデックス (0-based, 下の行から row-major) からマスを作る。
+        ///
+        /// # Safety
+        ///
+        /// `idx < Square::NUM` でなければならない。
+        const unsafe fn from_game_index_unchecked(idx: usize) -> Self {
+            assert_unchecked!(idx < Self::NUM);
+
+            let col = Col::from_index_unchecked(idx % Col::NUM);
+            let row = Row::from_index_unchecked(idx / Col::NUM);
+
+            Self::new(col, row)
+        }
+    }
+
+    /// ゲーム内部の盤面インデックス (0-based, 下の行から row-major) に変換する。
+    ///
+    /// ゲーム内では盤面は左下のマスから右方向へ、行を下から上へと並べて格納されている。
+    pub const fn to_game_index(self) -> usize {
+        Col::NUM * self.row().to_index() + self.col().to_index()
+    }
 }
 
 impl std::str::FromStr for Square {
@@ -192,6 +228,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_square_game_index() {
+        assert_eq!(Square::new(COL_1, ROW_1).to_game_index(), 0);
+        assert_eq!(Square::new(COL_8, ROW_1).to_game_index(), 7);
+        assert_eq!(Square::new(COL_1, ROW_2).to_game_index(), 8);
+        assert_eq!(Square::new(COL_8, ROW_6).to_game_index(), 47);
+
+        for sq in Square::all() {
+            assert_eq!(Square::from_game_index(sq.to_game_index()), Some(sq));
+        }
+        for idx in 0..Square::NUM {
+            assert_eq!(Square::from_game_index(idx).unwrap().to_game_index(), idx);
+        }
+        assert_eq!(Square::from_game_index(Square::NUM), None);
+    }
+
     #[test]
     fn test_square_io() {
         for sq in Square::all() {
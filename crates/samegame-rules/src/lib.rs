@@ -0,0 +1,147 @@
+//! SFC『鮫亀』: さめがめ「かんたん」のゲームルールのみを扱う最小限の API。
+//!
+//! GUI やボットなど、ソルバーを必要としないフロントエンドへの組み込み向け。
+//! ソルバー、乱数生成器、ハッシュ、およびプラットフォーム固有の命令 (BMI2 など) には依存せず、
+//! 依存クレートも持たない `no_std` クレートなので、組み込み環境や WASM でもそのままビルドできる。
+//!
+//! 盤面は単純な 2 次元配列 `Grid` で表す。`samegame-core` クレートはこのクレートを `rules` モジュールとして
+//! 再エクスポートしており、`Board::to_grid()`, `Board::from_grid()` で相互変換できる。
+
+#![no_std]
+
+/// 盤面の列数。
+pub const COL_COUNT: usize = 8;
+
+/// 盤面の行数。
+pub const ROW_COUNT: usize = 6;
+
+/// パーフェクト達成時に得られるボーナススコア。
+pub const PERFECT_BONUS: u32 = 200;
+
+/// 盤面を表す 2 次元配列。
+///
+/// `grid[c][r]` が列 `c` (0-based, 左から)、行 `r` (0-based, 下から) の駒を表す。
+/// 値は空白なら 0, 駒なら `1..=5` の駒種。
+///
+/// 各関数は、盤面が重力により下詰めかつ左詰めになっていることを仮定する。
+pub type Grid = [[u8; ROW_COUNT]; COL_COUNT];
+
+/// マス `(col, row)` (0-based) のクリックが合法手かどうかを返す。
+///
+/// マスに駒があり、かつ同種の駒が 2 個以上繋がっていれば合法。
+pub fn is_legal_click(grid: &Grid, col: usize, row: usize) -> bool {
+    group_size(grid, col, row) >= 2
+}
+
+/// マス `(col, row)` (0-based) のクリックによる獲得スコアを返す。合法手でなければ `None` を返す。
+///
+/// パーフェクトボーナスは含まない (`final_bonus()` を参照)。
+pub fn resulting_score(grid: &Grid, col: usize, row: usize) -> Option<u32> {
+    let n = group_size(grid, col, row);
+
+    (n >= 2).then(|| (n - 1).pow(2))
+}
+
+/// マス `(col, row)` (0-based) をクリックした後の盤面を返す。合法手でなければ `None` を返す。
+pub fn next_board(grid: &Grid, col: usize, row: usize) -> Option<Grid> {
+    let mut erased = [[false; ROW_COUNT]; COL_COUNT];
+    if flood_fill(grid, col, row, &mut erased) < 2 {
+        return None;
+    }
+
+    // 各列を下詰めし、空になった列を詰めて左詰めにする。
+    let mut res: Grid = [[0; ROW_COUNT]; COL_COUNT];
+    let mut c_dst = 0;
+    for c in 0..COL_COUNT {
+        let mut r_dst = 0;
+        for r in 0..ROW_COUNT {
+            if grid[c][r] != 0 && !erased[c][r] {
+                res[c_dst][r_dst] = grid[c][r];
+                r_dst += 1;
+            }
+        }
+        if r_dst > 0 {
+            c_dst += 1;
+        }
+    }
+
+    Some(res)
+}
+
+/// 合法手が存在しない (ゲーム終了) かどうかを返す。
+pub fn is_game_over(grid: &Grid) -> bool {
+    for c in 0..COL_COUNT {
+        for r in 0..ROW_COUNT {
+            let value = grid[c][r];
+            if value == 0 {
+                continue;
+            }
+            if (c + 1 < COL_COUNT && grid[c + 1][r] == value)
+                || (r + 1 < ROW_COUNT && grid[c][r + 1] == value)
+            {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// ゲーム終了時の盤面に対するボーナススコアを返す。盤面が空ならパーフェクトボーナス、さもなくば 0。
+pub fn final_bonus(grid: &Grid) -> u32 {
+    if grid.iter().flatten().all(|&value| value == 0) {
+        PERFECT_BONUS
+    } else {
+        0
+    }
+}
+
+/// マス `(col, row)` の駒と繋がっている同種の駒の個数を返す (自身を含む)。空白や範囲外なら 0 を返す。
+fn group_size(grid: &Grid, col: usize, row: usize) -> u32 {
+    let mut visited = [[false; ROW_COUNT]; COL_COUNT];
+
+    flood_fill(grid, col, row, &mut visited)
+}
+
+/// マス `(col, row)` の駒と繋がっている同種の駒たちに印を付け、その個数を返す。
+fn flood_fill(
+    grid: &Grid,
+    col: usize,
+    row: usize,
+    visited: &mut [[bool; ROW_COUNT]; COL_COUNT],
+) -> u32 {
+    if col >= COL_COUNT || row >= ROW_COUNT || grid[col][row] == 0 {
+        return 0;
+    }
+    let value = grid[col][row];
+
+    let mut stack = [(0_usize, 0_usize); COL_COUNT * ROW_COUNT];
+    let mut len = 0;
+    let mut count = 0;
+
+    visited[col][row] = true;
+    stack[len] = (col, row);
+    len += 1;
+
+    while len > 0 {
+        len -= 1;
+        let (c, r) = stack[len];
+        count += 1;
+
+        let neighbors = [
+            (c.wrapping_sub(1), r),
+            (c + 1, r),
+            (c, r.wrapping_sub(1)),
+            (c, r + 1),
+        ];
+        for (nc, nr) in neighbors {
+            if nc < COL_COUNT && nr < ROW_COUNT && !visited[nc][nr] && grid[nc][nr] == value {
+                visited[nc][nr] = true;
+                stack[len] = (nc, nr);
+                len += 1;
+            }
+        }
+    }
+
+    count
+}
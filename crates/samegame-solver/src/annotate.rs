@@ -0,0 +1,504 @@
+//! 注釈付き最善手順関連。
+//!
+//! 「なぜこの手なのか」を説明するチュートリアルや解析ツール向け。
+
+use anyhow::ensure;
+use samegame_core::{Action, ActionHistory, Board, Score, Square};
+
+use crate::Position;
+use crate::Solver;
+
+/// `Solver::solve_annotated()` の設定。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AnnotateConfig {
+    /// 各ノードで評価する代替手の最大個数。
+    /// 代替手は (獲得スコア + 上界関数による子局面の追加獲得スコア上界) の降順に選ぶ。
+    pub max_alternatives: usize,
+    /// 代替手の正確な値を求める範囲。
+    /// 最善手との差がこの値以内の代替手は正確な値を求め、それ以外は上界のみを示す。
+    /// 大きくするほど代替手の評価が重くなる。
+    pub window: Score,
+}
+
+impl Default for AnnotateConfig {
+    fn default() -> Self {
+        Self {
+            max_alternatives: 3,
+            window: Score::new(100),
+        }
+    }
+}
+
+/// 代替手を選んだ場合の、そのノードからの追加獲得スコア。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AlternativeValue {
+    /// 正確な値。
+    Exact(Score),
+    /// この値以下であることのみ判明している。
+    AtMost(Score),
+}
+
+/// 最善手順の各ノードで採用されなかった代替手。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Alternative {
+    /// 着手 (最小マス)。
+    pub square: Square,
+    /// 着手による獲得スコア。
+    pub gain: Score,
+    /// この手を選んだ場合の、そのノードからの追加獲得スコア (着手による獲得スコアを含む)。
+    pub value: AlternativeValue,
+}
+
+/// 最善手順の 1 手。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AnnotatedStep {
+    /// 着手 (最小マス)。
+    pub square: Square,
+    /// 着手による獲得スコア。
+    pub gain: Score,
+    /// 着手前の局面からの正確な追加獲得スコア (この手の獲得スコアおよび終了局面評価を含む)。
+    pub remaining_gain: Score,
+    /// 採用されなかった代替手たち。値の降順に並ぶ。
+    pub alternatives: Vec<Alternative>,
+}
+
+/// 注釈付き最善手順。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AnnotatedSolution {
+    /// 最終スコア。
+    pub score: Score,
+    /// 最善手順の各手。
+    pub steps: Vec<AnnotatedStep>,
+}
+
+impl AnnotatedSolution {
+    /// 最善手順の各手を順に列挙する。
+    pub fn iter(&self) -> std::slice::Iter<'_, AnnotatedStep> {
+        self.steps.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AnnotatedSolution {
+    type Item = &'a AnnotatedStep;
+    type IntoIter = std::slice::Iter<'a, AnnotatedStep>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// 手順上の 1 手の自由度。`Solver::analyze_move_criticality()` の結果。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MoveCriticality {
+    /// 他のどの手でも手順のスコアに届かない (唯一手)。
+    Forced,
+    /// 他にも手順のスコアに届く手がある。
+    Free {
+        /// 手順のスコアに届く他の手たち (最小マス、合法手の列挙順)。
+        alternatives: Vec<Square>,
+    },
+    /// 探索予算内に判定できなかった手があり、それら以外には手順のスコアに届く手がない。
+    Undecided {
+        /// 判定できなかった手たち (最小マス、合法手の列挙順)。
+        undecided: Vec<Square>,
+    },
+}
+
+/// 手順上の 1 手とその自由度。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MoveAnalysis {
+    /// 着手 (最小マス)。
+    pub square: Square,
+    /// この手の自由度。
+    pub criticality: MoveCriticality,
+}
+
+impl Solver {
+    /// 盤面 `board` の最善手順を、各手の正確な追加獲得スコアおよび代替手の値とともに返す。
+    ///
+    /// まず通常通り `solve()` で最善手順を求め、その上の各ノードで代替手を子局面から解き直す。
+    /// よって `solve()` より (代替手の個数と `config.window` に応じて) 何倍も重い。
+    /// 解が見つからなければ `None` を返す。
+    ///
+    /// 各手の `remaining_gain` が正確な値となるのは、見つかった解が最適解である場合に限る
+    /// (枝刈り用スコア閾値が最適値未満であればよい)。
+    /// 代替手の評価中は枝刈り用スコア閾値を一時的に変更するが、終了時に元に戻す。
+    pub fn solve_annotated(
+        &mut self,
+        board: Board,
+        config: &AnnotateConfig,
+    ) -> Option<AnnotatedSolution> {
+        let (score, solution) = self.solve(board.clone())?;
+
+        let prune_score_max_orig = self.prune_score_max();
+        let rule_set = *self.rule_set();
+
+        let mut pos = Position::new(board);
+        let mut score_cur = Score::ZERO;
+        let mut steps = Vec::with_capacity(solution.len());
+
+        for &sq in &solution {
+            let remaining_gain = score - score_cur;
+
+            let mut actions: Vec<Action> = pos.actions_with_rules(&rule_set).collect();
+            let idx = actions
+                .iter()
+                .position(|action| action.least_square() == sq)
+                .expect("解の手順は合法手のみからなるはず");
+            let action = actions.swap_remove(idx);
+
+            // 上界の降順に代替手を選ぶ。
+            let mut candidates: Vec<(Score, Action, Position)> = actions
+                .into_iter()
+                .map(|action| {
+                    let pos_child = pos.do_action_with_rules(&action, &rule_set);
+                    let ub = action.gain() + self.child_upper_bound(&pos_child);
+                    (ub, action, pos_child)
+                })
+                .collect();
+            candidates.sort_by_key(|&(ub, _, _)| std::cmp::Reverse(ub));
+            candidates.truncate(config.max_alternatives);
+
+            let mut alternatives: Vec<Alternative> = candidates
+                .into_iter()
+                .map(|(ub, action, pos_child)| {
+                    let value = self.alternative_value(
+                        remaining_gain,
+                        config.window,
+                        ub,
+                        &action,
+                        pos_child,
+                    );
+                    Alternative {
+                        square: action.least_square(),
+                        gain: action.gain(),
+                        value,
+                    }
+                })
+                .collect();
+            alternatives.sort_by_key(|alt| {
+                std::cmp::Reverse(match alt.value {
+                    AlternativeValue::Exact(value) | AlternativeValue::AtMost(value) => value,
+                })
+            });
+
+            steps.push(AnnotatedStep {
+                square: sq,
+                gain: action.gain(),
+                remaining_gain,
+                alternatives,
+            });
+
+            score_cur += action.gain();
+            pos = pos.do_action_with_rules(&action, &rule_set);
+        }
+
+        self.set_prune_score_max(prune_score_max_orig);
+
+        Some(AnnotatedSolution { score, steps })
+    }
+
+    /// 盤面 `board` の手順 `history` (通常は最適解) の各手について、他の手でも手順のスコアに届くかを判定する。
+    ///
+    /// 唯一手と自由に選べる手を区別し、ルート中で融通が利く箇所を知るのに使う。
+    /// 各代替手は子局面を手順の残りのスコア以上の解のみを探して解き直し、1 回あたりの訪問ノード数を `node_budget` までとする
+    /// (`None` なら無制限)。予算内に判定できなかった手は `MoveCriticality::Undecided` として報告する。
+    /// 探索の目的は最大スコア (`Objective::MaxScore` など) でなければならない。
+    /// 判定中は枝刈り用スコア閾値と訪問ノード数の上限を一時的に変更するが、終了時に元に戻す。
+    ///
+    /// `history` が合法な手順でないか、終了局面に至らない場合はエラーを返す。
+    pub fn analyze_move_criticality(
+        &mut self,
+        board: Board,
+        history: &ActionHistory,
+        node_budget: Option<u64>,
+    ) -> anyhow::Result<Vec<MoveAnalysis>> {
+        let (pos_last, gain_total) = self.replay(&board, history, None)?;
+        ensure!(
+            !pos_last.has_action_with_rules(self.rule_set()),
+            "手順が終了局面に至らない"
+        );
+        let score = gain_total + self.terminal_eval().final_gain(&pos_last);
+
+        let prune_score_max_orig = self.prune_score_max();
+        let node_limit_orig = self.node_limit();
+        self.set_node_limit(node_budget);
+        let rule_set = *self.rule_set();
+
+        let mut pos = Position::new(board);
+        let mut score_cur = Score::ZERO;
+        let mut analyses = Vec::with_capacity(history.len());
+
+        for &sq in history {
+            let remaining_gain = score - score_cur;
+
+            let mut alternatives = Vec::new();
+            let mut undecided = Vec::new();
+            let mut action_taken = None;
+            for action in pos.actions_with_rules(&rule_set) {
+                if action.least_square() == sq {
+                    action_taken = Some(action);
+                    continue;
+                }
+                match self.reaches_gain(&pos, &action, remaining_gain) {
+                    Some(true) => alternatives.push(action.least_square()),
+                    Some(false) => {}
+                    None => undecided.push(action.least_square()),
+                }
+            }
+
+            let criticality = if !alternatives.is_empty() {
+                MoveCriticality::Free { alternatives }
+            } else if !undecided.is_empty() {
+                MoveCriticality::Undecided { undecided }
+            } else {
+                MoveCriticality::Forced
+            };
+            analyses.push(MoveAnalysis {
+                square: sq,
+                criticality,
+            });
+
+            let action = action_taken.expect("手順は合法手のみからなるはず");
+            score_cur += action.gain();
+            pos = pos.do_action_with_rules(&action, &rule_set);
+        }
+
+        self.set_prune_score_max(prune_score_max_orig);
+        self.set_node_limit(node_limit_orig);
+
+        Ok(analyses)
+    }
+
+    /// 局面 `pos` で手 `action` を指して、追加獲得スコア `gain_needed` 以上を得られるかを返す。
+    /// 訪問ノード数の上限に達して判定できなければ `None` を返す。
+    fn reaches_gain(
+        &mut self,
+        pos: &Position,
+        action: &Action,
+        gain_needed: Score,
+    ) -> Option<bool> {
+        let gain_child_needed = match gain_needed.checked_sub(action.gain()) {
+            None | Some(Score::ZERO) => return Some(true),
+            Some(gain) => gain,
+        };
+
+        let pos_child = pos.do_action_with_rules(action, self.rule_set());
+        if !pos_child.has_action_with_rules(self.rule_set()) {
+            return Some(self.terminal_eval().final_gain(&pos_child) >= gain_child_needed);
+        }
+        if self.child_upper_bound(&pos_child) < gain_child_needed {
+            return Some(false);
+        }
+
+        // gain_child_needed 以上の解のみを探す。
+        self.set_prune_score_max(gain_child_needed.saturating_sub(Score::new(1)));
+        let (res, stats) = self.solve_with_stats(pos_child.board().clone());
+        match res {
+            Some((value, _)) if value >= gain_child_needed => Some(true),
+            _ if stats.node_limit_reached || stats.cancelled => None,
+            _ => Some(false),
+        }
+    }
+
+    /// 局面 `pos` からの追加獲得スコア上界 (終了局面なら終了局面評価) を返す。
+    fn child_upper_bound(&self, pos: &Position) -> Score {
+        if pos.has_action_with_rules(self.rule_set()) {
+            self.bound_fn().gain_upper_bound(pos)
+        } else {
+            self.terminal_eval().final_gain(pos)
+        }
+    }
+
+    /// 代替手 `action` (上界 `ub`) の値を求める。
+    fn alternative_value(
+        &mut self,
+        remaining_gain: Score,
+        window: Score,
+        ub: Score,
+        action: &Action,
+        pos_child: Position,
+    ) -> AlternativeValue {
+        // 終了局面なら上界は正確な値。
+        if !pos_child.has_action_with_rules(self.rule_set()) {
+            return AlternativeValue::Exact(ub);
+        }
+
+        // 閾値 `threshold` を超える値だけを探せば、最善手との差が window 以内の値は正確に求まる。
+        let threshold = remaining_gain.saturating_sub(window);
+        if ub <= threshold {
+            return AlternativeValue::AtMost(ub);
+        }
+        let threshold_child = threshold.saturating_sub(action.gain());
+
+        self.set_prune_score_max(threshold_child);
+        match self.solve(pos_child.board().clone()) {
+            Some((value, _)) if value > threshold_child => {
+                AlternativeValue::Exact(action.gain() + value)
+            }
+            _ => AlternativeValue::AtMost(threshold.max(action.gain())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::{GameTerminalEval, TerminalEval as _};
+
+    #[test]
+    fn test_solve_annotated() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+
+        let mut solver = Solver::new(Score::ZERO);
+        let (score, solution) = solver.solve(board.clone()).unwrap();
+
+        let config = AnnotateConfig {
+            max_alternatives: usize::MAX,
+            window: Score::new(10000),
+        };
+        let annotated = solver.solve_annotated(board.clone(), &config).unwrap();
+        assert_eq!(solver.prune_score_max(), Score::ZERO);
+        assert_eq!(annotated.score, score);
+        assert_eq!(annotated.steps.len(), solution.len());
+        assert_eq!(annotated.steps[0].remaining_gain, score);
+
+        let mut pos = Position::new(board.clone());
+        for step in &annotated {
+            for alt in &step.alternatives {
+                // 窓が十分広いので全て正確な値となり、最善手を上回らない。
+                let AlternativeValue::Exact(value) = alt.value else {
+                    panic!("代替手の値が正確でない: {alt:?}");
+                };
+                assert!(value <= step.remaining_gain);
+
+                // 子局面を直接解いた結果と一致する。
+                let action = Action::from_board_square(pos.board(), alt.square).unwrap();
+                let pos_child = pos.do_action(&action);
+                let expected = if pos_child.has_action() {
+                    Solver::new(Score::ZERO)
+                        .solve(pos_child.board().clone())
+                        .unwrap()
+                        .0
+                } else {
+                    GameTerminalEval.final_gain(&pos_child)
+                };
+                assert_eq!(value, alt.gain + expected);
+            }
+            assert_eq!(step.alternatives.len() + 1, pos.action_count() as usize);
+            let action = Action::from_board_square(pos.board(), step.square).unwrap();
+            pos = pos.do_action(&action);
+        }
+
+        // 窓が 0 なら、終了局面に至らない代替手の値は上界のみとなる。代替手数も制限される。
+        let config = AnnotateConfig {
+            max_alternatives: 1,
+            window: Score::ZERO,
+        };
+        let annotated_narrow = solver.solve_annotated(board, &config).unwrap();
+        assert_eq!(annotated_narrow.score, score);
+        for (step, step_full) in std::iter::zip(&annotated_narrow, &annotated) {
+            assert!(step.alternatives.len() <= 1);
+            for alt in &step.alternatives {
+                let exact = step_full
+                    .alternatives
+                    .iter()
+                    .find(|alt_full| alt_full.square == alt.square)
+                    .unwrap()
+                    .value;
+                match alt.value {
+                    AlternativeValue::Exact(value) => assert_eq!(alt.value, exact, "{value}"),
+                    AlternativeValue::AtMost(value) => {
+                        let AlternativeValue::Exact(exact) = exact else {
+                            unreachable!()
+                        };
+                        assert!(exact <= value);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_analyze_move_criticality() {
+        /// 局面 `pos` からの最大の追加獲得スコアを全探索で求める。
+        fn brute_force(pos: &Position) -> Score {
+            if !pos.has_action() {
+                return GameTerminalEval.final_gain(pos);
+            }
+            pos.actions()
+                .map(|action| action.gain() + brute_force(&pos.do_action(&action)))
+                .max()
+                .unwrap()
+        }
+
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+
+        let mut solver = Solver::new(Score::ZERO);
+        let (_, solution) = solver.solve(board.clone()).unwrap();
+        let analyses = solver
+            .analyze_move_criticality(board.clone(), &solution, None)
+            .unwrap();
+        assert_eq!(solver.prune_score_max(), Score::ZERO);
+        assert_eq!(solver.node_limit(), None);
+        assert_eq!(analyses.len(), solution.len());
+
+        let mut pos = Position::new(board.clone());
+        let mut saw_forced = false;
+        for (analysis, &sq) in std::iter::zip(&analyses, &solution) {
+            assert_eq!(analysis.square, sq);
+            let best = brute_force(&pos);
+            let expected: Vec<Square> = pos
+                .actions()
+                .filter(|action| action.least_square() != sq)
+                .filter(|action| action.gain() + brute_force(&pos.do_action(action)) == best)
+                .map(|action| action.least_square())
+                .collect();
+            match &analysis.criticality {
+                MoveCriticality::Forced => {
+                    assert!(expected.is_empty());
+                    saw_forced = true;
+                }
+                MoveCriticality::Free { alternatives } => assert_eq!(*alternatives, expected),
+                MoveCriticality::Undecided { .. } => panic!("予算無制限なら全て判定できるはず"),
+            }
+            pos = pos.do_action(&Action::from_board_square(pos.board(), sq).unwrap());
+        }
+        assert!(saw_forced);
+
+        // 予算が足りなければ判定できない手が残る。
+        let analyses = solver
+            .analyze_move_criticality(board.clone(), &solution, Some(1))
+            .unwrap();
+        assert!(matches!(
+            analyses[0].criticality,
+            MoveCriticality::Undecided { .. }
+        ));
+        assert_eq!(solver.node_limit(), None);
+
+        // 終了局面に至らない手順はエラー。
+        assert!(solver
+            .analyze_move_criticality(board, &solution.split_at(1).0, None)
+            .is_err());
+    }
+}
@@ -26,3 +26,17 @@ macro_rules! asset_include_bytes {
 }
 #[allow(unused_imports)]
 pub(crate) use asset_include_bytes;
+
+/// ${CARGO_MANIFEST_DIR}/asset/ 直下のファイルを `include_str!` する。
+#[allow(unused_macros)]
+macro_rules! asset_include_str {
+    ($file:expr) => {
+        ::std::include_str!(::std::concat!(
+            ::std::env!("CARGO_MANIFEST_DIR"),
+            "/asset/",
+            $file
+        ))
+    };
+}
+#[allow(unused_imports)]
+pub(crate) use asset_include_str;
@@ -0,0 +1,155 @@
+//! ビームサーチ関連。
+
+use samegame_core::{ActionHistory, Board, FastRng, Score};
+
+use crate::eval::{Eval, ZeroEval};
+use crate::hash::U64HashMap;
+use crate::position::Position;
+use crate::terminal::{GameTerminalEval, TerminalEval as _};
+
+/// 近似解を高速に求めるビームサーチ。
+///
+/// 各深さで (現スコア + 評価値) の上位 `width` 局面のみを残して展開する。最適解は保証しないが、
+/// 得られた解のスコアは厳密探索の枝刈り用スコア閾値の初期値として使える。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BeamSearch {
+    width: usize,
+    tie_break_seed: Option<u64>,
+}
+
+impl BeamSearch {
+    /// ビーム幅を `width` としてビームサーチを作る。`width` は 1 以上でなければならない。
+    pub fn new(width: usize) -> Self {
+        assert!(width >= 1, "ビーム幅は 1 以上でなければならない");
+
+        Self {
+            width,
+            tie_break_seed: None,
+        }
+    }
+
+    /// 同値の局面の順序付けに使う乱数のシードを設定する。
+    ///
+    /// デフォルト (`None`) では同値の局面はハッシュ値で順序付ける。
+    /// シードを変えて複数回探索すれば、異なる近似解が得られうる。いずれにせよ結果は決定的。
+    pub fn tie_break_seed(mut self, seed: Option<u64>) -> Self {
+        self.tie_break_seed = seed;
+        self
+    }
+
+    /// ビーム幅を返す。
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// 与えられた盤面に対して探索し、見つかった中で最良の (最終スコア, 手順) を返す。
+    ///
+    /// 局面は現スコアのみで順位付けする。
+    pub fn search(&self, board: Board) -> (Score, ActionHistory) {
+        self.search_with_eval(board, &ZeroEval)
+    }
+
+    /// 評価関数 `eval` を用いて局面を順位付けしつつ探索し、見つかった中で最良の (最終スコア, 手順) を返す。
+    pub fn search_with_eval(&self, board: Board, eval: &dyn Eval) -> (Score, ActionHistory) {
+        let mut best = (Score::ZERO, ActionHistory::new());
+        let mut beam = vec![(Position::new(board), Score::ZERO, ActionHistory::new())];
+        let mut tie_break_rng = self.tie_break_seed.map(FastRng::new);
+
+        while !beam.is_empty() {
+            // 同一局面は現スコアが高いもののみ残す。
+            let mut nexts = U64HashMap::<Position, (Score, ActionHistory)>::default();
+
+            for (pos, score, history) in beam {
+                if !pos.has_action() {
+                    let score_final = score + GameTerminalEval.final_gain(&pos);
+                    if score_final > best.0 || history.is_empty() {
+                        best = (score_final, history);
+                    }
+                    continue;
+                }
+
+                for action in pos.actions() {
+                    let pos_child = pos.do_action(&action);
+                    let score_child = score + action.gain();
+                    if nexts
+                        .get(&pos_child)
+                        .is_some_and(|&(score_other, _)| score_other >= score_child)
+                    {
+                        continue;
+                    }
+                    let mut history_child = history.clone();
+                    history_child.push(action.least_square());
+                    nexts.insert(pos_child, (score_child, history_child));
+                }
+            }
+
+            let mut nexts: Vec<_> = nexts
+                .into_iter()
+                .map(|(pos, (score, history))| {
+                    let value = i64::from(score.to_inner()) + i64::from(eval.eval(&pos));
+                    (value, pos, score, history)
+                })
+                .collect();
+            // 結果を決定的にするため、まずハッシュ値で並べておく。
+            nexts.sort_unstable_by_key(|(_, pos, _, _)| pos.key());
+            if let Some(rng) = &mut tie_break_rng {
+                rng.shuffle(&mut nexts);
+            }
+            nexts.sort_by_key(|&(value, _, _, _)| std::cmp::Reverse(value));
+            nexts.truncate(self.width);
+            beam = nexts
+                .into_iter()
+                .map(|(_, pos, score, history)| (pos, score, history))
+                .collect();
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::solver::Solver;
+
+    use super::*;
+
+    #[test]
+    fn test_beam_search() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+
+        let (score_opt, _) = Solver::new(Score::ZERO).solve(board.clone()).unwrap();
+
+        for width in [1, 2, 100] {
+            let (score, solution) = BeamSearch::new(width).search(board.clone());
+            assert!(score <= score_opt);
+            assert_eq!(crate::verify::replay(&board, &solution), Ok(score));
+        }
+
+        let (score, solution) =
+            BeamSearch::new(1).search_with_eval(board.clone(), &crate::eval::GreedyPotential);
+        assert!(score <= score_opt);
+        assert_eq!(crate::verify::replay(&board, &solution), Ok(score));
+
+        for seed in 0..5 {
+            let beam = BeamSearch::new(2).tie_break_seed(Some(seed));
+            let (score, solution) = beam.search(board.clone());
+            assert!(score <= score_opt);
+            assert_eq!(crate::verify::replay(&board, &solution), Ok(score));
+            assert_eq!(beam.search(board.clone()), (score, solution));
+        }
+
+        // 十分広いビームなら最適解が得られるはず。
+        assert_eq!(BeamSearch::new(100).search(board).0, score_opt);
+    }
+}
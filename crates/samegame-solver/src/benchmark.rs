@@ -0,0 +1,143 @@
+//! ベンチマーク用盤面関連。
+//!
+//! 性能の議論やバグ報告で同一の入力を参照できるよう、出所を明記した少数の盤面を同梱する。
+//! 各盤面の選定方法は `asset/benchmark_boards.tsv` の先頭のコメントを参照。
+
+use std::sync::OnceLock;
+
+use anyhow::{bail, ensure, Context as _};
+use samegame_core::{ActionHistory, Board, RandomBoardParam, Score};
+
+use crate::asset::asset_include_str;
+
+/// ベンチマーク用盤面の分類。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BenchmarkKind {
+    /// 最適性の証明が最も容易な盤面。
+    Easiest,
+    /// 最適性の証明が困難な盤面。
+    Hardest,
+    /// 既知の最大スコアが高い盤面。
+    HighestScore,
+    /// 異なるパラメータから同一の盤面が生成される組の一方。
+    Duplicate,
+}
+
+impl std::str::FromStr for BenchmarkKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easiest" => Ok(Self::Easiest),
+            "hardest" => Ok(Self::Hardest),
+            "highest_score" => Ok(Self::HighestScore),
+            "duplicate" => Ok(Self::Duplicate),
+            _ => bail!("ベンチマーク盤面の分類が無効: '{s}'"),
+        }
+    }
+}
+
+/// ベンチマーク用盤面。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BenchmarkBoard {
+    /// 名前 (一意)。
+    pub name: &'static str,
+    /// 分類。
+    pub kind: BenchmarkKind,
+    /// 盤面生成パラメータ。
+    pub param: RandomBoardParam,
+    /// 盤面。
+    pub board: Board,
+    /// 既知の最大スコア。
+    pub known_best: Score,
+    /// `known_best` が最適値であると証明されているかどうか。
+    pub proven_optimal: bool,
+    /// `known_best` を達成する手順。
+    pub solution: ActionHistory,
+}
+
+/// 同梱のベンチマーク用盤面たちを返す。
+pub fn benchmark_boards() -> &'static [BenchmarkBoard] {
+    static BOARDS: OnceLock<Vec<BenchmarkBoard>> = OnceLock::new();
+
+    BOARDS.get_or_init(|| {
+        parse_benchmark_boards(asset_include_str!("benchmark_boards.tsv"))
+            .expect("同梱のベンチマーク盤面のパースに失敗")
+    })
+}
+
+/// 名前が `name` であるベンチマーク用盤面を返す。
+pub fn benchmark_board(name: &str) -> Option<&'static BenchmarkBoard> {
+    benchmark_boards().iter().find(|bb| bb.name == name)
+}
+
+fn parse_benchmark_boards(s: &'static str) -> anyhow::Result<Vec<BenchmarkBoard>> {
+    let mut res = Vec::new();
+
+    for (i, line) in s.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parse = || -> anyhow::Result<BenchmarkBoard> {
+            let fields: Vec<&'static str> = line.split('\t').collect();
+            ensure!(fields.len() == 6, "フィールド数が 6 でない");
+
+            let param: RandomBoardParam = fields[2].parse()?;
+            let (board, legal, _) = param.gen_board();
+            ensure!(legal, "パラメータ {param} の盤面はゲーム内に現れない");
+
+            Ok(BenchmarkBoard {
+                name: fields[0],
+                kind: fields[1].parse()?,
+                param,
+                board,
+                known_best: fields[3].parse()?,
+                proven_optimal: fields[4].parse()?,
+                solution: fields[5].parse()?,
+            })
+        };
+        res.push(parse().with_context(|| format!("{} 行目のパースに失敗", i + 1))?);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_benchmark_boards() {
+        let boards = benchmark_boards();
+        assert!(!boards.is_empty());
+
+        for bb in boards {
+            // 手順は既知の最大スコアを達成する。
+            assert_eq!(
+                crate::verify::replay(&bb.board, &bb.solution),
+                Ok(bb.known_best),
+                "{}",
+                bb.name
+            );
+            assert_eq!(benchmark_board(bb.name), Some(bb));
+        }
+
+        // 重複の組は同一の盤面。
+        let dups: Vec<_> = boards
+            .iter()
+            .filter(|bb| bb.kind == BenchmarkKind::Duplicate)
+            .collect();
+        assert_eq!(dups.len(), 2);
+        assert_ne!(dups[0].param, dups[1].param);
+        assert_eq!(dups[0].board, dups[1].board);
+
+        // 最も容易な盤面については最適性を確かめる。
+        let easiest = benchmark_board("easiest").unwrap();
+        assert!(easiest.proven_optimal);
+        let res = Solver::new(easiest.known_best).solve(easiest.board.clone());
+        assert!(res.is_none_or(|(score, _)| score <= easiest.known_best));
+
+        assert!(benchmark_board("nonexistent").is_none());
+    }
+}
@@ -0,0 +1,459 @@
+//! 任意の大きさの盤面関連。
+//!
+//! クレート本体の `Board`, `MaskBoard`, `Square` は「かんたん」の 8x6 盤面専用で、
+//! 列を 1 語に詰めたビット演算や `Square` の内部表現がこの大きさに依存している。
+//! このモジュールでは「ふつう」「むずかしい」などのより大きな盤面向けに、
+//! 列数と行数を const generics で受け取る単純な 2 次元配列表現の盤面と、それを解く厳密探索を提供する。
+//! ルールは「かんたん」と同じ (n 個消して (n-1)^2 点、全消しでパーフェクトボーナス)。
+//!
+//! 厳密探索 (`LargeSolver`) は本体のソルバー (`Solver`) とは独立した単純な深さ優先探索で、
+//! 本体の探索機構 (上界関数 `BoundFn`、DP テーブル、`SearchObserver` による進捗報告など) は使えない。
+//! これらはいずれも `Position` (8x6 盤面) を前提としているため。
+//! 上界は駒数のみによるもの、置換表は到達スコアによる枝刈りのみで、本体よりずっと遅い。
+//!
+//! ```
+//! # use samegame_solver::board_large::*;
+//! let board: LargeBoard<10, 3> = "\
+//! ..........
+//! 12........
+//! 1122......
+//! ".parse()?;
+//! let (score, solution) = LargeSolver::new().solve(&board).unwrap();
+//! assert_eq!(score.to_inner(), 4 + 4 + 200);
+//! assert_eq!(solution.len(), 2);
+//! # anyhow::Ok(())
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure};
+use samegame_core::{calc_score_erase, Score, SCORE_PERFECT};
+
+/// 駒種の最大値。駒の値は `1..=PIECE_KIND_MAX`。
+pub const PIECE_KIND_MAX: u8 = 9;
+
+/// `COL` 列 `ROW` 行の盤面。
+///
+/// `grid[c][r]` が列 `c` (0-based, 左から)、行 `r` (0-based, 下から) の駒を表す (`rules::Grid` と同じ向き)。
+/// 値は空白なら 0, 駒なら `1..=PIECE_KIND_MAX` の駒種。
+/// 常に下詰めかつ左詰めされている。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LargeBoard<const COL: usize, const ROW: usize> {
+    grid: [[u8; ROW]; COL],
+}
+
+/// `LargeBoard` 上の着手。消える塊の最小マス (列優先で最初のマス) と、塊の駒数を持つ。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LargeAction {
+    /// 塊の最小マスの列 (0-based)。
+    pub col: usize,
+    /// 塊の最小マスの行 (0-based)。
+    pub row: usize,
+    /// 塊の駒数。
+    pub square_count: u32,
+}
+
+impl<const COL: usize, const ROW: usize> LargeBoard<COL, ROW> {
+    const CHAR_BLANK: char = '.';
+
+    /// 2 次元配列から盤面を生成する。値が無効だったり、下詰めまたは左詰めされていなければエラーを返す。
+    pub fn from_grid(grid: [[u8; ROW]; COL]) -> anyhow::Result<Self> {
+        let mut seen_empty_col = false;
+        for (c, col) in grid.iter().enumerate() {
+            ensure!(
+                col.iter().all(|&value| value <= PIECE_KIND_MAX),
+                "列 {c} に無効な駒の値がある"
+            );
+            let height = col.iter().take_while(|&&value| value != 0).count();
+            ensure!(
+                col[height..].iter().all(|&value| value == 0),
+                "列 {c} が下詰めされていない"
+            );
+            if height == 0 {
+                seen_empty_col = true;
+            } else {
+                ensure!(!seen_empty_col, "列 {c} が左詰めされていない");
+            }
+        }
+
+        Ok(Self { grid })
+    }
+
+    /// 2 次元配列表現を返す。
+    pub fn as_grid(&self) -> &[[u8; ROW]; COL] {
+        &self.grid
+    }
+
+    /// マス `(col, row)` (0-based) の駒を返す。空白や範囲外なら `None` を返す。
+    pub fn get(&self, col: usize, row: usize) -> Option<u8> {
+        let value = *self.grid.get(col)?.get(row)?;
+        (value != 0).then_some(value)
+    }
+
+    /// 盤面上の駒の総数を返す。
+    pub fn piece_count_total(&self) -> u32 {
+        self.grid
+            .iter()
+            .flatten()
+            .filter(|&&value| value != 0)
+            .count() as u32
+    }
+
+    /// 盤面が空かどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.grid.iter().all(|col| col[0] == 0)
+    }
+
+    /// 合法手が存在しない (ゲーム終了) かどうかを返す。
+    pub fn is_game_over(&self) -> bool {
+        (0..COL).all(|c| {
+            (0..ROW).all(|r| {
+                let value = self.grid[c][r];
+                value == 0
+                    || !((c + 1 < COL && self.grid[c + 1][r] == value)
+                        || (r + 1 < ROW && self.grid[c][r + 1] == value))
+            })
+        })
+    }
+
+    /// 全ての合法手を列挙する (最小マスの列優先順)。
+    pub fn actions(&self) -> Vec<LargeAction> {
+        let mut visited = [[false; ROW]; COL];
+        let mut res = Vec::new();
+
+        for c in 0..COL {
+            for r in 0..ROW {
+                if self.grid[c][r] == 0 || visited[c][r] {
+                    continue;
+                }
+                let square_count = self.flood_fill(c, r, &mut visited);
+                if square_count >= 2 {
+                    res.push(LargeAction {
+                        col: c,
+                        row: r,
+                        square_count,
+                    });
+                }
+            }
+        }
+
+        res
+    }
+
+    /// マス `(col, row)` (0-based) をクリックした後の盤面と獲得スコアを返す。合法手でなければ `None` を返す。
+    ///
+    /// パーフェクトボーナスは含まない (`final_bonus()` を参照)。
+    pub fn click(&self, col: usize, row: usize) -> Option<(Self, Score)> {
+        self.get(col, row)?;
+        let mut erased = [[false; ROW]; COL];
+        let n = self.flood_fill(col, row, &mut erased);
+        if n < 2 {
+            return None;
+        }
+
+        // 各列を下詰めし、空になった列を詰めて左詰めにする。
+        let mut grid = [[0; ROW]; COL];
+        let mut c_dst = 0;
+        for (col, erased_col) in self.grid.iter().zip(&erased) {
+            let mut r_dst = 0;
+            for (&value, &erased) in col.iter().zip(erased_col) {
+                if value != 0 && !erased {
+                    grid[c_dst][r_dst] = value;
+                    r_dst += 1;
+                }
+            }
+            if r_dst > 0 {
+                c_dst += 1;
+            }
+        }
+
+        Some((Self { grid }, calc_score_erase(n)))
+    }
+
+    /// ゲーム終了時の盤面に対するボーナススコアを返す。盤面が空ならパーフェクトボーナス、さもなくば 0。
+    pub fn final_bonus(&self) -> Score {
+        if self.is_empty() {
+            SCORE_PERFECT
+        } else {
+            Score::ZERO
+        }
+    }
+
+    /// この盤面から追加で獲得しうるスコアの上界を返す。
+    ///
+    /// `Position::gain_upper_bound()` と同様、駒種ごとに全ての駒を 1 手で消せると仮定して見積もる。
+    pub fn gain_upper_bound(&self) -> Score {
+        let mut counts = [0_u32; PIECE_KIND_MAX as usize + 1];
+        for &value in self.grid.iter().flatten() {
+            counts[usize::from(value)] += 1;
+        }
+
+        let mut res = Score::ZERO;
+        let mut perfect = true;
+        for &count in &counts[1..] {
+            match count {
+                0 => {}
+                1 => perfect = false,
+                _ => res += calc_score_erase(count),
+            }
+        }
+        if perfect {
+            res += SCORE_PERFECT;
+        }
+
+        res
+    }
+
+    /// マス `(col, row)` の駒と繋がっている同種の駒たちに印を付け、その個数を返す。
+    fn flood_fill(&self, col: usize, row: usize, visited: &mut [[bool; ROW]; COL]) -> u32 {
+        let value = self.grid[col][row];
+
+        let mut stack = vec![(col, row)];
+        visited[col][row] = true;
+        let mut count = 0;
+
+        while let Some((c, r)) = stack.pop() {
+            count += 1;
+
+            let neighbors = [
+                (c.wrapping_sub(1), r),
+                (c + 1, r),
+                (c, r.wrapping_sub(1)),
+                (c, r + 1),
+            ];
+            for (nc, nr) in neighbors {
+                if nc < COL && nr < ROW && !visited[nc][nr] && self.grid[nc][nr] == value {
+                    visited[nc][nr] = true;
+                    stack.push((nc, nr));
+                }
+            }
+        }
+
+        count
+    }
+}
+
+impl<const COL: usize, const ROW: usize> std::str::FromStr for LargeBoard<COL, ROW> {
+    type Err = anyhow::Error;
+
+    /// `Board` と同じテキスト形式 (上の行から順に 1 行ずつ、空白は `.`) からパースする。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<_> = s.lines().collect();
+        ensure!(
+            lines.len() == ROW,
+            "盤面の行数が {ROW} でない: {}",
+            lines.len()
+        );
+
+        let mut grid = [[0; ROW]; COL];
+        for (i, line) in lines.iter().enumerate() {
+            let r = ROW - 1 - i;
+            let chars: Vec<_> = line.chars().collect();
+            ensure!(
+                chars.len() == COL,
+                "盤面の {} 行目の列数が {COL} でない: {}",
+                i + 1,
+                chars.len()
+            );
+            for (c, ch) in chars.into_iter().enumerate() {
+                grid[c][r] = match ch {
+                    Self::CHAR_BLANK => 0,
+                    '1'..='9' => ch as u8 - b'0',
+                    _ => bail!("盤面に無効な文字がある: '{ch}'"),
+                };
+            }
+        }
+
+        Self::from_grid(grid)
+    }
+}
+
+impl<const COL: usize, const ROW: usize> std::fmt::Display for LargeBoard<COL, ROW> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write as _;
+
+        for r in (0..ROW).rev() {
+            for c in 0..COL {
+                let value = self.grid[c][r];
+                let ch = if value == 0 {
+                    Self::CHAR_BLANK
+                } else {
+                    char::from(b'0' + value)
+                };
+                f.write_char(ch)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `LargeBoard` の最大スコアを求める厳密探索。
+///
+/// `Solver` とは独立した実装で、上界関数の差し替え、DP テーブル、オブザーバーなどには対応しない
+/// (モジュールのドキュメントを参照)。設定できるのは枝刈り用スコア閾値と訪問ノード数の上限のみ。
+#[derive(Debug, Default)]
+pub struct LargeSolver {
+    prune_score_max: Score,
+    node_limit: Option<u64>,
+}
+
+impl LargeSolver {
+    /// ソルバーを生成する。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 枝刈り用スコア閾値を設定する (デフォルトは 0)。このスコア以下の解は探さない。
+    pub fn set_prune_score_max(&mut self, prune_score_max: Score) {
+        self.prune_score_max = prune_score_max;
+    }
+
+    /// 訪問ノード数の上限を設定する。`None` なら無制限 (デフォルト)。
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.node_limit = node_limit;
+    }
+
+    /// 盤面 `board` の最大スコアとそれを達成する手順を返す。手順は各着手の最小マス `(col, row)` の列。
+    ///
+    /// 枝刈り用スコア閾値を超える解がないか、訪問ノード数の上限に達した場合、`None` を返す。
+    pub fn solve<const COL: usize, const ROW: usize>(
+        &mut self,
+        board: &LargeBoard<COL, ROW>,
+    ) -> Option<(Score, Vec<(usize, usize)>)> {
+        let mut search = LargeSearch {
+            node_limit: self.node_limit,
+            nodes: 0,
+            aborted: false,
+            best: self.prune_score_max,
+            best_solution: None,
+            history: Vec::new(),
+            table: HashMap::new(),
+        };
+        search.dfs(board, Score::ZERO);
+
+        if search.aborted {
+            return None;
+        }
+        search.best_solution.map(|solution| (search.best, solution))
+    }
+}
+
+/// `LargeSolver::solve()` の探索状態。
+struct LargeSearch<const COL: usize, const ROW: usize> {
+    node_limit: Option<u64>,
+    nodes: u64,
+    aborted: bool,
+    /// 暫定解のスコア (未発見なら枝刈り用スコア閾値)。
+    best: Score,
+    best_solution: Option<Vec<(usize, usize)>>,
+    history: Vec<(usize, usize)>,
+    /// 局面ごとの、これまでに到達したときの最大の獲得スコア。
+    table: HashMap<LargeBoard<COL, ROW>, Score>,
+}
+
+impl<const COL: usize, const ROW: usize> LargeSearch<COL, ROW> {
+    fn dfs(&mut self, board: &LargeBoard<COL, ROW>, score: Score) {
+        if self.aborted {
+            return;
+        }
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+            self.aborted = true;
+            return;
+        }
+        self.nodes += 1;
+
+        let actions = board.actions();
+        if actions.is_empty() {
+            let score = score + board.final_bonus();
+            if score > self.best {
+                self.best = score;
+                self.best_solution = Some(self.history.clone());
+            }
+            return;
+        }
+
+        if score + board.gain_upper_bound() <= self.best {
+            return;
+        }
+
+        // 同じ局面に同じかより低いスコアで到達したなら、これ以上良くならない。
+        match self.table.get_mut(board) {
+            Some(arrived) if *arrived >= score => return,
+            Some(arrived) => *arrived = score,
+            None => {
+                self.table.insert(board.clone(), score);
+            }
+        }
+
+        let mut actions = actions;
+        actions.sort_unstable_by_key(|action| std::cmp::Reverse(action.square_count));
+        for action in actions {
+            let (child, gain) = board.click(action.col, action.row).unwrap();
+            self.history.push((action.col, action.row));
+            self.dfs(&child, score + gain);
+            self.history.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use samegame_core::{rules, Board, FastRng};
+
+    use super::*;
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_large_board() {
+        // 8x6 盤面では本体のソルバーと同じ最大スコアを得る。
+        let mut rng = FastRng::new(1);
+        for _ in 0..10 {
+            let board = Board::random_with(&mut rng, 3);
+            let large: LargeBoard<{ rules::COL_COUNT }, { rules::ROW_COUNT }> =
+                board.to_string().parse().unwrap();
+            assert_eq!(large.to_string(), board.to_string());
+            assert_eq!(large.piece_count_total(), board.piece_count_total());
+
+            let expect = Solver::new(Score::ZERO)
+                .solve(board)
+                .map(|(score, _)| score);
+            let actual = LargeSolver::new().solve(&large);
+            assert_eq!(actual.as_ref().map(|&(score, _)| score), expect);
+
+            // 手順を再生すると、そのスコアを得て終了する。
+            if let Some((score, solution)) = actual {
+                let (board_final, total) =
+                    solution
+                        .iter()
+                        .fold((large, Score::ZERO), |(board, total), &(col, row)| {
+                            let (board, gain) = board.click(col, row).unwrap();
+                            (board, total + gain)
+                        });
+                assert!(board_final.is_game_over());
+                assert_eq!(total + board_final.final_bonus(), score);
+            }
+        }
+
+        // より大きな盤面。
+        let board: LargeBoard<12, 4> = "\
+            ............\n\
+            3...........\n\
+            21.....11...\n\
+            123322112233\n"
+            .parse()
+            .unwrap();
+        assert!(!board.is_game_over());
+        assert_eq!(board.actions().len(), 5);
+        let (score, _) = LargeSolver::new().solve(&board).unwrap();
+        assert!(score <= board.gain_upper_bound());
+
+        let mut solver = LargeSolver::new();
+        solver.set_node_limit(Some(1));
+        assert_eq!(solver.solve(&board), None);
+
+        // 左詰めされていない盤面は受け付けない。
+        assert!("...\n1.1\n".parse::<LargeBoard<3, 2>>().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! 探索時に用いる追加獲得スコア上界関連。
+
+use arrayvec::ArrayVec;
+use samegame_core::{
+    calc_score_erase, Board, Col, ColArray, MaskBoard, Piece, Row, RuleSet, Score, Square,
+};
+
+use crate::position::Position;
+
+/// 局面から追加で獲得しうるスコアの上界を与える関数。
+///
+/// ソルバーはこの値を用いて枝刈りを行う。
+/// 返す値は、その局面から実際に獲得しうる追加スコア (パーフェクトボーナスを含む) 以上でなければならない。
+/// この条件を満たさない場合、ソルバーは最適解を見逃しうる。
+///
+/// デバッグビルドでは、ソルバーは探索中に実際に得られた追加スコアとこの値を照合し、
+/// 条件違反を検出したら panic する。
+///
+/// `Fn(&Position) -> Score` を満たすクロージャはこのトレイトを実装している。
+pub trait BoundFn {
+    /// `pos` から追加で獲得しうるスコアの上界を返す。
+    fn gain_upper_bound(&self, pos: &Position) -> Score;
+}
+
+impl<F: Fn(&Position) -> Score> BoundFn for F {
+    fn gain_upper_bound(&self, pos: &Position) -> Score {
+        self(pos)
+    }
+}
+
+impl BoundFn for RuleSet {
+    fn gain_upper_bound(&self, pos: &Position) -> Score {
+        pos.gain_upper_bound_with_rules(self)
+    }
+}
+
+/// デフォルトの上界関数。`Position::gain_upper_bound` をそのまま用いる。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DefaultBound;
+
+impl BoundFn for DefaultBound {
+    fn gain_upper_bound(&self, pos: &Position) -> Score {
+        pos.gain_upper_bound()
+    }
+}
+
+/// 列の高さの推移に基づく上界関数。
+///
+/// 決して消せない駒 (`unerasable_squares()`) を求め、それらを除いた駒数で `Position::gain_upper_bound()` と同様に見積もる。
+/// 決して消せない駒があればパーフェクトボーナスも得られない。
+/// 駒数のみによる上界より厳しいが計算は重い。実際のゲームのルール専用。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HeightProfileBound;
+
+impl BoundFn for HeightProfileBound {
+    fn gain_upper_bound(&self, pos: &Position) -> Score {
+        let board = pos.board();
+        let dead = unerasable_squares(board);
+        if dead.is_empty() {
+            return pos.gain_upper_bound();
+        }
+
+        Piece::all()
+            .map(|piece| board.piece_mask(piece).subtract(&dead).square_count())
+            .filter(|&count| count >= 2)
+            .map(calc_score_erase)
+            .sum()
+    }
+}
+
+/// 探索時に用いる上界関数の種類。`SolverConfig` で選ぶ。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BoundKind {
+    /// 駒種ごとの駒数のみによる上界 (`DefaultBound`)。
+    #[default]
+    PieceCount,
+    /// 決して消せない駒を除いた駒数による上界 (`HeightProfileBound`)。
+    HeightProfile,
+}
+
+impl BoundFn for BoundKind {
+    fn gain_upper_bound(&self, pos: &Position) -> Score {
+        match self {
+            Self::PieceCount => DefaultBound.gain_upper_bound(pos),
+            Self::HeightProfile => HeightProfileBound.gain_upper_bound(pos),
+        }
+    }
+}
+
+/// 盤面 `board` のうち、以降どう着手しても決して消せない駒があるマスの集合を返す。
+///
+/// 駒は下と左にしか動かず、列の順序も列内の駒の順序も変わらないことを用いる。
+/// 2 駒が同種の駒と隣接するには、同じ列なら間の駒が全て、異なる列なら間の列が丸ごと消えねばならない。
+/// また、駒は自身より下にある決して消せない駒の上にしか落ちないので、駒が取りうる行は
+/// (その下の決して消せない駒の数 + 1) 行目から現在の行までに限られ、異なる列の 2 駒が隣接するにはこの範囲が重ならねばならない。
+/// どの同種の駒ともこれらの条件を満たさない駒は決して消せない。1 個しかない駒種の駒から始め、不動点まで繰り返す。
+///
+/// 必要条件のみを見ているので、決して消せない駒を全て求めるとは限らない。
+pub fn unerasable_squares(board: &Board) -> MaskBoard {
+    let pieces: ArrayVec<(Square, Piece), { Square::NUM }> = Square::all()
+        .filter_map(|sq| board.get(sq).map(|piece| (sq, piece)))
+        .collect();
+
+    let mut dead = MaskBoard::empty();
+    loop {
+        let is_barrier: ColArray<bool> =
+            ColArray::from_fn(|col| Row::all().any(|row| dead.test(Square::new(col, row))));
+        // 駒が取りうる最も低い行のインデックス。
+        let lowest_row = |sq: Square| {
+            Row::all()
+                .take(sq.row().to_index())
+                .filter(|&row| dead.test(Square::new(sq.col(), row)))
+                .count()
+        };
+        let can_meet = |x: Square, y: Square| {
+            let (x, y) = if x.col().to_index() <= y.col().to_index() {
+                (x, y)
+            } else {
+                (y, x)
+            };
+            if x.col() == y.col() {
+                let (lo, hi) = if x.row().to_index() < y.row().to_index() {
+                    (x, y)
+                } else {
+                    (y, x)
+                };
+                return Row::all()
+                    .skip(lo.row().to_index() + 1)
+                    .take(hi.row().to_index() - lo.row().to_index() - 1)
+                    .all(|row| !dead.test(Square::new(x.col(), row)));
+            }
+            let between_cols = Col::all()
+                .skip(x.col().to_index() + 1)
+                .take(y.col().to_index() - x.col().to_index() - 1);
+            if between_cols.into_iter().any(|col| is_barrier[col]) {
+                return false;
+            }
+            lowest_row(x).max(lowest_row(y)) <= x.row().to_index().min(y.row().to_index())
+        };
+
+        let newly_dead: ArrayVec<Square, { Square::NUM }> = pieces
+            .iter()
+            .filter(|&&(x, piece_x)| {
+                !dead.test(x)
+                    && !pieces.iter().any(|&(y, piece_y)| {
+                        y != x && piece_y == piece_x && !dead.test(y) && can_meet(x, y)
+                    })
+            })
+            .map(|&(sq, _)| sq)
+            .collect();
+        if newly_dead.is_empty() {
+            return dead;
+        }
+        for sq in newly_dead {
+            dead.set(sq, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use samegame_core::FastRng;
+
+    use super::*;
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_height_profile_bound() {
+        // 列 2 の駒 3 は決して消せないので、列 1 と列 3 の駒は出会えない。
+        // 列 3 の 3 個の駒 1 のみが消せる。
+        let board: Board = indoc! {"
+            ........
+            ........
+            ..1.....
+            ..1.....
+            1.1.....
+            232.....
+        "}
+        .parse()
+        .unwrap();
+        let expect: MaskBoard = indoc! {"
+            ........
+            ........
+            ........
+            ........
+            *.......
+            ***.....
+        "}
+        .parse()
+        .unwrap();
+        assert_eq!(unerasable_squares(&board), expect);
+
+        let pos = Position::new(board.clone());
+        assert_eq!(pos.gain_upper_bound(), Score::new(9 + 1));
+        assert_eq!(HeightProfileBound.gain_upper_bound(&pos), Score::new(4));
+        assert_eq!(
+            BoundKind::HeightProfile.gain_upper_bound(&pos),
+            Score::new(4)
+        );
+        assert_eq!(
+            Solver::new(Score::ZERO).solve(board).unwrap().0,
+            Score::new(4)
+        );
+
+        // 決して消せない駒の上に載った駒は、それより低い行の駒と横に並べない。
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            ........
+            12......
+            31......
+        "}
+        .parse()
+        .unwrap();
+        assert_eq!(unerasable_squares(&board).square_count(), 4);
+
+        // ランダムな盤面で、真の最適値以上かつ駒数のみによる上界以下であることを確かめる。
+        // デバッグビルドのソルバーは探索中の全ノードで上界を検証する。
+        let mut rng = FastRng::new(1263);
+        let mut tighter = 0;
+        for i in 0..200 {
+            let board = Board::random_with(&mut rng, 2 + (i % 4) as u8);
+            if board.piece_count_total() > 20 {
+                continue;
+            }
+            let pos = Position::new(board.clone());
+            let bound = HeightProfileBound.gain_upper_bound(&pos);
+            assert!(bound <= pos.gain_upper_bound());
+            if bound < pos.gain_upper_bound() {
+                tighter += 1;
+            }
+
+            // 閾値を超える解がなければ解なしとなるので、そのときは 0 とみなす。
+            let solve = |solver: &mut Solver| {
+                solver
+                    .solve(board.clone())
+                    .map_or(Score::ZERO, |(score, _)| score)
+            };
+            let expect = solve(&mut Solver::new(Score::ZERO));
+            let mut solver = Solver::new(Score::ZERO);
+            solver.set_bound_fn(HeightProfileBound);
+            assert_eq!(solve(&mut solver), expect);
+            assert!(expect <= bound);
+        }
+        assert!(tighter > 0);
+    }
+}
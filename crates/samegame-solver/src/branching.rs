@@ -0,0 +1,81 @@
+//! 経験的な分岐数統計関連。
+//!
+//! 多数のゲーム内の盤面からのランダムプレイアウトで得た、残り駒数ごとの合法手数などの統計を埋め込んでいる。
+//! 統計は `examples/branching_stats.rs` で生成する。
+
+use samegame_core::Square;
+
+use crate::asset::asset_include;
+
+/// 残り駒数が一定の局面たちの分岐数統計。
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BranchingStats {
+    /// 標本の局面数。
+    pub sample_count: u64,
+    /// 合法手数の平均値 (終了局面を含む)。
+    pub avg_branching: f64,
+    /// 合法手数の最大値 (標本中の最悪ケース)。
+    pub max_branching: u32,
+    /// 1 手で消える駒数の平均値 (合法手があった局面の全ての手についての平均)。
+    pub avg_removed: f64,
+}
+
+/// 残り駒数 (0..=48) ごとの分岐数統計。標本がない駒数のエントリは `sample_count` が 0 となる。
+pub static BRANCHING_STATS: [BranchingStats; Square::NUM + 1] =
+    asset_include!("branching_stats.in");
+
+/// 標本全体での合法手数の最大値 (最悪ケースの分岐数)。
+pub fn max_branching() -> u32 {
+    BRANCHING_STATS
+        .iter()
+        .map(|stats| stats.max_branching)
+        .max()
+        .unwrap()
+}
+
+/// 残り駒数が `piece_count` の局面から、枝刈りも DP による合流もなしに全探索した場合の訪問ノード数の期待値を返す。
+///
+/// 各局面では平均分岐数だけ子ノードがあり、各手で平均駒数が消えるとみなして概算する。
+/// 難易度の見積もりや、面ごとの探索の割り当ての目安に使う。
+pub fn expected_nodes(piece_count: u32) -> f64 {
+    let piece_count = (piece_count as usize).min(Square::NUM);
+
+    // nodes[n]: 残り駒数 n の局面からの期待ノード数。
+    let mut nodes = [1.0_f64; Square::NUM + 1];
+    for n in 0..=piece_count {
+        let stats = &BRANCHING_STATS[n];
+        if stats.sample_count == 0 || stats.avg_branching == 0.0 {
+            continue;
+        }
+        let removed = (stats.avg_removed.round() as usize).clamp(2, n);
+        nodes[n] = 1.0 + stats.avg_branching * nodes[n - removed];
+    }
+
+    nodes[piece_count]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_nodes() {
+        // 空の盤面は終了局面のみ。
+        assert_eq!(expected_nodes(0), 1.0);
+
+        // 初期盤面は十分に標本がある。
+        let stats = &BRANCHING_STATS[Square::NUM];
+        assert!(stats.sample_count > 0);
+        assert!(stats.avg_branching > 1.0);
+        assert!(stats.max_branching as f64 >= stats.avg_branching);
+        assert!(max_branching() >= stats.max_branching);
+        assert!(max_branching() as usize <= Square::NUM / 2);
+
+        // 駒数が多いほど期待ノード数は増える。
+        assert!(expected_nodes(20) > 1.0);
+        assert!(expected_nodes(40) > expected_nodes(20));
+        assert!(expected_nodes(Square::NUM as u32) > expected_nodes(40));
+        assert!(expected_nodes(Square::NUM as u32) > 1e6);
+        assert_eq!(expected_nodes(1000), expected_nodes(Square::NUM as u32));
+    }
+}
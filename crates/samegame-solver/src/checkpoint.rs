@@ -0,0 +1,200 @@
+//! 1 面の探索の中断・再開用チェックポイント関連。
+//!
+//! ソルバーは深さ優先探索なので、探索中の手順 (探索フロンティア) の各深さについて、
+//! それより前に列挙される手の部分木は探索済みである。よってこの手順と暫定解を保存しておけば、
+//! 再開時には探索済みの部分木を飛ばして続きから探索できる (DP テーブルの内容は失われるので多少の再探索は生じる)。
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context as _};
+use samegame_core::{ActionHistory, Board, Score};
+
+use crate::convert::{decode_compact, encode_compact, COMPACT_LEN};
+
+const MAGIC: &str = "samegame-checkpoint";
+
+/// チェックポイントの保存設定。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CheckpointConfig {
+    /// 保存先のファイル。
+    pub path: PathBuf,
+    /// 保存間隔。
+    pub interval: Duration,
+}
+
+impl CheckpointConfig {
+    /// 保存先 `path`、保存間隔 `interval` として設定を作る。
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+        }
+    }
+}
+
+/// 1 面の探索のチェックポイント。
+///
+/// `Solver::set_checkpoint()` で定期的に保存し、`Solver::solve_resume()` で探索を再開する。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolveCheckpoint {
+    /// 探索対象の盤面。
+    pub board: Board,
+    /// 探索時の枝刈り用スコア閾値。
+    pub prune_score_max: Score,
+    /// これまでの訪問ノード数の総和。
+    pub nodes: u64,
+    /// 暫定解 (あれば)。
+    pub best: Option<(Score, ActionHistory)>,
+    /// 探索フロンティア。
+    ///
+    /// 各深さ d について、`frontier[d]` より前に列挙される手の部分木は探索済み。
+    pub frontier: ActionHistory,
+}
+
+impl SolveCheckpoint {
+    /// チェックポイントファイルの形式のバージョン。
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// ファイル `path` に保存する。
+    ///
+    /// 書き込み途中で中断されても既存のファイルが壊れないよう、一時ファイルに書いてから置き換える。
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let mut path_tmp = path.as_os_str().to_owned();
+        path_tmp.push(".tmp");
+
+        std::fs::write(&path_tmp, self.to_string())
+            .with_context(|| format!("チェックポイントの書き込みに失敗: {}", path.display()))?;
+        std::fs::rename(&path_tmp, path)
+            .with_context(|| format!("チェックポイントの置き換えに失敗: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// ファイル `path` から読み込む。
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("チェックポイントの読み込みに失敗: {}", path.display()))?;
+
+        s.parse()
+            .with_context(|| format!("チェックポイントのパースに失敗: {}", path.display()))
+    }
+}
+
+impl std::fmt::Display for SolveCheckpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{MAGIC}\t{}", Self::FORMAT_VERSION)?;
+
+        write!(f, "board\t")?;
+        for b in encode_compact(&self.board) {
+            write!(f, "{b:02X}")?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "prune_score_max\t{}", self.prune_score_max)?;
+        writeln!(f, "nodes\t{}", self.nodes)?;
+        match &self.best {
+            Some((score, solution)) => writeln!(f, "best\t{score}\t{solution}")?,
+            None => writeln!(f, "best\t-")?,
+        }
+        writeln!(f, "frontier\t{}", self.frontier)?;
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for SolveCheckpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let mut field = |name: &str| -> anyhow::Result<&str> {
+            let line = lines.next().with_context(|| format!("'{name}' 行がない"))?;
+            let (key, value) = line.split_once('\t').unwrap_or((line, ""));
+            ensure!(key == name, "'{name}' 行があるべき位置に '{key}' 行がある");
+            Ok(value)
+        };
+
+        let version: u32 = field(MAGIC)?.parse().context("バージョンが無効")?;
+        ensure!(
+            version == Self::FORMAT_VERSION,
+            "チェックポイントのバージョンが一致しない (対応: {}, 入力: {version})",
+            Self::FORMAT_VERSION
+        );
+
+        let board = {
+            let hex = field("board")?;
+            ensure!(hex.is_ascii() && hex.len() == 2 * COMPACT_LEN, "盤面が無効");
+            let mut buf = [0; COMPACT_LEN];
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).context("盤面が無効")?;
+            }
+            decode_compact(&buf)?
+        };
+
+        let prune_score_max = field("prune_score_max")?.parse()?;
+        let nodes = field("nodes")?.parse()?;
+
+        let best = match field("best")? {
+            "-" => None,
+            value => {
+                let Some((score, solution)) = value.split_once('\t') else {
+                    bail!("暫定解が無効: '{value}'");
+                };
+                Some((score.parse()?, solution.parse()?))
+            }
+        };
+
+        let frontier = field("frontier")?.parse()?;
+
+        Ok(Self {
+            board,
+            prune_score_max,
+            nodes,
+            best,
+            frontier,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_checkpoint_io() {
+        let board = "0x0123,0x45,40,2"
+            .parse::<samegame_core::RandomBoardParam>()
+            .unwrap()
+            .gen_board()
+            .0;
+        let ckpt = SolveCheckpoint {
+            board,
+            prune_score_max: Score::new(100),
+            nodes: 12345,
+            best: Some((Score::new(123), "1,1 2,3".parse().unwrap())),
+            frontier: "1,1 2,2".parse().unwrap(),
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "samegame-checkpoint-test-{}.txt",
+            std::process::id()
+        ));
+        ckpt.save(&path).unwrap();
+        assert_eq!(SolveCheckpoint::load(&path).unwrap(), ckpt);
+        std::fs::remove_file(&path).unwrap();
+
+        let ckpt = SolveCheckpoint {
+            best: None,
+            frontier: ActionHistory::new(),
+            ..ckpt
+        };
+        assert_eq!(ckpt.to_string().parse::<SolveCheckpoint>().unwrap(), ckpt);
+
+        // バージョン違いは拒否する。
+        let s = ckpt.to_string().replacen("\t1\n", "\t2\n", 1);
+        assert!(s.parse::<SolveCheckpoint>().is_err());
+    }
+}
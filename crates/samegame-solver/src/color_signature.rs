@@ -0,0 +1,166 @@
+//! 駒種ごとの駒数の分布による局面の分類関連。
+//!
+//! 駒の配置は違っても駒数の分布 (駒種の入れ替えを同一視した多重集合) が異なる局面は一致しえないので、
+//! データベースで原像探索やパターン検索をする際、候補を駒数の分布で絞り込むのに使う。
+
+use samegame_core::Piece;
+
+use crate::hash::U64HashMap;
+use crate::position::PositionRef;
+use crate::zobrist::ZOBRIST_TABLE;
+
+/// 駒種ごとの駒数 `counts` の多重集合のハッシュ値を返す。
+///
+/// `counts` の順序によらない。`Position::color_signature()` と同じ値を返すので、
+/// 局面を作らずに駒数の分布から検索キーを得るのに使える。
+pub fn color_signature_from_counts(counts: impl IntoIterator<Item = u8>) -> u64 {
+    counts
+        .into_iter()
+        .map(|count| ZOBRIST_TABLE.color_count(count))
+        .fold(0, u64::wrapping_add)
+}
+
+/// 駒種ごとの駒数 `counts` を降順に並べたものを返す (駒数の多重集合の正規形)。
+pub fn color_profile_from_counts(counts: [u8; Piece::NUM]) -> [u8; Piece::NUM] {
+    let mut profile = counts;
+    profile.sort_unstable_by(|lhs, rhs| rhs.cmp(lhs));
+    profile
+}
+
+/// 値を局面の駒数の分布ごとに分類して保持する。
+///
+/// 同じバケットには駒数の分布が同じ局面の値のみが入る (ハッシュ値の衝突は駒数の正規形で区別する)。
+#[derive(Clone, Debug)]
+pub struct ColorBuckets<T> {
+    buckets: U64HashMap<u64, Vec<([u8; Piece::NUM], T)>>,
+    len: usize,
+}
+
+impl<T> Default for ColorBuckets<T> {
+    fn default() -> Self {
+        Self {
+            buckets: U64HashMap::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> ColorBuckets<T> {
+    /// 空の分類を作る。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 値の総数を返す。
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// 値がないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 相異なる駒数の分布の個数を返す。
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// 局面 `pos` に対応する値 `value` を追加する。
+    pub fn insert<'a>(&mut self, pos: impl Into<PositionRef<'a>>, value: T) {
+        let pos = pos.into();
+        self.buckets
+            .entry(pos.color_signature())
+            .or_default()
+            .push((pos.color_profile(), value));
+        self.len += 1;
+    }
+
+    /// 局面 `pos` と駒数の分布が同じ局面たちの値を列挙する。
+    pub fn candidates<'a>(
+        &self,
+        pos: impl Into<PositionRef<'a>>,
+    ) -> impl Iterator<Item = &T> + Clone + '_ {
+        let pos = pos.into();
+        let profile = pos.color_profile();
+        self.buckets
+            .get(&pos.color_signature())
+            .into_iter()
+            .flatten()
+            .filter(move |(p, _)| *p == profile)
+            .map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use samegame_core::Board;
+
+    use super::*;
+    use crate::position::Position;
+
+    #[test]
+    fn test_color_signature() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ..3.....
+            3121....
+            12231...
+            121321..
+        "}
+        .parse()
+        .unwrap();
+        let pos = Position::new(board);
+
+        // 駒種を入れ替えた盤面は同じシグネチャを持つ。
+        let swapped: Board = pos
+            .board()
+            .to_string()
+            .chars()
+            .map(|ch| match ch {
+                '1' => '2',
+                '2' => '1',
+                ch => ch,
+            })
+            .collect::<String>()
+            .parse()
+            .unwrap();
+        let pos_swapped = Position::new(swapped);
+        assert_ne!(pos.key(), pos_swapped.key());
+        assert_eq!(pos.color_signature(), pos_swapped.color_signature());
+        assert_eq!(pos.color_profile(), [7, 5, 4, 0, 0]);
+        assert_eq!(
+            color_signature_from_counts([0, 4, 0, 5, 7]),
+            pos.color_signature()
+        );
+        assert_eq!(
+            PositionRef::new(pos.board()).color_signature(),
+            pos.color_signature()
+        );
+
+        // 着手で駒数の分布が変われば (ほぼ確実に) シグネチャも変わる。
+        let mut buckets = ColorBuckets::new();
+        buckets.insert(&pos, 0);
+        buckets.insert(&pos_swapped, 1);
+        let mut pos_child = pos.clone();
+        for (i, action) in pos.actions().enumerate() {
+            pos_child = pos.do_action(&action);
+            assert_ne!(pos_child.color_signature(), pos.color_signature());
+            buckets.insert(&pos_child, 2 + i);
+        }
+        assert_eq!(buckets.len(), 2 + pos.actions().count());
+        itertools::assert_equal(buckets.candidates(&pos), &[0, 1]);
+        assert!(buckets
+            .candidates(&pos_child)
+            .all(|&i| i >= 2 && i < buckets.len()));
+        assert!(buckets.bucket_count() < buckets.len());
+        assert_eq!(
+            ColorBuckets::<()>::new()
+                .candidates(&Position::new(Board::empty()))
+                .count(),
+            0
+        );
+    }
+}
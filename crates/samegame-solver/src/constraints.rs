@@ -0,0 +1,56 @@
+//! 探索の制約関連。
+
+use samegame_core::{Action, ActionHistory, MaskBoard, PieceSet};
+
+use crate::position::Position;
+
+/// 探索に課す制約。`Solver::solve_with_constraints()` で用いる。
+///
+/// 「最初にこの塊を消さなければならないとしたら最大スコアはいくらか」
+/// 「8 列目に一切触れない手順のうち最良のものは何か」といった、手順の比較や練習用の問いに答えるためのもの。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolveConstraints {
+    /// 最初に必ず指す手順。
+    pub forced_prefix: ActionHistory,
+    /// 着手してはならないマスの集合。
+    ///
+    /// 着手時点の盤面上で、消去する駒群がこの集合と交わる手は指せない (`forced_prefix` にも適用する)。
+    /// マスは盤面上の位置を表すので、駒が落下・左詰めされると別の駒が該当するようになる。
+    /// 指せる手がない非終了局面は行き詰まりとし、解とはみなさない。
+    pub forbidden_squares: MaskBoard,
+    /// 終了局面で全て消えていなければならない駒種の集合。
+    pub must_clear_colors: PieceSet,
+}
+
+impl Default for SolveConstraints {
+    fn default() -> Self {
+        Self {
+            forced_prefix: ActionHistory::new(),
+            forbidden_squares: MaskBoard::empty(),
+            must_clear_colors: PieceSet::empty(),
+        }
+    }
+}
+
+impl SolveConstraints {
+    /// 着手 `action` が着手禁止マスに触れないかどうかを返す。
+    pub fn allows(&self, action: &Action) -> bool {
+        !action.mask().intersects(&self.forbidden_squares)
+    }
+
+    /// 局面 `pos` が、消すべき駒種を全て消した終了局面になりうるかどうかを返す (必要条件のみ判定する)。
+    ///
+    /// 消すべき駒種の駒が 1 個だけ残っていたら、それはもう消せない。
+    pub fn can_clear(&self, pos: &Position) -> bool {
+        self.must_clear_colors
+            .iter()
+            .all(|piece| pos.piece_count(piece) != 1)
+    }
+
+    /// 終了局面 `pos` が消すべき駒種を全て消しているかどうかを返す。
+    pub fn is_cleared(&self, pos: &Position) -> bool {
+        self.must_clear_colors
+            .iter()
+            .all(|piece| pos.piece_count(piece) == 0)
+    }
+}
@@ -0,0 +1,295 @@
+//! 盤面の符号化形式間の変換。
+//!
+//! 以下の 3 形式を扱う:
+//!
+//! * テキスト: `Board` の `Display`/`FromStr` と同じ 6 行の文字列。複数の盤面は空行で区切る。
+//! * コンパクト: 1 マス 3bit に詰めた `COMPACT_LEN` (18) バイト (`Board::to_bytes()`)。マスの順序は `Square::to_index()` 順。
+//! * WRAM: ゲーム内の盤面配列と同じ並びの `WRAM_LEN` (48) バイト。マスの順序は `Square::to_game_index()` 順
+//!   (行優先、下の行から)、値は 0 が空白、`1..=5` が駒種。
+//!
+//! バイナリ形式では各盤面を固定長レコードとして単に連結する。
+//! 出所の異なるデータセットを正規化する際に使う。
+
+use std::io::{BufRead, Read, Write};
+
+use anyhow::{bail, ensure, Context as _};
+use samegame_core::{rules, Board, Col, Piece, Row, Square};
+
+/// コンパクト形式の 1 盤面あたりのバイト数。
+pub const COMPACT_LEN: usize = Board::BYTES_LEN;
+
+/// WRAM 形式の 1 盤面あたりのバイト数。
+pub const WRAM_LEN: usize = Square::NUM;
+
+/// 盤面の符号化形式。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Format {
+    /// テキスト形式。
+    Text,
+    /// 1 マス 3bit のコンパクト形式。
+    Compact,
+    /// ゲーム内の盤面配列と同じ WRAM 形式。
+    Wram,
+}
+
+impl std::str::FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "compact" => Ok(Self::Compact),
+            "wram" => Ok(Self::Wram),
+            _ => bail!("盤面の符号化形式が無効: '{s}'"),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Text => "text",
+            Self::Compact => "compact",
+            Self::Wram => "wram",
+        };
+        f.write_str(s)
+    }
+}
+
+/// `reader` から `from` 形式の盤面たちを読み、`to` 形式で `writer` に書き出す。変換した盤面数を返す。
+///
+/// 盤面を 1 つずつ処理するので、巨大なデータセットもメモリを食わずに変換できる。
+/// 不正な盤面 (重力に反する、左詰めになっていないなど) があればその位置を含むエラーを返す。
+pub fn boards<R: BufRead, W: Write>(
+    mut reader: R,
+    from: Format,
+    to: Format,
+    mut writer: W,
+) -> anyhow::Result<usize> {
+    let mut count = 0;
+
+    while let Some(board) =
+        read_board(&mut reader, from).with_context(|| format!("盤面 #{count} の読み込みに失敗"))?
+    {
+        write_board(&mut writer, &board, to)?;
+        count += 1;
+    }
+
+    writer.flush()?;
+
+    Ok(count)
+}
+
+/// `reader` から `format` 形式の盤面を 1 つ読む。入力の終端に達していれば `None` を返す。
+pub fn read_board<R: BufRead>(reader: &mut R, format: Format) -> anyhow::Result<Option<Board>> {
+    match format {
+        Format::Text => {
+            let mut lines = Vec::<String>::with_capacity(Row::NUM);
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    break;
+                }
+                let line = line.trim_end_matches(['\r', '\n']);
+                if line.is_empty() {
+                    // 盤面間の区切り (連続する空行や先頭の空行は無視)。
+                    if lines.is_empty() {
+                        continue;
+                    }
+                    break;
+                }
+                lines.push(line.to_owned());
+                if lines.len() == Row::NUM {
+                    break;
+                }
+            }
+            if lines.is_empty() {
+                return Ok(None);
+            }
+            let board = lines.join("\n").parse::<Board>()?;
+            ensure_gravity(&board)?;
+            Ok(Some(board))
+        }
+        Format::Compact => read_record::<_, COMPACT_LEN>(reader)?
+            .map(|buf| decode_compact(&buf))
+            .transpose(),
+        Format::Wram => read_record::<_, WRAM_LEN>(reader)?
+            .map(|buf| decode_wram(&buf))
+            .transpose(),
+    }
+}
+
+/// 盤面 `board` を `format` 形式で `writer` に書き出す。
+pub fn write_board<W: Write>(writer: &mut W, board: &Board, format: Format) -> std::io::Result<()> {
+    match format {
+        Format::Text => writeln!(writer, "{board}"),
+        Format::Compact => writer.write_all(&encode_compact(board)),
+        Format::Wram => writer.write_all(&encode_wram(board)),
+    }
+}
+
+/// 盤面をコンパクト形式で符号化する (`Board::to_bytes()` と同じ)。
+pub fn encode_compact(board: &Board) -> [u8; COMPACT_LEN] {
+    board.to_bytes()
+}
+
+/// コンパクト形式の盤面を復号する。不正な値を含む場合や、重力に反する場合はエラーを返す。
+pub fn decode_compact(buf: &[u8; COMPACT_LEN]) -> anyhow::Result<Board> {
+    Board::from_bytes(buf)
+}
+
+/// 盤面を WRAM 形式で符号化する。
+pub fn encode_wram(board: &Board) -> [u8; WRAM_LEN] {
+    std::array::from_fn(|idx| {
+        let sq = Square::from_game_index(idx).unwrap();
+        board.get(sq).map_or(0, Piece::to_inner)
+    })
+}
+
+/// WRAM 形式の盤面を復号する。不正な値を含む場合や、重力に反する場合はエラーを返す。
+pub fn decode_wram(buf: &[u8; WRAM_LEN]) -> anyhow::Result<Board> {
+    let mut grid = rules::Grid::default();
+
+    for (idx, &value) in buf.iter().enumerate() {
+        let sq = Square::from_game_index(idx).unwrap();
+        grid[sq.col().to_index()][sq.row().to_index()] = value;
+    }
+
+    Board::from_grid(&grid)
+}
+
+/// テキスト形式は行ごとの左詰めしか検査しないので、列ごとの下詰めを確かめる。
+fn ensure_gravity(board: &Board) -> anyhow::Result<()> {
+    for col in Col::all() {
+        let mut below_blank = false;
+        for row in Row::all() {
+            let sq = Square::new(col, row);
+            match board.get(sq) {
+                Some(_) => ensure!(!below_blank, "盤面の列 {col} が下詰めになっていない"),
+                None => below_blank = true,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 固定長レコードを 1 つ読む。入力の終端に達していれば `None` を返す。
+/// レコードの途中で終端に達した場合はエラーを返す。
+fn read_record<R: Read, const N: usize>(reader: &mut R) -> anyhow::Result<Option<[u8; N]>> {
+    let mut buf = [0; N];
+    let mut len = 0;
+
+    while len < N {
+        let n = reader.read(&mut buf[len..])?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+    }
+
+    match len {
+        0 => Ok(None),
+        _ if len == N => Ok(Some(buf)),
+        _ => bail!("レコードが途中で切れている ({len}/{N} バイト)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use samegame_core::RandomBoardParam;
+
+    #[test]
+    fn test_convert_boards() {
+        let samples: Vec<Board> = [
+            "0x0012,0x34,40,3"
+                .parse::<RandomBoardParam>()
+                .unwrap()
+                .gen_board()
+                .0,
+            indoc! {"
+                ........
+                ........
+                ........
+                1.......
+                12.5....
+                21155...
+            "}
+            .parse()
+            .unwrap(),
+            Board::empty(),
+        ]
+        .into();
+
+        let mut text = Vec::new();
+        for board in &samples {
+            write_board(&mut text, board, Format::Text).unwrap();
+        }
+
+        // 全形式の組について往復変換で元に戻ることを確かめる。
+        let formats = [Format::Text, Format::Compact, Format::Wram];
+        for from in formats {
+            let mut src = Vec::new();
+            assert_eq!(
+                boards(text.as_slice(), Format::Text, from, &mut src).unwrap(),
+                3
+            );
+            for to in formats {
+                let mut dst = Vec::new();
+                assert_eq!(boards(src.as_slice(), from, to, &mut dst).unwrap(), 3);
+                let mut back = Vec::new();
+                boards(dst.as_slice(), to, Format::Text, &mut back).unwrap();
+                assert_eq!(back, text, "{from} -> {to}");
+            }
+        }
+
+        assert_eq!(encode_compact(&samples[0]).len(), 18);
+        let wram = encode_wram(&samples[1]);
+        assert_eq!(wram[..8], [2, 1, 1, 5, 5, 0, 0, 0]);
+
+        // 先頭や連続する空行は無視する。
+        let mut dst = Vec::new();
+        let src = format!("\n\n{}\n\n", String::from_utf8(text.clone()).unwrap());
+        assert_eq!(
+            boards(src.as_bytes(), Format::Text, Format::Text, &mut dst).unwrap(),
+            3
+        );
+        assert_eq!(dst, text);
+    }
+
+    #[test]
+    fn test_convert_invalid() {
+        let sink = || std::io::sink();
+
+        // 浮いた駒。
+        let src = indoc! {"
+            ........
+            ........
+            .3......
+            1.......
+            12.5....
+            21155...
+        "};
+        assert!(boards(src.as_bytes(), Format::Text, Format::Wram, sink()).is_err());
+
+        // 無効な値。
+        let mut wram = [1; WRAM_LEN];
+        wram[0] = 6;
+        assert!(boards(wram.as_slice(), Format::Wram, Format::Text, sink()).is_err());
+
+        // 左詰めになっていない。
+        let mut wram = [0; WRAM_LEN];
+        wram[1] = 1;
+        assert!(boards(wram.as_slice(), Format::Wram, Format::Text, sink()).is_err());
+
+        // 途中で切れたレコード。
+        let src = [0; COMPACT_LEN + 1];
+        assert!(boards(src.as_slice(), Format::Compact, Format::Text, sink()).is_err());
+
+        assert_eq!("compact".parse::<Format>().unwrap(), Format::Compact);
+        assert!("json".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,481 @@
+//! ソルバー用 DP テーブル関連。
+
+use std::fs::File;
+use std::io::{BufWriter, Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use samegame_core::Score;
+
+use crate::convert::{encode_compact, COMPACT_LEN};
+use crate::hash::{hashmap_memory_usage, U64HashMap};
+use crate::logging::warn;
+use crate::position::Position;
+
+/// DP テーブルをディスクへ退避 (spill) する設定。
+///
+/// メモリ上のエントリ数が `mem_entry_limit` を超えたら、最後に参照されてから長いエントリから順に、
+/// `mem_entry_limit` の半数を残してディスク上の一時ファイルへ退避する。
+/// 退避したエントリは盤面を 3bit/マスに圧縮して書き出し、メモリ上にはハッシュ値からファイル内位置への索引のみを残す。
+/// 退避エントリを取り出すとファイル上のレコードは無効になり、無効なレコードが半数を超えたらファイルを詰め直す。
+///
+/// 退避によりメモリ使用量は抑えられるが、退避エントリの参照にはディスク I/O を伴う。
+/// そのコストは `SearchStats` の `dp_spill_*` フィールドで確認できる。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DpSpillConfig {
+    /// メモリ上に保持する DP エントリ数の上限。
+    pub mem_entry_limit: usize,
+    /// 一時ファイルを作るディレクトリ。
+    pub dir: PathBuf,
+}
+
+impl DpSpillConfig {
+    /// 一時ファイルのディレクトリを OS の一時ディレクトリとして設定を作る。
+    pub fn new(mem_entry_limit: usize) -> Self {
+        Self {
+            mem_entry_limit,
+            dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// DP テーブルのディスク退避に関する統計。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct DpSpillStats {
+    pub(crate) writes: u64,
+    pub(crate) reads: u64,
+    pub(crate) io_elapsed: Duration,
+}
+
+/// 各局面から追加で獲得しうるスコアの上界を記録する DP テーブル。
+///
+/// エントリを失っても上界が緩くなるだけで探索結果の正しさには影響しないので、
+/// ディスク退避で I/O エラーが起きた場合は警告を出して退避をやめ、以降はメモリ上のみで動作する。
+#[derive(Debug, Default)]
+pub(crate) struct DpTable {
+    mem: U64HashMap<Position, DpEntry>,
+    /// エントリを参照するたびに増える時刻。退避対象の選択に使う。
+    clock: u64,
+    spill: Option<DpSpill>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct DpEntry {
+    value: Score,
+    /// 最後に参照された時刻 (`DpTable::clock`)。
+    last_used: u64,
+}
+
+impl DpTable {
+    pub(crate) fn new(spill_config: Option<&DpSpillConfig>) -> Self {
+        let spill = spill_config.and_then(|config| match DpSpill::new(config) {
+            Ok(spill) => Some(spill),
+            Err(e) => {
+                warn!("DP 退避ファイルを作れないため、退避を無効にする: {e:#}");
+                None
+            }
+        });
+
+        Self {
+            mem: U64HashMap::default(),
+            clock: 0,
+            spill,
+        }
+    }
+
+    /// メモリ上のエントリ数を返す。
+    pub(crate) fn len(&self) -> usize {
+        self.mem.len()
+    }
+
+    /// ディスクへ退避したエントリ数を返す。
+    pub(crate) fn spilled_len(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.index.len())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0 && self.spilled_len() == 0
+    }
+
+    /// 全エントリを削除し、退避統計をリセットする。
+    pub(crate) fn clear(&mut self) {
+        self.mem.clear();
+        self.clock = 0;
+        if let Some(spill) = &mut self.spill {
+            if let Err(e) = spill.clear() {
+                warn!("DP 退避ファイルをクリアできないため、退避を無効にする: {e:#}");
+                self.spill = None;
+            }
+        }
+    }
+
+    /// メモリ上のエントリを全て取り出し、残りのエントリを削除する。
+    pub(crate) fn take_mem(&mut self) -> U64HashMap<Position, Score> {
+        let mem = std::mem::take(&mut self.mem);
+        self.clear();
+        mem.into_iter()
+            .map(|(pos, entry)| (pos, entry.value))
+            .collect()
+    }
+
+    /// メモリ使用量の概算値 (バイト数) を返す。退避ファイルの分は含まない。
+    pub(crate) fn approx_memory_usage(&self) -> usize {
+        let spill = self
+            .spill
+            .as_ref()
+            .map_or(0, |spill| hashmap_memory_usage(&spill.index));
+
+        hashmap_memory_usage(&self.mem) + spill
+    }
+
+    /// 退避統計を返す。
+    pub(crate) fn spill_stats(&self) -> DpSpillStats {
+        self.spill
+            .as_ref()
+            .map_or_else(DpSpillStats::default, |spill| spill.stats.clone())
+    }
+
+    /// `pos` のエントリの値を返す。エントリがなければ `f()` の値を挿入してそれを返す。
+    pub(crate) fn get_or_insert_with(
+        &mut self,
+        pos: &Position,
+        f: impl FnOnce() -> Score,
+    ) -> Score {
+        let now = self.tick();
+        if let Some(entry) = self.mem.get_mut(pos) {
+            entry.last_used = now;
+            return entry.value;
+        }
+
+        let value = self.take_spilled(pos).unwrap_or_else(f);
+        self.insert(pos.clone(), value);
+
+        value
+    }
+
+    /// `pos` のエントリの値を設定する。
+    pub(crate) fn set(&mut self, pos: &Position, value: Score) {
+        let now = self.tick();
+        if let Some(entry) = self.mem.get_mut(pos) {
+            *entry = DpEntry {
+                value,
+                last_used: now,
+            };
+            return;
+        }
+
+        // 退避済みのエントリがあれば古くなるので索引から消しておく。
+        if let Some(spill) = &mut self.spill {
+            spill.index.remove(&pos.key());
+        }
+        self.insert(pos.clone(), value);
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// 時刻は `tick()` で進めておくこと。
+    fn insert(&mut self, pos: Position, value: Score) {
+        let entry = DpEntry {
+            value,
+            last_used: self.clock,
+        };
+        self.mem.insert(pos, entry);
+
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        if self.mem.len() <= spill.mem_entry_limit {
+            return;
+        }
+
+        if let Err(e) = spill.evict(&mut self.mem) {
+            warn!("DP エントリを退避できないため、退避を無効にする: {e:#}");
+            self.spill = None;
+        }
+    }
+
+    /// 退避済みのエントリがあれば取り出す (索引からは削除する)。
+    fn take_spilled(&mut self, pos: &Position) -> Option<Score> {
+        let spill = self.spill.as_mut()?;
+
+        match spill.take(pos) {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("DP 退避ファイルを読めないため、退避を無効にする: {e:#}");
+                self.spill = None;
+                None
+            }
+        }
+    }
+}
+
+/// 退避レコードのバイト数 (盤面 18 バイト + 値 4 バイト)。
+const SPILL_RECORD_LEN: usize = SPILL_BOARD_LEN + 4;
+const SPILL_BOARD_LEN: usize = COMPACT_LEN;
+
+#[derive(Debug)]
+struct DpSpill {
+    mem_entry_limit: usize,
+
+    path: PathBuf,
+    file: File,
+    file_len: u64,
+
+    /// ハッシュ値からファイル内のレコード位置への索引。
+    index: U64HashMap<u64, u64>,
+
+    stats: DpSpillStats,
+}
+
+impl DpSpill {
+    fn new(config: &DpSpillConfig) -> std::io::Result<Self> {
+        static SEQ: AtomicU64 = AtomicU64::new(0);
+
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = config
+            .dir
+            .join(format!("samegame-dp-{}-{seq}.spill", std::process::id()));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(Self {
+            mem_entry_limit: config.mem_entry_limit.max(1),
+            path,
+            file,
+            file_len: 0,
+            index: U64HashMap::default(),
+            stats: DpSpillStats::default(),
+        })
+    }
+
+    fn clear(&mut self) -> std::io::Result<()> {
+        self.file.set_len(0)?;
+        self.file_len = 0;
+        self.index.clear();
+        self.stats = DpSpillStats::default();
+
+        Ok(())
+    }
+
+    /// メモリ上のエントリのうち最近参照された `mem_entry_limit` の半数を残し、
+    /// 残りをファイルへ書き出してメモリから削除する。
+    fn evict(&mut self, mem: &mut U64HashMap<Position, DpEntry>) -> std::io::Result<()> {
+        let start = Instant::now();
+
+        if self.dead_record_count() > self.index.len() {
+            self.compact()?;
+        }
+
+        // 参照時刻は相異なるので、これより前に参照されたエントリがちょうど `mem.len() - keep` 個ある。
+        let keep = self.mem_entry_limit / 2;
+        let mut last_useds: Vec<u64> = mem.values().map(|entry| entry.last_used).collect();
+        let evict_count = mem.len() - keep;
+        let threshold = if keep == 0 {
+            u64::MAX
+        } else {
+            *last_useds.select_nth_unstable(evict_count).1
+        };
+
+        let mut buf = Vec::<u8>::with_capacity(SPILL_RECORD_LEN * evict_count);
+        let mut offset = self.file_len;
+        mem.retain(|pos, entry| {
+            if entry.last_used >= threshold {
+                return true;
+            }
+            buf.extend_from_slice(&encode_compact(pos.board()));
+            buf.extend_from_slice(&entry.value.to_inner().to_le_bytes());
+            self.index.insert(pos.key(), offset);
+            offset += SPILL_RECORD_LEN as u64;
+            false
+        });
+
+        self.file.seek(SeekFrom::Start(self.file_len))?;
+        self.file.write_all(&buf)?;
+        self.stats.writes += (buf.len() / SPILL_RECORD_LEN) as u64;
+        self.file_len = offset;
+
+        self.stats.io_elapsed += start.elapsed();
+
+        Ok(())
+    }
+
+    /// ファイル上の無効な (索引から参照されない) レコード数を返す。
+    fn dead_record_count(&self) -> usize {
+        self.file_len as usize / SPILL_RECORD_LEN - self.index.len()
+    }
+
+    /// 有効なレコードのみを新しいファイルへ詰めて書き直し、元のファイルと置き換える。
+    fn compact(&mut self) -> std::io::Result<()> {
+        let mut records: Vec<(u64, u64)> = self
+            .index
+            .iter()
+            .map(|(&key, &offset)| (offset, key))
+            .collect();
+        records.sort_unstable();
+
+        let path = self.path.with_extension("spill.compact");
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+
+        let mut wtr = BufWriter::new(&file);
+        let mut record = [0; SPILL_RECORD_LEN];
+        for (i, &(offset, key)) in records.iter().enumerate() {
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.read_exact(&mut record)?;
+            wtr.write_all(&record)?;
+            self.index.insert(key, (SPILL_RECORD_LEN * i) as u64);
+        }
+        wtr.flush()?;
+        drop(wtr);
+
+        std::fs::rename(&path, &self.path)?;
+        self.file = file;
+        self.file_len = (SPILL_RECORD_LEN * records.len()) as u64;
+
+        Ok(())
+    }
+
+    fn take(&mut self, pos: &Position) -> std::io::Result<Option<Score>> {
+        let Some(&offset) = self.index.get(&pos.key()) else {
+            return Ok(None);
+        };
+
+        let start = Instant::now();
+
+        let mut record = [0; SPILL_RECORD_LEN];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut record)?;
+        self.stats.reads += 1;
+
+        self.stats.io_elapsed += start.elapsed();
+
+        // ハッシュ値が衝突しているなら別の局面なので無視する。
+        if record[..SPILL_BOARD_LEN] != encode_compact(pos.board()) {
+            return Ok(None);
+        }
+        self.index.remove(&pos.key());
+
+        let value = u32::from_le_bytes(record[SPILL_BOARD_LEN..].try_into().unwrap());
+        Ok(Some(Score::new(value)))
+    }
+}
+
+impl Drop for DpSpill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_dp_spill() {
+        let boards = [
+            indoc! {"
+                ........
+                ........
+                ........
+                1.......
+                12.5....
+                21155...
+            "},
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                .34.....
+                2251....
+            "},
+            indoc! {"
+                1......2
+                155....2
+                111.4..2
+                12144..1
+                12133.51
+                12135551
+            "},
+        ];
+        let positions: Vec<Position> = boards
+            .into_iter()
+            .map(|s| Position::new(s.parse().unwrap()))
+            .collect();
+
+        let mut dp = DpTable::new(Some(&DpSpillConfig::new(2)));
+        for (i, pos) in positions.iter().enumerate() {
+            dp.get_or_insert_with(pos, || Score::new(i as u32));
+        }
+        assert!(dp.spilled_len() > 0);
+
+        for (i, pos) in positions.iter().enumerate() {
+            assert_eq!(
+                dp.get_or_insert_with(pos, || unreachable!()),
+                Score::new(i as u32)
+            );
+        }
+        assert!(dp.spill_stats().reads > 0);
+
+        // 最近参照されたエントリはメモリに残る。
+        let positions: Vec<Position> = (1..=6)
+            .map(|n| {
+                let s = format!("{}{:.<8}\n", "........\n".repeat(5), "1".repeat(n));
+                Position::new(s.parse().unwrap())
+            })
+            .collect();
+        let mut dp = DpTable::new(Some(&DpSpillConfig::new(4)));
+        for (i, pos) in positions[..4].iter().enumerate() {
+            dp.set(pos, Score::new(i as u32));
+        }
+        assert_eq!(
+            dp.get_or_insert_with(&positions[0], || unreachable!()),
+            Score::new(0)
+        );
+        dp.set(&positions[4], Score::new(4));
+        assert_eq!(dp.len(), 2);
+        assert_eq!(dp.spilled_len(), 3);
+        assert!(dp.mem.contains_key(&positions[0]));
+        assert!(dp.mem.contains_key(&positions[4]));
+
+        // 取り出して無効になったレコードが半数を超えると、次の退避時にファイルを詰め直す。
+        for (i, pos) in positions[1..3].iter().enumerate() {
+            assert_eq!(
+                dp.get_or_insert_with(pos, || unreachable!()),
+                Score::new(i as u32 + 1)
+            );
+        }
+        let spill = dp.spill.as_ref().unwrap();
+        assert_eq!(spill.file_len, 3 * SPILL_RECORD_LEN as u64);
+        assert_eq!(spill.index.len(), 1);
+        dp.set(&positions[5], Score::new(5));
+        let spill = dp.spill.as_ref().unwrap();
+        assert_eq!(spill.dead_record_count(), 0);
+        assert_eq!(
+            spill.file_len,
+            (spill.index.len() * SPILL_RECORD_LEN) as u64
+        );
+        for (i, pos) in positions.iter().enumerate() {
+            assert_eq!(
+                dp.get_or_insert_with(pos, || unreachable!()),
+                Score::new(i as u32)
+            );
+        }
+
+        dp.clear();
+        assert!(dp.is_empty());
+    }
+}
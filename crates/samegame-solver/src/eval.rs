@@ -0,0 +1,235 @@
+//! 局面のヒューリスティック評価関数関連。
+//!
+//! ビームサーチでの局面の順位付けや、対話的なツールでの候補手の順位付けに使う。
+//! 探索の枝刈りに使う上界関数 (`BoundFn`) とは異なり、admissible である必要はない。
+
+use samegame_core::{Action, ActionHistory, FastRng, Piece, Score};
+
+use crate::position::Position;
+use crate::terminal::{GameTerminalEval, TerminalEval as _};
+
+/// 局面の評価関数。値が大きいほど良い局面とみなす。
+///
+/// 値は「その局面から今後得られそうなスコア」程度の尺度を想定している (現スコアは含まない)。
+///
+/// `Fn(&Position) -> i32` を満たすクロージャはこのトレイトを実装している。
+pub trait Eval {
+    /// 局面 `pos` を評価する。
+    fn eval(&self, pos: &Position) -> i32;
+}
+
+impl<F: Fn(&Position) -> i32> Eval for F {
+    fn eval(&self, pos: &Position) -> i32 {
+        self(pos)
+    }
+}
+
+/// 常に 0 を返す評価関数。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ZeroEval;
+
+impl Eval for ZeroEval {
+    fn eval(&self, _pos: &Position) -> i32 {
+        0
+    }
+}
+
+/// 獲得スコア最大の手を選び続けたときの追加獲得スコア (パーフェクトボーナスを含む)。
+///
+/// 最も正確だが最も重い。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GreedyPotential;
+
+impl Eval for GreedyPotential {
+    fn eval(&self, pos: &Position) -> i32 {
+        let mut pos = pos.clone();
+        let mut gain = 0;
+
+        while pos.has_action() {
+            let action = pos.actions().max_by_key(Action::square_count).unwrap();
+            gain += action.gain().to_inner();
+            pos = pos.do_action(&action);
+        }
+        gain += GameTerminalEval.final_gain(&pos).to_inner();
+
+        gain as i32
+    }
+}
+
+/// 各合法手の獲得スコアの総和。
+///
+/// 大きな塊が多い局面ほど高く評価する。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GroupSquares;
+
+impl Eval for GroupSquares {
+    fn eval(&self, pos: &Position) -> i32 {
+        pos.actions()
+            .map(|action| action.gain().to_inner() as i32)
+            .sum()
+    }
+}
+
+/// 駒数の少ない駒種へのペナルティ。
+///
+/// 盤面上に少数しかない駒種は繋げて消すのが難しいので、駒数 `n` (`1..=2`) の駒種ごとに `-(3 - n)` を加える。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ColorBalance;
+
+impl Eval for ColorBalance {
+    fn eval(&self, pos: &Position) -> i32 {
+        Piece::all()
+            .map(|piece| match pos.piece_count(piece) {
+                0 => 0,
+                n @ 1..=2 => -(3 - i32::from(n)),
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// 全消しのしやすさの見積もり。
+///
+/// 孤立した駒 1 個につき -1, 1 個しかない (よって決して消せない) 駒種 1 つにつき -100 とする。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Clearability;
+
+impl Eval for Clearability {
+    fn eval(&self, pos: &Position) -> i32 {
+        let isolated = pos
+            .board()
+            .piece_components()
+            .filter(|(_piece, mb)| mb.is_single())
+            .count() as i32;
+        let singletons = Piece::all()
+            .filter(|&piece| pos.piece_count(piece) == 1)
+            .count() as i32;
+
+        -isolated - 100 * singletons
+    }
+}
+
+/// 複数の評価関数の重み付き和。
+#[derive(Default)]
+pub struct WeightedSum {
+    terms: Vec<(i32, Box<dyn Eval + Send + Sync>)>,
+}
+
+impl std::fmt::Debug for WeightedSum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeightedSum")
+            .field("term_count", &self.terms.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl WeightedSum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 重み `weight` の項として評価関数 `eval` を追加する。
+    pub fn term(mut self, weight: i32, eval: impl Eval + Send + Sync + 'static) -> Self {
+        self.terms.push((weight, Box::new(eval)));
+        self
+    }
+}
+
+impl Eval for WeightedSum {
+    fn eval(&self, pos: &Position) -> i32 {
+        self.terms
+            .iter()
+            .map(|(weight, eval)| weight * eval.eval(pos))
+            .sum()
+    }
+}
+
+/// 局面 `pos` の合法手を (獲得スコア + 着手後の局面の評価値) の降順に並べて返す。
+///
+/// 同値の手の順序は `Position::actions()` の列挙順となる。
+pub fn rank_actions(pos: &Position, eval: &dyn Eval) -> Vec<(Action, i32)> {
+    let mut res: Vec<(Action, i32)> = pos
+        .actions()
+        .map(|action| {
+            let value = action.gain().to_inner() as i32 + eval.eval(&pos.do_action(&action));
+            (action, value)
+        })
+        .collect();
+    res.sort_by_key(|&(_, value)| std::cmp::Reverse(value));
+
+    res
+}
+
+/// 局面 `pos` から一様ランダムに合法手を選び続けるプレイアウトを行い、(追加獲得スコア, 手順) を返す。
+///
+/// 追加獲得スコアはパーフェクトボーナスを含む。
+pub fn random_playout(pos: &Position, rng: &mut FastRng) -> (Score, ActionHistory) {
+    let mut pos = pos.clone();
+    let mut gain = Score::ZERO;
+    let mut history = ActionHistory::new();
+
+    while pos.has_action() {
+        let actions: Vec<Action> = pos.actions().collect();
+        let action = rng.choose(&actions).unwrap();
+        gain += action.gain();
+        history.push(action.least_square());
+        pos = pos.do_action(action);
+    }
+    gain += GameTerminalEval.final_gain(&pos);
+
+    (gain, history)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use samegame_core::{Board, Score};
+
+    use super::*;
+    use crate::solver::Solver;
+
+    #[test]
+    fn test_eval() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+        let pos = Position::new(board.clone());
+
+        // 貪欲法のスコアは最適値を超えない。
+        let (score_opt, _) = Solver::new(Score::ZERO).solve(board).unwrap();
+        let greedy = GreedyPotential.eval(&pos);
+        assert!(0 < greedy && greedy <= score_opt.to_inner() as i32);
+
+        // 駒 1 の 2 個の塊が 2 つ、駒 5 の 3 個の塊が 1 つ。
+        assert_eq!(GroupSquares.eval(&pos), 1 + 1 + 4);
+        // 駒 2 のみ 2 個。
+        assert_eq!(ColorBalance.eval(&pos), -1);
+        // 孤立駒は (1,1), (2,2) の駒 2。
+        assert_eq!(Clearability.eval(&pos), -2);
+
+        let eval = WeightedSum::new()
+            .term(2, GroupSquares)
+            .term(1, Clearability);
+        assert_eq!(eval.eval(&pos), 2 * 6 - 2);
+
+        let ranked = rank_actions(&pos, &ZeroEval);
+        assert_eq!(ranked.len(), pos.actions().count());
+        assert!(ranked.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert_eq!(ranked[0].0.piece(), Piece::from_inner(5).unwrap());
+
+        let playout = |seed| random_playout(&pos, &mut FastRng::new(seed));
+        for seed in 0..10 {
+            let (gain, history) = playout(seed);
+            assert!(gain <= score_opt);
+            assert_eq!(crate::verify::replay(pos.board(), &history), Ok(gain));
+            assert_eq!(playout(seed), (gain, history));
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! ソルバー設定の比較実験 (A/B テスト) 用ユーティリティ。
+//!
+//! 枝刈りや上界などを変更した際、固定された盤面サンプルに対して 2 つのソルバー設定を走らせ、
+//! 探索統計を比較する。
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use samegame_core::{Board, FastRng, GameEntropy, RandomBoardParam, Score};
+
+use crate::solver::{SearchStats, Solver};
+
+/// 盤面生成パラメータ全体から等間隔に `n` 個の有効な盤面を選ぶ。
+///
+/// 結果は常に同じになるので、再現性のある実験入力として使える。
+/// 選んだパラメータが再生成判定に引っかかる場合、それ以降で最初に有効なパラメータを使う。
+pub fn sample_boards(n: usize) -> Vec<(RandomBoardParam, Board)> {
+    if n == 0 {
+        return vec![];
+    }
+
+    let step = (PARAM_COUNT / n).max(1);
+
+    let mut res = Vec::with_capacity(n);
+    let mut idx_next = 0;
+    for i in 0..n {
+        let idx_start = (i * step).max(idx_next);
+        let Some((idx, param, board)) = (idx_start..PARAM_COUNT).find_map(|idx| {
+            let param = param_from_index(idx);
+            param
+                .gen_legal_board()
+                .map(|(board, _)| (idx, param, board))
+        }) else {
+            break;
+        };
+        res.push((param, board));
+        idx_next = idx + 1;
+    }
+
+    res
+}
+
+/// 有効な盤面全体から一様かつ非復元に `n` 個の盤面を抽出する。結果は列挙順 (`RandomBoardParam` の順序) に並ぶ。
+///
+/// 同じ `(n, seed)` に対しては常に同じ結果となる。
+/// `sample_boards()` と異なり、再生成判定に引っかかるパラメータの直後のパラメータが選ばれやすくなる偏りはない
+/// (全パラメータの列挙順の添字を一様に選び、無効なものや重複は棄却して選び直す)。
+///
+/// `n` が有効な盤面の総数以上なら、有効な盤面を全て返す。
+/// `n` が全パラメータ数の 1/4 を超える場合は棄却が多くなるので、代わりに全列挙してから抽出する。
+pub fn sample_legal_boards(n: usize, seed: u64) -> Vec<(RandomBoardParam, Board)> {
+    let param_count = PARAM_COUNT;
+    let mut rng = FastRng::new(seed);
+
+    let mut res: Vec<(RandomBoardParam, Board)> = if n > param_count / 4 {
+        let mut all: Vec<_> = RandomBoardParam::all()
+            .filter_map(|param| param.gen_legal_board().map(|(board, _)| (param, board)))
+            .collect();
+        rng.shuffle(&mut all);
+        all.truncate(n);
+        all
+    } else {
+        let mut chosen = HashSet::<usize>::with_capacity(n);
+        let mut res = Vec::with_capacity(n);
+        while res.len() < n {
+            let idx = rng.gen_below(param_count as u64) as usize;
+            if chosen.contains(&idx) {
+                continue;
+            }
+            chosen.insert(idx);
+            let param = param_from_index(idx);
+            if let Some((board, _)) = param.gen_legal_board() {
+                res.push((param, board));
+            }
+        }
+        res
+    };
+
+    res.sort_unstable_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+    res
+}
+
+const NMI_COUNTER_COUNT: usize = u8::MAX as usize + 1;
+
+/// `RandomBoardParam::all()` が列挙するパラメータの総数。
+const PARAM_COUNT: usize = 0x8000 * NMI_COUNTER_COUNT * GameEntropy::NUM;
+
+/// `RandomBoardParam::all()` の列挙順で `idx` 番目のパラメータを返す。
+fn param_from_index(idx: usize) -> RandomBoardParam {
+    let entropy_count = GameEntropy::NUM;
+    let nmi_counter_count = NMI_COUNTER_COUNT;
+
+    let entropy = GameEntropy::from_inner((idx % entropy_count) as u8).unwrap();
+    let nmi_counter = ((idx / entropy_count) % nmi_counter_count) as u8;
+    let rng_state = (idx / entropy_count / nmi_counter_count) as u16;
+
+    RandomBoardParam::new(rng_state, nmi_counter, 40, entropy).unwrap()
+}
+
+/// 1 つの盤面に対する試行結果。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrialResult {
+    /// 得られた最大スコア。枝刈りにより解が見つからなかった場合は `None`。
+    pub score: Option<Score>,
+    /// 探索統計。
+    pub stats: SearchStats,
+}
+
+/// 2 つのソルバー設定による試行結果の組。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AbComparison {
+    pub trials_a: Vec<TrialResult>,
+    pub trials_b: Vec<TrialResult>,
+}
+
+impl AbComparison {
+    /// 比較結果の要約を返す。
+    pub fn summary(&self) -> AbSummary {
+        fn sum_nodes(trials: &[TrialResult]) -> u64 {
+            trials.iter().map(|trial| trial.stats.nodes).sum()
+        }
+        fn sum_elapsed(trials: &[TrialResult]) -> Duration {
+            trials.iter().map(|trial| trial.stats.elapsed).sum()
+        }
+
+        let score_mismatch_count = self
+            .trials_a
+            .iter()
+            .zip(&self.trials_b)
+            .filter(|(a, b)| a.score != b.score)
+            .count();
+
+        AbSummary {
+            board_count: self.trials_a.len(),
+            nodes_a: sum_nodes(&self.trials_a),
+            nodes_b: sum_nodes(&self.trials_b),
+            elapsed_a: sum_elapsed(&self.trials_a),
+            elapsed_b: sum_elapsed(&self.trials_b),
+            score_mismatch_count,
+        }
+    }
+}
+
+/// A/B 比較結果の要約。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AbSummary {
+    /// 盤面数。
+    pub board_count: usize,
+    /// 設定 A の総訪問ノード数。
+    pub nodes_a: u64,
+    /// 設定 B の総訪問ノード数。
+    pub nodes_b: u64,
+    /// 設定 A の総探索時間。
+    pub elapsed_a: Duration,
+    /// 設定 B の総探索時間。
+    pub elapsed_b: Duration,
+    /// 両設定で結果のスコアが異なった盤面数。
+    pub score_mismatch_count: usize,
+}
+
+impl AbSummary {
+    /// 設定 A に対する設定 B の訪問ノード数の比を返す。
+    pub fn node_ratio(&self) -> f64 {
+        self.nodes_b as f64 / self.nodes_a as f64
+    }
+}
+
+impl std::fmt::Display for AbSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "boards: {}", self.board_count)?;
+        writeln!(
+            f,
+            "nodes: A={} B={} (B/A={:.3})",
+            self.nodes_a,
+            self.nodes_b,
+            self.node_ratio()
+        )?;
+        writeln!(
+            f,
+            "elapsed: A={:.3?} B={:.3?}",
+            self.elapsed_a, self.elapsed_b
+        )?;
+        writeln!(f, "score mismatches: {}", self.score_mismatch_count)
+    }
+}
+
+/// 同じ盤面列に対して 2 つのソルバーを走らせ、結果を比較する。
+///
+/// 各ソルバーの枝刈り用スコア閾値は実験中に変更しない。
+pub fn run_ab(boards: &[Board], solver_a: &mut Solver, solver_b: &mut Solver) -> AbComparison {
+    fn run(boards: &[Board], solver: &mut Solver) -> Vec<TrialResult> {
+        boards
+            .iter()
+            .map(|board| {
+                let (res, stats) = solver.solve_with_stats(board.clone());
+                TrialResult {
+                    score: res.map(|(score, _)| score),
+                    stats,
+                }
+            })
+            .collect()
+    }
+
+    AbComparison {
+        trials_a: run(boards, solver_a),
+        trials_b: run(boards, solver_b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_run_ab() {
+        let boards: Vec<Board> = [
+            indoc! {"
+                ........
+                ........
+                ........
+                1.......
+                1.5.....
+                234.....
+            "},
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                .34.....
+                2251....
+            "},
+        ]
+        .into_iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+
+        let cmp = run_ab(
+            &boards,
+            &mut Solver::new(Score::ZERO),
+            &mut Solver::new(Score::ZERO),
+        );
+        let summary = cmp.summary();
+
+        assert_eq!(summary.board_count, 2);
+        assert_eq!(summary.nodes_a, summary.nodes_b);
+        assert_eq!(summary.score_mismatch_count, 0);
+    }
+
+    #[test]
+    fn test_sample_boards() {
+        let sample = sample_boards(16);
+        assert_eq!(sample.len(), 16);
+        assert!(sample.windows(2).all(|w| w[0].0 < w[1].0));
+        for (param, board) in &sample {
+            assert_eq!(param.gen_legal_board().unwrap().0, *board);
+        }
+
+        // パラメータ空間全体に散らばる (i 番目は内部状態が 0x8000 * i / 16 付近)。
+        for (i, (param, _)) in sample.iter().enumerate() {
+            let rng_state = usize::from(param.rng_state);
+            assert!(
+                (0x800 * i..0x800 * (i + 1)).contains(&rng_state),
+                "{i}: {param}"
+            );
+        }
+
+        assert!(sample_boards(0).is_empty());
+    }
+
+    #[test]
+    fn test_sample_legal_boards() {
+        let sample = sample_legal_boards(50, 12345);
+        assert_eq!(sample.len(), 50);
+        assert!(sample.windows(2).all(|w| w[0].0 < w[1].0));
+        for (param, board) in &sample {
+            assert_eq!(param.gen_legal_board().unwrap().0, *board);
+        }
+
+        // 再現性がある。
+        assert_eq!(sample_legal_boards(50, 12345), sample);
+        assert_ne!(sample_legal_boards(50, 54321), sample);
+
+        assert_eq!(param_from_index(0), RandomBoardParam::all().next().unwrap());
+        assert_eq!(
+            param_from_index(PARAM_COUNT - 1),
+            "0x7FFF,0xFF,40,4".parse().unwrap()
+        );
+        assert_eq!(
+            param_from_index(1234 * 5 + 3),
+            "0x0004,0xD2,40,3".parse().unwrap()
+        );
+
+        assert!(sample_legal_boards(0, 0).is_empty());
+    }
+}
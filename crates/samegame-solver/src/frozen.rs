@@ -0,0 +1,143 @@
+//! 読み取り専用のヒント問い合わせ用ソルバー関連。
+
+use std::sync::Arc;
+
+use samegame_core::{Action, ActionHistory, RuleSet, Score};
+
+use crate::bound::BoundFn;
+use crate::hash::{hashmap_memory_usage, U64HashMap};
+use crate::position::Position;
+use crate::solver::Solver;
+use crate::terminal::TerminalEval;
+
+/// 1 面を解いた後の DP テーブルを凍結した、読み取り専用のソルバー。`Solver::solve_frozen()` で作る。
+///
+/// 全メソッドは `&self` を取り、内部状態を変更しないので、`Arc<FrozenSolver>` を複数スレッドで共有して
+/// ロックなしに並行に問い合わせられる (GUI や Web サービスのヒント表示向け)。
+///
+/// 問い合わせのたびに、凍結済みテーブルを追加獲得スコアの上界として参照しつつ問い合わせ局面から探索し直す。
+/// 解いた面の最適解の近くの局面ならば上界がほぼ確定しているので、探索はすぐに終わる。
+/// 問い合わせ中に得た上界は捨てるので、問い合わせ同士が干渉することはない。
+pub struct FrozenSolver {
+    pub(crate) table: Arc<U64HashMap<Position, Score>>,
+    pub(crate) bound_fn: Arc<dyn BoundFn + Send + Sync>,
+    pub(crate) terminal_eval: Arc<dyn TerminalEval + Send + Sync>,
+    pub(crate) rule_set: RuleSet,
+    pub(crate) prefer_shorter: bool,
+}
+
+impl std::fmt::Debug for FrozenSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrozenSolver")
+            .field("table_len", &self.table.len())
+            .field("rule_set", &self.rule_set)
+            .field("prefer_shorter", &self.prefer_shorter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FrozenSolver {
+    /// 凍結済みテーブルのエントリ数を返す。
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// 凍結済みテーブルが空かどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// メモリ使用量の概算値 (バイト数) を返す。凍結済みテーブルは複製間で共有されるが、その分も含める。
+    pub fn approx_memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>() + hashmap_memory_usage(&self.table)
+    }
+
+    /// 凍結済みテーブルに記録された、非終了局面 `pos` から追加で獲得しうるスコアの上界を返す。
+    ///
+    /// 探索せずに引くだけなので、エントリがなければ `None` を返す。
+    pub fn lookup(&self, pos: &Position) -> Option<Score> {
+        self.table.get(pos).copied()
+    }
+
+    /// 局面 `pos` からの最大追加獲得スコア (終了局面評価を含む) とその手順を返す。
+    ///
+    /// 追加獲得スコアが正となる手順がなければ `None` を返す。
+    pub fn solve(&self, pos: &Position) -> Option<(Score, ActionHistory)> {
+        Solver::from_frozen(self).solve(pos.board().clone())
+    }
+
+    /// 局面 `pos` における最善手を返す。`pos` が終了局面ならば `None` を返す。
+    pub fn best_action(&self, pos: &Position) -> Option<Action> {
+        let mut actions = pos.actions_with_rules(&self.rule_set);
+
+        // どう指しても追加獲得スコアが 0 ならば、どの手も最善手。
+        let Some((_, solution)) = self.solve(pos) else {
+            return actions.next();
+        };
+        // 終了局面でも終了局面評価により解が得られることがある (手順は空)。
+        let &sq = solution.as_slice().first()?;
+        actions.find(|action| action.least_square() == sq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use samegame_core::Board;
+
+    use super::*;
+
+    use crate::benchmark::benchmark_board;
+    use crate::terminal::GameTerminalEval;
+
+    #[test]
+    fn test_frozen_solver() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FrozenSolver>();
+
+        let bb = benchmark_board("easiest").unwrap();
+        let mut solver = Solver::new(Score::ZERO);
+        let (res, frozen) = solver.solve_frozen(bb.board.clone());
+        assert_eq!(res.map(|(score, _)| score), Some(bb.known_best));
+        assert!(!frozen.is_empty());
+
+        let pos = Position::new(bb.board.clone());
+        assert!(frozen.lookup(&pos).unwrap() >= bb.known_best);
+
+        // 複数スレッドから並行に最善手を辿っても最適スコアが得られる。
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut pos = pos.clone();
+                    let mut score = Score::ZERO;
+                    while let Some(action) = frozen.best_action(&pos) {
+                        score += action.gain();
+                        pos = pos.do_action(&action);
+                    }
+                    score += GameTerminalEval.final_gain(&pos);
+                    assert_eq!(score, bb.known_best);
+                });
+            }
+        });
+
+        assert_eq!(
+            frozen.solve(&pos).map(|(score, _)| score),
+            Some(bb.known_best)
+        );
+
+        // 終了局面では最善手はない (盤面が空ならパーフェクトボーナスのみの空の手順が解となる)。
+        let pos_empty = Position::new(Board::empty());
+        assert_eq!(
+            frozen
+                .solve(&pos_empty)
+                .map(|(score, solution)| (score, solution.len())),
+            Some((Score::new(200), 0))
+        );
+        assert_eq!(frozen.best_action(&pos_empty), None);
+
+        // ソルバーは引き続き別の面を解ける。
+        assert_eq!(
+            solver.solve(bb.board.clone()).map(|(score, _)| score),
+            Some(bb.known_best)
+        );
+    }
+}
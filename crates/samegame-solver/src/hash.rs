@@ -0,0 +1,92 @@
+//! zobrist hash を標準の `HashSet`, `HashMap` で使うためのアダプタ。
+//!
+//! set, map のキー型は `Hash::hash()` 内で自身の zobrist hash 値を `Hasher::write_u64()` に渡すこと。
+//! (`u64` 型はこの条件を満たす)
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+
+/// zobrist hash 値をそのままキーとして使える set。
+pub type U64HashSet<T> = HashSet<T, BuildHasherDefault<U64Hasher>>;
+
+pub fn u64_hashset_with_capacity<T>(capacity: usize) -> U64HashSet<T> {
+    U64HashSet::with_capacity_and_hasher(capacity, Default::default())
+}
+
+/// zobrist hash 値をそのままキーとして使える map。
+pub type U64HashMap<K, V> = HashMap<K, V, BuildHasherDefault<U64Hasher>>;
+
+pub fn u64_hashmap_with_capacity<K, V>(capacity: usize) -> U64HashMap<K, V> {
+    U64HashMap::with_capacity_and_hasher(capacity, Default::default())
+}
+
+/// ハッシュ表 `map` のヒープ使用量の概算値 (バイト数) を返す。
+///
+/// 標準ライブラリの実装 (SwissTable) に基づき、容量からバケット数を推定して
+/// バケットごとのエントリと制御バイトの分を数える。キーや値が指すヒープ領域は含まない。
+pub fn hashmap_memory_usage<K, V, S>(map: &HashMap<K, V, S>) -> usize {
+    table_memory_usage::<(K, V)>(map.capacity())
+}
+
+/// ハッシュ集合 `set` のヒープ使用量の概算値 (バイト数) を返す。`hashmap_memory_usage()` を参照。
+pub fn hashset_memory_usage<T, S>(set: &HashSet<T, S>) -> usize {
+    table_memory_usage::<T>(set.capacity())
+}
+
+fn table_memory_usage<T>(capacity: usize) -> usize {
+    /// 制御バイトをまとめて読むグループの幅 (SSE2 の場合)。末尾にこの分の制御バイトが余分に確保される。
+    const GROUP_WIDTH: usize = 16;
+
+    if capacity == 0 {
+        return 0;
+    }
+
+    // 容量はバケット数の 7/8 (バケット数 8 未満ならバケット数 - 1)。
+    let buckets = if capacity < 8 {
+        capacity + 1
+    } else {
+        (capacity * 8 / 7).next_power_of_two()
+    };
+
+    buckets * (std::mem::size_of::<T>() + 1) + GROUP_WIDTH
+}
+
+#[repr(transparent)]
+#[derive(Debug, Default)]
+pub struct U64Hasher(u64);
+
+impl Hasher for U64Hasher {
+    fn write(&mut self, _: &[u8]) {
+        unimplemented!();
+    }
+
+    fn write_u64(&mut self, x: u64) {
+        self.0 = x;
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashmap_memory_usage() {
+        let map = U64HashMap::<u64, u64>::default();
+        assert_eq!(hashmap_memory_usage(&map), 0);
+
+        for n in [1, 3, 7, 8, 100, 1000, 12345] {
+            let map: U64HashMap<u64, u64> = (0..n).map(|i| (i, i)).collect();
+            let usage = hashmap_memory_usage(&map);
+            // エントリ分以上、かつその数倍程度に収まる。
+            assert!(usage >= 16 * map.len());
+            assert!(usage <= 17 * 4 * map.len() + 64);
+        }
+
+        let set: U64HashSet<u64> = (0..100).collect();
+        assert!(hashset_memory_usage(&set) >= 8 * 100);
+    }
+}
@@ -0,0 +1,192 @@
+//! 段階的に探索手法を切り替えるハイブリッドソルバー関連。
+
+use samegame_core::{ActionHistory, Board, Score};
+
+use crate::beam::BeamSearch;
+use crate::eval::GreedyPotential;
+use crate::logging::info;
+use crate::solver::Solver;
+
+/// `HybridSolver` の設定。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HybridConfig {
+    /// ビームサーチ段階で順に試すビーム幅たち。
+    pub beam_widths: Vec<usize>,
+    /// 厳密探索段階で順に試す訪問ノード数の上限たち。`None` は無制限。
+    ///
+    /// 各試行は直前までの最良スコアを閾値として最初から探索し直す。
+    /// 探索を完了した (上限に達しなかった) 時点で最適性が証明されるので、残りの試行は行わない。
+    pub exact_node_limits: Vec<Option<u64>>,
+}
+
+impl Default for HybridConfig {
+    fn default() -> Self {
+        Self {
+            beam_widths: vec![16, 256],
+            exact_node_limits: vec![Some(1_000_000), Some(100_000_000)],
+        }
+    }
+}
+
+/// 解を見つけた段階。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HybridStage {
+    /// 貪欲法 (最大の塊から消す)。
+    Greedy,
+    /// ビームサーチ。
+    Beam { width: usize },
+    /// 厳密探索。
+    Exact { node_limit: Option<u64> },
+}
+
+/// `HybridSolver::solve()` の結果。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HybridOutcome {
+    /// 最終スコア。
+    pub score: Score,
+    /// 手順。
+    pub solution: ActionHistory,
+    /// この解を見つけた段階。
+    pub stage: HybridStage,
+    /// 厳密探索の完了により最適性が証明されたかどうか。
+    pub proven_optimal: bool,
+    /// 厳密探索段階での訪問ノード数の総和。
+    pub exact_nodes: u64,
+}
+
+/// 貪欲法 → ビームサーチ → 厳密探索と予算を増やしつつ段階的に解くソルバー。
+///
+/// 前段の近似解のスコアを厳密探索の枝刈り用スコア閾値に用いるので、大規模な走査でも
+/// 簡単な面は安く済み、難しい面も予算内で最良の解を返す。
+///
+/// 貪欲法およびビームサーチは実際のゲームのルールで探索する。
+/// 厳密探索のソルバーの設定 (上界関数など) は `solver_mut()` で変更できるが、
+/// ゲームルールや終了局面の評価関数を変える場合はビームサーチの解と整合しなくなることに注意。
+#[derive(Debug)]
+pub struct HybridSolver {
+    config: HybridConfig,
+    solver: Solver,
+}
+
+impl HybridSolver {
+    /// 設定 `config` でソルバーを作る。
+    pub fn new(config: HybridConfig) -> Self {
+        Self {
+            config,
+            solver: Solver::new(Score::ZERO),
+        }
+    }
+
+    /// 設定を返す。
+    pub fn config(&self) -> &HybridConfig {
+        &self.config
+    }
+
+    /// 厳密探索段階で用いるソルバーを返す。
+    ///
+    /// 枝刈り用スコア閾値および訪問ノード数の上限は各試行の開始時に上書きされる。
+    pub fn solver_mut(&mut self) -> &mut Solver {
+        &mut self.solver
+    }
+
+    /// 盤面 `board` を解き、最良の解とそれを見つけた段階を返す。
+    pub fn solve(&mut self, board: Board) -> HybridOutcome {
+        let (score, solution) =
+            BeamSearch::new(1).search_with_eval(board.clone(), &GreedyPotential);
+        let mut outcome = HybridOutcome {
+            score,
+            solution,
+            stage: HybridStage::Greedy,
+            proven_optimal: false,
+            exact_nodes: 0,
+        };
+        info!("Hybrid greedy: {}", outcome.score);
+
+        for &width in &self.config.beam_widths {
+            let (score, solution) = BeamSearch::new(width).search(board.clone());
+            if score > outcome.score {
+                outcome.score = score;
+                outcome.solution = solution;
+                outcome.stage = HybridStage::Beam { width };
+            }
+            info!("Hybrid beam (width {width}): {score}");
+        }
+
+        for &node_limit in &self.config.exact_node_limits {
+            // 既知の解より真に良い解のみを探す。
+            self.solver.set_prune_score_max(outcome.score);
+            self.solver.set_node_limit(node_limit);
+            let (res, stats) = self.solver.solve_with_stats(board.clone());
+            outcome.exact_nodes += stats.nodes;
+
+            if let Some((score, solution)) = res {
+                if score > outcome.score {
+                    outcome.score = score;
+                    outcome.solution = solution;
+                    outcome.stage = HybridStage::Exact { node_limit };
+                }
+            }
+            info!(
+                "Hybrid exact (node limit {node_limit:?}): {} (nodes: {}, completed: {})",
+                outcome.score, stats.nodes, !stats.node_limit_reached
+            );
+
+            if !stats.node_limit_reached {
+                outcome.proven_optimal = true;
+                break;
+            }
+        }
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_hybrid_solver() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ..3.....
+            3121....
+            12231...
+            121321..
+        "}
+        .parse()
+        .unwrap();
+        let (score_opt, _) = Solver::new(Score::ZERO).solve(board.clone()).unwrap();
+
+        // 予算が十分なら最適性が証明される。
+        let mut solver = HybridSolver::new(HybridConfig {
+            beam_widths: vec![1],
+            exact_node_limits: vec![Some(1), None],
+        });
+        let outcome = solver.solve(board.clone());
+        assert_eq!(outcome.score, score_opt);
+        assert!(outcome.proven_optimal);
+        assert_eq!(
+            crate::verify::replay(&board, &outcome.solution),
+            Ok(outcome.score)
+        );
+
+        // 厳密探索の予算がなければ近似解のまま。
+        let mut solver = HybridSolver::new(HybridConfig {
+            beam_widths: vec![],
+            exact_node_limits: vec![Some(1)],
+        });
+        let outcome = solver.solve(board.clone());
+        assert_eq!(outcome.stage, HybridStage::Greedy);
+        assert!(!outcome.proven_optimal);
+        assert!(outcome.score <= score_opt);
+        assert_eq!(outcome.exact_nodes, 1);
+        assert_eq!(
+            crate::verify::replay(&board, &outcome.solution),
+            Ok(outcome.score)
+        );
+    }
+}
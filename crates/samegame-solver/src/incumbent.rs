@@ -0,0 +1,101 @@
+//! 複数スレッドで共有する暫定最良解関連。
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use samegame_core::{ActionHistory, Score};
+
+/// スレッド間で共有できる暫定最良スコアおよび最良解。
+///
+/// スコアはアトミック変数で保持するので、枝刈り閾値として頻繁に読み出してもロックを取らない。
+/// 解の更新時のみロックを取り、スコアと解の対応が崩れないようにする。
+#[derive(Debug, Default)]
+pub struct AtomicScoreBest {
+    score: AtomicU32,
+    best: Mutex<Option<(Score, ActionHistory)>>,
+}
+
+impl AtomicScoreBest {
+    /// 初期スコアを `score` とする (解は持たない)。
+    pub fn new(score: Score) -> Self {
+        Self {
+            score: AtomicU32::new(score.to_inner()),
+            best: Mutex::new(None),
+        }
+    }
+
+    /// 現在の暫定最良スコアを返す。
+    pub fn score(&self) -> Score {
+        Score::new(self.score.load(Ordering::Acquire))
+    }
+
+    /// 暫定最良スコアを chmax する (解は更新しない)。更新されたかどうかを返す。
+    ///
+    /// 外部から既知のスコアを与えて枝刈り閾値として使う場合など向け。
+    pub fn chmax_score(&self, score: Score) -> bool {
+        let prev = self.score.fetch_max(score.to_inner(), Ordering::AcqRel);
+        score.to_inner() > prev
+    }
+
+    /// スコア `score` の解 `solution` を報告する。
+    /// `score` が暫定最良スコアを上回る場合のみ更新し、更新されたかどうかを返す。
+    pub fn offer(&self, score: Score, solution: &ActionHistory) -> bool {
+        // ロックを取らずに済む場合は先に弾く。
+        if score <= self.score() {
+            return false;
+        }
+
+        let mut best = self.best.lock().unwrap();
+        // ロック待ちの間に他スレッドが更新しているかもしれないので再確認する。
+        if !self.chmax_score(score) {
+            return false;
+        }
+        *best = Some((score, solution.clone()));
+
+        true
+    }
+
+    /// 最良解 (あれば) を返す。
+    pub fn best(&self) -> Option<(Score, ActionHistory)> {
+        self.best.lock().unwrap().clone()
+    }
+
+    /// 最良解 (あれば) を取り出す。
+    pub fn into_best(self) -> Option<(Score, ActionHistory)> {
+        self.best.into_inner().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_score_best() {
+        let best = AtomicScoreBest::new(Score::new(100));
+        let solution: ActionHistory = "1,1 2,2".parse().unwrap();
+
+        assert!(!best.offer(Score::new(100), &solution));
+        assert_eq!(best.best(), None);
+
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let best = &best;
+                let solution = &solution;
+                s.spawn(move || {
+                    for score in 0..200 {
+                        best.offer(Score::new(101 + 8 * score + i), solution);
+                    }
+                });
+            }
+        });
+
+        let max = Score::new(101 + 8 * 199 + 7);
+        assert_eq!(best.score(), max);
+        assert_eq!(best.best(), Some((max, solution.clone())));
+
+        assert!(best.chmax_score(Score::new(10000)));
+        assert!(!best.offer(Score::new(9999), &solution));
+        assert_eq!(best.into_best(), Some((max, solution)));
+    }
+}
@@ -0,0 +1,72 @@
+//! SFC『鮫亀』: さめがめ「かんたん」用ソルバー。
+//!
+//! 局面、探索 (ソルバー、ビームサーチなど)、上界関数、評価関数を含む。
+//! 結果ファイルやデータベース、CLI などのツール類は `samegame-tools` クレートにある。
+
+mod annotate;
+mod asset;
+mod beam;
+mod benchmark;
+pub mod board_large;
+mod bound;
+mod branching;
+mod checkpoint;
+mod cmp;
+mod color_signature;
+mod constraints;
+pub mod convert;
+mod dp;
+mod eval;
+mod experiments;
+mod frozen;
+mod hash;
+mod hybrid;
+mod incumbent;
+#[doc(hidden)]
+pub mod logging;
+mod merge;
+mod observer;
+mod opening;
+mod parallel;
+#[cfg(feature = "plugin")]
+mod plugin;
+mod position;
+mod progress;
+mod reach;
+#[cfg(test)]
+mod reference;
+mod remaining;
+mod solver;
+mod terminal;
+mod tree_dump;
+mod verify;
+mod zobrist;
+
+pub use self::annotate::*;
+pub use self::beam::*;
+pub use self::benchmark::*;
+pub use self::bound::*;
+pub use self::branching::*;
+pub use self::checkpoint::*;
+pub use self::color_signature::*;
+pub use self::constraints::*;
+pub use self::dp::*;
+pub use self::eval::*;
+pub use self::experiments::*;
+pub use self::frozen::*;
+pub use self::hash::*;
+pub use self::hybrid::*;
+pub use self::incumbent::*;
+pub use self::merge::*;
+pub use self::observer::*;
+pub use self::opening::*;
+pub use self::parallel::*;
+#[cfg(feature = "plugin")]
+pub use self::plugin::*;
+pub use self::position::*;
+pub use self::progress::*;
+pub use self::reach::*;
+pub use self::solver::*;
+pub use self::terminal::*;
+pub use self::tree_dump::*;
+pub use self::verify::*;
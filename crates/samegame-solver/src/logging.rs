@@ -0,0 +1,68 @@
+#![allow(unused_imports)]
+
+//! ログ出力用マクロ。
+//!
+//! feature `log` が有効なら `log` クレートの同名マクロに転送する。
+//! 無効ならログ出力は行わない (引数は型チェックのみ行われる)。
+//!
+//! `samegame-tools` も使うので公開しているが、クレート外から使うことは想定していない。
+//! feature の判定はこのクレートの定義時に行うので、使う側のクレートは `log` に依存しなくてよい。
+
+#[cfg(feature = "log")]
+#[doc(hidden)]
+pub use ::log as __log;
+
+#[cfg(feature = "log")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __samegame_info {
+    ($($arg:tt)*) => {
+        $crate::logging::__log::info!($($arg)*)
+    };
+}
+#[cfg(not(feature = "log"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __samegame_info {
+    ($($arg:tt)*) => {{
+        let _ = ::std::format_args!($($arg)*);
+    }};
+}
+pub use __samegame_info as info;
+
+#[cfg(feature = "log")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __samegame_debug {
+    ($($arg:tt)*) => {
+        $crate::logging::__log::debug!($($arg)*)
+    };
+}
+#[cfg(not(feature = "log"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __samegame_debug {
+    ($($arg:tt)*) => {{
+        let _ = ::std::format_args!($($arg)*);
+    }};
+}
+pub use __samegame_debug as debug;
+
+#[cfg(feature = "log")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __samegame_warn {
+    ($($arg:tt)*) => {
+        $crate::logging::__log::warn!($($arg)*)
+    };
+}
+#[cfg(not(feature = "log"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __samegame_warn {
+    ($($arg:tt)*) => {{
+        let _ = ::std::format_args!($($arg)*);
+    }};
+}
+// `warn` は組み込み属性と名前が衝突するが、`use` で別名を付ける分には問題ない。
+pub use __samegame_warn as warn;
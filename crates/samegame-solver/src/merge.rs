@@ -0,0 +1,87 @@
+//! 列の消滅による同種駒の合流候補関連。
+
+use samegame_core::{Board, Col, ColArray, Row, Square};
+
+/// 各列について、その列が空になったときに左右の列で新たに隣接する同種駒の組の集合。
+///
+/// 列を挟んだ同種駒の合流を調べる解析用 (`Position::merge_candidates()`)。
+/// 探索の上界関数や着手順序付けは使わないので、`Position` はキャッシュとして保持しない。
+///
+/// 列を空にする着手が他の列の高さも変える場合 (複数列にまたがる塊を消す場合) は考慮しない。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeCandidates(ColArray<u8>);
+
+impl MergeCandidates {
+    /// 盤面 `board` の合流候補を求める。
+    pub fn new(board: &Board) -> Self {
+        Self(ColArray::from_fn(|col| board.bridged_rows(col)))
+    }
+
+    /// 列 `col` が空になったときに同種駒同士が新たに隣接する行の集合を返す (bit i が行 i+1 に対応)。
+    pub fn rows(&self, col: Col) -> u8 {
+        self.0[col]
+    }
+
+    /// 合流候補の総数を返す。
+    pub fn count(&self) -> u32 {
+        self.0.as_array().iter().map(|rows| rows.count_ones()).sum()
+    }
+
+    /// 合流候補がないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.0.as_array().iter().all(|&rows| rows == 0)
+    }
+
+    /// 合流候補を (空になる列, 行) の昇順に列挙する。
+    ///
+    /// 合流する駒は `Square::new(col.prev(), row)` と `Square::new(col.next(), row)` にある。
+    pub fn iter(&self) -> impl std::iter::FusedIterator<Item = Square> + Clone + '_ {
+        Col::all()
+            .flat_map(move |col| {
+                Row::all()
+                    .filter(move |row| (self.0[col] & (1 << row.to_index())) != 0)
+                    .map(move |row| Square::new(col, row))
+            })
+            .fuse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use samegame_core::*;
+
+    use super::*;
+    use crate::position::Position;
+
+    #[test]
+    fn test_merge_candidates() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            1.1.....
+            2321....
+            12132...
+            31224...
+        "}
+        .parse()
+        .unwrap();
+
+        let merge = MergeCandidates::new(&board);
+        itertools::assert_equal(
+            merge.iter(),
+            [
+                Square::new(COL_2, ROW_2),
+                Square::new(COL_2, ROW_3),
+                Square::new(COL_2, ROW_4),
+            ],
+        );
+        assert_eq!(merge.rows(COL_2), 0b1110);
+        assert_eq!(merge.rows(COL_1), 0);
+        assert_eq!(merge.count(), 3);
+        assert!(!merge.is_empty());
+        assert!(MergeCandidates::new(&Board::empty()).is_empty());
+
+        assert_eq!(Position::new(board).merge_candidates(), merge);
+    }
+}
@@ -0,0 +1,171 @@
+//! 探索の監視関連。
+
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use samegame_core::{ActionHistory, Score};
+
+use crate::logging::info;
+
+/// 探索中に定期的に報告される状況。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Heartbeat {
+    /// 現在の面の探索開始からの訪問ノード数。
+    pub nodes: u64,
+    /// 現在の面の探索開始からの経過時間。
+    pub elapsed: Duration,
+    /// DP テーブルのエントリ数。
+    pub dp_entry_count: usize,
+    /// 現在の暫定解のスコア (暫定解がなければ `None`)。
+    pub best_score: Option<Score>,
+    /// 現在展開中のノードの深さ (初期盤面からの手数)。
+    pub depth: usize,
+    /// プロセスの RSS (バイト単位) の推定値。取得できない環境では `None`。
+    pub rss_bytes: Option<u64>,
+}
+
+impl Heartbeat {
+    /// 秒間訪問ノード数を返す。
+    pub fn nodes_per_sec(&self) -> f64 {
+        self.nodes as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// ソルバーの探索を監視するオブザーバー。
+///
+/// 全てのメソッドはデフォルトで何もしない。
+pub trait SearchObserver {
+    /// ハートビート間隔 (`Solver::set_heartbeat_interval()`) ごとに呼ばれる。
+    fn on_heartbeat(&mut self, _heartbeat: &Heartbeat) {}
+
+    /// 暫定解が更新されるたびに呼ばれる。ログ出力方針 (`IncumbentLogPolicy`) とは無関係に全ての更新が報告される。
+    fn on_incumbent(&mut self, _score: Score, _solution: &ActionHistory) {}
+
+    /// 探索中に一定ノード数ごとに呼ばれる。`ControlFlow::Break` を返すと探索を中断する。
+    ///
+    /// 中断した場合、ソルバーはそれまでに見つかった最良の解を返す (最適解とは限らない)。
+    /// 中断したかどうかは `SearchStats::cancelled` でわかる。
+    fn poll(&mut self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// 探索を他のスレッドから中断するためのトークン。`Solver::set_cancel_token()` で設定する。
+///
+/// 複製したトークンは状態を共有する。一度中断を要求したトークンは `reset()` するまで中断状態のままとなる。
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// 中断を要求していないトークンを作る。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 中断を要求する。探索中のソルバーは一定ノード数以内に探索を中断する。
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// 中断が要求されているかどうかを返す。
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// 中断の要求を取り消す。
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// 何もしないオブザーバー。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NullObserver;
+
+impl SearchObserver for NullObserver {}
+
+/// ハートビートを 1 行のログとして出力するオブザーバー。
+///
+/// 外部スクリプトから監視しやすいよう、`key=value` 形式で出力する。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HeartbeatLogger {
+    /// 現在処理中の盤面生成パラメータのインデックス (あれば)。ドライバー側で更新すること。
+    pub param_index: Option<u64>,
+}
+
+impl SearchObserver for HeartbeatLogger {
+    fn on_heartbeat(&mut self, heartbeat: &Heartbeat) {
+        let rss = heartbeat
+            .rss_bytes
+            .map_or_else(|| "-".to_owned(), |rss| rss.to_string());
+        let param_index = self
+            .param_index
+            .map_or_else(|| "-".to_owned(), |idx| idx.to_string());
+        let best = heartbeat
+            .best_score
+            .map_or_else(|| "-".to_owned(), |score| score.to_string());
+
+        info!(
+            "heartbeat nodes={} elapsed={:.3} nps={:.0} dp={} best={best} depth={} rss={rss} param_index={param_index}",
+            heartbeat.nodes,
+            heartbeat.elapsed.as_secs_f64(),
+            heartbeat.nodes_per_sec(),
+            heartbeat.dp_entry_count,
+            heartbeat.depth,
+        );
+    }
+}
+
+/// 探索中の暫定解の更新をログ出力する方針。
+///
+/// 簡単な面を大量に解く場合など、全ての更新を `info` レベルで出力すると出力が溢れて遅くなるので、
+/// 条件を満たさない更新は `debug` レベルで出力するようにできる。
+///
+/// デフォルトでは全ての更新を `info` レベルで出力する。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IncumbentLogPolicy {
+    /// 直前に `info` 出力したスコアからの改善量がこの値未満の更新は `debug` 出力とする。
+    pub min_delta: Score,
+    /// 直前の `info` 出力からの経過時間がこの値未満の更新は `debug` 出力とする。
+    pub min_interval: Duration,
+    /// 探索終了時に最終的な解を `info` 出力するかどうか。
+    pub log_final: bool,
+}
+
+impl IncumbentLogPolicy {
+    /// 全ての更新を `info` 出力する (デフォルト)。
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// 最初の暫定解、前回の `info` 出力から `min_delta` 以上改善しかつ `min_interval` 以上経過した更新、
+    /// および最終的な解のみを `info` 出力する。
+    pub fn sparse(min_delta: Score, min_interval: Duration) -> Self {
+        Self {
+            min_delta,
+            min_interval,
+            log_final: true,
+        }
+    }
+}
+
+/// プロセスの RSS (バイト単位) を推定する。取得できない場合は `None` を返す。
+///
+/// 現状 Linux でのみ `/proc/self/statm` を読んで求める (ページサイズは 4 KiB と仮定)。
+pub fn rss_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        const PAGE_SIZE: u64 = 4096;
+
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_ascii_whitespace().nth(1)?.parse().ok()?;
+
+        Some(PAGE_SIZE * pages)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,247 @@
+//! 序盤定跡 (opening book) 関連。
+//!
+//! 全面探索では、異なる初期盤面から序盤に同じ局面 (駒種の入れ替えを同一視) に至ることがある。
+//! 初期盤面を「最大の塊を消した後の局面の正規形」で分類し、頻出する分類の局面について
+//! 最適な追加獲得スコアと続きの手順を定跡として持っておけば、ソルバーは浅いノードで定跡を引いて探索を省ける
+//! (`Solver::set_opening_book()`)。
+//!
+//! 組み込みの定跡は `examples/opening_book.rs` で生成する。
+
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{ensure, Context as _};
+use samegame_core::{rules, Action, ActionHistory, Board, Score, Square};
+
+use crate::asset::asset_include_bytes;
+use crate::hash::U64HashMap;
+use crate::position::Position;
+
+/// 定跡の 1 局面の情報。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BookEntry {
+    /// 局面からの最適な追加獲得スコア (終了局面評価を含む)。
+    pub gain: Score,
+    /// それを達成する手順。
+    pub continuation: ActionHistory,
+}
+
+/// 序盤定跡。局面 (正規形) から最適な続きを引く。
+///
+/// 値はゲーム通りのルールと終了局面評価の下での最適値なので、それ以外の設定のソルバーでは使えない。
+///
+/// バイト列形式はエントリの列で、各エントリは
+/// 盤面 18 バイト (マスの昇順に各 3 bit の駒の値を LSB 側から詰める), 追加獲得スコア 2 バイト (LE),
+/// 手数 1 バイト, 手順の各マス 1 バイトずつ、からなる。
+#[derive(Clone, Debug, Default)]
+pub struct OpeningBook {
+    entries: U64HashMap<Position, BookEntry>,
+    /// 登録局面の駒数の分布のハッシュ値たち。正規化する前の絞り込み用。
+    color_signatures: U64HashMap<u64, ()>,
+}
+
+impl OpeningBook {
+    /// ソルバーが定跡を引く最大の深さ (初期盤面からの手数)。
+    pub const PROBE_DEPTH_MAX: usize = 2;
+
+    /// 空の定跡を作る。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 組み込みの定跡を返す。
+    pub fn builtin() -> Arc<Self> {
+        static BOOK: OnceLock<Arc<OpeningBook>> = OnceLock::new();
+
+        let book = BOOK.get_or_init(|| {
+            let book = Self::from_bytes(asset_include_bytes!("opening_book.bin"))
+                .expect("組み込みの定跡は正しい形式のはず");
+            Arc::new(book)
+        });
+        Arc::clone(book)
+    }
+
+    /// 登録局面数を返す。
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 登録局面がないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 局面 `pos` の情報を登録する。局面は正規形に直して登録する。
+    pub fn insert(&mut self, pos: &Position, entry: BookEntry) {
+        let pos = canonical_position(pos);
+        self.color_signatures.insert(pos.color_signature(), ());
+        self.entries.insert(pos, entry);
+    }
+
+    /// 局面 `pos` (正規形でなくてよい) の情報を引く。
+    pub fn probe(&self, pos: &Position) -> Option<&BookEntry> {
+        if !self.color_signatures.contains_key(&pos.color_signature()) {
+            return None;
+        }
+
+        self.entries.get(&canonical_position(pos))
+    }
+
+    /// 登録局面と情報の組たちを列挙する (順序は未規定)。
+    pub fn iter(&self) -> impl Iterator<Item = (&Position, &BookEntry)> {
+        self.entries.iter()
+    }
+
+    /// バイト列形式に変換する。各エントリの盤面は `Board::to_bytes()` の形式で、その昇順に並べる。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(pos, entry)| (pos.board().to_bytes(), entry))
+            .collect();
+        entries.sort_unstable_by_key(|&(packed, _)| packed);
+
+        let mut buf = Vec::new();
+        for (packed, entry) in entries {
+            buf.extend_from_slice(&packed);
+            buf.extend_from_slice(&(entry.gain.to_inner() as u16).to_le_bytes());
+            buf.push(entry.continuation.len() as u8);
+            buf.extend(entry.continuation.iter().map(|sq| sq.to_inner()));
+        }
+
+        buf
+    }
+
+    /// バイト列形式から読み込む。
+    pub fn from_bytes(mut buf: &[u8]) -> anyhow::Result<Self> {
+        let mut this = Self::new();
+
+        while !buf.is_empty() {
+            ensure!(
+                buf.len() >= Board::BYTES_LEN + 3,
+                "定跡のエントリが途中で切れている"
+            );
+            let (packed, rest) = buf.split_at(Board::BYTES_LEN);
+            let board = Board::from_bytes(packed).context("定跡の盤面が無効")?;
+            let gain = Score::new(u32::from(u16::from_le_bytes([rest[0], rest[1]])));
+            let len = usize::from(rest[2]);
+            let rest = &rest[3..];
+            ensure!(rest.len() >= len, "定跡の手順が途中で切れている");
+            let continuation = rest[..len]
+                .iter()
+                .map(|&inner| Square::from_inner(inner).context("定跡の手順のマスが無効"))
+                .collect::<anyhow::Result<ActionHistory>>()?;
+            buf = &rest[len..];
+
+            this.insert(&Position::new(board), BookEntry { gain, continuation });
+        }
+
+        Ok(this)
+    }
+}
+
+/// 局面 `pos` の正規形を返す。
+///
+/// 駒種の入れ替えで移り合う局面は同じ正規形を持つ。マスの昇順に見て初めて現れた駒種から順に値 1, 2, ... を振り直す。
+pub fn canonical_position(pos: &Position) -> Position {
+    let grid = pos.board().to_grid();
+
+    let mut relabel = [0_u8; 8];
+    let mut next = 1;
+    let grid: rules::Grid = grid.map(|col| {
+        col.map(|value| {
+            if value == 0 {
+                return 0;
+            }
+            let label = &mut relabel[usize::from(value)];
+            if *label == 0 {
+                *label = next;
+                next += 1;
+            }
+            *label
+        })
+    });
+
+    Position::new(Board::from_grid(&grid).expect("駒種を振り直しても盤面の形は変わらない"))
+}
+
+/// 初期盤面 `board` の分類キー (最大の塊を消した後の局面の正規形) を返す。
+///
+/// 最大の塊が複数あれば最小マスが最小のものを消す。合法手がなければ `None` を返す。
+pub fn opening_key(board: &Board) -> Option<Position> {
+    let pos = Position::new(board.clone());
+    let action: Action = pos.actions().max_by_key(|action| {
+        (
+            action.square_count(),
+            std::cmp::Reverse(action.least_square()),
+        )
+    })?;
+
+    Some(canonical_position(&pos.do_action(&action)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use samegame_core::RuleSet;
+
+    use crate::benchmark::benchmark_board;
+    use crate::solver::Solver;
+    use crate::verify::replay;
+
+    #[test]
+    fn test_opening_book() {
+        let board = benchmark_board("easiest").unwrap().board.clone();
+        let pos = Position::new(board.clone());
+
+        // 駒種を入れ替えた盤面も同じ正規形を持つ。
+        let swapped: rules::Grid = board
+            .to_grid()
+            .map(|col| col.map(|value| if value == 0 { 0 } else { 6 - value }));
+        let pos_swapped = Position::new(Board::from_grid(&swapped).unwrap());
+        assert_ne!(pos_swapped, pos);
+        assert_eq!(canonical_position(&pos_swapped), canonical_position(&pos));
+        assert_eq!(opening_key(pos_swapped.board()), opening_key(pos.board()));
+        assert_eq!(
+            canonical_position(&canonical_position(&pos)),
+            canonical_position(&pos)
+        );
+
+        // 登録した局面は入れ替えた局面からも引ける。バイト列形式を経由しても変わらない。
+        let key = opening_key(&board).unwrap();
+        let (gain, continuation) = Solver::new(Score::ZERO).solve(key.board().clone()).unwrap();
+        let mut book = OpeningBook::new();
+        book.insert(&key, BookEntry { gain, continuation });
+        let key_swapped = opening_key(pos_swapped.board()).unwrap();
+        assert_eq!(book.probe(&key_swapped).unwrap().gain, gain);
+        assert!(book.probe(&pos).is_none());
+
+        // ソルバーは定跡を引くが、ゲームルールや終了局面の評価関数を変更していれば引かない。
+        let book = Arc::new(book);
+        let book_hits = |solver: &mut Solver| {
+            solver.set_opening_book(Some(Arc::clone(&book)));
+            solver.solve_with_stats(key.board().clone()).1.book_hits
+        };
+        assert_eq!(book_hits(&mut Solver::new(Score::ZERO)), 1);
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_rule_set(RuleSet::GAME.with_perfect_bonus(Score::ZERO));
+        assert_eq!(book_hits(&mut solver), 0);
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_terminal_eval(|_: &Position| Score::ZERO);
+        assert_eq!(book_hits(&mut solver), 0);
+
+        let book = OpeningBook::from_bytes(&book.to_bytes()).unwrap();
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.probe(&key).unwrap().gain, gain);
+        assert!(OpeningBook::from_bytes(&[0; 5]).is_err());
+
+        // 組み込みの定跡の手順は、その局面で記録通りのスコアを得る。
+        let builtin = OpeningBook::builtin();
+        assert!(!builtin.is_empty());
+        // 組み込みの定跡のバイト列形式は変換し直しても変わらない。
+        assert_eq!(builtin.to_bytes(), asset_include_bytes!("opening_book.bin"));
+        for (pos, entry) in builtin.iter().take(20) {
+            assert_eq!(replay(pos.board(), &entry.continuation), Ok(entry.gain));
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! 複数の面を並列に解くソルバー関連。
+//!
+//! 並列化の単位は面とし、各スレッドが共有カウンタから次の面を取っていく (早く終わったスレッドが残りの面を引き受ける)。
+//! 1 面の探索時間は面によって桁違いにばらつくので、事前に面を均等分割するより負荷が偏りにくい。
+//!
+//! DP テーブルはスレッドごとに持つ (各スレッドが自前の `Solver` を使う)。
+//! DP テーブルは面ごとにクリアされ、異なる面の間で局面が一致することはまずないので、
+//! 面単位の並列化ではテーブルを共有 (シャーディング) しても得るものがなく、ロックのコストだけがかかる。
+//! スレッド間で共有するのは暫定最良スコアのみで、これはアトミック変数 (`AtomicScoreBest`) で持つ。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use samegame_core::{ActionHistory, Board, Score};
+
+use crate::incumbent::AtomicScoreBest;
+use crate::progress::{NullProgress, ProgressEvent, ProgressSink};
+use crate::solver::Solver;
+
+/// 複数の面を並列に解くソルバー。
+///
+/// `raise_threshold` が真 (既定) の場合、`solve_all` と同様に、各面を解き始める時点での
+/// 全スレッドの最大スコア - 1 で枝刈り用スコア閾値を chmax する。
+/// 最大スコアはバッチをまたいで引き継ぐので、`solve_batch()` を繰り返し呼んで全面を解ける。
+/// 閾値の引き上げは面の開始時のみで、探索中の面の閾値は変わらない。
+pub struct ParallelSolver {
+    solvers: Vec<Solver>,
+    prune_score_max: Score,
+    raise_threshold: bool,
+    best: AtomicScoreBest,
+    progress: Arc<dyn ProgressSink + Send + Sync>,
+}
+
+impl std::fmt::Debug for ParallelSolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParallelSolver")
+            .field("thread_count", &self.solvers.len())
+            .field("prune_score_max", &self.prune_score_max)
+            .field("raise_threshold", &self.raise_threshold)
+            .field("best", &self.best)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ParallelSolver {
+    /// スレッド数 `thread_count`、枝刈り用スコア閾値の初期値 `prune_score_max` としてソルバーを作る。
+    ///
+    /// `thread_count` は 1 以上でなければならない。
+    pub fn new(thread_count: usize, prune_score_max: Score) -> Self {
+        Self::with_solver(thread_count, prune_score_max, || {
+            Solver::new(prune_score_max)
+        })
+    }
+
+    /// スレッドごとのソルバーを `make_solver` で作る。上界関数などを設定したい場合に使う。
+    ///
+    /// 各ソルバーの枝刈り用スコア閾値は面ごとに設定し直すので、`make_solver` 内での設定は無視される。
+    /// `thread_count` は 1 以上でなければならない。
+    pub fn with_solver(
+        thread_count: usize,
+        prune_score_max: Score,
+        make_solver: impl FnMut() -> Solver,
+    ) -> Self {
+        assert!(thread_count >= 1, "スレッド数は 1 以上でなければならない");
+
+        Self {
+            solvers: std::iter::repeat_with(make_solver)
+                .take(thread_count)
+                .collect(),
+            prune_score_max,
+            raise_threshold: true,
+            best: AtomicScoreBest::new(Score::ZERO),
+            progress: Arc::new(NullProgress),
+        }
+    }
+
+    /// スレッド数を返す。
+    pub fn thread_count(&self) -> usize {
+        self.solvers.len()
+    }
+
+    /// 面を解くたびに全スレッドの最大スコア - 1 で閾値を chmax するかどうかを設定する。
+    pub fn set_raise_threshold(&mut self, raise_threshold: bool) {
+        self.raise_threshold = raise_threshold;
+    }
+
+    /// 進捗イベント (`ProgressEvent`) の通知先を設定する。
+    ///
+    /// 各面の求解の開始と終了、および最良解の更新を通知する。`index` は `solve_batch()` に渡した盤面列内の位置。
+    pub fn set_progress(&mut self, sink: impl ProgressSink + Send + Sync + 'static) {
+        self.progress = Arc::new(sink);
+    }
+
+    /// これまでに解いた全ての面の最大スコアを返す (解がなければ 0)。
+    pub fn best_score(&self) -> Score {
+        self.best.score()
+    }
+
+    /// これまでに解いた全ての面の最良解 (あれば) を返す。
+    pub fn best(&self) -> Option<(Score, ActionHistory)> {
+        self.best.best()
+    }
+
+    /// 盤面たち `boards` を並列に解き、盤面順に結果を返す。
+    ///
+    /// 各結果は `Solver::solve()` と同様で、枝刈り用スコア閾値を超える解がなければ `None` となる。
+    pub fn solve_batch(&mut self, boards: &[Board]) -> Vec<Option<(Score, ActionHistory)>> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(boards.len()));
+
+        std::thread::scope(|s| {
+            for solver in self.solvers.iter_mut() {
+                let next = &next;
+                let results = &results;
+                let best = &self.best;
+                let progress = self.progress.as_ref();
+                let prune_score_max = self.prune_score_max;
+                let raise_threshold = self.raise_threshold;
+                s.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(board) = boards.get(i) else {
+                        break;
+                    };
+
+                    let mut threshold = prune_score_max;
+                    if raise_threshold {
+                        // 同点の解は全て列挙したいので -1 する。
+                        threshold = threshold.max(best.score().saturating_sub(Score::new(1)));
+                    }
+                    solver.set_prune_score_max(threshold);
+
+                    let index = i as u64;
+                    progress.on_event(&ProgressEvent::SolveStarted {
+                        index,
+                        param: None,
+                        prune_score_max: threshold,
+                    });
+                    let start = Instant::now();
+                    let res = solver.solve(board.clone());
+                    progress.on_event(&ProgressEvent::SolveFinished {
+                        index,
+                        param: None,
+                        score: res.as_ref().map(|&(score, _)| score),
+                        elapsed: start.elapsed(),
+                    });
+                    if let Some((score, solution)) = &res {
+                        if best.offer(*score, solution) {
+                            progress.on_event(&ProgressEvent::Incumbent {
+                                index,
+                                param: None,
+                                score: *score,
+                                solution: solution.clone(),
+                            });
+                        }
+                    }
+                    results.lock().unwrap().push((i, res));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_unstable_by_key(|&(i, _)| i);
+        results.into_iter().map(|(_, res)| res).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use samegame_core::Action;
+
+    use super::*;
+    use crate::benchmark::benchmark_board;
+    use crate::position::Position;
+
+    #[test]
+    fn test_parallel_solver() {
+        // テストを軽くするため、ベンチマーク用の易しい面とその最適解の途中局面たちを解く。
+        let bb = benchmark_board("easiest").unwrap();
+        let mut boards = Vec::new();
+        let mut pos = Position::new(bb.board.clone());
+        for (i, &sq) in bb.solution.iter().enumerate() {
+            if i % 2 == 0 {
+                boards.push(pos.board().clone());
+            }
+            pos = pos.do_action(&Action::from_board_square(pos.board(), sq).unwrap());
+        }
+        let threshold = Score::new(100);
+
+        // 閾値を引き上げなければ、各面を逐次に解いた結果と一致する。
+        let mut solver = Solver::new(threshold);
+        let expect: Vec<_> = boards
+            .iter()
+            .map(|board| solver.solve(board.clone()))
+            .collect();
+        let mut par = ParallelSolver::new(3, threshold);
+        par.set_raise_threshold(false);
+        assert_eq!(par.thread_count(), 3);
+        assert_eq!(par.solve_batch(&boards), expect);
+
+        let score_max = expect.iter().flatten().map(|&(score, _)| score).max();
+        assert_eq!(score_max, Some(bb.known_best));
+        assert_eq!(par.best().map(|(score, _)| score), score_max);
+
+        // 閾値を引き上げても最大スコアは変わらない。
+        let mut par = ParallelSolver::new(2, threshold);
+        let (first, second) = boards.split_at(boards.len() / 2);
+        par.solve_batch(first);
+        par.solve_batch(second);
+        assert_eq!(par.best().map(|(score, _)| score), score_max);
+    }
+}
@@ -0,0 +1,348 @@
+//! 外部の共有ライブラリから上界関数や評価関数を読み込むプラグイン機構 (feature `plugin`)。
+//!
+//! Rust 以外の言語で書いたヒューリスティックを試せるよう、C ABI の関数テーブル (`PluginVTable`) を介して呼び出す。
+//!
+//! 共有ライブラリは `PLUGIN_ENTRY_SYMBOL` という名前で以下の C 関数をエクスポートしなければならない:
+//!
+//! ```c
+//! int32_t samegame_plugin_entry(PluginVTable *vtable);
+//! ```
+//!
+//! この関数は `vtable` の各フィールドを埋めて 0 を返す。失敗した場合は 0 以外を返す。
+//! `abi_version` には `PLUGIN_ABI_VERSION` を設定しなければならない。
+//!
+//! # Safety
+//!
+//! プラグインのコードはこのクレートの安全性保証の外にある。
+//! ソルバーは複数スレッドから同時にコールバックを呼びうるので、コールバックはスレッドセーフでなければならない。
+//! また、上界関数は `BoundFn` の契約を満たさねばならない (満たさない場合、ソルバーは最適解を見逃しうる)。
+
+use std::ffi::{c_void, CString};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context as _};
+use samegame_core::{rules, Piece, Score};
+
+use crate::bound::BoundFn;
+use crate::eval::Eval;
+use crate::position::Position;
+
+/// プラグインの ABI バージョン。ABI を変更したらインクリメントする。
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// プラグインがエクスポートすべきエントリ関数のシンボル名。
+pub const PLUGIN_ENTRY_SYMBOL: &str = "samegame_plugin_entry";
+
+/// プラグインに渡す局面。
+#[repr(C)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginBoard {
+    /// 盤面。形式は `rules::Grid` と同じ (`grid[c][r]`, 列は左から、行は下から、空白は 0)。
+    pub grid: rules::Grid,
+    /// 各駒種 (`1..=5`) の数。`piece_counts[i]` が駒種 `i + 1` の数。
+    pub piece_counts: [u8; Piece::NUM],
+}
+
+impl PluginBoard {
+    fn from_position(pos: &Position) -> Self {
+        Self {
+            grid: pos.board().to_grid(),
+            piece_counts: std::array::from_fn(|i| {
+                pos.piece_count(Piece::from_inner(i as u8 + 1).unwrap())
+            }),
+        }
+    }
+}
+
+/// 追加獲得スコア上界を返すコールバック。
+pub type PluginBoundCallback =
+    unsafe extern "C" fn(user_data: *mut c_void, board: *const PluginBoard) -> u32;
+
+/// 局面の評価値を返すコールバック。
+pub type PluginEvalCallback =
+    unsafe extern "C" fn(user_data: *mut c_void, board: *const PluginBoard) -> i32;
+
+/// `user_data` を解放するコールバック。
+pub type PluginDropCallback = unsafe extern "C" fn(user_data: *mut c_void);
+
+/// プラグインの関数テーブル。
+///
+/// 不要なコールバックは NULL としてよい。
+#[repr(C)]
+#[derive(Debug)]
+pub struct PluginVTable {
+    /// `PLUGIN_ABI_VERSION` を設定する。
+    pub abi_version: u32,
+    /// 各コールバックの第 1 引数として渡される任意のポインタ。
+    pub user_data: *mut c_void,
+    /// 上界関数。`BoundFn::gain_upper_bound()` に対応する。
+    pub gain_upper_bound: Option<PluginBoundCallback>,
+    /// 評価関数。`Eval::eval()` に対応する。
+    pub eval: Option<PluginEvalCallback>,
+    /// プラグインの破棄時に一度だけ呼ばれる。
+    pub drop: Option<PluginDropCallback>,
+}
+
+impl Default for PluginVTable {
+    fn default() -> Self {
+        Self {
+            abi_version: 0,
+            user_data: std::ptr::null_mut(),
+            gain_upper_bound: None,
+            eval: None,
+            drop: None,
+        }
+    }
+}
+
+/// 読み込んだプラグイン。
+///
+/// `bound_fn()`, `eval_fn()` でソルバーやビームサーチに渡せる関数を得る。
+#[derive(Debug)]
+pub struct Plugin {
+    vtable: PluginVTable,
+    // vtable.drop を呼んだ後にライブラリを閉じる必要があるので、フィールドの順序に注意。
+    _library: Option<Library>,
+}
+
+// SAFETY: コールバックがスレッドセーフであることはプラグイン側の責任とする (モジュールのドキュメントを参照)。
+unsafe impl Send for Plugin {}
+unsafe impl Sync for Plugin {}
+
+impl Plugin {
+    /// 共有ライブラリ `path` を読み込み、エントリ関数を呼んでプラグインを作る。
+    ///
+    /// # Safety
+    ///
+    /// ライブラリの初期化処理およびエントリ関数が実行される。
+    /// ライブラリはモジュールのドキュメントの要件を満たさねばならない。
+    #[cfg(unix)]
+    pub unsafe fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let library = Library::open(path)
+            .with_context(|| format!("プラグイン '{}' を読み込めない", path.display()))?;
+
+        let entry = library.symbol(PLUGIN_ENTRY_SYMBOL)?;
+        let entry: unsafe extern "C" fn(*mut PluginVTable) -> i32 = std::mem::transmute(entry);
+
+        let mut vtable = PluginVTable::default();
+        let code = entry(&mut vtable);
+        ensure!(code == 0, "プラグインの初期化に失敗: エラーコード {code}");
+
+        let mut plugin = Self::from_vtable(vtable)?;
+        plugin._library = Some(library);
+
+        Ok(plugin)
+    }
+
+    /// 関数テーブルから直接プラグインを作る。Rust 以外の言語から静的にリンクする場合などに使う。
+    ///
+    /// ABI バージョンが一致しない場合、エラーを返す (このとき `drop` コールバックは呼ばれない)。
+    ///
+    /// # Safety
+    ///
+    /// `vtable` の各コールバックはモジュールのドキュメントの要件を満たさねばならない。
+    pub unsafe fn from_vtable(vtable: PluginVTable) -> anyhow::Result<Self> {
+        ensure!(
+            vtable.abi_version == PLUGIN_ABI_VERSION,
+            "プラグインの ABI バージョンが一致しない: {} (期待値: {PLUGIN_ABI_VERSION})",
+            vtable.abi_version
+        );
+
+        Ok(Self {
+            vtable,
+            _library: None,
+        })
+    }
+
+    /// プラグインの上界関数を返す。プラグインが上界関数を提供しない場合、エラーを返す。
+    pub fn bound_fn(self: &Arc<Self>) -> anyhow::Result<impl BoundFn + Send + Sync + 'static> {
+        let Some(callback) = self.vtable.gain_upper_bound else {
+            bail!("プラグインは上界関数を提供していない");
+        };
+
+        let plugin = Arc::clone(self);
+        Ok(move |pos: &Position| {
+            let board = PluginBoard::from_position(pos);
+            Score::new(unsafe { callback(plugin.vtable.user_data, &board) })
+        })
+    }
+
+    /// プラグインの評価関数を返す。プラグインが評価関数を提供しない場合、エラーを返す。
+    pub fn eval_fn(self: &Arc<Self>) -> anyhow::Result<impl Eval + Send + Sync + 'static> {
+        let Some(callback) = self.vtable.eval else {
+            bail!("プラグインは評価関数を提供していない");
+        };
+
+        let plugin = Arc::clone(self);
+        Ok(move |pos: &Position| {
+            let board = PluginBoard::from_position(pos);
+            unsafe { callback(plugin.vtable.user_data, &board) }
+        })
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        if let Some(drop) = self.vtable.drop {
+            unsafe { drop(self.vtable.user_data) }
+        }
+    }
+}
+
+/// `dlopen()` で開いた共有ライブラリ。
+#[derive(Debug)]
+struct Library(*mut c_void);
+
+#[cfg(unix)]
+mod dl {
+    use std::ffi::{c_char, c_int, c_void};
+
+    pub(super) const RTLD_NOW: c_int = 2;
+
+    #[link(name = "dl")]
+    extern "C" {
+        pub(super) fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+        pub(super) fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        pub(super) fn dlclose(handle: *mut c_void) -> c_int;
+        pub(super) fn dlerror() -> *mut c_char;
+    }
+
+    /// 直前の `dl*` 関数のエラーメッセージを返す。
+    pub(super) fn last_error() -> String {
+        let msg = unsafe { dlerror() };
+        if msg.is_null() {
+            "不明なエラー".to_owned()
+        } else {
+            unsafe { std::ffi::CStr::from_ptr(msg) }
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Library {
+    unsafe fn open(path: &Path) -> anyhow::Result<Self> {
+        use std::os::unix::ffi::OsStrExt as _;
+
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let handle = dl::dlopen(path.as_ptr(), dl::RTLD_NOW);
+        ensure!(!handle.is_null(), "{}", dl::last_error());
+
+        Ok(Self(handle))
+    }
+
+    unsafe fn symbol(&self, name: &str) -> anyhow::Result<*mut c_void> {
+        let name_c = CString::new(name)?;
+        let sym = dl::dlsym(self.0, name_c.as_ptr());
+        ensure!(
+            !sym.is_null(),
+            "シンボル '{name}' が見つからない: {}",
+            dl::last_error()
+        );
+
+        Ok(sym)
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            dl::dlclose(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use indoc::indoc;
+    use samegame_core::Board;
+
+    use super::*;
+    use crate::solver::Solver;
+
+    static DROP_COUNT: AtomicU32 = AtomicU32::new(0);
+
+    unsafe extern "C" fn bound(_user_data: *mut c_void, board: *const PluginBoard) -> u32 {
+        // 駒数に基づく緩い上界。
+        let board = &*board;
+        let n: u32 = board
+            .piece_counts
+            .iter()
+            .map(|&count| u32::from(count))
+            .sum();
+        n * n + 200
+    }
+
+    unsafe extern "C" fn eval(user_data: *mut c_void, board: *const PluginBoard) -> i32 {
+        let weight = *(user_data as *const i32);
+        weight * i32::from((*board).grid[0][0])
+    }
+
+    unsafe extern "C" fn drop_user_data(user_data: *mut c_void) {
+        drop(Box::from_raw(user_data as *mut i32));
+        DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_plugin() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+
+        let vtable = PluginVTable {
+            abi_version: PLUGIN_ABI_VERSION,
+            user_data: Box::into_raw(Box::new(10_i32)) as *mut c_void,
+            gain_upper_bound: Some(bound),
+            eval: Some(eval),
+            drop: Some(drop_user_data),
+        };
+        let plugin = Arc::new(unsafe { Plugin::from_vtable(vtable) }.unwrap());
+
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_bound_fn(plugin.bound_fn().unwrap());
+        assert_eq!(solver.solve(board.clone()).unwrap().0, Score::new(207));
+
+        let eval_fn = plugin.eval_fn().unwrap();
+        assert_eq!(eval_fn.eval(&Position::new(board)), 20);
+
+        drop(solver);
+        drop(eval_fn);
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+        drop(plugin);
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+
+        let vtable = PluginVTable {
+            abi_version: PLUGIN_ABI_VERSION + 1,
+            ..PluginVTable::default()
+        };
+        assert!(unsafe { Plugin::from_vtable(vtable) }.is_err());
+
+        let plugin = Arc::new(
+            unsafe {
+                Plugin::from_vtable(PluginVTable {
+                    abi_version: PLUGIN_ABI_VERSION,
+                    ..PluginVTable::default()
+                })
+            }
+            .unwrap(),
+        );
+        assert!(plugin.bound_fn().is_err());
+        assert!(plugin.eval_fn().is_err());
+
+        assert!(unsafe { Plugin::load("/nonexistent/plugin.so") }.is_err());
+    }
+}
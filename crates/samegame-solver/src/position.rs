@@ -0,0 +1,882 @@
+//! 局面関連。
+
+use arrayvec::ArrayVec;
+use samegame_core::{
+    calc_score_erase, Action, Board, Col, MoveList, Piece, PieceArray, PieceSet, Row, RuleSet,
+    Score, Square,
+};
+
+use crate::color_signature::{color_profile_from_counts, color_signature_from_counts};
+use crate::merge::MergeCandidates;
+use crate::terminal::TerminalEval;
+use crate::zobrist::ZOBRIST_TABLE;
+
+/// 局面。
+///
+/// 初期盤面からの獲得スコアを持ち、着手のたびに加算する (`score()`, `final_score()`)。
+/// ただし比較とハッシュ値は盤面のみで決まり、獲得スコアは無視する (置換表などのキーとして使うため)。
+#[derive(Clone, Debug)]
+pub struct Position {
+    board: Board,
+    key: u64,
+    piece_counts: PieceArray<u8>,
+    score: Score,
+}
+
+impl Position {
+    /// 初期盤面を指定して局面を作る。
+    pub fn new(board: Board) -> Self {
+        let key = board_key(&board);
+        let piece_counts = board_piece_counts(&board);
+
+        Self {
+            board,
+            key,
+            piece_counts,
+            score: Score::ZERO,
+        }
+    }
+
+    /// 盤面を返す。
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// ハッシュ値を返す。
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// 初期盤面からここまでの着手による獲得スコアを返す。パーフェクトボーナスは含まない。
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// 獲得スコアに、実際のゲームのルールにおける終了局面評価 (盤面が空ならパーフェクトボーナス) を加えたものを返す。
+    ///
+    /// 終了局面でこれを呼べば、ゲーム通りの最終スコアとなる。
+    pub fn final_score(&self) -> Score {
+        self.final_score_with_rules(&RuleSet::GAME)
+    }
+
+    /// 獲得スコアに、ルール `rules` における終了局面評価を加えたものを返す。
+    pub fn final_score_with_rules(&self, rules: &RuleSet) -> Score {
+        self.score + rules.final_gain(self)
+    }
+
+    /// 指定した駒種の数を返す。
+    pub fn piece_count(&self, piece: Piece) -> u8 {
+        self.piece_counts[piece]
+    }
+
+    /// 駒種ごとの駒数の多重集合のハッシュ値を返す。駒種の入れ替えに対して不変。
+    ///
+    /// 駒数の分布で局面を分類する二次キーとして使う (`ColorBuckets` を参照)。
+    pub fn color_signature(&self) -> u64 {
+        color_signature_from_counts(Piece::all().map(|piece| self.piece_counts[piece]))
+    }
+
+    /// 駒種ごとの駒数を降順に並べたものを返す。
+    pub fn color_profile(&self) -> [u8; Piece::NUM] {
+        color_profile_from_counts(*self.piece_counts.as_array())
+    }
+
+    /// 列の消滅による同種駒の合流候補を返す。呼ぶたびに盤面から求める。
+    pub fn merge_candidates(&self) -> MergeCandidates {
+        MergeCandidates::new(&self.board)
+    }
+
+    /// 合法手があるかどうかを返す。
+    pub fn has_action(&self) -> bool {
+        self.board().has_action()
+    }
+
+    /// 合法手が存在する駒種の集合を返す。
+    ///
+    /// 盤面上に存在するがこの集合に含まれない駒種は、現時点では消せない。
+    pub fn colors_with_actions(&self) -> PieceSet {
+        Piece::all()
+            .filter(|&piece| self.piece_count(piece) >= 2 && self.board.has_action_for(piece))
+            .collect()
+    }
+
+    /// 合法手の数を返す。着手を生成するより軽い。
+    pub fn action_count(&self) -> u32 {
+        self.board.action_count()
+    }
+
+    /// 合法手を列挙する。
+    ///
+    /// 列挙順は決定的であることを保証する: 駒種の昇順、同じ駒種内では着手の最小マス (`Action::least_square()`) の昇順。
+    pub fn actions(&self) -> impl std::iter::FusedIterator<Item = Action> + Clone + '_ {
+        self.board
+            .action_components()
+            .map(|(piece, mb)| Action::new(piece, mb))
+    }
+
+    /// ルール `rules` の下での合法手を列挙する。列挙順は `actions()` と同じ。
+    pub fn actions_with_rules<'a>(
+        &'a self,
+        rules: &'a RuleSet,
+    ) -> impl std::iter::FusedIterator<Item = Action> + Clone + 'a {
+        self.actions()
+            .filter(|action| rules.is_legal_action(action))
+    }
+
+    /// ルール `rules` の下で合法手があるかどうかを返す。
+    pub fn has_action_with_rules(&self, rules: &RuleSet) -> bool {
+        if rules.is_default_group_rule() {
+            self.has_action()
+        } else {
+            self.actions_with_rules(rules).next().is_some()
+        }
+    }
+
+    /// 合法手をバッファ `out` に集める (`out` の元の内容は捨てる)。順序は `actions()` と同じ。
+    ///
+    /// 並べ替えたい場合や、同じバッファを使い回したい場合に用いる。
+    pub fn collect_actions(&self, out: &mut MoveList) {
+        out.clear();
+        out.extend(self.actions());
+    }
+
+    /// ルール `rules` の下での合法手をバッファ `out` に集める (`out` の元の内容は捨てる)。順序は `actions()` と同じ。
+    pub fn collect_actions_with_rules(&self, rules: &RuleSet, out: &mut MoveList) {
+        out.clear();
+        out.extend(self.actions_with_rules(rules));
+    }
+
+    /// 駒種の順序を指定して合法手を列挙する。
+    ///
+    /// 列挙順は `pieces` の順、同じ駒種内では着手の最小マスの昇順となる。
+    /// `pieces` に含まれない駒種の着手は列挙されない。
+    pub fn actions_in_piece_order<'a>(
+        &'a self,
+        pieces: &'a [Piece],
+    ) -> impl std::iter::FusedIterator<Item = Action> + Clone + 'a {
+        pieces
+            .iter()
+            .flat_map(|&piece| {
+                self.board
+                    .piece_mask(piece)
+                    .components()
+                    .filter(|mb| !mb.is_single())
+                    .map(move |mb| Action::new(piece, mb))
+            })
+            .fuse()
+    }
+
+    /// 着手を行い、結果の局面を返す。
+    pub fn do_action(&self, action: &Action) -> Self {
+        let board = self.board.erase(action.mask());
+
+        self.with_board_after(action, board)
+    }
+
+    /// ルール `rules` の下で着手を行い、結果の局面を返す。
+    ///
+    /// `action` が `rules` の下で合法手かどうかはチェックしない。
+    pub fn do_action_with_rules(&self, action: &Action, rules: &RuleSet) -> Self {
+        let board = rules.erase(&self.board, action.mask());
+
+        self.with_board_after(action, board)
+    }
+
+    /// その場で着手を行い、`undo_action()` で着手前に戻すための情報を返す。
+    ///
+    /// `do_action()` と異なり新たな局面を作らないので、探索で局面を 1 つだけ持ち回せる。
+    pub fn make_action(&mut self, action: &Action) -> PositionUndo {
+        self.make_action_with_rules(action, &RuleSet::GAME)
+    }
+
+    /// ルール `rules` の下でその場で着手を行い、`undo_action()` で着手前に戻すための情報を返す。
+    ///
+    /// `action` が `rules` の下で合法手かどうかはチェックしない。
+    pub fn make_action_with_rules(&mut self, action: &Action, rules: &RuleSet) -> PositionUndo {
+        let board_before = self.board.clone();
+        let erased_count = rules.erase_in_place(&mut self.board, action.mask()) as u8;
+        let key = key_after(self.key, action, &board_before, &self.board);
+
+        let undo = PositionUndo {
+            board: board_before,
+            key: self.key,
+            score: self.score,
+            piece: action.piece(),
+            erased_count,
+        };
+
+        self.key = key;
+        self.piece_counts[undo.piece] -= erased_count;
+        self.score += action.gain();
+
+        undo
+    }
+
+    /// `make_action()` (または `make_action_with_rules()`) による着手を取り消す。
+    ///
+    /// `undo` はこの局面で最後に行った着手のものでなければならない。
+    pub fn undo_action(&mut self, undo: PositionUndo) {
+        let PositionUndo {
+            board,
+            key,
+            score,
+            piece,
+            erased_count,
+        } = undo;
+
+        self.board = board;
+        self.key = key;
+        self.piece_counts[piece] += erased_count;
+        self.score = score;
+    }
+
+    /// 着手 `action` により盤面が `board` になったときの局面を返す。ハッシュ値は差分更新する。
+    fn with_board_after(&self, action: &Action, board: Board) -> Self {
+        let key = key_after(self.key, action, &self.board, &board);
+
+        self.finish_action(action, board, key)
+    }
+
+    fn finish_action(&self, action: &Action, board: Board, key: u64) -> Self {
+        let mut piece_counts = self.piece_counts.clone();
+        piece_counts[action.piece()] -= action.square_count() as u8;
+
+        Self {
+            board,
+            key,
+            piece_counts,
+            score: self.score + action.gain(),
+        }
+    }
+
+    /// この局面から追加で獲得しうるスコアの上界を返す。
+    /// 探索は一切行わず、粗く見積もる。
+    ///
+    /// 盤面が空の場合、追加でパーフェクトボーナスを獲得できるとみなす。
+    ///
+    /// この関数が 0 を返すならば、`self` はパーフェクトでない終了局面である。
+    /// ただし逆は成り立たない (例: `121.......`)。
+    ///
+    /// 駒種ごとの連結成分に基づく見積もり (各成分のみを 1 手で消すと仮定する) は許容的でない
+    /// (途中の着手により同種の成分同士が合流しうる) ので用いない。
+    pub fn gain_upper_bound(&self) -> Score {
+        self.gain_upper_bound_with_rules(&RuleSet::GAME)
+    }
+
+    /// ルール `rules` の下でこの局面から追加で獲得しうるスコアの上界を返す。
+    /// `gain_upper_bound()` と同様に粗く見積もる。
+    pub fn gain_upper_bound_with_rules(&self, rules: &RuleSet) -> Score {
+        // 着手可能な個数以上存在する駒種全てが 1 手で全消しできると仮定して上界を求める。
+        // 適宜パーフェクトボーナスも加算する。
+
+        let min_group_size = rules.min_group_size.max(2);
+        let mut res = Score::ZERO;
+        let mut perfect = true;
+        for piece in Piece::all() {
+            let count = self.piece_count(piece);
+            if count == 0 {
+                continue;
+            }
+            if count < min_group_size {
+                perfect = false;
+            } else {
+                res += calc_score_erase(u32::from(count));
+            }
+        }
+
+        if perfect && !self.is_perfect_blocked(min_group_size) {
+            res += rules.perfect_bonus;
+        }
+
+        res
+    }
+
+    /// 駒数の条件を満たすにもかかわらず、パーフェクトが不可能だと確定するかどうかを返す。
+    ///
+    /// 駒数が `min_group_size` 以上 3 以下の駒種は、パーフェクトのためには 1 手で全て消さねばならない。
+    /// またこのとき、それらの駒は消す時点で連結でなければならないので、
+    /// 同じ列にある 2 駒の間の駒、および間にその駒種を含まない列の駒は全て先に消す必要がある
+    /// (3 駒以下では、同じ列の 2 駒を他の列経由でつなぐことはできない)。
+    /// こうした駒種間の前後関係に循環があれば、パーフェクトは不可能。
+    fn is_perfect_blocked(&self, min_group_size: u8) -> bool {
+        let is_target = |piece: Piece| (min_group_size..=3).contains(&self.piece_count(piece));
+        if !Piece::all().any(is_target) {
+            return false;
+        }
+
+        // deps[x] は、駒種 x より先に消さねばならない駒種の集合。
+        let mut deps = PieceArray::<PieceSet>::default();
+        for piece in Piece::all().filter(|&piece| is_target(piece)) {
+            let mut add = |sq: Square| {
+                if let Some(blocker) = self.board.get(sq) {
+                    if blocker != piece && is_target(blocker) {
+                        deps[piece].insert(blocker);
+                    }
+                }
+            };
+
+            let has_piece = |col: Col| {
+                Row::all().any(|row| self.board.get(Square::new(col, row)) == Some(piece))
+            };
+            let cols: ArrayVec<Col, { Col::NUM }> = self
+                .board
+                .nonempty_cols()
+                .filter(|&col| has_piece(col))
+                .collect();
+            for &col in &cols {
+                let rows: ArrayVec<Row, { Row::NUM }> = Row::all()
+                    .filter(|&row| self.board.get(Square::new(col, row)) == Some(piece))
+                    .collect();
+                for row in Row::all().filter(|&row| rows[0] < row && row < rows[rows.len() - 1]) {
+                    add(Square::new(col, row));
+                }
+            }
+            for col in self
+                .board
+                .nonempty_cols()
+                .filter(|&col| cols[0] < col && col < cols[cols.len() - 1] && !has_piece(col))
+            {
+                for row in Row::all() {
+                    add(Square::new(col, row));
+                }
+            }
+        }
+
+        // 推移閉包をとり、自身に戻る駒種があるか調べる。
+        for _ in Piece::all() {
+            for piece in Piece::all() {
+                let reach = deps[piece]
+                    .iter()
+                    .fold(deps[piece], |acc, other| acc | deps[other]);
+                deps[piece] = reach;
+            }
+        }
+
+        Piece::all().any(|piece| deps[piece].contains(piece))
+    }
+}
+
+/// 局面のハッシュ値が `key` で、着手 `action` により盤面が `board_before` から `board` になったとき、
+/// 着手後のハッシュ値を返す。ハッシュ値は差分更新する。
+fn key_after(mut key: u64, action: &Action, board_before: &Board, board: &Board) -> u64 {
+    // 1 列のみの着手で列の詰め直しが起こらなければ (盤面の幅が変わらなければ)、変化するのはその列のみ。
+    // 大半の着手がこれに当たるので、盤面全体の差分を取らずに済ませる。
+    let changed_col = action
+        .mask()
+        .single_col()
+        .filter(|_| board.width_remain() == board_before.width_remain());
+    if let Some(col) = changed_col {
+        for row in Row::all() {
+            let sq = Square::new(col, row);
+            let piece_before = board_before.get(sq);
+            let piece_after = board.get(sq);
+            if piece_before == piece_after {
+                continue;
+            }
+            if let Some(piece_before) = piece_before {
+                key ^= ZOBRIST_TABLE.board(piece_before, sq);
+            }
+            if let Some(piece_after) = piece_after {
+                key ^= ZOBRIST_TABLE.board(piece_after, sq);
+            }
+        }
+
+        return key;
+    }
+
+    for sq in board_before.xor_mask(board).squares() {
+        // 着手前、sq には駒があったとは限らないことに注意(列が詰め直されるケースがあるので)。
+        if let Some(piece_before) = board_before.get(sq) {
+            key ^= ZOBRIST_TABLE.board(piece_before, sq);
+        }
+        if let Some(piece_after) = board.get(sq) {
+            key ^= ZOBRIST_TABLE.board(piece_after, sq);
+        }
+    }
+
+    key
+}
+
+/// `Position::make_action()` による着手を取り消すための情報。
+///
+/// 着手前の盤面とキャッシュを持つ。盤面は固定長なので、これを作ってもヒープ確保は起こらない。
+#[derive(Clone, Debug)]
+pub struct PositionUndo {
+    board: Board,
+    key: u64,
+    score: Score,
+    piece: Piece,
+    erased_count: u8,
+}
+
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+    }
+}
+
+impl Eq for Position {}
+
+impl std::hash::Hash for Position {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+/// 盤面を借用する軽量な局面。ハッシュ値や駒数は必要になった時点で計算し、キャッシュする。
+///
+/// 重複除去やデータベースの検索など、多数の盤面について一時的にハッシュ値や駒数を使いたい解析用コード向け。
+/// 盤面をクローンして `Position` を作るより軽い。
+///
+/// ハッシュ値は同じ盤面に対する `Position::key()` と一致する。
+/// キャッシュは内部可変だが、ハッシュ値はキャッシュの有無によらないので `HashMap` などのキーとして使える。
+#[derive(Clone, Debug)]
+pub struct PositionRef<'a> {
+    board: &'a Board,
+    key: std::cell::OnceCell<u64>,
+    piece_counts: std::cell::OnceCell<PieceArray<u8>>,
+}
+
+impl<'a> PositionRef<'a> {
+    pub fn new(board: &'a Board) -> Self {
+        Self {
+            board,
+            key: std::cell::OnceCell::new(),
+            piece_counts: std::cell::OnceCell::new(),
+        }
+    }
+
+    /// 盤面を返す。
+    pub fn board(&self) -> &'a Board {
+        self.board
+    }
+
+    /// ハッシュ値を返す。
+    pub fn key(&self) -> u64 {
+        *self.key.get_or_init(|| board_key(self.board))
+    }
+
+    /// 指定した駒種の数を返す。
+    pub fn piece_count(&self, piece: Piece) -> u8 {
+        self.piece_counts
+            .get_or_init(|| board_piece_counts(self.board))[piece]
+    }
+
+    /// 駒種ごとの駒数の多重集合のハッシュ値を返す。`Position::color_signature()` と一致する。
+    pub fn color_signature(&self) -> u64 {
+        color_signature_from_counts(Piece::all().map(|piece| self.piece_count(piece)))
+    }
+
+    /// 駒種ごとの駒数を降順に並べたものを返す。`Position::color_profile()` と一致する。
+    pub fn color_profile(&self) -> [u8; Piece::NUM] {
+        let piece_counts = self
+            .piece_counts
+            .get_or_init(|| board_piece_counts(self.board));
+        color_profile_from_counts(*piece_counts.as_array())
+    }
+
+    /// 盤面をクローンして `Position` を作る。計算済みのハッシュ値や駒数は再利用する。獲得スコアは 0 とする。
+    pub fn to_position(&self) -> Position {
+        Position {
+            board: self.board.clone(),
+            key: self.key(),
+            piece_counts: self
+                .piece_counts
+                .get()
+                .cloned()
+                .unwrap_or_else(|| board_piece_counts(self.board)),
+            score: Score::ZERO,
+        }
+    }
+}
+
+impl<'a> From<&'a Position> for PositionRef<'a> {
+    /// `Position` が持つハッシュ値と駒数をそのまま使う。
+    fn from(pos: &'a Position) -> Self {
+        Self {
+            board: &pos.board,
+            key: std::cell::OnceCell::from(pos.key),
+            piece_counts: std::cell::OnceCell::from(pos.piece_counts.clone()),
+        }
+    }
+}
+
+impl PartialEq for PositionRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+    }
+}
+
+impl Eq for PositionRef<'_> {}
+
+impl std::hash::Hash for PositionRef<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+/// 盤面のハッシュ値を計算する。
+fn board_key(board: &Board) -> u64 {
+    Square::all()
+        .map(|sq| {
+            board
+                .get(sq)
+                .map_or(0, |piece| ZOBRIST_TABLE.board(piece, sq))
+        })
+        .reduce(std::ops::BitXor::bitxor)
+        .unwrap()
+}
+
+/// 盤面の駒種ごとの駒数を計算する。
+fn board_piece_counts(board: &Board) -> PieceArray<u8> {
+    PieceArray::from_fn(|piece| board.piece_count(piece) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use itertools::assert_equal;
+    use samegame_core::rules::{COL_COUNT, ROW_COUNT};
+    use samegame_core::*;
+
+    use super::*;
+    use crate::hash::U64HashMap;
+
+    const P1: Piece = Piece::from_inner(1).unwrap();
+    const P2: Piece = Piece::from_inner(2).unwrap();
+    const P3: Piece = Piece::from_inner(3).unwrap();
+    const P4: Piece = Piece::from_inner(4).unwrap();
+    const P5: Piece = Piece::from_inner(5).unwrap();
+
+    fn sq_new(col: Col, row: Row) -> Square {
+        Square::new(col, row)
+    }
+
+    fn parse_board(s: impl AsRef<str>) -> Board {
+        s.as_ref().parse().unwrap()
+    }
+
+    fn pos_do_action(pos: &Position, sq: Square) -> Position {
+        let action = Action::from_board_square(pos.board(), sq).unwrap();
+        pos.do_action(&action)
+    }
+
+    #[test]
+    fn test_position() {
+        assert_eq!(Position::new(Board::empty()).key(), 0);
+
+        let pos_start = Position::new(parse_board(indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "}));
+        let pos = pos_do_action(&pos_start, sq_new(COL_2, ROW_5));
+        let pos = pos_do_action(&pos, sq_new(COL_1, ROW_1));
+
+        let pos_expect = Position::new(parse_board(indoc! {"
+            .....2..
+            .....2..
+            ..4..2..
+            244..1..
+            233.51..
+            235551..
+        "}));
+        assert_eq!(pos.board(), pos_expect.board());
+        assert_eq!(pos.key(), pos_expect.key());
+        for piece in Piece::all() {
+            assert_eq!(pos.piece_count(piece), pos_expect.piece_count(piece));
+        }
+    }
+
+    #[test]
+    fn test_collect_actions() {
+        let pos = Position::new(parse_board(indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "}));
+
+        let mut actions = MoveList::new();
+        pos.collect_actions(&mut actions);
+        assert_equal(actions.iter().cloned(), pos.actions());
+
+        // 消す駒数の降順に並べ替える。同数の手は列挙順を保つ。
+        actions.sort_largest_first();
+        let mut expect: Vec<_> = pos.actions().collect();
+        expect.sort_by_key(|action| std::cmp::Reverse(action.square_count()));
+        assert_eq!(actions.as_slice(), expect.as_slice());
+
+        // バッファを使い回すと元の内容は捨てられる。
+        let pos_child = pos.do_action(&actions.as_slice()[0]);
+        pos_child.collect_actions(&mut actions);
+        assert_equal(actions, pos_child.actions());
+    }
+
+    #[test]
+    fn test_make_undo_action() {
+        let pos_start = Position::new(parse_board(indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "}));
+        let no_compaction = RuleSet {
+            column_compaction: false,
+            ..RuleSet::GAME
+        };
+
+        for rules in [RuleSet::GAME, no_compaction] {
+            // 常に最後の合法手を選んで終了局面まで進め、各手で do_action の結果と一致することを確かめる。
+            let mut pos = pos_start.clone();
+            let mut stack = vec![];
+            while let Some(action) = pos.actions_with_rules(&rules).last() {
+                let expect = pos.do_action_with_rules(&action, &rules);
+                let undo = pos.make_action_with_rules(&action, &rules);
+                assert_eq!(pos, expect);
+                assert_eq!(pos.key(), expect.key());
+                assert_eq!(pos.score(), expect.score());
+                stack.push((expect, undo));
+            }
+            assert!(stack.len() >= 2);
+
+            // 取り消すと各手の前の局面に戻る。
+            while let Some((expect, undo)) = stack.pop() {
+                assert_eq!(pos, expect);
+                assert_eq!(pos.score(), expect.score());
+                pos.undo_action(undo);
+            }
+            assert_eq!(pos, pos_start);
+            assert_eq!(pos.score(), Score::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_score() {
+        let pos = Position::new(parse_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            12......
+            1122....
+        "}));
+        assert_eq!(pos.score(), Score::ZERO);
+
+        // 1 を 3 個消し、続けて 2 を 3 個消すとパーフェクト。
+        let action = pos.actions().next().unwrap();
+        let pos_1 = pos.do_action(&action);
+        assert_eq!(pos_1.score(), Score::new(4));
+        assert_eq!(pos_1.final_score(), Score::new(4));
+        let action = pos_1.actions().next().unwrap();
+        let pos_2 = pos_1.do_action(&action);
+        assert!(pos_2.board().is_empty());
+        assert_eq!(pos_2.score(), Score::new(8));
+        assert_eq!(pos_2.final_score(), Score::new(208));
+        assert_eq!(
+            pos_2.final_score_with_rules(&RuleSet::GAME.with_perfect_bonus(Score::new(500))),
+            Score::new(508)
+        );
+
+        // 比較とハッシュ値は獲得スコアによらない。
+        let pos_again = pos_2.clone();
+        assert_eq!(pos_again, Position::new(pos_2.board().clone()));
+        let mut table = U64HashMap::default();
+        table.insert(pos_2, ());
+        assert!(table.contains_key(&Position::new(Board::empty())));
+    }
+
+    #[test]
+    fn test_gain_upper_bound() {
+        // 1 と 2 はそれぞれ 1 手で消さねばならないが、互いに相手を先に消す必要があるのでパーフェクトは不可能。
+        let pos = Position::new(parse_board(indoc! {"
+            ........
+            ........
+            1.......
+            2.......
+            1.......
+            2.......
+        "}));
+        assert_eq!(pos.gain_upper_bound(), Score::new(2));
+
+        // 間の駒を先に消せるならばパーフェクトボーナスを含める。
+        let pos = Position::new(parse_board(indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            22......
+            12......
+        "}));
+        assert_eq!(pos.gain_upper_bound(), Score::new(1 + 4 + 200));
+
+        // 小さな盤面たちについて、パーフェクトボーナスを除外した場合に実際にパーフェクトが不可能なことを全探索で確かめる。
+        fn can_perfect(pos: &Position, memo: &mut U64HashMap<Position, bool>) -> bool {
+            if pos.board().is_empty() {
+                return true;
+            }
+            if let Some(&res) = memo.get(pos) {
+                return res;
+            }
+            let res = pos
+                .actions()
+                .any(|action| can_perfect(&pos.do_action(&action), memo));
+            memo.insert(pos.clone(), res);
+            res
+        }
+
+        let mut state = 0x12345678_u32;
+        let mut blocked_count = 0;
+        for _ in 0..3000 {
+            let mut grid = [[0; ROW_COUNT]; COL_COUNT];
+            for cell in grid
+                .iter_mut()
+                .take(4)
+                .flat_map(|col| col.iter_mut().take(3))
+            {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                *cell = (state % 4) as u8 + 1;
+            }
+            let pos = Position::new(Board::from_grid(&grid).unwrap());
+            let counts_ok = Piece::all().all(|piece| pos.piece_count(piece) != 1);
+            if !counts_ok || !pos.is_perfect_blocked(2) {
+                continue;
+            }
+            blocked_count += 1;
+            assert!(
+                !can_perfect(&pos, &mut U64HashMap::default()),
+                "{}",
+                pos.board()
+            );
+        }
+        assert!(blocked_count > 0);
+    }
+
+    #[test]
+    fn test_actions_order() {
+        let pos = Position::new(parse_board(indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "}));
+
+        let expect = [
+            (P1, sq_new(COL_1, ROW_1)),
+            (P1, sq_new(COL_8, ROW_1)),
+            (P2, sq_new(COL_2, ROW_1)),
+            (P2, sq_new(COL_8, ROW_4)),
+            (P3, sq_new(COL_4, ROW_1)),
+            (P4, sq_new(COL_4, ROW_3)),
+            (P5, sq_new(COL_2, ROW_5)),
+            (P5, sq_new(COL_5, ROW_1)),
+        ];
+        assert_equal(
+            pos.actions()
+                .map(|action| (action.piece(), action.least_square())),
+            expect,
+        );
+        assert_eq!(pos.action_count() as usize, expect.len());
+        assert_eq!(pos.colors_with_actions(), PieceSet::full());
+
+        let order = [P5, P3, P1];
+        let expect_ordered = [
+            (P5, sq_new(COL_2, ROW_5)),
+            (P5, sq_new(COL_5, ROW_1)),
+            (P3, sq_new(COL_4, ROW_1)),
+            (P1, sq_new(COL_1, ROW_1)),
+            (P1, sq_new(COL_8, ROW_1)),
+        ];
+        assert_equal(
+            pos.actions_in_piece_order(&order)
+                .map(|action| (action.piece(), action.least_square())),
+            expect_ordered,
+        );
+    }
+
+    #[test]
+    fn test_hash() {
+        let pos1 = Position::new(parse_board(indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "}));
+        let pos2 = Position::new(parse_board(indoc! {"
+            ......2.
+            ......2.
+            5..4..2.
+            2.44..1.
+            2.33.51.
+            2535551.
+        "}));
+        let pos3 = Position::new(parse_board(indoc! {"
+            1......2
+            1......2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "}));
+
+        let mut map = U64HashMap::<Position, u32>::default();
+        map.insert(pos1.clone(), 1);
+        map.insert(pos2.clone(), 2);
+        map.insert(pos3.clone(), 3);
+
+        assert_eq!(map.get(&pos1), Some(&1));
+        assert_eq!(map.get(&pos2), Some(&2));
+        assert_eq!(map.get(&pos3), Some(&3));
+    }
+
+    #[test]
+    fn test_position_ref() {
+        let board = parse_board(indoc! {"
+            1......2
+            155....2
+            111.4..2
+            12144..1
+            12133.51
+            12135551
+        "});
+        let pos = Position::new(board.clone());
+
+        let pos_ref = PositionRef::new(&board);
+        assert_eq!(pos_ref.key(), pos.key());
+        for piece in Piece::all() {
+            assert_eq!(pos_ref.piece_count(piece), pos.piece_count(piece));
+        }
+        assert_eq!(pos_ref.to_position(), pos);
+        assert_eq!(PositionRef::new(&board).to_position(), pos);
+
+        let pos_child = pos_do_action(&pos, sq_new(COL_1, ROW_1));
+        let pos_child_ref = PositionRef::from(&pos_child);
+        assert_eq!(pos_child_ref.key(), pos_child.key());
+        assert_eq!(
+            PositionRef::new(pos_child.board()).key(),
+            pos_child_ref.key()
+        );
+        assert_ne!(pos_child_ref, pos_ref);
+
+        // キャッシュは内部可変だがハッシュ値は変わらないので、キーとして使っても問題ない。
+        #[allow(clippy::mutable_key_type)]
+        let mut map = U64HashMap::<PositionRef, u32>::default();
+        map.insert(pos_ref, 1);
+        map.insert(pos_child_ref, 2);
+        assert_eq!(map.get(&PositionRef::new(&board)), Some(&1));
+        assert_eq!(map.get(&PositionRef::new(pos_child.board())), Some(&2));
+    }
+}
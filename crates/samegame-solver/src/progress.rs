@@ -0,0 +1,143 @@
+//! 一括処理の進捗イベント関連。
+//!
+//! パイプラインや並列ソルバーなどの一括処理 API は、進捗を共通の `ProgressEvent` として
+//! `ProgressSink` に通知する。フロントエンド (TUI のダッシュボードや Web の状況ページなど) は
+//! どの処理の進捗も同じ方法で表示できる。
+
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use samegame_core::{ActionHistory, RandomBoardParam, Score};
+
+/// 一括処理の進捗イベント。
+///
+/// `index` は処理ごとの面の通し番号 (パイプラインでは再開時にスキップしたものを除いた処理順、
+/// 並列ソルバーでは `solve_batch()` に渡した盤面列内の位置)。
+/// `param` は面の盤面生成パラメータで、盤面を直接与えた場合は `None`。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ProgressEvent {
+    /// パラメータの列挙が進んだ (バッチごと)。
+    EnumerationProgress {
+        /// 列挙したパラメータ数 (再開時にスキップしたものを含む)。
+        processed: u64,
+        /// パラメータの総数 (事前にわかる場合のみ)。
+        total: Option<u64>,
+    },
+    /// 面の厳密求解を開始した。
+    SolveStarted {
+        index: u64,
+        param: Option<RandomBoardParam>,
+        /// この面の枝刈り用スコア閾値。
+        prune_score_max: Score,
+    },
+    /// 処理全体の最良解が更新された。
+    Incumbent {
+        index: u64,
+        param: Option<RandomBoardParam>,
+        score: Score,
+        solution: ActionHistory,
+    },
+    /// 面の厳密求解を終えた。
+    SolveFinished {
+        index: u64,
+        param: Option<RandomBoardParam>,
+        /// 得られた解のスコア (枝刈り用スコア閾値を超える解がなければ `None`)。
+        score: Option<Score>,
+        /// 求解に要した時間。
+        elapsed: Duration,
+    },
+    /// 再開用のチェックポイント (結果ファイルなど) を書き出した。
+    CheckpointWritten {
+        path: PathBuf,
+        /// この時点までに列挙したパラメータ数。
+        processed: u64,
+    },
+    /// 分割して処理した結果ファイル (シャード) の 1 つを統合した。
+    ShardMerged {
+        path: PathBuf,
+        /// シャードから読み込んだレコード数。
+        record_count: u64,
+    },
+}
+
+/// 進捗イベントの通知先。
+///
+/// 複数のスレッドから並行に呼ばれうるので `&self` を取る。
+/// `Fn(&ProgressEvent)` を満たすクロージャ、およびチャネルの送信側 `Sender<ProgressEvent>` はこのトレイトを実装している。
+pub trait ProgressSink {
+    /// イベント `event` を通知する。
+    fn on_event(&self, event: &ProgressEvent);
+}
+
+impl<F: Fn(&ProgressEvent)> ProgressSink for F {
+    fn on_event(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+impl ProgressSink for Sender<ProgressEvent> {
+    fn on_event(&self, event: &ProgressEvent) {
+        // 受信側が既に閉じていても処理は続ける。
+        let _ = self.send(event.clone());
+    }
+}
+
+/// 何もしない通知先。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NullProgress;
+
+impl ProgressSink for NullProgress {
+    fn on_event(&self, _event: &ProgressEvent) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use samegame_core::Action;
+
+    use super::*;
+    use crate::benchmark::benchmark_board;
+    use crate::parallel::ParallelSolver;
+    use crate::position::Position;
+
+    #[test]
+    fn test_progress_events() {
+        // 並列ソルバー: 各面の開始・終了と、スコアが単調増加する最良解の更新が通知される。
+        let bb = benchmark_board("easiest").unwrap();
+        let mut boards = Vec::new();
+        let mut pos = Position::new(bb.board.clone());
+        for &sq in bb.solution.iter().take(4) {
+            boards.push(pos.board().clone());
+            pos = pos.do_action(&Action::from_board_square(pos.board(), sq).unwrap());
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut par = ParallelSolver::new(2, Score::new(100));
+        par.set_progress({
+            let events = Arc::clone(&events);
+            move |event: &ProgressEvent| events.lock().unwrap().push(event.clone())
+        });
+        par.solve_batch(&boards);
+
+        let events = events.lock().unwrap();
+        for index in 0..boards.len() as u64 {
+            assert!(events.iter().any(
+                |event| matches!(event, ProgressEvent::SolveStarted { index: i, .. } if *i == index)
+            ));
+            assert!(events.iter().any(
+                |event| matches!(event, ProgressEvent::SolveFinished { index: i, .. } if *i == index)
+            ));
+        }
+        let incumbents: Vec<Score> = events
+            .iter()
+            .filter_map(|event| match event {
+                ProgressEvent::Incumbent { score, .. } => Some(*score),
+                _ => None,
+            })
+            .collect();
+        assert!(incumbents.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(incumbents.last(), Some(&bb.known_best));
+    }
+}
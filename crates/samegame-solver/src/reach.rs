@@ -0,0 +1,341 @@
+//! 盤面間の到達可能性関連。
+
+use std::collections::VecDeque;
+
+use samegame_core::{ActionHistory, Board, Piece, PieceArray};
+
+use crate::hash::{U64HashMap, U64HashSet};
+use crate::position::Position;
+
+/// 盤面 `src` から盤面 `dst` へ `budget` 手以内で到達できるなら、そのような最短の手順を返す。
+///
+/// `src == dst` なら空の手順を返す。
+/// コミュニティで共有された手順の途中盤面の検証や、書き写しミスの発見などに使う。
+pub fn moves_between(src: &Board, dst: &Board, budget: usize) -> Option<ActionHistory> {
+    let budget = budget.min(ActionHistory::CAPACITY);
+
+    let src = Position::new(src.clone());
+    let dst = Position::new(dst.clone());
+
+    let mut searcher = MovesBetween {
+        dst,
+        failed: U64HashMap::default(),
+        history: ActionHistory::new(),
+    };
+
+    // 反復深化により最短手順を求める。
+    for depth in 0..=budget {
+        if searcher.dfs(&src, depth) {
+            return Some(searcher.history);
+        }
+    }
+
+    None
+}
+
+/// 盤面 `board` から到達可能な相異なる局面たちを幅優先で列挙する (初期局面を含む)。
+///
+/// 最大 `limit` 個まで列挙する。同じ深さ (手数) の局面たちの列挙順は未規定。
+/// 状態空間の大きさの計測などに使う。
+pub fn reachable_positions(board: Board, limit: usize) -> ReachablePositions {
+    let pos = Position::new(board);
+
+    let mut seen = U64HashSet::default();
+    seen.insert(pos.clone());
+
+    ReachablePositions {
+        queue: VecDeque::from([pos]),
+        seen,
+        remain: limit,
+    }
+}
+
+/// `reachable_positions()` が返すイテレータ。
+#[derive(Debug)]
+pub struct ReachablePositions {
+    queue: VecDeque<Position>,
+    seen: U64HashSet<Position>,
+    remain: usize,
+}
+
+impl ReachablePositions {
+    /// これまでに発見した (列挙済みおよび列挙待ちの) 局面数を返す。
+    pub fn discovered_count(&self) -> usize {
+        self.seen.len()
+    }
+}
+
+impl Iterator for ReachablePositions {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remain == 0 {
+            return None;
+        }
+        let pos = self.queue.pop_front()?;
+        self.remain -= 1;
+
+        for action in pos.actions() {
+            let pos_child = pos.do_action(&action);
+            if !self.seen.contains(&pos_child) {
+                self.seen.insert(pos_child.clone());
+                self.queue.push_back(pos_child);
+            }
+        }
+
+        Some(pos)
+    }
+}
+
+impl std::iter::FusedIterator for ReachablePositions {}
+
+/// `state_space_stats()` の探索上限。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StateSpaceLimits {
+    /// 展開する局面数の上限。
+    pub max_positions: usize,
+}
+
+impl Default for StateSpaceLimits {
+    fn default() -> Self {
+        Self {
+            max_positions: usize::MAX,
+        }
+    }
+}
+
+/// 盤面の状態空間の統計。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateSpaceStats {
+    /// 到達可能な相異なる局面数 (初期局面を含む)。
+    pub position_count: u64,
+    /// 初期局面から局面への最短手数の最大値。
+    pub max_depth: u32,
+    /// 到達可能な相異なる終了局面数。
+    pub terminal_count: u64,
+    /// 全局面を展開し終えたかどうか。
+    /// `false` なら探索上限に達して打ち切ったので、各値は下界となる。
+    pub complete: bool,
+}
+
+/// 盤面 `board` の状態空間の統計を幅優先探索により求める。
+///
+/// 局面数は盤面の難しさ (厳密探索のコスト) の目安となる。
+/// 探索上限に達した場合は打ち切り、その時点での値を返す (`StateSpaceStats::complete` が `false` となる)。
+pub fn state_space_stats(board: Board, limits: &StateSpaceLimits) -> StateSpaceStats {
+    let pos = Position::new(board);
+
+    let mut stats = StateSpaceStats::default();
+    let mut seen = U64HashSet::default();
+    seen.insert(pos.clone());
+    let mut queue = VecDeque::from([(pos, 0_u32)]);
+
+    while let Some((pos, depth)) = queue.pop_front() {
+        if stats.position_count as usize >= limits.max_positions {
+            // 未展開の局面も到達可能であることはわかっている。
+            stats.position_count += queue.len() as u64 + 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            return stats;
+        }
+
+        stats.position_count += 1;
+        stats.max_depth = depth;
+        if !pos.has_action() {
+            stats.terminal_count += 1;
+            continue;
+        }
+
+        for action in pos.actions() {
+            let pos_child = pos.do_action(&action);
+            if !seen.contains(&pos_child) {
+                seen.insert(pos_child.clone());
+                queue.push_back((pos_child, depth + 1));
+            }
+        }
+    }
+
+    stats.complete = true;
+    stats
+}
+
+#[derive(Debug)]
+struct MovesBetween {
+    dst: Position,
+    /// 到達不能と判明した局面と、そのときの残り手数。
+    /// これ以下の残り手数ではやはり到達不能。
+    failed: U64HashMap<Position, usize>,
+    history: ActionHistory,
+}
+
+impl MovesBetween {
+    fn dfs(&mut self, pos: &Position, depth: usize) -> bool {
+        if pos == &self.dst {
+            return true;
+        }
+
+        // 各駒種について、消すべき数を求める。
+        // 増やすことはできず、また 1 個だけ消すこともできない。
+        let mut diffs = PieceArray::<u8>::default();
+        for piece in Piece::all() {
+            let (have, want) = (pos.piece_count(piece), self.dst.piece_count(piece));
+            if have < want || have - want == 1 {
+                return false;
+            }
+            diffs[piece] = have - want;
+        }
+
+        // 1 手で消せるのは 1 駒種のみ。
+        let need = Piece::all().filter(|&piece| diffs[piece] > 0).count();
+        if need > depth {
+            return false;
+        }
+
+        if self
+            .failed
+            .get(pos)
+            .is_some_and(|&failed_depth| depth <= failed_depth)
+        {
+            return false;
+        }
+
+        for action in pos.actions() {
+            if u32::from(diffs[action.piece()]) < action.square_count() {
+                continue;
+            }
+
+            let pos_child = pos.do_action(&action);
+            self.history.push(action.least_square());
+            if self.dfs(&pos_child, depth - 1) {
+                return true;
+            }
+            self.history.remove_last();
+        }
+
+        self.failed
+            .entry(pos.clone())
+            .and_modify(|failed_depth| *failed_depth = (*failed_depth).max(depth))
+            .or_insert(depth);
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn parse_board(s: impl AsRef<str>) -> Board {
+        s.as_ref().parse().unwrap()
+    }
+
+    #[test]
+    fn test_moves_between() {
+        let src = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            2.......
+            12.5....
+            21155...
+        "});
+        let mid = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            2.......
+            1.......
+            22......
+        "});
+        let dst = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            2.......
+            1.......
+        "});
+        let dst_bad = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            ........
+            ........
+            5.......
+        "});
+
+        assert_eq!(moves_between(&src, &src, 0), Some(ActionHistory::new()));
+
+        let history = moves_between(&src, &mid, 2).unwrap();
+        assert_eq!(history.len(), 2);
+        let pos = history.iter().fold(Position::new(src.clone()), |pos, &sq| {
+            let action = samegame_core::Action::from_board_square(pos.board(), sq).unwrap();
+            pos.do_action(&action)
+        });
+        assert_eq!(pos.board(), &mid);
+
+        assert_eq!(moves_between(&src, &mid, 1), None);
+        assert_eq!(moves_between(&mid, &src, 10), None);
+
+        assert_eq!(moves_between(&src, &dst, 10).map(|h| h.len()), Some(3));
+        assert_eq!(moves_between(&mid, &dst, 1).map(|h| h.len()), Some(1));
+
+        // 5 を 1 個だけ残すことはできない。
+        assert_eq!(moves_between(&src, &dst_bad, 10), None);
+    }
+
+    #[test]
+    fn test_reachable_positions() {
+        let src = parse_board(indoc! {"
+            ........
+            ........
+            ........
+            2.......
+            12.5....
+            21155...
+        "});
+
+        let positions: Vec<Position> = reachable_positions(src.clone(), usize::MAX).collect();
+        assert_eq!(positions[0].board(), &src);
+
+        // 全て相異なり、全て src から到達可能。
+        let set: U64HashSet<Position> = positions.iter().cloned().collect();
+        assert_eq!(set.len(), positions.len());
+        for pos in &positions {
+            assert!(moves_between(&src, pos.board(), ActionHistory::CAPACITY).is_some());
+        }
+        // 子局面も全て含まれている。
+        for pos in &positions {
+            for action in pos.actions() {
+                assert!(set.contains(&pos.do_action(&action)));
+            }
+        }
+
+        assert_eq!(reachable_positions(src.clone(), 3).count(), 3);
+
+        let stats = state_space_stats(src.clone(), &StateSpaceLimits::default());
+        assert!(stats.complete);
+        assert_eq!(stats.position_count as usize, positions.len());
+        assert_eq!(
+            stats.terminal_count as usize,
+            positions.iter().filter(|pos| !pos.has_action()).count()
+        );
+        assert_eq!(
+            stats.max_depth as usize,
+            positions
+                .iter()
+                .map(
+                    |pos| moves_between(&src, pos.board(), ActionHistory::CAPACITY)
+                        .unwrap()
+                        .len()
+                )
+                .max()
+                .unwrap()
+        );
+
+        let stats_partial = state_space_stats(src, &StateSpaceLimits { max_positions: 2 });
+        assert!(!stats_partial.complete);
+        assert!(stats_partial.position_count <= stats.position_count);
+    }
+}
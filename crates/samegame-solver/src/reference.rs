@@ -0,0 +1,160 @@
+//! テスト用の、遅いが明らかに正しい盤面操作の参照実装。
+//!
+//! bitboard による実装 (`Board`, `MaskBoard`, `Position`) と照合する差分テストに用いる。
+
+use samegame_core::{Board, Col, FastRng, Piece, Row, Square};
+
+/// 参照実装の盤面。`cols[c][r]` が列 `c` (0-based, 左から)、行 `r` (0-based, 下から) の駒。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct RefBoard {
+    cols: Vec<Vec<Option<Piece>>>,
+}
+
+impl RefBoard {
+    pub(crate) fn from_board(board: &Board) -> Self {
+        let cols = Col::all()
+            .map(|col| {
+                Row::all()
+                    .map(|row| board.get(Square::new(col, row)))
+                    .collect()
+            })
+            .collect();
+
+        Self { cols }
+    }
+
+    pub(crate) fn to_board(&self) -> Board {
+        let grid = std::array::from_fn(|c| {
+            std::array::from_fn(|r| self.cols[c][r].map_or(0, Piece::to_inner))
+        });
+
+        Board::from_grid(&grid).unwrap()
+    }
+
+    fn get(&self, c: usize, r: usize) -> Option<Piece> {
+        self.cols.get(c)?.get(r).copied().flatten()
+    }
+
+    /// マス `(c, r)` の駒と上下左右に繋がっている同種の駒のマスたちを昇順で返す。空白なら空。
+    pub(crate) fn group(&self, c: usize, r: usize) -> Vec<(usize, usize)> {
+        let Some(piece) = self.get(c, r) else {
+            return Vec::new();
+        };
+
+        let mut res = vec![(c, r)];
+        let mut i = 0;
+        while i < res.len() {
+            let (c, r) = res[i];
+            i += 1;
+
+            let mut neighbors = vec![(c + 1, r), (c, r + 1)];
+            if c > 0 {
+                neighbors.push((c - 1, r));
+            }
+            if r > 0 {
+                neighbors.push((c, r - 1));
+            }
+            for (nc, nr) in neighbors {
+                if self.get(nc, nr) == Some(piece) && !res.contains(&(nc, nr)) {
+                    res.push((nc, nr));
+                }
+            }
+        }
+
+        res.sort_unstable();
+        res
+    }
+
+    /// 合法手の数 (2 個以上の塊の数) を返す。
+    pub(crate) fn action_count(&self) -> usize {
+        let mut seen = Vec::<(usize, usize)>::new();
+        let mut count = 0;
+
+        for c in 0..self.cols.len() {
+            for r in 0..self.cols[c].len() {
+                if seen.contains(&(c, r)) {
+                    continue;
+                }
+                let group = self.group(c, r);
+                if group.len() >= 2 {
+                    count += 1;
+                }
+                seen.extend(group);
+            }
+        }
+
+        count
+    }
+
+    /// マスたちの駒を消し、重力で下詰めし、空になった列を左詰めした盤面を返す。
+    pub(crate) fn erase(&self, sqs: &[(usize, usize)]) -> Self {
+        let mut cols: Vec<Vec<Option<Piece>>> = self
+            .cols
+            .iter()
+            .enumerate()
+            .map(|(c, col)| {
+                col.iter()
+                    .enumerate()
+                    .filter(|&(r, piece)| piece.is_some() && !sqs.contains(&(c, r)))
+                    .map(|(_, &piece)| piece)
+                    .collect()
+            })
+            .filter(|col: &Vec<_>| !col.is_empty())
+            .collect();
+
+        for col in &mut cols {
+            col.resize(Row::NUM, None);
+        }
+        cols.resize(Col::NUM, vec![None; Row::NUM]);
+
+        Self { cols }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use samegame_core::Action;
+
+    use super::*;
+    use crate::position::Position;
+
+    #[test]
+    fn test_differential() {
+        let mut rng = FastRng::new(1216);
+
+        for i in 0..300 {
+            // 駒種が少ないほど大きな塊ができやすい。
+            let board = Board::random_with(&mut rng, 1 + (i % Piece::NUM) as u8);
+            let ref_board = RefBoard::from_board(&board);
+            assert_eq!(ref_board.to_board(), board);
+
+            let pos = Position::new(board.clone());
+            assert_eq!(pos.action_count() as usize, ref_board.action_count());
+            assert_eq!(pos.has_action(), ref_board.action_count() > 0);
+
+            for sq in Square::all() {
+                let (c, r) = (sq.col().to_index(), sq.row().to_index());
+                let group = ref_board.group(c, r);
+
+                match Action::from_board_square(&board, sq) {
+                    Ok(action) => {
+                        assert!(group.len() >= 2);
+                        let sqs: Vec<(usize, usize)> = action
+                            .mask()
+                            .squares()
+                            .map(|sq| (sq.col().to_index(), sq.row().to_index()))
+                            .collect();
+                        assert_eq!(sqs, group);
+
+                        let expect = ref_board.erase(&group).to_board();
+                        assert_eq!(board.erase(action.mask()), expect);
+                        let pos_child = pos.do_action(&action);
+                        assert_eq!(pos_child.board(), &expect);
+                        assert_eq!(pos_child, Position::new(expect));
+                    }
+                    Err(_) => assert!(group.len() <= 1),
+                }
+            }
+        }
+    }
+}
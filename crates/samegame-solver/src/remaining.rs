@@ -0,0 +1,199 @@
+//! 残り駒数の最小化関連。
+//!
+//! パーフェクトボーナスに届かない面で、なるべく「きれいに」終わる手順を求める。
+
+use samegame_core::{ActionHistory, Board, MoveList, Piece, RuleSet};
+
+use crate::bound::unerasable_squares;
+use crate::hash::U64HashMap;
+use crate::observer::CancelToken;
+use crate::position::Position;
+use crate::solver::{Solver, HEARTBEAT_CHECK_NODES};
+
+impl Solver {
+    /// 初期盤面 `board` から合法手がなくなるまで着手したとき、残る駒数の最小値とそれを達成する手順を返す。
+    /// パーフェクト可能なら残り駒数は 0。
+    ///
+    /// スコアは一切考慮しない。ゲームルール、訪問ノード数の上限、中断要求の設定に従い、
+    /// それ以外の設定 (枝刈り用スコア閾値、上界関数など) は無視する。
+    /// 訪問ノード数の上限に達するか中断された場合、`None` を返す。
+    ///
+    /// 決して消せない駒 (`unerasable_squares()`) の数を残り駒数の下界として枝刈りする。
+    pub fn min_remaining_pieces(&mut self, board: Board) -> Option<(u32, ActionHistory)> {
+        let pos = Position::new(board);
+
+        let mut search = MinRemainingSearch {
+            rule_set: *self.rule_set(),
+            node_limit: self.node_limit(),
+            cancel_token: self.cancel_token(),
+            nodes: 0,
+            aborted: false,
+            history: ActionHistory::new(),
+            best: u32::MAX,
+            best_solution: ActionHistory::new(),
+            table: U64HashMap::default(),
+        };
+        search.dfs(&pos);
+
+        (!search.aborted).then_some((search.best, search.best_solution))
+    }
+}
+
+/// `Solver::min_remaining_pieces()` の探索状態。
+struct MinRemainingSearch<'solver> {
+    rule_set: RuleSet,
+    node_limit: Option<u64>,
+    cancel_token: Option<&'solver CancelToken>,
+
+    nodes: u64,
+    /// 訪問ノード数の上限に達したか中断されたかどうか。
+    aborted: bool,
+    history: ActionHistory,
+    /// 暫定解の残り駒数。
+    best: u32,
+    best_solution: ActionHistory,
+    /// 局面ごとの残り駒数の下界。
+    table: U64HashMap<Position, u8>,
+}
+
+impl MinRemainingSearch<'_> {
+    /// 局面 `pos` から合法手がなくなるまで着手したときの残り駒数の下界を返す。
+    ///
+    /// 戻り値が暫定解の残り駒数より小さければ、それは正確な値で、暫定解はそれを達成する手順に更新されている。
+    /// 探索を打ち切った後は何もせずに 0 を返す (このとき置換表の内容は正しくないが、探索終了後に捨てる)。
+    fn dfs(&mut self, pos: &Position) -> u32 {
+        if self.aborted {
+            return 0;
+        }
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+            self.aborted = true;
+            return 0;
+        }
+        self.nodes += 1;
+        if (self.nodes & (HEARTBEAT_CHECK_NODES - 1)) == 0
+            && self.cancel_token.is_some_and(CancelToken::is_cancelled)
+        {
+            self.aborted = true;
+            return 0;
+        }
+
+        if !pos.has_action_with_rules(&self.rule_set) {
+            let remaining = pos.board().piece_count_total();
+            if remaining < self.best {
+                self.best = remaining;
+                self.best_solution = self.history.clone();
+            }
+            return remaining;
+        }
+
+        let lb = match self.table.get(pos) {
+            Some(&lb) => u32::from(lb),
+            None => self.remaining_lower_bound(pos),
+        };
+        if lb >= self.best {
+            return lb;
+        }
+
+        let mut actions = MoveList::new();
+        pos.collect_actions_with_rules(&self.rule_set, &mut actions);
+        actions.sort_largest_first();
+
+        let mut res = u32::MAX;
+        for action in &actions {
+            self.history.push(action.least_square());
+            let res_child = self.dfs(&pos.do_action_with_rules(action, &self.rule_set));
+            self.history.remove_last();
+
+            res = res.min(res_child);
+            // 下界に達したらこれ以上良くならない。
+            if res <= lb {
+                break;
+            }
+        }
+        let res = res.max(lb);
+
+        self.table.insert(pos.clone(), res as u8);
+        res
+    }
+
+    /// 局面 `pos` から合法手がなくなるまで着手したときの残り駒数の下界を、探索せずに求める。
+    fn remaining_lower_bound(&self, pos: &Position) -> u32 {
+        let dead = unerasable_squares(pos.board());
+
+        // 最小の塊の大きさに満たない駒種は消せない。
+        let dead_small: u32 = Piece::all()
+            .filter(|&piece| pos.piece_count(piece) < self.rule_set.min_group_size)
+            .map(|piece| pos.board().piece_mask(piece).subtract(&dead).square_count())
+            .sum();
+
+        dead.square_count() + dead_small
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use samegame_core::{Action, Score};
+
+    use super::*;
+    use crate::verify::replay;
+
+    #[test]
+    fn test_min_remaining_pieces() {
+        /// 全手順を列挙し、残り駒数の最小値を返す。
+        fn brute_force(pos: &Position) -> u32 {
+            pos.actions()
+                .map(|action| brute_force(&pos.do_action(&action)))
+                .min()
+                .unwrap_or_else(|| pos.board().piece_count_total())
+        }
+
+        let boards: [Board; 3] = [
+            // パーフェクト可能。
+            indoc! {"
+                ........
+                ........
+                ........
+                ........
+                12......
+                1122....
+            "},
+            indoc! {"
+                ........
+                ........
+                3.......
+                21......
+                123.....
+                1123....
+            "},
+            indoc! {"
+                ........
+                ........
+                22......
+                113.....
+                2231....
+                11323...
+            "},
+        ]
+        .map(|s| s.parse().unwrap());
+
+        for board in boards.clone() {
+            let expect = brute_force(&Position::new(board.clone()));
+            let (remaining, solution) = Solver::new(Score::ZERO)
+                .min_remaining_pieces(board.clone())
+                .unwrap();
+            assert_eq!(remaining, expect);
+
+            // 手順を再生すると、その残り駒数の終了局面に至る。
+            assert!(replay(&board, &solution).is_ok());
+            let board_final = solution.iter().fold(board, |board, &sq| {
+                board.erase(Action::from_board_square(&board, sq).unwrap().mask())
+            });
+            assert_eq!(board_final.piece_count_total(), remaining);
+        }
+
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_node_limit(Some(1));
+        assert_eq!(solver.min_remaining_pieces(boards[2].clone()), None);
+    }
+}
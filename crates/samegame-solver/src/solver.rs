@@ -0,0 +1,2537 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Context as _};
+use samegame_core::{
+    audited_add, ActionHistory, Board, MoveList, Piece, RuleSet, Score, Square, SquareArray,
+};
+
+use crate::bound::{BoundFn, BoundKind, DefaultBound};
+use crate::checkpoint::{CheckpointConfig, SolveCheckpoint};
+use crate::cmp::chmax;
+use crate::constraints::SolveConstraints;
+use crate::dp::{DpSpillConfig, DpTable};
+use crate::frozen::FrozenSolver;
+use crate::hash::U64HashMap;
+use crate::logging::{debug, info, warn};
+use crate::observer::{
+    rss_bytes, CancelToken, Heartbeat, IncumbentLogPolicy, NullObserver, SearchObserver,
+};
+use crate::opening::OpeningBook;
+use crate::position::Position;
+use crate::terminal::{GameTerminalEval, TerminalEval};
+
+/// ハートビート報告時刻に達したかを確認するノード数間隔 (時刻取得のコストを抑えるため)。2 の冪でなければならない。
+pub(crate) const HEARTBEAT_CHECK_NODES: u64 = 1 << 14;
+
+/// 探索統計。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchStats {
+    /// 訪問したノード数。
+    pub nodes: u64,
+    /// 探索に要した時間。
+    pub elapsed: Duration,
+    /// DP テーブルからディスクへ退避したエントリ数。
+    pub dp_spill_writes: u64,
+    /// 枝刈りしたノード数の理由ごとの内訳。
+    pub prunes: PruneCounts,
+    /// ディスクへ退避した DP エントリを読み戻した回数。
+    pub dp_spill_reads: u64,
+    /// DP テーブルの退避に伴うディスク I/O に要した時間。
+    pub dp_spill_io_elapsed: Duration,
+    /// 訪問ノード数の上限 (`Solver::set_node_limit()`) に達して探索を打ち切ったかどうか。
+    pub node_limit_reached: bool,
+    /// 中断要求 (`Solver::set_cancel_token()`, `SearchObserver::poll()`) により探索を中断したかどうか。
+    pub cancelled: bool,
+    /// 非終了局面の訪問時に DP テーブルにエントリがあった回数。
+    pub dp_hits: u64,
+    /// 非終了局面の訪問時に DP テーブルにエントリがなかった (上界を見積もって追加した) 回数。
+    pub dp_misses: u64,
+    /// 訪問したノードの最大深さ (初期盤面からの手数)。
+    pub max_depth: usize,
+    /// 序盤定跡 (`Solver::set_opening_book()`) を引いて探索を省いた回数。
+    pub book_hits: u64,
+    /// 探索終了時点のソルバーのメモリ使用量の概算値 (バイト数, `Solver::approx_memory_usage()` を参照)。
+    ///
+    /// DP テーブルは探索中に単調に増える (退避時を除く) ので、おおむね探索中の最大値となる。
+    pub approx_memory_usage: usize,
+}
+
+/// 1 面の探索戦略。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SearchStrategy {
+    /// 枝刈り用スコア閾値を固定して 1 回だけ深さ優先探索する (デフォルト)。
+    #[default]
+    DepthFirst,
+    /// 枝刈り用スコア閾値を高い値から `step` ずつ下げつつ、解が見つかるまで深さ優先探索を繰り返す。
+    ///
+    /// 最初の閾値は初期盤面の追加獲得スコア上界 - `step` とし、設定された閾値までは下げない。
+    /// 閾値が高いうちは枝刈りが強く効くので、最適値の見当がつかない場合でも探索範囲を抑えられる。
+    /// DP テーブルは反復の間で引き継ぐので (記録された上界は閾値によらず正しい)、
+    /// 失敗した反復で締まった上界が次の反復の枝刈りに効く。
+    ///
+    /// チェックポイントからの再開時は `DepthFirst` として扱う。
+    IterativeWidening {
+        /// 1 回の反復で閾値を下げる幅。0 は 1 とみなす。
+        step: Score,
+    },
+}
+
+/// 探索時に各ノードで子ノード (着手) を展開する順序。
+///
+/// 枝刈りは枝刈り用スコア閾値と暫定解のスコアの大きい方を基準に行うので、
+/// 良い解を早く見つけるほど以降の枝刈りが効く。どの順序でも返す解のスコアは変わらない。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MoveOrdering {
+    /// 合法手の列挙順 (`Position::actions()` の順) のまま展開する (デフォルト)。
+    #[default]
+    Natural,
+    /// 消す駒数が多い手から展開する。
+    LargestFirst,
+    /// 盤面上の残り駒数が少ない駒種を消す手ほど後に展開する。
+    ///
+    /// 少ない駒種はまとめて消したいので、先に多い駒種を消して合流させる手順を優先する。
+    RareColorsLast,
+    /// 探索中に暫定解の更新をもたらした手 (最小マス) ほど先に展開する (history heuristic)。
+    ///
+    /// 展開順が探索の経過に依存するので、チェックポイントを保存しない
+    /// (探索フロンティアから探索済みの部分木を復元できないため)。
+    History,
+}
+
+/// 探索の目的。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Objective {
+    /// 最終スコアを最大化する (デフォルト)。同スコアの解のうちどれを返すかは未規定。
+    #[default]
+    MaxScore,
+    /// 目標 `target` に達する手順のうち、手数が最小のものを探す。
+    ///
+    /// TAS で手数がフレーム数に直結する場合向け。手数の上限を 1 ずつ増やしながら反復深化の深さ優先探索を行う。
+    /// 枝刈り用スコア閾値、上界関数、探索戦略、展開順序は用いず、チェックポイントも保存しない。
+    /// 最短手数の解が複数あればどれを返すかは未規定で、返すスコアはその解の最終スコアとなる。
+    /// 目標に達する手順がなければ解はない。
+    MinMoves { target: MoveTarget },
+    /// 最終スコアを最大化し、同スコアの解のうち手数が最小のものを返す (`Solver::set_prefer_shorter()` を参照)。
+    MaxScoreThenMinMoves,
+}
+
+/// 最短手数探索 (`Objective::MinMoves`) の目標。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MoveTarget {
+    /// 任意の終了局面 (合法手がない局面)。
+    #[default]
+    Terminal,
+    /// 盤面が空の終了局面 (パーフェクト)。
+    Perfect,
+}
+
+/// ソルバーの設定。`Solver::with_config()` でまとめて設定する。
+///
+/// 各項目の意味は `Solver` の対応するメソッドを参照。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SolverConfig {
+    /// 枝刈り用スコア閾値 (`Solver::set_prune_score_max()`)。
+    pub prune_score_max: Score,
+    /// 探索戦略 (`Solver::set_search_strategy()`)。
+    pub strategy: SearchStrategy,
+    /// 探索の目的 (`Solver::set_objective()`)。
+    pub objective: Objective,
+    /// 1 面あたりの訪問ノード数の上限 (`Solver::set_node_limit()`)。
+    pub node_limit: Option<u64>,
+    /// DP テーブルのディスク退避設定 (`Solver::set_dp_spill()`)。
+    pub dp_spill: Option<DpSpillConfig>,
+    /// 着手の展開順序 (`Solver::set_move_ordering()`)。
+    pub move_ordering: MoveOrdering,
+    /// 上界関数の種類 (`Solver::set_bound_fn()`)。
+    pub bound: BoundKind,
+}
+
+/// 最適解の列挙結果。`Solver::solve_all_optimal()` の戻り値。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OptimalSolutions {
+    /// 最大スコア。
+    pub score: Score,
+    /// 最大スコアを達成する手順たち (探索順)。
+    pub solutions: Vec<ActionHistory>,
+    /// 列挙を打ち切ったかどうか。
+    ///
+    /// 列挙数の上限を超える解があったか、訪問ノード数の上限に達した場合に真となる。
+    /// 後者の場合、`score` も最大スコアとは限らない。
+    pub truncated: bool,
+}
+
+/// ノードを枝刈りした理由。
+///
+/// 新たな枝刈り手法を追加したら、ここにも理由を追加する。
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PruneReason {
+    /// 上界関数による最終スコアの上界が枝刈り用スコア閾値 (暫定解のスコアの方が大きければそれ) 以下。
+    Bound,
+    /// DP テーブルに記録された最終スコアの上界が枝刈り用スコア閾値 (暫定解のスコアの方が大きければそれ) 以下。
+    /// 以前の訪問時の探索により上界関数より厳しくなった上界か、退避から読み戻した上界による。
+    Table,
+    /// 暫定解と同スコアにしかならず、かつ暫定解より短くなりえない (`Solver::set_prefer_shorter()` を参照)。
+    NotShorter,
+    /// 探索の制約を満たす解がない (`Solver::solve_with_constraints()` を参照)。
+    Constraint,
+}
+
+/// 枝刈りの理由ごとのノード数。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PruneCounts {
+    pub bound: u64,
+    pub table: u64,
+    pub not_shorter: u64,
+    pub constraint: u64,
+}
+
+impl SearchStats {
+    /// 枝刈りしたノード数の総和を返す。
+    pub fn pruned(&self) -> u64 {
+        self.prunes.total()
+    }
+
+    /// 同じ面の別の反復の統計 `other` を合算する。
+    ///
+    /// DP 退避に関する統計は面ごとに累積されているので `other` の値で置き換え、
+    /// メモリ使用量も `other` の値とする。
+    fn accumulate(&mut self, other: &Self) {
+        self.nodes += other.nodes;
+        self.elapsed += other.elapsed;
+        self.prunes.bound += other.prunes.bound;
+        self.prunes.table += other.prunes.table;
+        self.prunes.not_shorter += other.prunes.not_shorter;
+        self.prunes.constraint += other.prunes.constraint;
+        self.dp_spill_writes = other.dp_spill_writes;
+        self.dp_spill_reads = other.dp_spill_reads;
+        self.dp_spill_io_elapsed = other.dp_spill_io_elapsed;
+        self.node_limit_reached |= other.node_limit_reached;
+        self.cancelled |= other.cancelled;
+        self.dp_hits += other.dp_hits;
+        self.dp_misses += other.dp_misses;
+        chmax!(self.max_depth, other.max_depth);
+        self.book_hits += other.book_hits;
+        self.approx_memory_usage = other.approx_memory_usage;
+    }
+}
+
+impl PruneCounts {
+    /// 理由 `reason` による枝刈り数を返す。
+    pub fn get(&self, reason: PruneReason) -> u64 {
+        match reason {
+            PruneReason::Bound => self.bound,
+            PruneReason::Table => self.table,
+            PruneReason::NotShorter => self.not_shorter,
+            PruneReason::Constraint => self.constraint,
+        }
+    }
+
+    /// 枝刈り数の総和を返す。
+    pub fn total(&self) -> u64 {
+        self.bound + self.table + self.not_shorter + self.constraint
+    }
+
+    fn add(&mut self, reason: PruneReason) {
+        match reason {
+            PruneReason::Bound => self.bound += 1,
+            PruneReason::Table => self.table += 1,
+            PruneReason::NotShorter => self.not_shorter += 1,
+            PruneReason::Constraint => self.constraint += 1,
+        }
+    }
+}
+
+/// 最大スコア探索用ソルバー。複数の面を連続で解ける。
+pub struct Solver {
+    /// 探索時の枝刈り用スコア閾値。
+    /// 最終スコアがこの値 (暫定解のスコアの方が大きければそれ) を超えないと判明した時点でそのノードは枝刈りする。
+    /// ただし終了局面については一応解を記録する。
+    prune_score_max: Score,
+
+    /// 各局面から追加で獲得しうるスコアの上界を記録する DP テーブル。
+    /// メモリ効率は若干悪いが、スコア閾値を適切に設定すればメモリ不足になることはないはず。
+    dp: DpTable,
+
+    /// DP テーブルのディスク退避設定。
+    dp_spill: Option<DpSpillConfig>,
+
+    /// 各局面から追加で獲得しうるスコアの上界を与える関数。
+    bound_fn: Arc<dyn BoundFn + Send + Sync>,
+
+    /// 終了局面において追加で獲得するスコアを与える関数。
+    terminal_eval: Arc<dyn TerminalEval + Send + Sync>,
+
+    /// 終了局面の評価関数がゲーム通り (`GameTerminalEval` または `set_rule_set()` で設定したもの) か。
+    /// 序盤定跡を引けるかの判定に使う。
+    game_terminal_eval: bool,
+
+    /// ゲームルール。
+    rule_set: RuleSet,
+
+    /// ハートビート間隔。`None` ならハートビートを報告しない。
+    heartbeat_interval: Option<Duration>,
+
+    /// 探索の目的。
+    objective: Objective,
+
+    /// 暫定解の更新のログ出力方針。
+    incumbent_log: IncumbentLogPolicy,
+
+    /// 1 面あたりの訪問ノード数の上限。`None` なら無制限。
+    node_limit: Option<u64>,
+
+    /// チェックポイントの保存設定。`None` なら保存しない。
+    checkpoint: Option<CheckpointConfig>,
+
+    /// 探索戦略。
+    strategy: SearchStrategy,
+
+    /// 着手の展開順序。
+    move_ordering: MoveOrdering,
+
+    /// 探索の中断用トークン。
+    cancel_token: Option<CancelToken>,
+
+    /// DP テーブルにエントリがない局面の上界として、上界関数の値とともに参照する凍結済みテーブル。
+    /// `FrozenSolver` の問い合わせ用。
+    dp_seed: Option<Arc<U64HashMap<Position, Score>>>,
+
+    /// 同スコアの解の間の優劣を決めるコスト関数。`None` なら用いない。
+    tie_break_cost: Option<Arc<TieBreakCost>>,
+
+    /// 浅いノードで引く序盤定跡。`None` なら用いない。
+    opening_book: Option<Arc<OpeningBook>>,
+}
+
+/// 同スコアの解の間の優劣を決めるコスト関数 (`Solver::set_tie_break_cost()` を参照)。
+pub type TieBreakCost = dyn Fn(&ActionHistory) -> u32 + Send + Sync;
+
+impl std::fmt::Debug for Solver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Solver")
+            .field("prune_score_max", &self.prune_score_max)
+            .field("dp", &self.dp)
+            .field("dp_spill", &self.dp_spill)
+            .field("rule_set", &self.rule_set)
+            .field("heartbeat_interval", &self.heartbeat_interval)
+            .field("objective", &self.objective)
+            .field("incumbent_log", &self.incumbent_log)
+            .field("node_limit", &self.node_limit)
+            .field("checkpoint", &self.checkpoint)
+            .field("strategy", &self.strategy)
+            .field("move_ordering", &self.move_ordering)
+            .field("cancel_token", &self.cancel_token)
+            .field("tie_break_cost", &self.tie_break_cost.is_some())
+            .field(
+                "opening_book",
+                &self.opening_book.as_ref().map(|book| book.len()),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl Solver {
+    /// 枝刈り用スコア閾値を `prune_score_max` としてソルバーを作る。
+    pub fn new(prune_score_max: Score) -> Self {
+        Self {
+            prune_score_max,
+            dp: DpTable::default(),
+            dp_spill: None,
+            bound_fn: Arc::new(DefaultBound),
+            terminal_eval: Arc::new(GameTerminalEval),
+            game_terminal_eval: true,
+            rule_set: RuleSet::GAME,
+            heartbeat_interval: None,
+            objective: Objective::default(),
+            incumbent_log: IncumbentLogPolicy::default(),
+            node_limit: None,
+            checkpoint: None,
+            strategy: SearchStrategy::default(),
+            move_ordering: MoveOrdering::default(),
+            cancel_token: None,
+            dp_seed: None,
+            tie_break_cost: None,
+            opening_book: None,
+        }
+    }
+
+    /// 設定 `config` に従ってソルバーを作る。
+    pub fn with_config(config: &SolverConfig) -> Self {
+        let mut this = Self::new(config.prune_score_max);
+        this.set_search_strategy(config.strategy);
+        this.set_objective(config.objective);
+        this.set_node_limit(config.node_limit);
+        this.set_dp_spill(config.dp_spill.clone());
+        this.set_move_ordering(config.move_ordering);
+        this.set_bound_fn(config.bound);
+        this
+    }
+
+    /// `frozen` の問い合わせ用のソルバーを作る。
+    ///
+    /// 上界関数などの設定を引き継ぎ、凍結済みテーブルを上界として参照する。
+    pub(crate) fn from_frozen(frozen: &FrozenSolver) -> Self {
+        Self {
+            bound_fn: Arc::clone(&frozen.bound_fn),
+            terminal_eval: Arc::clone(&frozen.terminal_eval),
+            rule_set: frozen.rule_set,
+            objective: if frozen.prefer_shorter {
+                Objective::MaxScoreThenMinMoves
+            } else {
+                Objective::MaxScore
+            },
+            dp_seed: Some(Arc::clone(&frozen.table)),
+            ..Self::new(Score::ZERO)
+        }
+    }
+
+    /// 現時点での枝刈り用スコア閾値を返す。
+    pub fn prune_score_max(&self) -> Score {
+        self.prune_score_max
+    }
+
+    /// 枝刈り用スコア閾値を設定する。
+    pub fn set_prune_score_max(&mut self, prune_score_max: Score) {
+        self.prune_score_max = prune_score_max;
+    }
+
+    /// 枝刈り用スコア閾値を chmax する。
+    pub fn chmax_prune_score_max(&mut self, score: Score) {
+        chmax!(self.prune_score_max, score);
+    }
+
+    /// 探索時に用いる追加獲得スコア上界関数を設定する。
+    ///
+    /// `bound_fn` は `BoundFn` の契約 (真の追加獲得スコア以上の値を返すこと) を満たさねばならない。
+    pub fn set_bound_fn(&mut self, bound_fn: impl BoundFn + Send + Sync + 'static) {
+        self.bound_fn = Arc::new(bound_fn);
+    }
+
+    /// 終了局面の評価関数を設定する。
+    ///
+    /// 上界関数は `terminal_eval` と整合していなければならない (`TerminalEval` のドキュメントを参照)。
+    pub fn set_terminal_eval(&mut self, terminal_eval: impl TerminalEval + Send + Sync + 'static) {
+        self.terminal_eval = Arc::new(terminal_eval);
+        self.game_terminal_eval = false;
+    }
+
+    /// ゲームルールを設定する (デフォルトは実際のゲームのルール)。
+    ///
+    /// 上界関数および終了局面の評価関数も `rules` に合わせたものに置き換える。
+    /// 独自の上界関数などを使う場合、この関数の後に設定すること。
+    pub fn set_rule_set(&mut self, rules: RuleSet) {
+        self.rule_set = rules;
+        self.bound_fn = Arc::new(rules);
+        self.terminal_eval = Arc::new(rules);
+        self.game_terminal_eval = true;
+    }
+
+    /// DP テーブルのディスク退避設定を行う。`None` なら退避しない (デフォルト)。
+    ///
+    /// メモリ不足で探索を続けられない難しい盤面向け。
+    pub fn set_dp_spill(&mut self, config: Option<DpSpillConfig>) {
+        self.dp = DpTable::new(config.as_ref());
+        self.dp_spill = config;
+    }
+
+    /// ハートビート間隔を設定する。`None` ならハートビートを報告しない (デフォルト)。
+    ///
+    /// ハートビートは `solve_with_observer()` に渡したオブザーバーに報告される。
+    pub fn set_heartbeat_interval(&mut self, interval: Option<Duration>) {
+        self.heartbeat_interval = interval;
+    }
+
+    /// 同スコアの解のうち手数が短いものを優先するかどうかを設定する (デフォルトは `false`)。
+    ///
+    /// `true` の場合、目的関数は (スコア降順, 手数昇順) の辞書式順序となり、返す解は最適解の中で最短手数のものとなる。
+    /// このとき、最終スコアが枝刈り用スコア閾値に等しい解も探索対象となる (閾値と同スコアでより短い解を探せるように)。
+    ///
+    /// `false` の場合、同スコアの解のうちどれを返すかは未規定。
+    ///
+    /// `set_objective()` で `Objective::MaxScoreThenMinMoves` (`true`) または `Objective::MaxScore` (`false`) を設定するのと同じ。
+    pub fn set_prefer_shorter(&mut self, prefer_shorter: bool) {
+        self.objective = if prefer_shorter {
+            Objective::MaxScoreThenMinMoves
+        } else {
+            Objective::MaxScore
+        };
+    }
+
+    /// 探索の目的を設定する (デフォルトは `Objective::MaxScore`)。
+    pub fn set_objective(&mut self, objective: Objective) {
+        self.objective = objective;
+    }
+
+    /// 同スコアの解のうち、コスト `cost` が小さいものを優先するよう設定する。
+    ///
+    /// コントローラ入力のコスト (`ActionHistory::cursor_travel()` など) を渡せば、TAS 向けにフレーム数の少ない解を直接得られる。
+    /// 手数優先 (`Objective::MaxScoreThenMinMoves`) と併用した場合、目的関数は (スコア降順, 手数昇順, コスト昇順) の辞書式順序となる。
+    /// 手数優先と同様、最終スコアが枝刈り用スコア閾値に等しい解も探索対象となる。
+    ///
+    /// コストは手順全体に対して評価するので部分手順からは枝刈りできず、同スコアの解を全て辿ることになる (その分探索は遅くなる)。
+    /// 最適解の列挙および最短手数探索 (`Objective::MinMoves`) では用いない。
+    pub fn set_tie_break_cost(
+        &mut self,
+        cost: impl Fn(&ActionHistory) -> u32 + Send + Sync + 'static,
+    ) {
+        self.tie_break_cost = Some(Arc::new(cost));
+    }
+
+    /// 同スコアの解の間のコストの設定を解除する。
+    pub fn clear_tie_break_cost(&mut self) {
+        self.tie_break_cost = None;
+    }
+
+    /// 序盤定跡を設定する。`None` なら用いない (デフォルト)。
+    ///
+    /// 初期盤面から `OpeningBook::PROBE_DEPTH_MAX` 手以内の局面が定跡にあれば、その部分木は探索せず定跡の値と手順を用いる。
+    /// 定跡の値はゲーム通りのルールと終了局面評価の下での最適値なので、
+    /// ゲームルール (`set_rule_set()`) や終了局面の評価関数 (`set_terminal_eval()`) を変更したソルバーでは定跡を引かない。
+    /// 手数優先、同スコアの解の間のコスト、制約付き探索、最適解の列挙、チェックポイントからの再開の際には定跡を引かない。
+    pub fn set_opening_book(&mut self, book: Option<Arc<OpeningBook>>) {
+        self.opening_book = book;
+    }
+
+    /// 暫定解の更新のログ出力方針を設定する。次回以降の探索に適用される。
+    pub fn set_incumbent_log_policy(&mut self, policy: IncumbentLogPolicy) {
+        self.incumbent_log = policy;
+    }
+
+    /// 1 面あたりの訪問ノード数の上限を返す。
+    pub fn node_limit(&self) -> Option<u64> {
+        self.node_limit
+    }
+
+    /// 1 面あたりの訪問ノード数の上限を設定する。`None` なら無制限 (デフォルト)。
+    ///
+    /// 上限に達した時点で探索を打ち切り、それまでに見つかった最良の解を返す (最適解とは限らない)。
+    /// 打ち切ったかどうかは `SearchStats::node_limit_reached` でわかる。
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.node_limit = node_limit;
+    }
+
+    /// 探索戦略を設定する (デフォルトは `SearchStrategy::DepthFirst`)。
+    pub fn set_search_strategy(&mut self, strategy: SearchStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// 着手の展開順序を設定する (デフォルトは `MoveOrdering::Natural`)。
+    ///
+    /// チェックポイントからの再開時は、保存したときと同じ順序でなければならない。
+    /// `MoveOrdering::History` の場合はチェックポイントを保存せず、再開もできない。
+    pub fn set_move_ordering(&mut self, ordering: MoveOrdering) {
+        self.move_ordering = ordering;
+    }
+
+    /// 探索の中断用トークンを設定する。`None` なら中断しない (デフォルト)。
+    ///
+    /// 探索中に `token` で中断が要求されると探索を中断し、それまでに見つかった最良の解を返す (最適解とは限らない)。
+    /// 中断したかどうかは `SearchStats::cancelled` でわかる。中断要求は自動では取り消されない。
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel_token = token;
+    }
+
+    /// チェックポイントの保存設定を変更する。`None` なら保存しない。
+    ///
+    /// 探索中、保存間隔ごとに探索フロンティアと暫定解を保存する。
+    /// 保存に失敗しても探索は続行する (警告をログ出力する)。
+    /// 探索の完了時にファイルは削除しないので、不要なら呼び出し側で削除すること。
+    pub fn set_checkpoint(&mut self, config: Option<CheckpointConfig>) {
+        self.checkpoint = config;
+    }
+
+    /// 探索時に用いる追加獲得スコア上界関数を返す。
+    pub(crate) fn bound_fn(&self) -> &dyn BoundFn {
+        &*self.bound_fn
+    }
+
+    /// メモリ使用量の概算値 (バイト数) を返す。
+    ///
+    /// ソルバー本体と DP テーブル (探索中でなければ空) の分を数える。
+    /// DP テーブルの退避ファイルや、`FrozenSolver` と共有する凍結済みテーブルの分は含まない。
+    pub fn approx_memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>() + self.dp.approx_memory_usage()
+    }
+
+    /// 終了局面の評価関数を返す。
+    pub(crate) fn terminal_eval(&self) -> &dyn TerminalEval {
+        &*self.terminal_eval
+    }
+
+    /// 中断用トークンを返す。
+    pub(crate) fn cancel_token(&self) -> Option<&CancelToken> {
+        self.cancel_token.as_ref()
+    }
+
+    /// 序盤定跡の値がこのソルバーの設定の下でも正しいかどうかを返す。
+    fn opening_book_applicable(&self) -> bool {
+        self.rule_set == RuleSet::GAME && self.game_terminal_eval
+    }
+
+    /// ゲームルールを返す。
+    pub(crate) fn rule_set(&self) -> &RuleSet {
+        &self.rule_set
+    }
+
+    /// 与えられた盤面に対する最大スコアを探索する。
+    pub fn solve(&mut self, board: Board) -> Option<(Score, ActionHistory)> {
+        self.solve_with_stats(board).0
+    }
+
+    /// 対局途中の局面 `position` からの最大スコアを探索する。
+    ///
+    /// `position` は初期盤面から手順 `prefix` を指した結果の局面で、それまでの獲得スコアが `score_so_far` であるとする。
+    /// 枝刈り用スコア閾値は (`score_so_far` を含む) 最終スコアに対して適用され、
+    /// 返すスコアおよび手順も `score_so_far` および `prefix` を含んだものとなる。
+    /// 対局中のヒント表示や、手順の途中からの修正に使う。
+    ///
+    /// `prefix` と `position` の整合性は検査しない。
+    pub fn solve_from(
+        &mut self,
+        position: &Position,
+        score_so_far: Score,
+        prefix: &ActionHistory,
+    ) -> Option<(Score, ActionHistory)> {
+        let prune_score_max_orig = self.prune_score_max;
+        self.prune_score_max = prune_score_max_orig.saturating_sub(score_so_far);
+        let res = self.solve(position.board().clone());
+        self.prune_score_max = prune_score_max_orig;
+
+        let (gain, solution) = res?;
+        let solution = prefix
+            .concat(&solution)
+            .expect("初期盤面からの手順は着手履歴の容量に収まるはず");
+
+        Some((score_so_far + gain, solution))
+    }
+
+    /// 与えられた盤面に対する最大スコアを探索し、探索統計とともに返す。
+    pub fn solve_with_stats(
+        &mut self,
+        board: Board,
+    ) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        self.solve_with_observer(board, &mut NullObserver)
+    }
+
+    /// オブザーバーに探索状況を報告しつつ、与えられた盤面に対する最大スコアを探索する。
+    pub fn solve_with_observer(
+        &mut self,
+        board: Board,
+        observer: &mut dyn SearchObserver,
+    ) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        self.solve_impl(board, observer, None, None)
+    }
+
+    /// 与えられた盤面に対する最大スコアを探索し、それを達成する全ての手順を列挙する。
+    ///
+    /// 列挙する手順は最大 `max_solutions` 個とし、それを超える場合は打ち切る。
+    /// 手順は着手の順序のみが異なるものも区別する (TAS で手順ごとのフレーム数を比較する用途など)。
+    /// `Solver::solve()` が解を返さない (枝刈り用スコア閾値を超える解がない) 場合は `None` を返す。
+    ///
+    /// 最大スコアを求めてから、それ未満にしかならないノードのみを枝刈りして探索し直す。
+    /// 探索の目的 (`Solver::set_objective()`) の設定は無視し、常に最大スコアの解を列挙する。
+    pub fn solve_all_optimal(
+        &mut self,
+        board: Board,
+        max_solutions: usize,
+    ) -> Option<OptimalSolutions> {
+        let objective_orig = self.objective;
+        let tie_break_cost_orig = self.tie_break_cost.take();
+        self.objective = Objective::MaxScore;
+        let (res, stats) = self.solve_with_stats(board.clone());
+        let Some((score, _)) = res else {
+            self.objective = objective_orig;
+            self.tie_break_cost = tie_break_cost_orig;
+            return None;
+        };
+
+        let prune_score_max_orig = self.prune_score_max;
+        self.prune_score_max = score.saturating_sub(Score::new(1));
+
+        let mut collector = SolutionCollector {
+            score,
+            solutions: Vec::new(),
+            max_solutions,
+            overflowed: false,
+        };
+        let mut observer = NullObserver;
+        let mut sub_solver = SubSolver::new(self, &mut observer, &board, None);
+        sub_solver.collector = Some(&mut collector);
+        let (_, stats_enum) = sub_solver.solve(board);
+        self.dp.clear();
+
+        self.prune_score_max = prune_score_max_orig;
+        self.objective = objective_orig;
+        self.tie_break_cost = tie_break_cost_orig;
+
+        Some(OptimalSolutions {
+            score,
+            solutions: collector.solutions,
+            truncated: collector.overflowed
+                || stats.node_limit_reached
+                || stats.cancelled
+                || stats_enum.node_limit_reached
+                || stats_enum.cancelled,
+        })
+    }
+
+    /// 与えられた盤面に対する最大スコアを探索し、探索後の DP テーブルを凍結した `FrozenSolver` とともに返す。
+    ///
+    /// `FrozenSolver` はこのソルバーの上界関数などの設定を引き継ぐ。
+    /// ディスクへ退避した DP エントリは引き継がない。
+    /// 訪問ノード数の上限に達した場合や中断した場合、DP テーブルの内容は正しくないので空のテーブルとなる。
+    pub fn solve_frozen(&mut self, board: Board) -> (Option<(Score, ActionHistory)>, FrozenSolver) {
+        let (res, stats) = self.search_with_strategy(board, &mut NullObserver, None, None);
+
+        let table = if stats.node_limit_reached || stats.cancelled {
+            self.dp.clear();
+            U64HashMap::default()
+        } else {
+            self.dp.take_mem()
+        };
+        let frozen = FrozenSolver {
+            table: Arc::new(table),
+            bound_fn: Arc::clone(&self.bound_fn),
+            terminal_eval: Arc::clone(&self.terminal_eval),
+            rule_set: self.rule_set,
+            prefer_shorter: self.objective == Objective::MaxScoreThenMinMoves,
+        };
+
+        (res, frozen)
+    }
+
+    /// チェックポイント `checkpoint` から探索を再開し、探索統計とともに返す。
+    ///
+    /// 枝刈り用スコア閾値はチェックポイントのものに変更される。その他の設定 (上界関数、ゲームルールなど) は
+    /// チェックポイントを保存したときと同じでなければならない (さもなくば探索済みの部分木の判定が狂う)。
+    /// 返す解はチェックポイント時点の暫定解を含めた最良のもの。探索統計は再開後の分のみ。
+    ///
+    /// 探索フロンティアが盤面上で合法な手順でなければエラーを返す。
+    pub fn solve_resume(
+        &mut self,
+        checkpoint: &SolveCheckpoint,
+    ) -> anyhow::Result<(Option<(Score, ActionHistory)>, SearchStats)> {
+        ensure!(
+            self.move_ordering != MoveOrdering::History,
+            "展開順序が探索の経過に依存する設定では再開できない"
+        );
+        ensure!(
+            !matches!(self.objective, Objective::MinMoves { .. }),
+            "最短手数探索は再開できない"
+        );
+        self.replay(&checkpoint.board, &checkpoint.frontier, None)
+            .context("探索フロンティアが無効")?;
+
+        self.prune_score_max = checkpoint.prune_score_max;
+
+        Ok(self.solve_impl(
+            checkpoint.board.clone(),
+            &mut NullObserver,
+            Some(checkpoint),
+            None,
+        ))
+    }
+
+    /// 制約 `constraints` の下で、与えられた盤面に対する最大スコアを探索する。
+    ///
+    /// 枝刈り用スコア閾値は (`forced_prefix` による獲得スコアを含む) 最終スコアに対して適用され、
+    /// 返す手順も `forced_prefix` を含んだものとなる。
+    ///
+    /// `forced_prefix` が合法な手順でないか、着手禁止マスに触れる場合はエラーを返す。
+    pub fn solve_with_constraints(
+        &mut self,
+        board: Board,
+        constraints: &SolveConstraints,
+    ) -> anyhow::Result<Option<(Score, ActionHistory)>> {
+        let (pos, score_so_far) = self
+            .replay(&board, &constraints.forced_prefix, Some(constraints))
+            .context("強制する手順が無効")?;
+
+        let prune_score_max_orig = self.prune_score_max;
+        self.prune_score_max = prune_score_max_orig.saturating_sub(score_so_far);
+        let (res, _) = self.solve_impl(
+            pos.board().clone(),
+            &mut NullObserver,
+            None,
+            Some(constraints),
+        );
+        self.prune_score_max = prune_score_max_orig;
+
+        let Some((gain, solution)) = res else {
+            return Ok(None);
+        };
+        let solution = constraints
+            .forced_prefix
+            .concat(&solution)
+            .expect("初期盤面からの手順は着手履歴の容量に収まるはず");
+
+        Ok(Some((score_so_far + gain, solution)))
+    }
+
+    /// 盤面 `board` からゲームルールに従って手順 `history` を指し、(結果の局面, 獲得スコア) を返す。
+    ///
+    /// 非合法手を含む場合、または `constraints` の着手禁止マスに触れる場合はエラーを返す。
+    pub(crate) fn replay(
+        &self,
+        board: &Board,
+        history: &ActionHistory,
+        constraints: Option<&SolveConstraints>,
+    ) -> anyhow::Result<(Position, Score)> {
+        let mut pos = Position::new(board.clone());
+        let mut score = Score::ZERO;
+        for (i, &sq) in history.iter().enumerate() {
+            let action = pos
+                .actions_with_rules(&self.rule_set)
+                .find(|action| action.least_square() == sq)
+                .with_context(|| format!("{} 手目 {sq} が合法手でない", i + 1))?;
+            ensure!(
+                constraints.is_none_or(|c| c.allows(&action)),
+                "{} 手目 {sq} が着手禁止マスに触れる",
+                i + 1
+            );
+            score = audited_add(score, action.gain(), self.rule_set.score_max(), || {
+                format!("{} 手目 {sq}", i + 1)
+            });
+            pos = pos.do_action_with_rules(&action, &self.rule_set);
+        }
+
+        Ok((pos, score))
+    }
+
+    fn solve_impl(
+        &mut self,
+        board: Board,
+        observer: &mut dyn SearchObserver,
+        resume: Option<&SolveCheckpoint>,
+        constraints: Option<&SolveConstraints>,
+    ) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        let (res, stats) = self.search_with_strategy(board, observer, resume, constraints);
+
+        // 次の面に備え、DP テーブルをクリア。
+        self.dp.clear();
+
+        (res, stats)
+    }
+
+    /// 探索戦略に従って探索を行う。DP テーブルはクリアしない。
+    fn search_with_strategy(
+        &mut self,
+        board: Board,
+        observer: &mut dyn SearchObserver,
+        resume: Option<&SolveCheckpoint>,
+        constraints: Option<&SolveConstraints>,
+    ) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        // 前回の面を解いた後、DP テーブルはクリアされているはず。
+        debug_assert!(self.dp.is_empty());
+
+        if let Objective::MinMoves { target } = self.objective {
+            return self.search_min_moves(board, observer, constraints, target);
+        }
+
+        match self.strategy {
+            SearchStrategy::IterativeWidening { step } if resume.is_none() => {
+                self.search_widening(board, observer, constraints, step)
+            }
+            _ => self.search(board, observer, resume, constraints),
+        }
+    }
+
+    /// 枝刈り用スコア閾値を段階的に下げつつ探索を行う (`SearchStrategy::IterativeWidening` を参照)。
+    /// DP テーブルはクリアしない。
+    fn search_widening(
+        &mut self,
+        board: Board,
+        observer: &mut dyn SearchObserver,
+        constraints: Option<&SolveConstraints>,
+        step: Score,
+    ) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        let step = step.max(Score::new(1));
+        let prune_score_max_orig = self.prune_score_max;
+        let node_limit_orig = self.node_limit;
+
+        let gain_ub = self
+            .bound_fn
+            .gain_upper_bound(&Position::new(board.clone()));
+        let mut threshold = gain_ub.saturating_sub(step).max(prune_score_max_orig);
+        let mut stats_total = SearchStats::default();
+        let res = loop {
+            self.prune_score_max = threshold;
+            let (res, stats) = self.search(board.clone(), observer, None, constraints);
+            stats_total.accumulate(&stats);
+
+            // 閾値を超える解が見つかれば、それが最適解。
+            // (閾値以下の解は、枝刈りされた手順により良いものがありうる)
+            let found = res.as_ref().is_some_and(|&(score, _)| score > threshold);
+            if found
+                || threshold == prune_score_max_orig
+                || stats.node_limit_reached
+                || stats.cancelled
+            {
+                break res;
+            }
+            debug!("No solution above {threshold}, widening");
+
+            threshold = threshold.saturating_sub(step).max(prune_score_max_orig);
+            self.node_limit = node_limit_orig.map(|limit| limit.saturating_sub(stats_total.nodes));
+        };
+
+        self.prune_score_max = prune_score_max_orig;
+        self.node_limit = node_limit_orig;
+
+        (res, stats_total)
+    }
+
+    /// 最短手数探索 (`Objective::MinMoves`) を行う。
+    fn search_min_moves(
+        &mut self,
+        board: Board,
+        observer: &mut dyn SearchObserver,
+        constraints: Option<&SolveConstraints>,
+        target: MoveTarget,
+    ) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        let mut search = MinMovesSearch {
+            target,
+            rule_set: self.rule_set,
+            terminal_eval: &*self.terminal_eval,
+            constraints,
+            node_limit: self.node_limit,
+            cancel_token: self.cancel_token.as_ref(),
+            observer,
+            history: ActionHistory::new(),
+            table: U64HashMap::default(),
+            stats: SearchStats::default(),
+        };
+        let start = Instant::now();
+        let pos = Position::new(board);
+
+        let mut res = None;
+        for moves_max in 0..=ActionHistory::CAPACITY {
+            match search.dfs(&pos, Score::ZERO, moves_max) {
+                Ok(score) => {
+                    search.observer.on_incumbent(score, &search.history);
+                    res = Some((score, search.history.clone()));
+                    break;
+                }
+                // 手数の上限により打ち切った部分木がなければ、上限を増やしても解はない。
+                Err(false) => break,
+                Err(true) => {
+                    if search.stats.node_limit_reached || search.stats.cancelled {
+                        break;
+                    }
+                    debug!("No solution within {moves_max} moves, deepening");
+                }
+            }
+        }
+
+        let mut stats = search.stats;
+        stats.elapsed = start.elapsed();
+        info!("Min-moves table entry count: {}", search.table.len());
+
+        (res, stats)
+    }
+
+    /// 探索を行う。DP テーブルはクリアしない。
+    fn search(
+        &mut self,
+        board: Board,
+        observer: &mut dyn SearchObserver,
+        resume: Option<&SolveCheckpoint>,
+        constraints: Option<&SolveConstraints>,
+    ) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        let mut sub_solver = SubSolver::new(self, observer, &board, constraints);
+        if let Some(ckpt) = resume {
+            sub_solver.resume_from(ckpt);
+        }
+        let (res, mut stats) = sub_solver.solve(board);
+
+        let spill_stats = self.dp.spill_stats();
+        stats.dp_spill_writes = spill_stats.writes;
+        stats.dp_spill_reads = spill_stats.reads;
+        stats.dp_spill_io_elapsed = spill_stats.io_elapsed;
+        stats.approx_memory_usage = self.approx_memory_usage();
+
+        info!(
+            "DP entry count: {} (spilled: {})",
+            self.dp.len(),
+            self.dp.spilled_len()
+        );
+
+        (res, stats)
+    }
+}
+
+/// 最短手数探索 (`Objective::MinMoves`) の状態。
+struct MinMovesSearch<'solver> {
+    target: MoveTarget,
+    rule_set: RuleSet,
+    terminal_eval: &'solver dyn TerminalEval,
+    constraints: Option<&'solver SolveConstraints>,
+    node_limit: Option<u64>,
+    cancel_token: Option<&'solver CancelToken>,
+    observer: &'solver mut dyn SearchObserver,
+
+    history: ActionHistory,
+    /// 局面ごとの、目標に達しないことが判明している残り手数の上限の最大値。
+    /// 手数の上限によらず目標に達しないなら `u8::MAX`。手数の上限を増やしても引き継ぐ。
+    table: U64HashMap<Position, u8>,
+    stats: SearchStats,
+}
+
+impl MinMovesSearch<'_> {
+    /// 局面 `pos` から残り `moves_remain` 手以内で目標に達する手順を探す。
+    ///
+    /// 見つかれば `self.history` をその手順としたまま最終スコアを返す。
+    /// 見つからなければ、手数の上限により打ち切った部分木があったかどうかを返す。
+    /// 訪問ノード数の上限に達した後、または中断した後は何もせずに `Err(false)` を返す。
+    fn dfs(&mut self, pos: &Position, score: Score, moves_remain: usize) -> Result<Score, bool> {
+        if self.stats.node_limit_reached || self.stats.cancelled {
+            return Err(false);
+        }
+        if self
+            .node_limit
+            .is_some_and(|limit| self.stats.nodes >= limit)
+        {
+            self.stats.node_limit_reached = true;
+            return Err(false);
+        }
+
+        self.stats.nodes += 1;
+        chmax!(self.stats.max_depth, self.history.len());
+        if (self.stats.nodes & (HEARTBEAT_CHECK_NODES - 1)) == 0 {
+            let cancelled = self.cancel_token.is_some_and(CancelToken::is_cancelled)
+                || self.observer.poll().is_break();
+            if cancelled {
+                info!("Search cancelled at {} nodes", self.stats.nodes);
+                self.stats.cancelled = true;
+                return Err(false);
+            }
+        }
+
+        if !pos.has_action_with_rules(&self.rule_set) {
+            let reached = (self.target == MoveTarget::Terminal || pos.board().is_empty())
+                && self.constraints.is_none_or(|c| c.is_cleared(pos));
+            if !reached {
+                return Err(false);
+            }
+            let gain = self.terminal_eval.final_gain(pos);
+            return Ok(audited_add(score, gain, self.rule_set.score_max(), || {
+                format!("終了局面評価, 手順: {}", self.history)
+            }));
+        }
+
+        if self.constraints.is_some_and(|c| !c.can_clear(pos)) {
+            self.stats.prunes.add(PruneReason::Constraint);
+            return Err(false);
+        }
+        let Some(moves_lb) = self.moves_lower_bound(pos) else {
+            self.stats.prunes.add(PruneReason::Bound);
+            return Err(false);
+        };
+        if moves_lb > moves_remain {
+            self.stats.prunes.add(PruneReason::Bound);
+            return Err(true);
+        }
+        match self.table.get(pos) {
+            Some(&failed) if moves_remain <= usize::from(failed) => {
+                self.stats.dp_hits += 1;
+                self.stats.prunes.add(PruneReason::Table);
+                return Err(failed != u8::MAX);
+            }
+            _ => self.stats.dp_misses += 1,
+        }
+
+        let mut cut = false;
+        let rule_set = self.rule_set;
+        for action in pos.actions_with_rules(&rule_set) {
+            if self.constraints.is_some_and(|c| !c.allows(&action)) {
+                continue;
+            }
+
+            self.history.push(action.least_square());
+
+            let pos_child = pos.do_action_with_rules(&action, &rule_set);
+            let score_child = audited_add(score, action.gain(), rule_set.score_max(), || {
+                format!("手順: {}", self.history)
+            });
+            match self.dfs(&pos_child, score_child, moves_remain - 1) {
+                Ok(score_final) => return Ok(score_final),
+                Err(child_cut) => cut |= child_cut,
+            }
+
+            self.history.remove_last();
+        }
+
+        if self.stats.node_limit_reached || self.stats.cancelled {
+            return Err(false);
+        }
+
+        let failed = if cut { moves_remain as u8 } else { u8::MAX };
+        self.table.insert(pos.clone(), failed);
+        Err(cut)
+    }
+
+    /// 非終了局面 `pos` から目標に達するまでの手数の下界を返す。目標に達しえないとわかれば `None` を返す。
+    fn moves_lower_bound(&self, pos: &Position) -> Option<usize> {
+        match self.target {
+            MoveTarget::Terminal => Some(1),
+            // 1 手で消せるのは 1 種の駒のみ。着手できない個数しかない駒種があれば全消しできない。
+            MoveTarget::Perfect => {
+                let min_group_size = self.rule_set.min_group_size.max(2);
+                let mut moves_lb = 0;
+                for piece in Piece::all() {
+                    let count = pos.piece_count(piece);
+                    if count == 0 {
+                        continue;
+                    }
+                    if count < min_group_size {
+                        return None;
+                    }
+                    moves_lb += 1;
+                }
+                Some(moves_lb)
+            }
+        }
+    }
+}
+
+/// 最適解の列挙中に、指定スコアの解を集めるもの。
+struct SolutionCollector {
+    score: Score,
+    solutions: Vec<ActionHistory>,
+    max_solutions: usize,
+    /// 上限を超える解が見つかったかどうか。真になったら探索を打ち切る。
+    overflowed: bool,
+}
+
+struct SubSolver<'solver> {
+    prune_score_max: Score,
+
+    best_score: Score,
+    best_solution: Option<ActionHistory>,
+    /// 暫定解のコスト (同スコアの解の間のコスト関数があれば)。
+    best_cost: Option<u32>,
+    history: ActionHistory,
+
+    dp: &'solver mut DpTable,
+    dp_seed: Option<&'solver U64HashMap<Position, Score>>,
+    bound_fn: &'solver dyn BoundFn,
+    terminal_eval: &'solver dyn TerminalEval,
+    rule_set: RuleSet,
+
+    heartbeat_interval: Option<Duration>,
+    prefer_shorter: bool,
+    tie_break_cost: Option<&'solver TieBreakCost>,
+    opening_book: Option<&'solver OpeningBook>,
+    move_ordering: MoveOrdering,
+    /// history heuristic 用の、着手 (最小マス) ごとの暫定解の更新への寄与。
+    history_scores: SquareArray<u64>,
+    incumbent_log: &'solver IncumbentLogPolicy,
+    node_limit: Option<u64>,
+    cancel_token: Option<&'solver CancelToken>,
+    observer: &'solver mut dyn SearchObserver,
+
+    /// チェックポイントの保存設定と探索対象の盤面。
+    checkpoint: Option<(&'solver CheckpointConfig, Board)>,
+    checkpoint_next: Option<Instant>,
+    /// 再開時の探索フロンティア。フロンティア上のノードを展開している間のみ `Some`。
+    resume: Option<ActionHistory>,
+    /// 再開前の訪問ノード数の総和。
+    nodes_before: u64,
+
+    constraints: Option<&'solver SolveConstraints>,
+
+    /// 最適解の列挙時、解を集めるもの。
+    collector: Option<&'solver mut SolutionCollector>,
+
+    start: Instant,
+    heartbeat_next: Option<Instant>,
+    /// 直前に暫定解を info 出力した (スコア, 時刻)。
+    incumbent_logged: Option<(Score, Instant)>,
+    stats: SearchStats,
+
+    /// 展開中のノードたちの (現スコア, 追加獲得スコア上界)。上界関数の検証用。
+    #[cfg(debug_assertions)]
+    bound_stack: Vec<(Score, Score)>,
+}
+
+impl<'solver> SubSolver<'solver> {
+    fn new(
+        solver: &'solver mut Solver,
+        observer: &'solver mut dyn SearchObserver,
+        board: &Board,
+        constraints: Option<&'solver SolveConstraints>,
+    ) -> Self {
+        let start = Instant::now();
+        let heartbeat_interval = solver.heartbeat_interval;
+        let checkpoint = solver.checkpoint.as_ref();
+        if checkpoint.is_some() && solver.move_ordering == MoveOrdering::History {
+            warn!("展開順序が探索の経過に依存するので、チェックポイントを保存しない");
+        }
+        let checkpoint = checkpoint.filter(|_| solver.move_ordering != MoveOrdering::History);
+        let opening_book = solver.opening_book.as_deref();
+        if opening_book.is_some() && !solver.opening_book_applicable() {
+            warn!("ゲームルールまたは終了局面の評価関数が変更されているので、序盤定跡を引かない");
+        }
+        let opening_book = opening_book.filter(|_| solver.opening_book_applicable());
+
+        Self {
+            prune_score_max: solver.prune_score_max,
+
+            best_score: Score::ZERO,
+            best_solution: None,
+            best_cost: None,
+            history: ActionHistory::new(),
+
+            dp: &mut solver.dp,
+            dp_seed: solver.dp_seed.as_deref(),
+            bound_fn: &*solver.bound_fn,
+            terminal_eval: &*solver.terminal_eval,
+            rule_set: solver.rule_set,
+
+            heartbeat_interval,
+            prefer_shorter: solver.objective == Objective::MaxScoreThenMinMoves,
+            tie_break_cost: solver.tie_break_cost.as_deref(),
+            opening_book,
+            move_ordering: solver.move_ordering,
+            history_scores: SquareArray::default(),
+            incumbent_log: &solver.incumbent_log,
+            node_limit: solver.node_limit,
+            cancel_token: solver.cancel_token.as_ref(),
+            observer,
+
+            checkpoint: checkpoint.map(|config| (config, board.clone())),
+            checkpoint_next: checkpoint.map(|config| start + config.interval),
+            resume: None,
+            nodes_before: 0,
+
+            constraints,
+
+            collector: None,
+
+            start,
+            heartbeat_next: heartbeat_interval.map(|interval| start + interval),
+            incumbent_logged: None,
+            stats: SearchStats::default(),
+
+            #[cfg(debug_assertions)]
+            bound_stack: Vec::new(),
+        }
+    }
+
+    /// チェックポイント `ckpt` の暫定解と探索フロンティアから探索を再開するよう設定する。
+    fn resume_from(&mut self, ckpt: &SolveCheckpoint) {
+        if let Some((score, solution)) = &ckpt.best {
+            self.best_score = *score;
+            self.best_solution = Some(solution.clone());
+            self.best_cost = self.tie_break_cost.map(|cost| cost(solution));
+        }
+        self.resume = Some(ckpt.frontier.clone());
+        self.nodes_before = ckpt.nodes;
+    }
+
+    fn solve(mut self, board: Board) -> (Option<(Score, ActionHistory)>, SearchStats) {
+        // DP テーブルは空か、同じ面の以前の反復 (`SearchStrategy::IterativeWidening`) の上界を持つ。
+        let mut pos = Position::new(board);
+        self.dfs(&mut pos, Score::ZERO);
+
+        if self.incumbent_log.log_final {
+            if let Some(solution) = &self.best_solution {
+                info!("Final {}: {}", self.best_score, solution);
+            }
+        }
+
+        let res = self
+            .best_solution
+            .map(|solution| (self.best_score, solution));
+
+        let mut stats = self.stats;
+        stats.elapsed = self.start.elapsed();
+
+        (res, stats)
+    }
+
+    /// 現スコアが `score` である局面 `pos` から追加で獲得しうるスコアの上界を返す。
+    ///
+    /// 訪問ノード数の上限に達した後、中断した後、または最適解の列挙を打ち切った後は何もせずに `Score::ZERO` を返す
+    /// (このとき DP テーブルの内容は正しくなくなるが、探索終了後にクリアされる)。
+    ///
+    /// 子ノードは `pos` 上でその場で着手・取り消しして探索する。戻るときには `pos` は元に戻っている。
+    fn dfs(&mut self, pos: &mut Position, score: Score) -> Score {
+        if self.stats.node_limit_reached
+            || self.stats.cancelled
+            || self
+                .collector
+                .as_ref()
+                .is_some_and(|collector| collector.overflowed)
+        {
+            return Score::ZERO;
+        }
+        if self
+            .node_limit
+            .is_some_and(|limit| self.stats.nodes >= limit)
+        {
+            self.stats.node_limit_reached = true;
+            return Score::ZERO;
+        }
+
+        self.stats.nodes += 1;
+        chmax!(self.stats.max_depth, self.history.len());
+        if (self.stats.nodes & (HEARTBEAT_CHECK_NODES - 1)) == 0 {
+            self.check_heartbeat();
+            self.check_checkpoint();
+            if self.check_cancel() {
+                return Score::ZERO;
+            }
+        }
+
+        // 再開時、pos が探索フロンティア上にあればフロンティア上の次の手を得る。
+        let resume_sq = self.resume_square();
+
+        // pos が終了局面ならば解の更新処理を行い、追加の獲得スコアを返す。
+        if let Some(gain) = self.final_gain(pos) {
+            if resume_sq.is_some() {
+                self.resume = None;
+            }
+            let score_final = audited_add(score, gain, self.rule_set.score_max(), || {
+                format!("終了局面評価, 手順: {}", self.history)
+            });
+
+            // 消すべき駒種が残っているなら解とはみなさない。
+            if self.constraints.is_some_and(|c| !c.is_cleared(pos)) {
+                self.stats.prunes.add(PruneReason::Constraint);
+                return Score::ZERO;
+            }
+
+            #[cfg(debug_assertions)]
+            self.verify_bounds(score_final);
+
+            if let Some(collector) = &mut self.collector {
+                if score_final == collector.score {
+                    if collector.solutions.len() < collector.max_solutions {
+                        collector.solutions.push(self.history.clone());
+                    } else {
+                        collector.overflowed = true;
+                    }
+                }
+            }
+
+            if self.is_better_solution(score_final) {
+                self.best_score = score_final;
+                self.best_solution.replace(self.history.clone());
+                self.best_cost = self.tie_break_cost.map(|cost| cost(&self.history));
+                self.report_incumbent();
+            }
+            return gain;
+        }
+
+        // 消すべき駒種がもう消せないなら解はない。
+        if self.constraints.is_some_and(|c| !c.can_clear(pos)) {
+            self.stats.prunes.add(PruneReason::Constraint);
+            return Score::ZERO;
+        }
+
+        // 浅いノードでは序盤定跡を引き、あれば探索せずにその値と手順を用いる。
+        if resume_sq.is_none() {
+            if let Some(gain) = self.probe_opening_book(pos, score) {
+                return gain;
+            }
+        }
+
+        // pos から追加で獲得しうるスコアについて現時点で最良の上界を得る。
+        // DP テーブルにエントリがあるならその値を使う。
+        // さもなくば探索せずにわかる範囲で見積もり、DP テーブルにその値を記録する。
+        // 凍結済みテーブルがあれば、そのエントリと上界関数の値の小さい方を使う。
+        let bound_fn = self.bound_fn;
+        let dp_seed = self.dp_seed;
+        let mut from_bound_fn = false;
+        let mut dp_miss = false;
+        let gain_ub = self.dp.get_or_insert_with(pos, || {
+            dp_miss = true;
+            let gain_ub = bound_fn.gain_upper_bound(pos);
+            match dp_seed.and_then(|seed| seed.get(pos)) {
+                Some(&seeded) if seeded < gain_ub => seeded,
+                _ => {
+                    from_bound_fn = true;
+                    gain_ub
+                }
+            }
+        });
+        if dp_miss {
+            self.stats.dp_misses += 1;
+        } else {
+            self.stats.dp_hits += 1;
+        }
+
+        // 最終スコアが prune_score_max を超えないなら枝刈り。
+        if let Some(reason) = self.prune_reason(score + gain_ub, from_bound_fn) {
+            if resume_sq.is_some() {
+                self.resume = None;
+            }
+            self.stats.prunes.add(reason);
+            return gain_ub;
+        }
+
+        #[cfg(debug_assertions)]
+        self.bound_stack.push((score, gain_ub));
+
+        // 最終スコアが prune_score_max を超えうるなら、全ての子ノードを探索して追加スコア上界を更新。
+        // ただし再開時、探索フロンティア上の手より前の子ノードは探索済みなので飛ばす。
+        let gain_ub_orig = gain_ub;
+        let mut gain_ub = Score::ZERO;
+        let mut skipping = resume_sq.is_some();
+        let mut expanded = false;
+        let rule_set = self.rule_set;
+        for action in self.ordered_actions(pos) {
+            if skipping {
+                if Some(action.least_square()) != resume_sq {
+                    continue;
+                }
+                skipping = false;
+            }
+            if self.constraints.is_some_and(|c| !c.allows(&action)) {
+                continue;
+            }
+            expanded = true;
+
+            self.history.push(action.least_square());
+
+            let undo = pos.make_action_with_rules(&action, &rule_set);
+            let gain_action = action.gain();
+            let score_child = audited_add(score, gain_action, rule_set.score_max(), || {
+                format!("手順: {}", self.history)
+            });
+            let best_score_before = self.best_score;
+            let gain_ub_child = self.dfs(pos, score_child);
+            pos.undo_action(undo);
+            chmax!(gain_ub, gain_action + gain_ub_child);
+
+            // 浅いノードでの更新ほど重く数える。
+            if self.best_score > best_score_before {
+                let depth_remain = (ActionHistory::CAPACITY - self.history.len()) as u64;
+                self.history_scores[action.least_square()] += depth_remain * depth_remain;
+            }
+
+            self.history.remove_last();
+
+            // フロンティア上の子ノードを探索し終えたら、以降は通常の探索。
+            self.resume = None;
+        }
+
+        // 制約により指せる手がなければ行き詰まり (gain_ub は 0 のまま)。
+        if !expanded && self.constraints.is_some() {
+            self.stats.prunes.add(PruneReason::Constraint);
+        }
+
+        // 飛ばした子ノードたちの上界は不明なので、展開前の上界を用いる。
+        if resume_sq.is_some() {
+            self.resume = None;
+            gain_ub = gain_ub_orig;
+        }
+
+        #[cfg(debug_assertions)]
+        self.bound_stack.pop();
+
+        // 新たな追加スコア上界を DP テーブルに記録してから返す。
+        // (NOTE: 所有権の都合上、DP テーブルエントリを 2 回探すことになるが、速度的には問題ない)
+        self.dp.set(pos, gain_ub);
+        gain_ub
+    }
+
+    /// 現スコアが `score` である非終了局面 `pos` で序盤定跡を引く。
+    ///
+    /// 定跡にあれば、定跡の手順で解の更新処理を行い、定跡の追加獲得スコア (正確な値) を返す。
+    fn probe_opening_book(&mut self, pos: &Position, score: Score) -> Option<Score> {
+        let book = self.opening_book?;
+        if self.history.len() > OpeningBook::PROBE_DEPTH_MAX
+            || self.prefer_shorter
+            || self.tie_break_cost.is_some()
+            || self.constraints.is_some()
+            || self.collector.is_some()
+        {
+            return None;
+        }
+
+        let entry = book.probe(pos)?;
+        self.stats.book_hits += 1;
+
+        let score_final = audited_add(score, entry.gain, self.rule_set.score_max(), || {
+            format!("序盤定跡, 手順: {}", self.history)
+        });
+        if self.is_better_solution(score_final) {
+            let len = self.history.len();
+            self.history
+                .try_extend_from_slice(entry.continuation.as_slice())
+                .expect("初期盤面からの手順は着手履歴の容量に収まるはず");
+
+            #[cfg(debug_assertions)]
+            self.verify_bounds(score_final);
+
+            self.best_score = score_final;
+            self.best_solution.replace(self.history.clone());
+            self.best_cost = None;
+            self.report_incumbent();
+            self.history.truncate(len);
+        }
+
+        Some(entry.gain)
+    }
+
+    /// 局面 `pos` の合法手たちを展開順序に従って並べて返す。
+    ///
+    /// 順序が同等の手同士は合法手の列挙順を保つ。
+    fn ordered_actions(&self, pos: &Position) -> MoveList {
+        let mut actions = MoveList::new();
+        pos.collect_actions_with_rules(&self.rule_set, &mut actions);
+
+        match self.move_ordering {
+            MoveOrdering::Natural => {}
+            MoveOrdering::LargestFirst => actions.sort_largest_first(),
+            MoveOrdering::RareColorsLast => {
+                actions.sort_by_key(|action| std::cmp::Reverse(pos.piece_count(action.piece())));
+            }
+            MoveOrdering::History => {
+                actions.sort_by_key(|action| {
+                    std::cmp::Reverse(self.history_scores[action.least_square()])
+                });
+            }
+        }
+
+        actions
+    }
+
+    /// 現在の手順が最終スコア `score_final` の解であるとき、それが暫定解より良いかどうかを返す。
+    fn is_better_solution(&self, score_final: Score) -> bool {
+        if score_final > self.best_score {
+            return true;
+        }
+
+        let Some(best) = &self.best_solution else {
+            return false;
+        };
+        if score_final < self.best_score {
+            return false;
+        }
+
+        if self.prefer_shorter && self.history.len() != best.len() {
+            return self.history.len() < best.len();
+        }
+
+        match (self.tie_break_cost, self.best_cost) {
+            (Some(cost), Some(best_cost)) => cost(&self.history) < best_cost,
+            _ => false,
+        }
+    }
+
+    /// 最終スコアの上界が `score_final_ub` である非終了局面を枝刈りできるならその理由を返す。
+    ///
+    /// `from_bound_fn` は上界が上界関数によるもの (DP テーブルにエントリがなかった) かどうか。
+    fn prune_reason(&self, score_final_ub: Score, from_bound_fn: bool) -> Option<PruneReason> {
+        let reason_ub = if from_bound_fn {
+            PruneReason::Bound
+        } else {
+            PruneReason::Table
+        };
+
+        if !self.prefer_shorter && self.tie_break_cost.is_none() {
+            // 暫定解と同スコアにしかならない解は不要。ただし最適解の列挙時は同スコアの解も全て辿る。
+            let threshold = if self.collector.is_some() {
+                self.prune_score_max
+            } else {
+                self.prune_score_max.max(self.best_score)
+            };
+            return (score_final_ub <= threshold).then_some(reason_ub);
+        }
+
+        // 閾値と同スコアの解は探索対象とする。
+        if score_final_ub < self.prune_score_max || score_final_ub < self.best_score {
+            return Some(reason_ub);
+        }
+
+        if !self.prefer_shorter {
+            return None;
+        }
+
+        // 暫定解と同スコアにしかならないなら、少なくともあと 1 手指すので暫定解より短くなりうる場合のみ探索する。
+        // 同スコアの解の間のコストがあれば、同じ手数でもコストで勝りうる。
+        let not_shorter = score_final_ub == self.best_score
+            && self.best_solution.as_ref().is_some_and(|best| {
+                let len_min = self.history.len() + 1;
+                if self.tie_break_cost.is_some() {
+                    len_min > best.len()
+                } else {
+                    len_min >= best.len()
+                }
+            });
+
+        not_shorter.then_some(PruneReason::NotShorter)
+    }
+
+    /// 暫定解の更新をオブザーバーに報告し、ログ出力方針に従ってログ出力する。
+    fn report_incumbent(&mut self) {
+        self.observer.on_incumbent(self.best_score, &self.history);
+
+        let now = Instant::now();
+        let notable = self.incumbent_logged.is_none_or(|(score, time)| {
+            self.best_score.saturating_sub(score) >= self.incumbent_log.min_delta
+                && now - time >= self.incumbent_log.min_interval
+        });
+
+        if notable {
+            info!("Found {}: {}", self.best_score, self.history);
+            self.incumbent_logged = Some((self.best_score, now));
+        } else {
+            debug!("Found {}: {}", self.best_score, self.history);
+        }
+    }
+
+    /// 再開時、現在のノードが探索フロンティア上にあればフロンティア上の次の手を返す。
+    ///
+    /// 現在のノードがフロンティアの末端ならば、その部分木は全て探索し直すので再開を終える。
+    fn resume_square(&mut self) -> Option<Square> {
+        let frontier = self.resume.as_ref()?;
+        let sq = frontier.as_slice().get(self.history.len()).copied();
+        if sq.is_none() {
+            self.resume = None;
+        }
+        sq
+    }
+
+    /// チェックポイントの保存時刻に達していたら保存を行う。
+    fn check_checkpoint(&mut self) {
+        let (Some((config, board)), Some(next)) = (&self.checkpoint, self.checkpoint_next) else {
+            return;
+        };
+
+        let now = Instant::now();
+        if now < next {
+            return;
+        }
+
+        // 現在の手順がそのまま探索フロンティアとなる。
+        let ckpt = SolveCheckpoint {
+            board: board.clone(),
+            prune_score_max: self.prune_score_max,
+            nodes: self.nodes_before + self.stats.nodes,
+            best: self
+                .best_solution
+                .as_ref()
+                .map(|solution| (self.best_score, solution.clone())),
+            frontier: self.history.clone(),
+        };
+        match ckpt.save(&config.path) {
+            Ok(()) => debug!("Checkpoint saved: {}", ckpt.frontier),
+            Err(e) => warn!("{e:#}"),
+        }
+
+        self.checkpoint_next = Some(now + config.interval);
+    }
+
+    /// 中断が要求されていれば探索を中断するよう記録し、`true` を返す。
+    fn check_cancel(&mut self) -> bool {
+        let cancelled = self.cancel_token.is_some_and(CancelToken::is_cancelled)
+            || self.observer.poll().is_break();
+        if cancelled {
+            info!("Search cancelled at {} nodes", self.stats.nodes);
+            self.stats.cancelled = true;
+        }
+        cancelled
+    }
+
+    /// ハートビート報告時刻に達していたら報告を行う。
+    fn check_heartbeat(&mut self) {
+        let (Some(interval), Some(next)) = (self.heartbeat_interval, self.heartbeat_next) else {
+            return;
+        };
+
+        let now = Instant::now();
+        if now < next {
+            return;
+        }
+
+        let heartbeat = Heartbeat {
+            nodes: self.stats.nodes,
+            elapsed: now - self.start,
+            dp_entry_count: self.dp.len(),
+            best_score: self.best_solution.as_ref().map(|_| self.best_score),
+            depth: self.history.len(),
+            rss_bytes: rss_bytes(),
+        };
+        self.observer.on_heartbeat(&heartbeat);
+
+        self.heartbeat_next = Some(now + interval);
+    }
+
+    /// `pos` が終了局面ならば追加の獲得スコアを返す。
+    fn final_gain(&self, pos: &Position) -> Option<Score> {
+        (!pos.has_action_with_rules(&self.rule_set)).then(|| self.terminal_eval.final_gain(pos))
+    }
+
+    /// 最終スコア `score_final` の解が見つかったとき、展開中の全ノードの上界がそれと矛盾しないことを確かめる。
+    #[cfg(debug_assertions)]
+    fn verify_bounds(&self, score_final: Score) {
+        for &(score, gain_ub) in &self.bound_stack {
+            assert!(
+                score_final - score <= gain_ub,
+                "上界関数が admissible でない: 現スコア {score}, 上界 {gain_ub}, 実際の最終スコア {score_final} (手順: {})",
+                self.history
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use samegame_core::{
+        calc_score_erase, Action, MaskBoard, Piece, PieceSet, Square, COL_1, COL_3, ROW_1, ROW_2,
+    };
+
+    use super::*;
+
+    fn parse_board(s: impl AsRef<str>) -> Board {
+        s.as_ref().parse().unwrap()
+    }
+
+    fn board_small() -> Board {
+        parse_board(indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "})
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut solver = Solver::new(Score::ZERO);
+        let (score, solution) = solver.solve(board_small()).unwrap();
+
+        assert_eq!(score, Score::new(207));
+        assert_eq!(solution.len(), 4);
+    }
+
+    #[test]
+    fn test_solve_from() {
+        let board = board_small();
+        let (score, solution) = Solver::new(Score::ZERO).solve(board.clone()).unwrap();
+
+        // 最善手順の 1 手目を指した局面から解けば、同じ最終スコアと手順が得られる。
+        let (prefix, _) = solution.split_at(1);
+        let action = Action::from_board_square(&board, prefix.as_slice()[0]).unwrap();
+        let pos = Position::new(board).do_action(&action);
+
+        let mut solver = Solver::new(Score::ZERO);
+        let (score_from, solution_from) = solver.solve_from(&pos, action.gain(), &prefix).unwrap();
+        assert_eq!(score_from, score);
+        assert_eq!(solution_from.prefix(1), prefix.as_slice());
+        assert_eq!(solution_from.len(), solution.len());
+        assert_eq!(solver.prune_score_max(), Score::ZERO);
+
+        // 閾値は最終スコアに対して適用される。
+        let mut solver = Solver::new(score);
+        assert_eq!(solver.solve_from(&pos, action.gain(), &prefix), None);
+        solver.set_prune_score_max(score - Score::new(1));
+        assert_eq!(
+            solver.solve_from(&pos, action.gain(), &prefix).unwrap().0,
+            score
+        );
+    }
+
+    #[test]
+    fn test_iterative_widening() {
+        let boards = [
+            board_small(),
+            crate::benchmark::benchmark_board("easiest")
+                .unwrap()
+                .board
+                .clone(),
+        ];
+
+        for board in boards {
+            let expect = Solver::new(Score::ZERO).solve(board.clone()).unwrap().0;
+
+            for step in [0, 7, 50, 10000] {
+                let mut solver = Solver::with_config(&SolverConfig {
+                    strategy: SearchStrategy::IterativeWidening {
+                        step: Score::new(step),
+                    },
+                    ..Default::default()
+                });
+                let (res, stats) = solver.solve_with_stats(board.clone());
+                let (score, solution) = res.unwrap();
+                assert_eq!(score, expect);
+                assert_eq!(crate::verify::replay(&board, &solution), Ok(score));
+                assert!(!stats.node_limit_reached);
+                assert_eq!(solver.prune_score_max(), Score::ZERO);
+
+                // 設定された閾値を超える解はない (閾値以下の終了局面は記録されうる)。
+                solver.set_prune_score_max(expect);
+                assert!(solver
+                    .solve(board.clone())
+                    .is_none_or(|(score, _)| score <= expect));
+            }
+        }
+
+        // 訪問ノード数の上限は反復の合計に対して適用される。
+        let mut solver = Solver::with_config(&SolverConfig {
+            strategy: SearchStrategy::IterativeWidening {
+                step: Score::new(1),
+            },
+            node_limit: Some(10),
+            ..Default::default()
+        });
+        let (_, stats) = solver.solve_with_stats(board_small());
+        assert!(stats.node_limit_reached);
+        assert_eq!(stats.nodes, 10);
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let mut solver = Solver::new(Score::ZERO);
+        let (res, stats) = solver.solve_with_stats(board_small());
+        let (_, solution) = res.unwrap();
+
+        // 非終了局面の訪問ごとに DP テーブルを 1 回引く。
+        assert!(stats.dp_misses > 0);
+        assert!(stats.dp_hits + stats.dp_misses <= stats.nodes);
+        assert!(stats.max_depth >= solution.len());
+        assert_eq!(stats.pruned(), stats.prunes.total());
+        assert!(stats.pruned() < stats.nodes);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let bb = crate::benchmark::benchmark_board("highest").unwrap();
+
+        // 中断を要求済みのトークンを設定すると、最初の確認時点で中断する。
+        let token = CancelToken::new();
+        token.cancel();
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_cancel_token(Some(token.clone()));
+        let (res, stats) = solver.solve_with_stats(bb.board.clone());
+        assert!(stats.cancelled);
+        assert_eq!(stats.nodes, HEARTBEAT_CHECK_NODES);
+        if let Some((score, solution)) = res {
+            assert_eq!(crate::verify::replay(&bb.board, &solution), Ok(score));
+        }
+
+        // 要求を取り消せば最後まで探索する。
+        token.reset();
+        let (res, stats) = solver.solve_with_stats(bb.board.clone());
+        assert!(!stats.cancelled);
+        assert_eq!(res.map(|(score, _)| score), Some(bb.known_best));
+
+        // オブザーバーからも中断できる。ハートビートは暫定解と深さを報告する。
+        #[derive(Default)]
+        struct Canceller {
+            heartbeats: Vec<Heartbeat>,
+        }
+        impl SearchObserver for Canceller {
+            fn on_heartbeat(&mut self, heartbeat: &Heartbeat) {
+                self.heartbeats.push(heartbeat.clone());
+            }
+            fn poll(&mut self) -> std::ops::ControlFlow<()> {
+                std::ops::ControlFlow::Break(())
+            }
+        }
+        let mut observer = Canceller::default();
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_heartbeat_interval(Some(Duration::ZERO));
+        let (res, stats) = solver.solve_with_observer(bb.board.clone(), &mut observer);
+        assert!(stats.cancelled);
+        assert_eq!(observer.heartbeats.len(), 1);
+        let heartbeat = &observer.heartbeats[0];
+        assert_eq!(heartbeat.nodes, HEARTBEAT_CHECK_NODES);
+        assert_eq!(heartbeat.best_score, res.map(|(score, _)| score));
+        assert!(heartbeat.depth > 0);
+    }
+
+    #[test]
+    fn test_move_ordering() {
+        let bb = crate::benchmark::benchmark_board("easiest").unwrap();
+        let boards = [board_small(), bb.board.clone()];
+
+        // どの展開順序でも最適スコアは変わらない。
+        for board in boards {
+            let expect = Solver::new(Score::ZERO).solve(board.clone()).unwrap().0;
+            for ordering in [
+                MoveOrdering::Natural,
+                MoveOrdering::LargestFirst,
+                MoveOrdering::RareColorsLast,
+                MoveOrdering::History,
+            ] {
+                let mut solver = Solver::with_config(&SolverConfig {
+                    move_ordering: ordering,
+                    ..Default::default()
+                });
+                let (score, solution) = solver.solve(board.clone()).unwrap();
+                assert_eq!(score, expect, "{ordering:?}");
+                assert_eq!(crate::verify::replay(&board, &solution), Ok(score));
+            }
+        }
+
+        // 展開順序が探索の経過に依存する設定では再開できない。
+        let ckpt = SolveCheckpoint {
+            board: bb.board.clone(),
+            prune_score_max: Score::ZERO,
+            nodes: 0,
+            best: None,
+            frontier: ActionHistory::new(),
+        };
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_move_ordering(MoveOrdering::History);
+        assert!(solver.solve_resume(&ckpt).is_err());
+        solver.set_move_ordering(MoveOrdering::LargestFirst);
+        let (res, _) = solver.solve_resume(&ckpt).unwrap();
+        assert_eq!(res.map(|(score, _)| score), Some(bb.known_best));
+    }
+
+    #[test]
+    fn test_solve_all_optimal() {
+        // 全手順を総当たりし、(最終スコア, 手順) を列挙する。
+        fn brute(
+            pos: &Position,
+            score: Score,
+            history: &mut ActionHistory,
+            out: &mut Vec<(Score, ActionHistory)>,
+        ) {
+            if !pos.has_action() {
+                out.push((score + GameTerminalEval.final_gain(pos), history.clone()));
+                return;
+            }
+            for action in pos.actions() {
+                history.push(action.least_square());
+                brute(&pos.do_action(&action), score + action.gain(), history, out);
+                history.remove_last();
+            }
+        }
+
+        let board = board_small();
+        let mut all = Vec::new();
+        brute(
+            &Position::new(board.clone()),
+            Score::ZERO,
+            &mut ActionHistory::new(),
+            &mut all,
+        );
+        let score_max = all.iter().map(|&(score, _)| score).max().unwrap();
+        let mut expect: Vec<_> = all
+            .into_iter()
+            .filter(|&(score, _)| score == score_max)
+            .map(|(_, solution)| solution.to_string())
+            .collect();
+        expect.sort_unstable();
+        assert!(expect.len() >= 2);
+
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_prefer_shorter(true);
+        let res = solver.solve_all_optimal(board.clone(), 1000).unwrap();
+        assert_eq!(res.score, score_max);
+        assert!(!res.truncated);
+        let mut actual: Vec<_> = res.solutions.iter().map(|s| s.to_string()).collect();
+        actual.sort_unstable();
+        assert_eq!(actual, expect);
+        assert_eq!(solver.objective, Objective::MaxScoreThenMinMoves);
+        assert_eq!(solver.prune_score_max(), Score::ZERO);
+
+        // 上限を超えたら打ち切る。
+        let res = solver
+            .solve_all_optimal(board.clone(), expect.len() - 1)
+            .unwrap();
+        assert!(res.truncated);
+        assert_eq!(res.solutions.len(), expect.len() - 1);
+        let res = solver
+            .solve_all_optimal(board.clone(), expect.len())
+            .unwrap();
+        assert!(!res.truncated);
+
+        // 閾値を超える解がなければ None。
+        solver.set_prune_score_max(Score::new(10000));
+        assert_eq!(solver.solve_all_optimal(board, 1000), None);
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let (_, stats) = Solver::new(Score::ZERO).solve_with_stats(board_small());
+        assert!(!stats.node_limit_reached);
+        assert!(stats.approx_memory_usage > std::mem::size_of::<Solver>());
+
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_node_limit(Some(3));
+        let (res, stats_limited) = solver.solve_with_stats(board_small());
+        assert!(stats_limited.node_limit_reached);
+        assert_eq!(stats_limited.nodes, 3);
+        if let Some((score, solution)) = res {
+            assert_eq!(crate::verify::replay(&board_small(), &solution), Ok(score));
+        }
+
+        // 上限が十分大きければ打ち切らない。
+        solver.set_node_limit(Some(stats.nodes));
+        let (res, stats_limited) = solver.solve_with_stats(board_small());
+        assert!(!stats_limited.node_limit_reached);
+        assert_eq!(res.unwrap().0, Score::new(207));
+    }
+
+    #[test]
+    fn test_solve_resume() {
+        let board = board_small();
+        let (score_opt, solution_opt) = Solver::new(Score::ZERO).solve(board.clone()).unwrap();
+
+        // 先頭 j 手目までの部分木を探索済みとしたチェックポイントから再開しても最適解が得られる。
+        let pos = Position::new(board.clone());
+        let actions: Vec<_> = pos.actions().collect();
+        for j in 0..actions.len() {
+            let mut best: Option<(Score, ActionHistory)> = None;
+            for action in &actions[..j] {
+                let mut prefix = ActionHistory::new();
+                prefix.push(action.least_square());
+                let res = Solver::new(Score::ZERO).solve_from(
+                    &pos.do_action(action),
+                    action.gain(),
+                    &prefix,
+                );
+                if let Some((score, solution)) = res {
+                    if best
+                        .as_ref()
+                        .is_none_or(|(best_score, _)| score > *best_score)
+                    {
+                        best = Some((score, solution));
+                    }
+                }
+            }
+
+            let mut frontier = ActionHistory::new();
+            frontier.push(actions[j].least_square());
+            let ckpt = SolveCheckpoint {
+                board: board.clone(),
+                prune_score_max: Score::ZERO,
+                nodes: 0,
+                best,
+                frontier,
+            };
+            let (res, _) = Solver::new(Score::ZERO).solve_resume(&ckpt).unwrap();
+            assert_eq!(res.unwrap().0, score_opt);
+        }
+
+        // 最適解の手順そのものをフロンティアとしても最適解が得られる。
+        let ckpt = SolveCheckpoint {
+            board: board.clone(),
+            prune_score_max: Score::ZERO,
+            nodes: 0,
+            best: None,
+            frontier: solution_opt.clone(),
+        };
+        let (res, _) = Solver::new(Score::ZERO).solve_resume(&ckpt).unwrap();
+        assert_eq!(res, Some((score_opt, solution_opt)));
+
+        // 非合法なフロンティアはエラー。
+        let ckpt = SolveCheckpoint {
+            frontier: "8,6".parse().unwrap(),
+            ..ckpt
+        };
+        assert!(Solver::new(Score::ZERO).solve_resume(&ckpt).is_err());
+    }
+
+    #[test]
+    fn test_solve_with_constraints() {
+        /// 制約を満たす全手順を列挙し、最大スコアを返す (`forced_prefix` は無視する)。
+        fn brute_force(
+            pos: &Position,
+            score: Score,
+            constraints: &SolveConstraints,
+        ) -> Option<Score> {
+            if !pos.has_action() {
+                return constraints
+                    .is_cleared(pos)
+                    .then(|| score + GameTerminalEval.final_gain(pos));
+            }
+            pos.actions()
+                .filter(|action| constraints.allows(action))
+                .filter_map(|action| {
+                    brute_force(&pos.do_action(&action), score + action.gain(), constraints)
+                })
+                .max()
+        }
+
+        let board = parse_board(indoc! {"
+            ........
+            ........
+            22......
+            113.....
+            2231....
+            11323...
+        "});
+        let pos = Position::new(board.clone());
+
+        let sq_forbidden = |col, row| MaskBoard::single(Square::new(col, row));
+        let piece = |inner| PieceSet::single(Piece::from_inner(inner).unwrap());
+
+        let mut constraints_all = vec![
+            SolveConstraints::default(),
+            SolveConstraints {
+                forbidden_squares: sq_forbidden(COL_3, ROW_1),
+                ..SolveConstraints::default()
+            },
+            SolveConstraints {
+                forbidden_squares: sq_forbidden(COL_3, ROW_1),
+                must_clear_colors: piece(1),
+                ..SolveConstraints::default()
+            },
+            SolveConstraints {
+                forbidden_squares: sq_forbidden(COL_1, ROW_2),
+                ..SolveConstraints::default()
+            },
+            SolveConstraints {
+                must_clear_colors: piece(1),
+                ..SolveConstraints::default()
+            },
+            // 3 は消しきれない。
+            SolveConstraints {
+                must_clear_colors: piece(3),
+                ..SolveConstraints::default()
+            },
+        ];
+        for action in pos.actions() {
+            let mut forced_prefix = ActionHistory::new();
+            forced_prefix.push(action.least_square());
+            constraints_all.push(SolveConstraints {
+                forced_prefix,
+                ..SolveConstraints::default()
+            });
+        }
+
+        for constraints in &constraints_all {
+            let expect = match constraints.forced_prefix.as_slice() {
+                [] => brute_force(&pos, Score::ZERO, constraints),
+                &[sq] => {
+                    let action = Action::from_board_square(&board, sq).unwrap();
+                    brute_force(&pos.do_action(&action), action.gain(), constraints)
+                }
+                _ => unreachable!(),
+            };
+
+            let res = Solver::new(Score::ZERO)
+                .solve_with_constraints(board.clone(), constraints)
+                .unwrap();
+            assert_eq!(
+                res.as_ref().map(|(score, _)| *score),
+                expect.filter(|&score| score > Score::ZERO),
+                "{constraints:?}"
+            );
+
+            // 解は制約を満たす。
+            if let Some((score, solution)) = res {
+                assert_eq!(crate::verify::replay(&board, &solution), Ok(score));
+                assert!(solution
+                    .as_slice()
+                    .starts_with(constraints.forced_prefix.as_slice()));
+                let mut pos = pos.clone();
+                for &sq in &solution {
+                    let action = Action::from_board_square(pos.board(), sq).unwrap();
+                    assert!(constraints.allows(&action));
+                    pos = pos.do_action(&action);
+                }
+                assert!(constraints.is_cleared(&pos));
+            }
+        }
+
+        // 制約が効いていることを確かめる。
+        let best = |constraints: &SolveConstraints| {
+            Solver::new(Score::ZERO)
+                .solve_with_constraints(board.clone(), constraints)
+                .unwrap()
+                .map(|(score, _)| score)
+        };
+        assert_eq!(best(&constraints_all[0]), Some(Score::new(25)));
+        assert_eq!(best(&constraints_all[1]), Some(Score::new(15)));
+        assert_eq!(best(&constraints_all[2]), None);
+        assert_eq!(best(&constraints_all[3]), Some(Score::new(14)));
+        assert_eq!(best(&constraints_all[4]), Some(Score::new(25)));
+        assert_eq!(best(&constraints_all[5]), None);
+
+        // 非合法な手順や着手禁止マスに触れる手順の強制はエラー。
+        let mut solver = Solver::new(Score::ZERO);
+        let constraints = SolveConstraints {
+            forced_prefix: "8,6".parse().unwrap(),
+            ..SolveConstraints::default()
+        };
+        assert!(solver
+            .solve_with_constraints(board.clone(), &constraints)
+            .is_err());
+        let constraints = SolveConstraints {
+            forced_prefix: "1,1".parse().unwrap(),
+            forbidden_squares: MaskBoard::single(Square::new(
+                samegame_core::COL_1,
+                samegame_core::ROW_1,
+            )),
+            ..SolveConstraints::default()
+        };
+        assert!(solver.solve_with_constraints(board, &constraints).is_err());
+    }
+
+    #[test]
+    fn test_prune_reasons() {
+        let board = parse_board(indoc! {"
+            ........
+            ........
+            ..3.....
+            3121....
+            12231...
+            121321..
+        "});
+
+        let (_, stats) = Solver::new(Score::ZERO).solve_with_stats(board.clone());
+        let (score_opt, _) = Solver::new(Score::ZERO).solve(board.clone()).unwrap();
+
+        // 最適値の直前を閾値とすれば、上界関数および DP テーブルによる枝刈りが起こる。
+        let (res, stats_pruned) =
+            Solver::new(score_opt - Score::new(1)).solve_with_stats(board.clone());
+        assert_eq!(res.unwrap().0, score_opt);
+        assert!(stats_pruned.prunes.get(PruneReason::Bound) > 0);
+        assert!(stats_pruned.prunes.get(PruneReason::Table) > 0);
+        assert_eq!(stats_pruned.prunes.get(PruneReason::NotShorter), 0);
+        assert!(stats_pruned.nodes < stats.nodes);
+
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_prefer_shorter(true);
+        let (_, stats) = solver.solve_with_stats(board);
+        assert!(stats.prunes.get(PruneReason::NotShorter) > 0);
+        assert_eq!(
+            stats.prunes.total(),
+            stats.prunes.bound + stats.prunes.table + stats.prunes.not_shorter
+        );
+    }
+
+    #[test]
+    fn test_bound_fn() {
+        // 常に十分大きい値を返す上界関数でも同じ結果が得られるはず。
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_bound_fn(|_: &Position| Score::new(10000));
+        let (score, _) = solver.solve(board_small()).unwrap();
+
+        assert_eq!(score, Score::new(207));
+    }
+
+    #[test]
+    fn test_terminal_eval() {
+        // 残り駒 1 個につき 1 点減点する目的関数 (全消し時 48 点)。
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_terminal_eval(|pos: &Position| {
+            Score::new(Square::NUM as u32 - pos.board().piece_count_total())
+        });
+        solver.set_bound_fn(|pos: &Position| {
+            let gain_erase: Score = Piece::all()
+                .map(|piece| pos.piece_count(piece))
+                .filter(|&count| count >= 2)
+                .map(|count| calc_score_erase(u32::from(count)))
+                .sum();
+            gain_erase + Score::new(Square::NUM as u32)
+        });
+        let (score, _) = solver.solve(board_small()).unwrap();
+
+        assert_eq!(score, Score::new(7 + 48));
+    }
+
+    #[test]
+    fn test_prefer_shorter() {
+        /// 全手順を列挙し、(最大スコア, その中での最短手数) を返す。
+        fn brute_force(
+            pos: &Position,
+            score: Score,
+            len: usize,
+        ) -> (Score, std::cmp::Reverse<usize>) {
+            if !pos.has_action() {
+                return (
+                    score + GameTerminalEval.final_gain(pos),
+                    std::cmp::Reverse(len),
+                );
+            }
+            pos.actions()
+                .map(|action| brute_force(&pos.do_action(&action), score + action.gain(), len + 1))
+                .max()
+                .unwrap()
+        }
+
+        let boards = [
+            board_small(),
+            parse_board(indoc! {"
+                ........
+                ........
+                3.......
+                21......
+                123.....
+                1123....
+            "}),
+            parse_board(indoc! {"
+                ........
+                ........
+                22......
+                113.....
+                2231....
+                11323...
+            "}),
+        ];
+
+        for board in boards {
+            let (score_expect, std::cmp::Reverse(len_expect)) =
+                brute_force(&Position::new(board.clone()), Score::ZERO, 0);
+
+            let mut solver = Solver::new(Score::ZERO);
+            solver.set_prefer_shorter(true);
+            let (score, solution) = solver.solve(board.clone()).unwrap();
+            assert_eq!(score, score_expect);
+            assert_eq!(solution.len(), len_expect);
+
+            // 閾値と同スコアの解も見つかるはず。
+            solver.set_prune_score_max(score_expect);
+            let (score, solution) = solver.solve(board).unwrap();
+            assert_eq!(score, score_expect);
+            assert_eq!(solution.len(), len_expect);
+        }
+    }
+
+    #[test]
+    fn test_tie_break_cost() {
+        /// 全手順を列挙し、(スコア, 手数, カーソル移動量) を集める。
+        fn collect_all(
+            pos: &Position,
+            score: Score,
+            history: &mut ActionHistory,
+            all: &mut Vec<(Score, usize, u32)>,
+        ) {
+            if !pos.has_action() {
+                all.push((
+                    score + GameTerminalEval.final_gain(pos),
+                    history.len(),
+                    history.cursor_travel(),
+                ));
+                return;
+            }
+            for action in pos.actions() {
+                history.push(action.least_square());
+                collect_all(&pos.do_action(&action), score + action.gain(), history, all);
+                history.remove_last();
+            }
+        }
+
+        let boards = [
+            board_small(),
+            parse_board(indoc! {"
+                ........
+                ........
+                22......
+                113.....
+                2231....
+                11323...
+            "}),
+        ];
+
+        for board in boards {
+            let mut all = Vec::new();
+            collect_all(
+                &Position::new(board.clone()),
+                Score::ZERO,
+                &mut ActionHistory::new(),
+                &mut all,
+            );
+            let score_max = all.iter().map(|&(score, _, _)| score).max().unwrap();
+            let optimal = || all.iter().filter(|&&(score, _, _)| score == score_max);
+
+            let mut solver = Solver::new(Score::ZERO);
+            solver.set_tie_break_cost(ActionHistory::cursor_travel);
+            let (score, solution) = solver.solve(board.clone()).unwrap();
+            assert_eq!(score, score_max);
+            assert_eq!(
+                solution.cursor_travel(),
+                optimal().map(|&(_, _, cost)| cost).min().unwrap()
+            );
+
+            // 手数優先と併用すると、手数が最優先となる。
+            solver.set_prefer_shorter(true);
+            let (score, solution) = solver.solve(board.clone()).unwrap();
+            assert_eq!(score, score_max);
+            assert_eq!(
+                (solution.len(), solution.cursor_travel()),
+                optimal().map(|&(_, len, cost)| (len, cost)).min().unwrap()
+            );
+
+            solver.clear_tie_break_cost();
+            let (score, solution) = solver.solve(board).unwrap();
+            assert_eq!(score, score_max);
+            assert_eq!(
+                solution.len(),
+                optimal().map(|&(_, len, _)| len).min().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_objective_min_moves() {
+        /// 全手順を列挙し、目標に達する最短手数を返す。
+        fn brute_force(pos: &Position, perfect: bool) -> Option<usize> {
+            if !pos.has_action() {
+                return (!perfect || pos.board().is_empty()).then_some(0);
+            }
+            pos.actions()
+                .filter_map(|action| brute_force(&pos.do_action(&action), perfect))
+                .min()
+                .map(|len| len + 1)
+        }
+
+        let boards = [
+            board_small(),
+            parse_board(indoc! {"
+                ........
+                ........
+                3.......
+                21......
+                123.....
+                1123....
+            "}),
+            parse_board(indoc! {"
+                ........
+                ........
+                22......
+                113.....
+                2231....
+                11323...
+            "}),
+        ];
+
+        for board in boards {
+            for (target, perfect) in [(MoveTarget::Terminal, false), (MoveTarget::Perfect, true)] {
+                let len_expect = brute_force(&Position::new(board.clone()), perfect);
+
+                let mut solver = Solver::with_config(&SolverConfig {
+                    objective: Objective::MinMoves { target },
+                    ..Default::default()
+                });
+                let res = solver.solve(board.clone());
+                assert_eq!(res.as_ref().map(|(_, solution)| solution.len()), len_expect);
+
+                // 返すスコアは手順通りのもの。
+                if let Some((score, solution)) = res {
+                    let (pos, gain) = solver.replay(&board, &solution, None).unwrap();
+                    assert!(!pos.has_action());
+                    assert_eq!(score, gain + GameTerminalEval.final_gain(&pos));
+                    assert!(!perfect || pos.board().is_empty());
+                }
+            }
+        }
+
+        // 最適解の列挙は目的によらず最大スコアの解を列挙する。
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_objective(Objective::MinMoves {
+            target: MoveTarget::Terminal,
+        });
+        let res = solver.solve_all_optimal(board_small(), 1).unwrap();
+        assert_eq!(res.score, Score::new(207));
+    }
+
+    #[test]
+    fn test_on_incumbent() {
+        #[derive(Default)]
+        struct IncumbentRecorder(Vec<Score>);
+
+        impl SearchObserver for IncumbentRecorder {
+            fn on_incumbent(&mut self, score: Score, _solution: &ActionHistory) {
+                self.0.push(score);
+            }
+        }
+
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_incumbent_log_policy(IncumbentLogPolicy::sparse(
+            Score::new(1000),
+            Duration::from_secs(1000),
+        ));
+        let mut recorder = IncumbentRecorder::default();
+        let (res, _) = solver.solve_with_observer(board_small(), &mut recorder);
+
+        // ログ出力方針によらず、全ての更新が報告されるはず。
+        assert!(!recorder.0.is_empty());
+        assert!(recorder.0.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(recorder.0.last(), Some(&res.unwrap().0));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn test_bound_fn_inadmissible() {
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_bound_fn(|_: &Position| Score::new(1));
+        solver.solve(board_small());
+    }
+
+    #[test]
+    fn test_rule_set() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+        let pos = Position::new(board.clone());
+
+        assert_eq!(RuleSet::default(), RuleSet::GAME);
+        assert_eq!(
+            pos.gain_upper_bound_with_rules(&RuleSet::GAME),
+            pos.gain_upper_bound()
+        );
+
+        // パーフェクトボーナスが 500 なら最適スコアも 300 増える。
+        let solve = |rules: RuleSet| {
+            let mut solver = Solver::new(Score::ZERO);
+            solver.set_rule_set(rules);
+            solver.solve(board.clone()).unwrap().0
+        };
+        assert_eq!(solve(RuleSet::GAME), Score::new(207));
+        assert_eq!(
+            solve(RuleSet::GAME.with_perfect_bonus(Score::new(500))),
+            Score::new(507)
+        );
+
+        // 3 個以上の塊のみ消せるなら、駒 5 の塊しか消せない。
+        let rules = RuleSet::GAME.with_min_group_size(3);
+        assert_eq!(pos.actions_with_rules(&rules).count(), 1);
+        assert!(!pos
+            .do_action(&pos.actions_with_rules(&rules).next().unwrap())
+            .has_action_with_rules(&rules));
+        assert_eq!(solve(rules), Score::new(4));
+
+        // 列を詰めないルール。
+        let rules = RuleSet::GAME.with_column_compaction(false);
+        let action = Action::from_board_square(&board, "3,1".parse().unwrap()).unwrap();
+        let pos_child = pos.do_action_with_rules(&action, &rules);
+        assert_eq!(pos_child, Position::new(pos_child.board().clone()));
+        assert_eq!(pos_child.board().width_remain(), 5);
+        assert!(pos_child.board().to_grid()[2]
+            .iter()
+            .all(|&value| value == 0));
+        assert_eq!(
+            pos.do_action_with_rules(&action, &RuleSet::GAME),
+            pos.do_action(&action)
+        );
+        let mut solver = Solver::new(Score::ZERO);
+        solver.set_rule_set(rules);
+        let (score, solution) = solver.solve(board.clone()).unwrap();
+        assert!(score <= Score::new(207));
+        let mut pos_replay = pos.clone();
+        for &sq in &solution {
+            let action = Action::from_board_square(pos_replay.board(), sq).unwrap();
+            pos_replay = pos_replay.do_action_with_rules(&action, &rules);
+        }
+        assert!(!pos_replay.has_action());
+
+        assert!(RuleSet::GAME.is_legal_board(&board));
+        assert!(!RuleSet::GAME
+            .with_regen_piece_count(4)
+            .is_legal_board(&board));
+    }
+}
@@ -0,0 +1,47 @@
+//! 終了局面の評価関連。
+
+use samegame_core::{RuleSet, Score, SCORE_PERFECT};
+
+use crate::position::Position;
+
+/// 終了局面 (合法手がない局面) において追加で獲得するスコアを与える関数。
+///
+/// 残り駒へのペナルティや特定の駒種を消し切った際のボーナスなど、ゲームとは異なる目的関数を表現するのに使える。
+/// ソルバーが正しい解を返すためには、上界関数 (`BoundFn`) がこの関数と整合していなければならない。
+/// すなわち、各局面に対する上界は、そこから到達しうる終了局面についてこの関数が返す値を含めた追加獲得スコア以上でなければならない。
+///
+/// `Fn(&Position) -> Score` を満たすクロージャはこのトレイトを実装している。
+pub trait TerminalEval {
+    /// 終了局面 `pos` において追加で獲得するスコアを返す。
+    fn final_gain(&self, pos: &Position) -> Score;
+}
+
+impl<F: Fn(&Position) -> Score> TerminalEval for F {
+    fn final_gain(&self, pos: &Position) -> Score {
+        self(pos)
+    }
+}
+
+impl TerminalEval for RuleSet {
+    fn final_gain(&self, pos: &Position) -> Score {
+        if pos.board().is_empty() {
+            self.perfect_bonus
+        } else {
+            Score::ZERO
+        }
+    }
+}
+
+/// ゲーム通りの終了局面評価。盤面が空ならば `SCORE_PERFECT`, さもなくば 0 を返す。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GameTerminalEval;
+
+impl TerminalEval for GameTerminalEval {
+    fn final_gain(&self, pos: &Position) -> Score {
+        if pos.board().is_empty() {
+            SCORE_PERFECT
+        } else {
+            Score::ZERO
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! 探索木の可視化出力関連。
+//!
+//! 枝刈りの挙動のデバッグや、ある盤面が難しい理由を説明する図の作成に使う。
+
+use std::io::Write;
+
+use samegame_core::{Board, Score, Square};
+
+use crate::Position;
+use crate::Solver;
+
+/// 探索木の出力形式。
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum TreeDumpFormat {
+    /// Graphviz の DOT 形式。
+    #[default]
+    Dot,
+    /// JSON 形式。各ノードは子ノードの配列 `children` を持つ入れ子のオブジェクトとなる。
+    Json,
+}
+
+/// 探索木のノード。
+#[derive(Debug)]
+struct TreeNode {
+    /// 親ノードからの着手 (最小マス)。根なら `None`。
+    sq: Option<Square>,
+    /// 着手による獲得スコア。
+    gain: Score,
+    /// 現スコア。
+    score: Score,
+    /// 追加獲得スコア上界 (終了局面なら終了局面評価による追加獲得スコア)。
+    bound: Score,
+    /// 終了局面かどうか。
+    terminal: bool,
+    /// 現在の枝刈り用スコア閾値の下で枝刈りされるかどうか。
+    pruned: bool,
+    /// 子ノードたち。ソルバーの探索順に並ぶ。深さ制限に達したノードや枝刈りされるノードでは空。
+    children: Vec<TreeNode>,
+}
+
+impl Solver {
+    /// 盤面 `board` の探索木の先頭 `depth` 手分を `format` 形式で `wtr` に出力する。
+    ///
+    /// 各ノードについて、現スコア、上界関数による追加獲得スコア上界、現在の枝刈り用スコア閾値で枝刈りされるかどうか、
+    /// および子ノードの探索順を出力する。
+    /// 実際の探索は行わないので、DP テーブルにより改善される上界は反映されない。
+    pub fn dump_tree<W: Write>(
+        &self,
+        board: Board,
+        depth: usize,
+        format: TreeDumpFormat,
+        mut wtr: W,
+    ) -> std::io::Result<()> {
+        let root = self.build_tree(&Position::new(board), None, Score::ZERO, Score::ZERO, depth);
+
+        match format {
+            TreeDumpFormat::Dot => {
+                writeln!(wtr, "digraph search_tree {{")?;
+                writeln!(wtr, "    node [shape=box, fontname=monospace];")?;
+                let mut id = 0;
+                write_dot(&mut wtr, &root, &mut id, None)?;
+                writeln!(wtr, "}}")?;
+            }
+            TreeDumpFormat::Json => {
+                write_json(&mut wtr, &root)?;
+                writeln!(wtr)?;
+            }
+        }
+
+        wtr.flush()
+    }
+
+    fn build_tree(
+        &self,
+        pos: &Position,
+        sq: Option<Square>,
+        gain: Score,
+        score: Score,
+        depth: usize,
+    ) -> TreeNode {
+        let rule_set = self.rule_set();
+        let terminal = !pos.has_action_with_rules(rule_set);
+
+        let bound = if terminal {
+            self.terminal_eval().final_gain(pos)
+        } else {
+            self.bound_fn().gain_upper_bound(pos)
+        };
+        let pruned = !terminal && score + bound <= self.prune_score_max();
+
+        let children = if terminal || pruned || depth == 0 {
+            Vec::new()
+        } else {
+            pos.actions_with_rules(rule_set)
+                .map(|action| {
+                    let pos_child = pos.do_action_with_rules(&action, rule_set);
+                    self.build_tree(
+                        &pos_child,
+                        Some(action.least_square()),
+                        action.gain(),
+                        score + action.gain(),
+                        depth - 1,
+                    )
+                })
+                .collect()
+        };
+
+        TreeNode {
+            sq,
+            gain,
+            score,
+            bound,
+            terminal,
+            pruned,
+            children,
+        }
+    }
+}
+
+fn write_dot<W: Write>(
+    wtr: &mut W,
+    node: &TreeNode,
+    id: &mut usize,
+    parent: Option<(usize, usize)>,
+) -> std::io::Result<()> {
+    let my_id = *id;
+    *id += 1;
+
+    let status = if node.terminal {
+        "\\nterminal"
+    } else if node.pruned {
+        "\\npruned"
+    } else {
+        ""
+    };
+    let style = if node.pruned { ", style=dashed" } else { "" };
+    writeln!(
+        wtr,
+        "    n{my_id} [label=\"score={}\\nub={}{status}\"{style}];",
+        node.score,
+        node.score + node.bound
+    )?;
+
+    if let (Some((parent_id, order)), Some(sq)) = (parent, node.sq) {
+        writeln!(
+            wtr,
+            "    n{parent_id} -> n{my_id} [label=\"#{order} {sq} +{}\"];",
+            node.gain
+        )?;
+    }
+
+    for (order, child) in node.children.iter().enumerate() {
+        write_dot(wtr, child, id, Some((my_id, order)))?;
+    }
+
+    Ok(())
+}
+
+fn write_json<W: Write>(wtr: &mut W, node: &TreeNode) -> std::io::Result<()> {
+    write!(wtr, "{{")?;
+    match node.sq {
+        Some(sq) => write!(wtr, "\"square\":\"{sq}\",")?,
+        None => write!(wtr, "\"square\":null,")?,
+    }
+    write!(
+        wtr,
+        "\"gain\":{},\"score\":{},\"bound\":{},\"terminal\":{},\"pruned\":{},\"children\":[",
+        node.gain, node.score, node.bound, node.terminal, node.pruned
+    )?;
+    for (i, child) in node.children.iter().enumerate() {
+        if i > 0 {
+            write!(wtr, ",")?;
+        }
+        write_json(wtr, child)?;
+    }
+    write!(wtr, "]}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn test_dump_tree() {
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+        let action_count = Position::new(board.clone()).action_count() as usize;
+
+        let dump = |solver: &Solver, depth, format| {
+            let mut buf = Vec::new();
+            solver
+                .dump_tree(board.clone(), depth, format, &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let solver = Solver::new(Score::ZERO);
+
+        let dot = dump(&solver, 1, TreeDumpFormat::Dot);
+        assert!(dot.starts_with("digraph search_tree {"));
+        assert_eq!(dot.matches(" -> ").count(), action_count);
+        assert!(dot.contains("n0 -> n1 [label=\"#0 "));
+
+        let json = dump(&solver, 1, TreeDumpFormat::Json);
+        assert!(json.starts_with("{\"square\":null,\"gain\":0,\"score\":0,"));
+        assert_eq!(json.matches("\"square\":").count(), 1 + action_count);
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+
+        // 深さ 0 なら根のみ。
+        assert_eq!(
+            dump(&solver, 0, TreeDumpFormat::Dot)
+                .matches(" -> ")
+                .count(),
+            0
+        );
+
+        // 閾値が十分大きければ根で枝刈りされる。
+        let solver = Solver::new(Score::new(10000));
+        let json = dump(&solver, 3, TreeDumpFormat::Json);
+        assert!(json.contains("\"pruned\":true,\"children\":[]"));
+        assert_eq!(json.matches("\"square\":").count(), 1);
+    }
+}
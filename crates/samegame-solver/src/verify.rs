@@ -0,0 +1,208 @@
+//! 既存の解 (手順) の検証関連。
+
+use samegame_core::{
+    audited_add, Action, ActionHistory, Board, RandomBoardParam, Score, Square, SCORE_MAX,
+};
+
+use crate::position::Position;
+use crate::terminal::{GameTerminalEval, TerminalEval as _};
+
+/// 手順の検証に失敗した理由。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VerificationFailure {
+    /// 盤面生成パラメータがゲーム内に現れない盤面 (再生成される盤面) を生成する。
+    IllegalBoard,
+    /// `index` 手目 (0-based) のマス `sq` が合法手でない。
+    IllegalAction { index: usize, sq: Square },
+    /// 手順の最後の局面に合法手が残っている。
+    NotFinished,
+    /// 申告スコアと実際のスコアが一致しない。
+    ScoreMismatch { claimed: Score, actual: Score },
+}
+
+impl std::fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IllegalBoard => f.write_str("illegal board"),
+            Self::IllegalAction { index, sq } => write!(f, "illegal action #{index}: {sq}"),
+            Self::NotFinished => f.write_str("not finished"),
+            Self::ScoreMismatch { claimed, actual } => {
+                write!(f, "score mismatch: claimed {claimed}, actual {actual}")
+            }
+        }
+    }
+}
+
+/// 一括検証の結果。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerificationReport {
+    /// 検証したレコード数。
+    pub record_count: usize,
+    /// 検証に失敗したレコードたち (入力順)。
+    pub failures: Vec<(RandomBoardParam, VerificationFailure)>,
+}
+
+impl VerificationReport {
+    /// 全レコードが検証に成功したかどうかを返す。
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// 検証に成功したレコード数を返す。
+    pub fn passed_count(&self) -> usize {
+        self.record_count - self.failures.len()
+    }
+}
+
+impl std::fmt::Display for VerificationReport {
+    /// 1 行目に集計を、以降各行に失敗したレコードを出力する。
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "records={} passed={} failed={}",
+            self.record_count,
+            self.passed_count(),
+            self.failures.len()
+        )?;
+        for (param, failure) in &self.failures {
+            writeln!(f, "{param}\t{failure}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 盤面 `board` で手順 `solution` を再生し、ゲーム通りの最終スコアを返す。
+///
+/// 途中に非合法手があったり、最後の局面に合法手が残っている場合はエラーを返す。
+pub fn replay(board: &Board, solution: &ActionHistory) -> Result<Score, VerificationFailure> {
+    let mut pos = Position::new(board.clone());
+    let mut score = Score::ZERO;
+
+    for (index, &sq) in solution.iter().enumerate() {
+        let action = Action::from_board_square(pos.board(), sq)
+            .map_err(|_| VerificationFailure::IllegalAction { index, sq })?;
+        score = audited_add(score, action.gain(), SCORE_MAX, || {
+            format!("{} 手目 {sq}", index + 1)
+        });
+        pos = pos.do_action(&action);
+    }
+
+    if pos.has_action() {
+        return Err(VerificationFailure::NotFinished);
+    }
+
+    Ok(audited_add(
+        score,
+        GameTerminalEval.final_gain(&pos),
+        SCORE_MAX,
+        || "終了局面評価".to_owned(),
+    ))
+}
+
+/// 盤面生成パラメータ `param` の盤面について、手順 `solution` のスコアが `claimed` であることを検証する。
+pub fn verify_record(
+    param: &RandomBoardParam,
+    claimed: Score,
+    solution: &ActionHistory,
+) -> Result<(), VerificationFailure> {
+    let (board, _) = param
+        .gen_legal_board()
+        .ok_or(VerificationFailure::IllegalBoard)?;
+
+    let actual = replay(&board, solution)?;
+    if actual != claimed {
+        return Err(VerificationFailure::ScoreMismatch { claimed, actual });
+    }
+
+    Ok(())
+}
+
+/// (盤面生成パラメータ, 申告スコア, 手順) の列を一括検証する。
+///
+/// 各レコードについて盤面を再生成して手順を再生し、申告スコアと一致するかを確かめ、失敗を集計する。
+/// 外部から提出された結果をデータベースへ取り込む前の監査用。
+pub fn verify_batch(
+    records: impl IntoIterator<Item = (RandomBoardParam, Score, ActionHistory)>,
+) -> VerificationReport {
+    let mut report = VerificationReport::default();
+
+    for (param, claimed, solution) in records {
+        report.record_count += 1;
+        if let Err(failure) = verify_record(&param, claimed, &solution) {
+            report.failures.push((param, failure));
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_batch() {
+        let param: RandomBoardParam = "0x1234,0x56,40,2".parse().unwrap();
+        let (board, _) = param.gen_legal_board().unwrap();
+
+        // 常に先頭の合法手を選ぶ手順を作る。
+        let mut pos = Position::new(board.clone());
+        let mut solution = ActionHistory::new();
+        let mut score = Score::ZERO;
+        loop {
+            let Some(action) = pos.actions().next() else {
+                break;
+            };
+            solution.push(action.least_square());
+            score += action.gain();
+            pos = pos.do_action(&action);
+        }
+        score += GameTerminalEval.final_gain(&pos);
+        assert_eq!(replay(&board, &solution), Ok(score));
+
+        let illegal_sq = Square::all()
+            .find(|&sq| Action::from_board_square(&board, sq).is_err())
+            .unwrap();
+        let illegal_param = RandomBoardParam::all()
+            .find(|param| param.gen_legal_board().is_none())
+            .unwrap();
+
+        let records = [
+            (param.clone(), score, solution.clone()),
+            (param.clone(), score + Score::new(1), solution.clone()),
+            (param.clone(), score, solution.split_at(1).0),
+            (
+                param.clone(),
+                score,
+                ActionHistory::from_slice(&[illegal_sq]).unwrap(),
+            ),
+            (illegal_param.clone(), score, solution.clone()),
+        ];
+        let report = verify_batch(records);
+
+        assert_eq!(report.record_count, 5);
+        assert_eq!(report.passed_count(), 1);
+        assert_eq!(
+            report.failures,
+            [
+                (
+                    param.clone(),
+                    VerificationFailure::ScoreMismatch {
+                        claimed: score + Score::new(1),
+                        actual: score
+                    }
+                ),
+                (param.clone(), VerificationFailure::NotFinished),
+                (
+                    param,
+                    VerificationFailure::IllegalAction {
+                        index: 0,
+                        sq: illegal_sq
+                    }
+                ),
+                (illegal_param, VerificationFailure::IllegalBoard),
+            ]
+        );
+    }
+}
@@ -0,0 +1,30 @@
+//! zobrist hash 関連。
+
+use samegame_core::{Piece, PieceArray, Square, SquareArray};
+
+use crate::asset::asset_include;
+
+type TableBoard = PieceArray<SquareArray<u64>>;
+type TableColorCount = [u64; Square::NUM + 1];
+
+#[derive(Debug)]
+pub struct ZobristTable;
+
+impl ZobristTable {
+    const BOARD: TableBoard = asset_include!("zobrist_board.in");
+    const COLOR_COUNT: TableColorCount = asset_include!("zobrist_color_count.in");
+
+    /// `piece` が `sq` にあるときのハッシュ値を返す。
+    pub fn board(&self, piece: Piece, sq: Square) -> u64 {
+        Self::BOARD[piece][sq]
+    }
+
+    /// ある駒種の駒数が `count` であるときのハッシュ値を返す。
+    ///
+    /// 駒種によらないので、全駒種についての和 (wrapping) は駒数の多重集合のハッシュ値となる。
+    pub fn color_count(&self, count: u8) -> u64 {
+        Self::COLOR_COUNT[usize::from(count)]
+    }
+}
+
+pub const ZOBRIST_TABLE: ZobristTable = ZobristTable;
@@ -0,0 +1,302 @@
+//! シードごとの注釈 (メモ) 関連。
+//!
+//! 「現行の TAS で使用」「メニュー操作のタイミングに関するメモ」といった、ルートに関する人手の知見を
+//! 盤面生成パラメータの識別子 (`SeedId`) ごとに任意のキーと値の組として持つ。
+//! 注釈ファイルは結果ファイルと並べて置き (`SeedAnnotations::path_for()`)、出力時に結果と突き合わせる。
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context as _};
+use samegame_core::SeedId;
+
+use crate::record::ResultRecord;
+
+/// シードごとの注釈の集合。
+///
+/// ファイル形式は 1 行 1 注釈のタブ区切り `seed_id key value` (`seed_id` は `SeedId` の文字列形式)。
+/// 値中のバックスラッシュ、タブ、改行はそれぞれ `\\`, `\t`, `\n` とエスケープする。
+/// 空行および `#` で始まる行は無視する。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SeedAnnotations {
+    notes: BTreeMap<SeedId, BTreeMap<String, String>>,
+}
+
+impl SeedAnnotations {
+    /// 空の注釈集合を作る。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 結果ファイル `result_path` に対応する注釈ファイルのパス (`<result_path>.notes.tsv`) を返す。
+    pub fn path_for(result_path: impl AsRef<Path>) -> PathBuf {
+        let mut path = result_path.as_ref().as_os_str().to_owned();
+        path.push(".notes.tsv");
+        PathBuf::from(path)
+    }
+
+    /// 注釈を持つシードの数を返す。
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// 注釈が 1 つもないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// シード `seed` の注釈たち (キー昇順) を返す。
+    pub fn get(&self, seed: SeedId) -> Option<&BTreeMap<String, String>> {
+        self.notes.get(&seed)
+    }
+
+    /// シード `seed` のキー `key` の注釈の値を返す。
+    pub fn get_value(&self, seed: SeedId, key: &str) -> Option<&str> {
+        self.notes.get(&seed)?.get(key).map(String::as_str)
+    }
+
+    /// シード `seed` にキー `key` の注釈 `value` を設定し、以前の値 (あれば) を返す。
+    ///
+    /// キーが空であるか、タブまたは改行を含む場合はエラーを返す。
+    pub fn insert(
+        &mut self,
+        seed: SeedId,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> anyhow::Result<Option<String>> {
+        let key = key.into();
+        ensure!(
+            !key.is_empty() && !key.contains(['\t', '\n', '\r']),
+            "注釈のキーが無効: {key:?}"
+        );
+
+        Ok(self
+            .notes
+            .entry(seed)
+            .or_default()
+            .insert(key, value.into()))
+    }
+
+    /// シード `seed` のキー `key` の注釈を削除し、その値 (あれば) を返す。
+    pub fn remove(&mut self, seed: SeedId, key: &str) -> Option<String> {
+        let notes = self.notes.get_mut(&seed)?;
+        let value = notes.remove(key);
+        if notes.is_empty() {
+            self.notes.remove(&seed);
+        }
+        value
+    }
+
+    /// 注釈集合 `other` を取り込む。同じシード・キーの注釈は `other` の値で上書きする。
+    pub fn merge(&mut self, other: &Self) {
+        for (seed, notes) in &other.notes {
+            let dst = self.notes.entry(*seed).or_default();
+            for (key, value) in notes {
+                dst.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    /// 注釈ファイルの内容を読み込む。
+    pub fn from_reader(rdr: impl BufRead) -> anyhow::Result<Self> {
+        let mut this = Self::new();
+
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse = || -> anyhow::Result<(SeedId, &str, String)> {
+                let mut fields = line.splitn(3, '\t');
+                let (Some(seed), Some(key), Some(value)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    bail!("注釈はタブ区切りの 3 フィールドでなければならない");
+                };
+                Ok((seed.parse()?, key, unescape(value)?))
+            };
+            let (seed, key, value) =
+                parse().with_context(|| format!("注釈ファイルの {} 行目のパースに失敗", i + 1))?;
+            this.insert(seed, key, value)?;
+        }
+
+        Ok(this)
+    }
+
+    /// 注釈ファイル `path` を読み込む。ファイルが存在しない場合、空の注釈集合を返す。
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("注釈ファイル '{}' を開けない", path.display()))
+            }
+        };
+
+        Self::from_reader(std::io::BufReader::new(file))
+    }
+
+    /// 注釈ファイルの形式で書き出す (シード昇順、キー昇順)。
+    pub fn write_to(&self, mut wtr: impl Write) -> anyhow::Result<()> {
+        for (seed, notes) in &self.notes {
+            for (key, value) in notes {
+                writeln!(wtr, "{seed}\t{key}\t{}", escape(value))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 注釈ファイル `path` に保存する。
+    ///
+    /// 書き込み途中で中断されても既存のファイルが壊れないよう、一時ファイルに書いてから置き換える。
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let mut path_tmp = path.as_os_str().to_owned();
+        path_tmp.push(".tmp");
+
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)?;
+        std::fs::write(&path_tmp, buf)
+            .with_context(|| format!("注釈ファイルの書き込みに失敗: {}", path.display()))?;
+        std::fs::rename(&path_tmp, path)
+            .with_context(|| format!("注釈ファイルの置き換えに失敗: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// 結果レコードたち `records` を、注釈のキー `keys` の列を加えたタブ区切りの表として書き出す。
+    ///
+    /// 1 行目はヘッダ行で、結果レコードの 6 フィールドに続けて `keys` を並べる。
+    /// 注釈がないセルは空とし、値はファイル形式と同様にエスケープする。
+    /// `SeedId` で表せないパラメータのレコードには注釈はない。
+    pub fn export_records<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a ResultRecord>,
+        keys: &[&str],
+        mut wtr: impl Write,
+    ) -> anyhow::Result<()> {
+        write!(
+            wtr,
+            "rng_state\tnmi_counter\tnmi_timing\tentropy\tscore\tsolution"
+        )?;
+        for key in keys {
+            write!(wtr, "\t{key}")?;
+        }
+        writeln!(wtr)?;
+
+        for record in records {
+            write!(wtr, "{record}")?;
+            let notes = record.seed_id().and_then(|seed| self.notes.get(&seed));
+            for key in keys {
+                let value = notes.and_then(|notes| notes.get(*key));
+                write!(wtr, "\t{}", value.map_or_else(String::new, |v| escape(v)))?;
+            }
+            writeln!(wtr)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '\t' => res.push_str("\\t"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn unescape(s: &str) -> anyhow::Result<String> {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => res.push('\\'),
+            Some('t') => res.push('\t'),
+            Some('n') => res.push('\n'),
+            Some('r') => res.push('\r'),
+            other => bail!(
+                "無効なエスケープ: '\\{}'",
+                other.map_or_else(String::new, String::from)
+            ),
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_annotations() {
+        let record: ResultRecord = "0x0123\t0x45\t40\t2\t800\t1,1 2,3".parse().unwrap();
+        let seed = record.seed_id().unwrap();
+        let other = SeedId::try_from(&"0x0001,0x00,40,0".parse().unwrap()).unwrap();
+
+        let mut notes = SeedAnnotations::new();
+        assert_eq!(notes.insert(seed, "tas", "現行 TAS で使用").unwrap(), None);
+        notes
+            .insert(seed, "menu", "2F 遅らせる\tタブ\\改行\n")
+            .unwrap();
+        notes.insert(other, "tas", "候補").unwrap();
+        assert!(notes.insert(seed, "bad\tkey", "x").is_err());
+        assert!(notes.insert(seed, "", "x").is_err());
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes.get_value(seed, "tas"), Some("現行 TAS で使用"));
+
+        // 書き出したものを読み込むと元に戻る。
+        let mut buf = Vec::new();
+        notes.write_to(&mut buf).unwrap();
+        let src = format!("# comment\n\n{}", String::from_utf8(buf).unwrap());
+        assert_eq!(SeedAnnotations::from_reader(src.as_bytes()).unwrap(), notes);
+        assert!(SeedAnnotations::from_reader("0x00000000\tkey".as_bytes()).is_err());
+        assert!(SeedAnnotations::from_reader("0x00000000\tkey\t\\x".as_bytes()).is_err());
+
+        // 結果ファイルと並べて保存する。
+        let result_path = std::env::temp_dir().join(format!(
+            "samegame-annotations-test-{}.tsv",
+            std::process::id()
+        ));
+        let path = SeedAnnotations::path_for(&result_path);
+        assert!(SeedAnnotations::load(&path).unwrap().is_empty());
+        notes.save(&path).unwrap();
+        assert_eq!(SeedAnnotations::load(&path).unwrap(), notes);
+        std::fs::remove_file(&path).unwrap();
+
+        // 取り込みは同じキーを上書きする。
+        let mut update = SeedAnnotations::new();
+        update.insert(other, "tas", "不採用").unwrap();
+        notes.merge(&update);
+        assert_eq!(notes.get_value(other, "tas"), Some("不採用"));
+        assert_eq!(notes.remove(other, "tas").as_deref(), Some("不採用"));
+        assert_eq!(notes.len(), 1);
+
+        // 結果と突き合わせた表。
+        let mut buf = Vec::new();
+        notes
+            .export_records([&record], &["tas", "missing"], &mut buf)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            format!(
+                "rng_state\tnmi_counter\tnmi_timing\tentropy\tscore\tsolution\ttas\tmissing\n{record}\t現行 TAS で使用\t\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,604 @@
+//! 各種ツールをサブコマンドとしてまとめた CLI (feature `cli`)。
+//!
+//! 各サブコマンドはライブラリの公開 API のみを用いて実装している。
+
+use std::collections::hash_map::Entry;
+use std::io::{BufRead as _, Write as _};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::Context as _;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::info;
+
+use samegame_core::*;
+use samegame_solver::*;
+use samegame_tools::*;
+
+#[derive(Debug, Parser)]
+#[command(name = "samegame", about = "SFC『鮫亀』さめがめ「かんたん」用ツール")]
+struct Cli {
+    /// 出力形式。
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// 人間向けのテキスト (TSV) 形式。
+    Text,
+    /// 1 行 1 オブジェクトの JSON Lines 形式。
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 盤面の最大スコア手順を求める。
+    Solve(SolveArgs),
+    /// パラメータから盤面を生成する。
+    GenBoard(ParamArgs),
+    /// パラメータたちの盤面を一括で解く。
+    SolveAll(SolveAllArgs),
+    /// 有効な盤面を生成するパラメータの集合を重複なしで出力する。
+    Dedup,
+    /// 結果レコード (TSV) を検証する。
+    Verify(VerifyArgs),
+    /// 手順を再生し、各手の後の盤面を出力する。
+    Replay(ReplayArgs),
+    /// 条件を満たす盤面を生成するパラメータを探す。
+    SearchSeeds(SearchSeedsArgs),
+    /// テキスト形式たちのスキーマを出力する。
+    Schema,
+}
+
+/// 盤面の指定方法。盤面ファイルか盤面生成パラメータのいずれか。
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+struct BoardSource {
+    /// 盤面ファイル (`-` なら標準入力)。
+    #[arg(long)]
+    board: Option<PathBuf>,
+
+    /// 盤面生成パラメータ (例: `0x1234,0x56,40,2`)。
+    #[arg(long)]
+    param: Option<RandomBoardParam>,
+}
+
+impl BoardSource {
+    fn load(&self) -> anyhow::Result<Board> {
+        if let Some(param) = &self.param {
+            let (board, legal, _) = param.gen_board();
+            if !legal {
+                log::warn!("パラメータ {param} の盤面はゲーム内に現れない");
+            }
+            return Ok(board);
+        }
+
+        let path = self.board.as_deref().unwrap();
+        let s = if path == Path::new("-") {
+            std::io::read_to_string(std::io::stdin())?
+        } else {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("盤面ファイル '{}' を読めない", path.display()))?
+        };
+
+        s.parse()
+            .with_context(|| format!("盤面ファイル '{}' のパースに失敗", path.display()))
+    }
+}
+
+#[derive(Debug, Args)]
+struct SolveArgs {
+    #[command(flatten)]
+    source: BoardSource,
+
+    /// 最終スコアがこの値を超えないとわかったノードを枝刈りする。
+    #[arg(long, default_value_t = Score::ZERO)]
+    prune_score_max: Score,
+
+    /// 同スコアの解のうち手数が短いものを優先する。
+    #[arg(long)]
+    prefer_shorter: bool,
+
+    /// 最大スコアではなく、この目標に達する最短手数の手順を求める。
+    #[arg(long, value_enum, conflicts_with = "prefer_shorter")]
+    min_moves: Option<MoveTargetArg>,
+
+    /// 枝刈り用スコア閾値を初期盤面の上界からこの幅ずつ下げつつ、解が見つかるまで探索を繰り返す。
+    #[arg(long)]
+    widen_step: Option<Score>,
+
+    /// 探索時の着手の展開順序。
+    #[arg(long, value_enum, default_value_t = MoveOrderingArg::Natural)]
+    move_ordering: MoveOrderingArg,
+
+    /// 探索時に用いる上界関数。
+    #[arg(long, value_enum, default_value_t = BoundKindArg::PieceCount)]
+    bound: BoundKindArg,
+}
+
+/// `MoveOrdering` のコマンドライン表現。
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum MoveOrderingArg {
+    /// 合法手の列挙順。
+    Natural,
+    /// 消す駒数が多い手から。
+    LargestFirst,
+    /// 残り駒数が少ない駒種を消す手ほど後に。
+    RareColorsLast,
+    /// 暫定解の更新をもたらした手から (history heuristic)。
+    History,
+}
+
+impl From<MoveOrderingArg> for MoveOrdering {
+    fn from(arg: MoveOrderingArg) -> Self {
+        match arg {
+            MoveOrderingArg::Natural => Self::Natural,
+            MoveOrderingArg::LargestFirst => Self::LargestFirst,
+            MoveOrderingArg::RareColorsLast => Self::RareColorsLast,
+            MoveOrderingArg::History => Self::History,
+        }
+    }
+}
+
+/// `BoundKind` のコマンドライン表現。
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum BoundKindArg {
+    /// 駒種ごとの駒数のみによる上界。
+    PieceCount,
+    /// 決して消せない駒を除いた駒数による上界 (より厳しいが重い)。
+    HeightProfile,
+}
+
+impl From<BoundKindArg> for BoundKind {
+    fn from(arg: BoundKindArg) -> Self {
+        match arg {
+            BoundKindArg::PieceCount => Self::PieceCount,
+            BoundKindArg::HeightProfile => Self::HeightProfile,
+        }
+    }
+}
+
+/// `MoveTarget` のコマンドライン表現。
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum MoveTargetArg {
+    /// 任意の終了局面。
+    Terminal,
+    /// パーフェクト。
+    Perfect,
+}
+
+impl From<MoveTargetArg> for MoveTarget {
+    fn from(arg: MoveTargetArg) -> Self {
+        match arg {
+            MoveTargetArg::Terminal => Self::Terminal,
+            MoveTargetArg::Perfect => Self::Perfect,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct ParamArgs {
+    /// 盤面生成パラメータ (例: `0x1234,0x56,40,2`)。
+    param: RandomBoardParam,
+}
+
+#[derive(Debug, Args)]
+struct SolveAllArgs {
+    /// 最終スコアがこの値を超えないとわかったノードを枝刈りする。
+    #[arg(long, default_value_t = Score::ZERO)]
+    prune_score_max: Score,
+
+    /// 厳密求解の前に、この幅のビームサーチで閾値を引き上げる。
+    #[arg(long)]
+    beam_width: Option<NonZeroUsize>,
+
+    /// スレッド数。
+    #[arg(long, default_value_t = NonZeroUsize::MIN)]
+    threads: NonZeroUsize,
+
+    /// 結果レコードの出力ファイル。
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// 出力ファイルの処理済みパラメータをスキップして再開する。
+    #[arg(long, requires = "output")]
+    resume: bool,
+
+    /// 対象とする乱数生成器の内部状態の範囲 (例: `0x0000..=0x00FF`)。省略時は全範囲。
+    #[arg(long)]
+    rng_states: Option<ParamChunk>,
+
+    /// 同一の盤面を生成するパラメータたちは代表元のみを解く。
+    #[arg(long)]
+    canonical: bool,
+}
+
+#[derive(Debug, Args)]
+struct VerifyArgs {
+    /// 結果レコードのファイル (`-` なら標準入力)。
+    path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ReplayArgs {
+    #[command(flatten)]
+    source: BoardSource,
+
+    /// 手順 (例: `1,1 3,2`)。
+    solution: ActionHistory,
+}
+
+#[derive(Debug, Args)]
+struct SearchSeedsArgs {
+    /// 追加獲得スコア上界がこの値を超える盤面のみを対象とする。
+    #[arg(long, default_value_t = Score::ZERO)]
+    min_upper_bound: Score,
+
+    /// この幅のビームサーチのスコアが `min_beam_score` 以上の盤面のみを対象とする。
+    #[arg(long, requires = "min_beam_score")]
+    beam_width: Option<NonZeroUsize>,
+
+    /// ビームサーチのスコアの下限。
+    #[arg(long, requires = "beam_width")]
+    min_beam_score: Option<Score>,
+
+    /// 対象とする乱数生成器の内部状態の範囲 (例: `0x0000..=0x00FF`)。省略時は全範囲。
+    #[arg(long)]
+    rng_states: Option<ParamChunk>,
+
+    /// 実機 RTA で到達可能なパラメータのみを対象とする。電源投入から乱数確定までの最大フレーム数を指定する。
+    #[arg(long, conflicts_with = "rng_states")]
+    rta_max_frames: Option<u32>,
+
+    /// 出力するパラメータ数の上限。
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+/// 盤面を各行の文字列の JSON 配列 (上の行から順) として返す。
+fn json_board(board: &Board) -> String {
+    let rows: Vec<String> = board.to_string().lines().map(json_str).collect();
+    format!("[{}]", rows.join(","))
+}
+
+fn json_record(param: &RandomBoardParam, score: Score, solution: &ActionHistory) -> String {
+    format!(
+        "{{\"param\":{},\"score\":{score},\"solution\":{}}}",
+        json_str(&param.to_string()),
+        json_str(&solution.to_string())
+    )
+}
+
+fn main() -> ExitCode {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+
+    let cli = Cli::parse();
+
+    match run(&cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli) -> anyhow::Result<ExitCode> {
+    let format = cli.format;
+    let mut out = std::io::stdout().lock();
+
+    match &cli.command {
+        Command::Solve(args) => {
+            let board = args.source.load()?;
+            let mut solver = Solver::with_config(&SolverConfig {
+                prune_score_max: args.prune_score_max,
+                strategy: args.widen_step.map_or(SearchStrategy::DepthFirst, |step| {
+                    SearchStrategy::IterativeWidening { step }
+                }),
+                objective: match args.min_moves {
+                    Some(target) => Objective::MinMoves {
+                        target: target.into(),
+                    },
+                    None if args.prefer_shorter => Objective::MaxScoreThenMinMoves,
+                    None => Objective::MaxScore,
+                },
+                move_ordering: args.move_ordering.into(),
+                bound: args.bound.into(),
+                ..SolverConfig::default()
+            });
+
+            let (res, stats) = solver.solve_with_stats(board);
+            info!(
+                "nodes={} dp_hits={} dp_misses={} max_depth={} pruned={} elapsed={:.3}",
+                stats.nodes,
+                stats.dp_hits,
+                stats.dp_misses,
+                stats.max_depth,
+                stats.pruned(),
+                stats.elapsed.as_secs_f64()
+            );
+            let Some((score, solution)) = res else {
+                info!("NO SOLUTION");
+                return Ok(ExitCode::FAILURE);
+            };
+            match format {
+                OutputFormat::Text => writeln!(out, "{score}\t{solution}")?,
+                OutputFormat::Json => writeln!(
+                    out,
+                    "{{\"score\":{score},\"solution\":{},\"nodes\":{},\"dp_hits\":{},\"dp_misses\":{},\"max_depth\":{},\"pruned\":{},\"elapsed_secs\":{:.3}}}",
+                    json_str(&solution.to_string()),
+                    stats.nodes,
+                    stats.dp_hits,
+                    stats.dp_misses,
+                    stats.max_depth,
+                    stats.pruned(),
+                    stats.elapsed.as_secs_f64()
+                )?,
+            }
+        }
+
+        Command::GenBoard(ParamArgs { param }) => {
+            let (board, legal, rng_after) = param.gen_board();
+            let pos = Position::new(board.clone());
+            let piece_counts: Vec<String> = Piece::all()
+                .map(|piece| pos.piece_count(piece).to_string())
+                .collect();
+
+            match format {
+                OutputFormat::Text => {
+                    info!("legal: {legal}");
+                    info!("RNG after: 0x{:04X}", rng_after.state());
+                    info!("piece counts: [{}]", piece_counts.join(", "));
+                    info!("gain upper bound: {}", pos.gain_upper_bound());
+                    write!(out, "{board}")?;
+                }
+                OutputFormat::Json => writeln!(
+                    out,
+                    "{{\"param\":{},\"legal\":{legal},\"rng_after\":{},\"piece_counts\":[{}],\"gain_upper_bound\":{},\"board\":{}}}",
+                    json_str(&param.to_string()),
+                    rng_after.state(),
+                    piece_counts.join(","),
+                    pos.gain_upper_bound(),
+                    json_board(&board)
+                )?,
+            }
+        }
+
+        Command::SolveAll(args) => {
+            let chunk = args.rng_states.unwrap_or(ParamChunk::ALL);
+            let mut pipeline = if args.canonical {
+                Pipeline::new().params(chunk.params_canonical().map(|(param, _)| param))
+            } else {
+                Pipeline::new().params(chunk.params())
+            };
+            pipeline = pipeline
+                .solve(ExactConfig {
+                    prune_score_max: args.prune_score_max,
+                    ..ExactConfig::default()
+                })
+                .threads(args.threads.get())
+                .resume(args.resume);
+            if let Some(width) = args.beam_width {
+                pipeline = pipeline.presolve(BeamSearch::new(width.get()));
+            }
+            if let Some(path) = &args.output {
+                pipeline = pipeline.export(ExportFormat::Tsv, path);
+            }
+
+            let summary = pipeline.run()?;
+            let best = summary
+                .best_score
+                .map_or_else(|| "null".to_owned(), |score| score.to_string());
+            match format {
+                OutputFormat::Text => writeln!(
+                    out,
+                    "params={} illegal={} filtered={} unsolved={} solved={} best={best}",
+                    summary.param_count,
+                    summary.illegal_count,
+                    summary.filtered_count,
+                    summary.unsolved_count,
+                    summary.solved_count
+                )?,
+                OutputFormat::Json => writeln!(
+                    out,
+                    "{{\"params\":{},\"illegal\":{},\"filtered\":{},\"unsolved\":{},\"solved\":{},\"best\":{best}}}",
+                    summary.param_count,
+                    summary.illegal_count,
+                    summary.filtered_count,
+                    summary.unsolved_count,
+                    summary.solved_count
+                )?,
+            }
+        }
+
+        Command::Dedup => {
+            let mut map = U64HashMap::<u64, RandomBoardParam>::default();
+            for (param, board, _rng_after) in enumerate_all_legal_board() {
+                let key = Position::new(board.clone()).key();
+                let unique = match map.entry(key) {
+                    Entry::Occupied(entry) => entry.get().gen_board().0 != board,
+                    Entry::Vacant(entry) => {
+                        entry.insert(param.clone());
+                        true
+                    }
+                };
+                if !unique {
+                    continue;
+                }
+                match format {
+                    OutputFormat::Text => writeln!(out, "{param}")?,
+                    OutputFormat::Json => {
+                        writeln!(out, "{{\"param\":{}}}", json_str(&param.to_string()))?
+                    }
+                }
+            }
+        }
+
+        Command::Schema => match format {
+            OutputFormat::Text => {
+                for schema in all_schemas() {
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}",
+                        schema.name, schema.version, schema.description
+                    )?;
+                }
+            }
+            OutputFormat::Json => {
+                for schema in all_schemas() {
+                    writeln!(out, "{}", schema.to_json())?;
+                }
+            }
+        },
+
+        Command::Verify(VerifyArgs { path }) => {
+            let rdr: Box<dyn std::io::BufRead> = if path == Path::new("-") {
+                Box::new(std::io::stdin().lock())
+            } else {
+                let file = std::fs::File::open(path)
+                    .with_context(|| format!("ファイル '{}' を開けない", path.display()))?;
+                Box::new(std::io::BufReader::new(file))
+            };
+
+            let mut records = Vec::new();
+            for line in rdr.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: ResultRecord = line.parse()?;
+                records.push((record.param, record.score, record.solution));
+            }
+
+            let report = verify_batch(records);
+            match format {
+                OutputFormat::Text => write!(out, "{report}")?,
+                OutputFormat::Json => {
+                    let failures: Vec<String> = report
+                        .failures
+                        .iter()
+                        .map(|(param, failure)| {
+                            format!(
+                                "{{\"param\":{},\"failure\":{}}}",
+                                json_str(&param.to_string()),
+                                json_str(&failure.to_string())
+                            )
+                        })
+                        .collect();
+                    writeln!(
+                        out,
+                        "{{\"records\":{},\"passed\":{},\"failures\":[{}]}}",
+                        report.record_count,
+                        report.passed_count(),
+                        failures.join(",")
+                    )?;
+                }
+            }
+
+            if !report.is_ok() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+
+        Command::Replay(args) => {
+            let board = args.source.load()?;
+            let mut pos = Position::new(board);
+            let mut score = Score::ZERO;
+
+            for (i, &sq) in args.solution.iter().enumerate() {
+                let action = Action::from_board_square(pos.board(), sq)
+                    .with_context(|| format!("{} 手目 {sq} は合法手でない", i + 1))?;
+                score += action.gain();
+                pos = pos.do_action(&action);
+
+                match format {
+                    OutputFormat::Text => {
+                        writeln!(out, "#{} {sq} +{} = {score}", i + 1, action.gain())?;
+                        write!(out, "{}", pos.board())?;
+                    }
+                    OutputFormat::Json => writeln!(
+                        out,
+                        "{{\"move\":{},\"square\":{},\"gain\":{},\"score\":{score},\"board\":{}}}",
+                        i + 1,
+                        json_str(&sq.to_string()),
+                        action.gain(),
+                        json_board(pos.board())
+                    )?,
+                }
+            }
+
+            if !pos.has_action() {
+                let bonus = GameTerminalEval.final_gain(&pos);
+                score += bonus;
+                match format {
+                    OutputFormat::Text => writeln!(out, "final +{bonus} = {score}")?,
+                    OutputFormat::Json => {
+                        writeln!(out, "{{\"final_bonus\":{bonus},\"score\":{score}}}")?
+                    }
+                }
+            }
+        }
+
+        Command::SearchSeeds(args) => {
+            let beam = args.beam_width.map(|width| BeamSearch::new(width.get()));
+            let mut found = 0;
+
+            let params: Box<dyn Iterator<Item = RandomBoardParam>> = match args.rta_max_frames {
+                Some(max_frames) => Box::new(
+                    RtaReachableParams {
+                        max_frames,
+                        ..RtaReachableParams::default()
+                    }
+                    .params(),
+                ),
+                None => Box::new(args.rng_states.unwrap_or(ParamChunk::ALL).params()),
+            };
+
+            for param in params {
+                if args.limit.is_some_and(|limit| found >= limit) {
+                    break;
+                }
+                let Some((board, _)) = param.gen_legal_board() else {
+                    continue;
+                };
+                let ub = Position::new(board.clone()).gain_upper_bound();
+                if ub <= args.min_upper_bound {
+                    continue;
+                }
+                let beam_res = match (&beam, args.min_beam_score) {
+                    (Some(beam), Some(min_score)) => {
+                        let res = beam.search(board);
+                        if res.0 < min_score {
+                            continue;
+                        }
+                        Some(res)
+                    }
+                    _ => None,
+                };
+
+                found += 1;
+                match (format, beam_res) {
+                    (OutputFormat::Text, None) => writeln!(out, "{param}\t{ub}")?,
+                    (OutputFormat::Text, Some((score, solution))) => {
+                        writeln!(out, "{param}\t{ub}\t{score}\t{solution}")?
+                    }
+                    (OutputFormat::Json, None) => writeln!(
+                        out,
+                        "{{\"param\":{},\"upper_bound\":{ub}}}",
+                        json_str(&param.to_string())
+                    )?,
+                    (OutputFormat::Json, Some((score, solution))) => {
+                        writeln!(out, "{}", json_record(&param, score, &solution))?
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
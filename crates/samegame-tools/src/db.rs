@@ -0,0 +1,272 @@
+//! 探索結果データベース関連。
+//!
+//! 盤面生成パラメータ全探索の結果 (パラメータ, 盤面のハッシュ値, スコア, 解) をファイルに永続化する。
+//! 全探索を中断しても、同じファイルを開き直せば続きから再開できる。
+
+use std::collections::hash_map::Entry;
+use std::fs::File;
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context as _};
+use samegame_core::{Board, Score};
+
+use crate::record::{ResultRecord, ScanProgress};
+use samegame_solver::Position;
+use samegame_solver::U64HashMap;
+
+const MAGIC: &str = "samegame-db";
+
+/// 探索結果データベース。
+///
+/// ファイル形式は、1 行目が `samegame-db バージョン` のヘッダで、以降 1 行 1 レコードで
+/// `key rng_state nmi_counter nmi_timing entropy score solution` をタブ区切りで並べたもの。
+/// `key` は盤面のハッシュ値 (`Position::key()`) の 16 進 16 桁で、残りは `ResultRecord` の文字列形式と同じ。
+///
+/// レコードは追記のみで、同一の盤面のレコードは 1 つだけを有効とする (盤面のハッシュ値で重複を判定する)。
+/// 異なるパラメータが同一の盤面を生成する場合も同様で、スコアが真に高いレコードが後から来たときのみ置き換える。
+/// `open()` 時にファイル全体を読み込み、盤面のハッシュ値からレコードへの索引と全探索の進捗 (`ScanProgress`) を作る。
+/// 書き込み途中で中断された末尾の不完全な行 (改行で終わらない行) は切り詰める。
+#[derive(Debug)]
+pub struct ResultDb {
+    file: File,
+    /// 盤面ごとに有効なレコードのみを、その盤面が最初に登録された順に並べたもの。
+    records: Vec<ResultRecord>,
+    /// 盤面のハッシュ値から `records` 内の位置への索引。
+    index: U64HashMap<u64, usize>,
+    progress: ScanProgress,
+}
+
+impl ResultDb {
+    /// データベースファイルの形式のバージョン。
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// データベースファイル `path` を開く。存在しなければ新規作成する。
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("データベースファイル '{}' を開けない", path.display()))?;
+
+        let mut this = Self {
+            file: file.try_clone()?,
+            records: Vec::new(),
+            index: U64HashMap::default(),
+            progress: ScanProgress::default(),
+        };
+
+        let mut rdr = BufReader::new(&mut file);
+        let mut header = String::new();
+        rdr.read_line(&mut header)?;
+        if header.is_empty() {
+            writeln!(this.file, "{MAGIC}\t{}", Self::FORMAT_VERSION)?;
+            this.file.flush()?;
+            return Ok(this);
+        }
+        let version = header
+            .trim_end()
+            .strip_prefix(MAGIC)
+            .and_then(|s| s.strip_prefix('\t'))
+            .with_context(|| {
+                format!(
+                    "データベースファイル '{}' の形式が無効 (ヘッダ不一致)",
+                    path.display()
+                )
+            })?;
+        ensure!(
+            version == Self::FORMAT_VERSION.to_string(),
+            "データベースファイルのバージョンが一致しない (対応: {}, 入力: {version})",
+            Self::FORMAT_VERSION
+        );
+
+        let mut len_valid = header.len() as u64;
+        let mut line = String::new();
+        for i in 2.. {
+            line.clear();
+            if rdr.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+                break;
+            }
+            let (key, record) = parse_line(line.trim_end_matches('\n')).with_context(|| {
+                format!(
+                    "データベースファイル '{}' の {i} 行目のパースに失敗",
+                    path.display()
+                )
+            })?;
+            this.add(key, record);
+            len_valid += line.len() as u64;
+        }
+
+        // 不完全な行の後ろに追記しないよう、切り詰めておく。
+        if file.metadata()?.len() != len_valid {
+            file.set_len(len_valid)?;
+        }
+
+        Ok(this)
+    }
+
+    /// 登録されているレコード数を返す。
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// 登録されているレコードがないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// 登録されているレコードたちを登録順に列挙する。
+    pub fn records(&self) -> impl ExactSizeIterator<Item = &ResultRecord> {
+        self.records.iter()
+    }
+
+    /// 全探索の進捗を返す。`ScanProgress::is_processed()` で処理済みのパラメータをスキップすれば再開できる。
+    pub fn progress(&self) -> &ScanProgress {
+        &self.progress
+    }
+
+    /// 登録されている中での最大スコアを返す。
+    pub fn best_score(&self) -> Option<Score> {
+        self.progress.best_score
+    }
+
+    /// 盤面 `board` のレコードを引く。登録されていなければ `None` を返す。
+    pub fn lookup(&self, board: &Board) -> Option<&ResultRecord> {
+        let key = Position::new(board.clone()).key();
+        let record = &self.records[*self.index.get(&key)?];
+
+        // ハッシュ値が衝突しているなら別の盤面。
+        (record.param.gen_board().0 == *board).then_some(record)
+    }
+
+    /// レコード `record` を登録し、直ちにファイルへ追記する。登録 (置き換え) したかどうかを返す。
+    ///
+    /// 同一の盤面のレコードが既にあれば、スコアが真に高いときのみ置き換える。
+    /// 登録しなかったレコードは全探索の進捗にも反映しない。
+    /// パラメータがゲーム内に現れない盤面を生成する場合や、盤面のハッシュ値が衝突した場合はエラーを返す。
+    pub fn insert(&mut self, record: ResultRecord) -> anyhow::Result<bool> {
+        let Some((board, _)) = record.param.gen_legal_board() else {
+            bail!("パラメータ {} の盤面はゲーム内に現れない", record.param);
+        };
+        let key = Position::new(board.clone()).key();
+
+        if let Some(&i) = self.index.get(&key) {
+            let existing = &self.records[i];
+            ensure!(
+                existing.param.gen_board().0 == board,
+                "盤面のハッシュ値が衝突した: {} と {}",
+                existing.param,
+                record.param
+            );
+            if existing.score >= record.score {
+                return Ok(false);
+            }
+        }
+
+        writeln!(self.file, "{key:016X}\t{record}")?;
+        self.file.flush()?;
+        self.add(key, record);
+
+        Ok(true)
+    }
+
+    /// ファイルに書かれているレコードを反映する。同一の盤面のレコードがあれば、スコアが真に高いときのみ置き換える。
+    fn add(&mut self, key: u64, record: ResultRecord) {
+        match self.index.entry(key) {
+            Entry::Occupied(entry) => {
+                let existing = &mut self.records[*entry.get()];
+                if existing.score < record.score {
+                    self.progress.add(&record);
+                    *existing = record;
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(self.records.len());
+                self.progress.add(&record);
+                self.records.push(record);
+            }
+        }
+    }
+}
+
+/// データベースファイルの 1 行をパースし、(盤面のハッシュ値, レコード) を返す。
+fn parse_line(line: &str) -> anyhow::Result<(u64, ResultRecord)> {
+    let (key, record) = line.split_once('\t').context("フィールドが足りない")?;
+    let key = u64::from_str_radix(key, 16).with_context(|| format!("key が無効: '{key}'"))?;
+    let record: ResultRecord = record.parse()?;
+
+    Ok((key, record))
+}
+
+#[cfg(test)]
+mod tests {
+    use samegame_core::{ActionHistory, RandomBoardParam};
+
+    use super::*;
+
+    #[test]
+    fn test_result_db() {
+        let path =
+            std::env::temp_dir().join(format!("samegame-db-test-{}.tsv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let params: Vec<RandomBoardParam> = RandomBoardParam::all_canonical_in(0x1234..=0x1234)
+            .map(|(param, _)| param)
+            .filter(|param| param.gen_legal_board().is_some())
+            .take(2)
+            .collect();
+        // 解の内容は検証しないので空で済ませる。
+        let record = |i: usize, score: u32| ResultRecord {
+            param: params[i].clone(),
+            score: Score::new(score),
+            solution: ActionHistory::new(),
+        };
+
+        {
+            let mut db = ResultDb::open(&path).unwrap();
+            assert!(db.is_empty());
+            assert!(db.insert(record(0, 500)).unwrap());
+            assert!(db.insert(record(1, 600)).unwrap());
+            // 同一盤面のレコードはスコアが真に高いときのみ置き換える。
+            assert!(!db.insert(record(1, 600)).unwrap());
+            assert!(db.insert(record(0, 700)).unwrap());
+            assert_eq!(db.len(), 2);
+            assert_eq!(db.best_score(), Some(Score::new(700)));
+        }
+
+        // 書き込み途中で中断された行は捨てる。
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        write!(file, "0123").unwrap();
+        drop(file);
+
+        let mut db = ResultDb::open(&path).unwrap();
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.best_score(), Some(Score::new(700)));
+        assert!(db.progress().is_processed(&params[0]));
+        assert!(db.progress().is_processed(&params[1]));
+        let (board, _) = params[0].gen_legal_board().unwrap();
+        assert_eq!(db.lookup(&board).unwrap().score, Score::new(700));
+
+        // 切り詰めた後に追記できる。
+        assert!(db.insert(record(1, 800)).unwrap());
+        drop(db);
+        let db = ResultDb::open(&path).unwrap();
+        assert_eq!(
+            db.records()
+                .map(|record| record.score.to_inner())
+                .collect::<Vec<_>>(),
+            [700, 800]
+        );
+
+        std::fs::write(&path, "samegame-db\t2\n").unwrap();
+        assert!(ResultDb::open(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+//! 盤面の異常検出。
+//!
+//! スクリーンショットから起こした盤面や手入力した盤面を長時間のソルバー実行に投入する前に、
+//! 入力ミスや破損がないかを確かめるのに使う。
+
+use samegame_core::{
+    Board, Col, GameEntropy, GameRng, Piece, RandomBoardParam, Row, RuleSet, Square,
+};
+
+/// 盤面の異常。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BoardAnomaly {
+    /// 真下のマスが空白なのに駒がある (重力に反している)。
+    FloatingPiece { sq: Square },
+    /// 右側に空でない列があるのに空になっている列 (左詰めになっていない)。
+    ColumnGap { col: Col },
+    /// 同種駒の個数が多すぎる。
+    /// ゲーム内ではこの個数に達する盤面は再生成されるし、駒数はゲーム中に増えないので、どの時点でも現れない。
+    TooManyPieces { piece: Piece, count: u32 },
+    /// 駒が埋まった盤面だが、どの盤面生成パラメータからも生成されない。
+    NotGeneratable,
+}
+
+impl std::fmt::Display for BoardAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FloatingPiece { sq } => write!(f, "マス {sq} の駒が浮いている (真下が空白)"),
+            Self::ColumnGap { col } => write!(f, "列 {col} が空だが、その右側に駒がある"),
+            Self::TooManyPieces { piece, count } => write!(
+                f,
+                "駒 {} が {count} 個ある (ゲーム内では {} 個以上にならない)",
+                piece.to_inner(),
+                RuleSet::GAME.regen_piece_count
+            ),
+            Self::NotGeneratable => write!(f, "どの盤面生成パラメータからも生成されない盤面"),
+        }
+    }
+}
+
+/// 盤面 `board` の異常を検出し、見つかったものを全て返す。異常がなければ空の `Vec` を返す。
+///
+/// 以下を調べる:
+///
+/// * 重力違反 (浮いている駒、左詰めになっていない列)。
+/// * ゲーム内で現れえない同種駒の個数。
+/// * 駒が埋まった盤面 (初期盤面とみなす) の場合、乱数生成器による生成可能性。
+///
+/// 生成可能性の判定は全パラメータを走査するので重い (リリースビルドで 1 秒程度)。
+/// 他の異常が見つかった場合は判定を省略する。
+/// NMI 発生タイミングは 40 固定としている (`RandomBoardParam::all()` と同じ)。
+pub fn diagnose_board(board: &Board) -> Vec<BoardAnomaly> {
+    let mut anomalies = Vec::new();
+
+    let mut seen_empty_col = None;
+    for col in Col::all() {
+        let col_empty = Row::all().all(|row| board.get(Square::new(col, row)).is_none());
+        if col_empty {
+            seen_empty_col.get_or_insert(col);
+            continue;
+        }
+        if let Some(col_empty) = seen_empty_col.take() {
+            anomalies.push(BoardAnomaly::ColumnGap { col: col_empty });
+        }
+
+        let mut below_blank = false;
+        for row in Row::all() {
+            let sq = Square::new(col, row);
+            match board.get(sq) {
+                Some(_) if below_blank => anomalies.push(BoardAnomaly::FloatingPiece { sq }),
+                Some(_) => {}
+                None => below_blank = true,
+            }
+        }
+    }
+
+    let regen_piece_count = u32::from(RuleSet::GAME.regen_piece_count);
+    for piece in Piece::all() {
+        let count = board.piece_count(piece);
+        if count >= regen_piece_count {
+            anomalies.push(BoardAnomaly::TooManyPieces { piece, count });
+        }
+    }
+
+    if anomalies.is_empty()
+        && board.piece_count_total() as usize == Square::NUM
+        && find_generating_param(board).is_none()
+    {
+        anomalies.push(BoardAnomaly::NotGeneratable);
+    }
+
+    anomalies
+}
+
+/// 駒が埋まった盤面 `board` を生成するパラメータを 1 つ探す。見つからなければ `None` を返す。
+///
+/// ゲーム内エントロピーは乱数列には影響しないので、(内部状態, NMI カウンタ) ごとに乱数列を 1 度だけ生成し、
+/// 全エントロピーについて同時に照合する。全エントロピーで食い違った時点で打ち切る。
+fn find_generating_param(board: &Board) -> Option<RandomBoardParam> {
+    const NMI_TIMING: usize = 40;
+
+    let target: [Option<Piece>; Square::NUM] =
+        std::array::from_fn(|idx| board.get(Square::from_game_index(idx).unwrap()));
+
+    for rng_state in 0..=0x7FFF {
+        for nmi_counter in 0..=u8::MAX {
+            let mut rng = GameRng::new(rng_state);
+            let mut alive = [true; GameEntropy::NUM];
+
+            for (idx, &want) in target.iter().enumerate() {
+                let nmi = if idx < NMI_TIMING {
+                    nmi_counter
+                } else {
+                    nmi_counter.wrapping_add(1)
+                };
+                for entropy in GameEntropy::all() {
+                    let alive = &mut alive[usize::from(entropy.to_inner())];
+                    if *alive {
+                        let mut rng_tmp = rng;
+                        *alive = Some(rng_tmp.gen_piece(nmi, entropy)) == want;
+                    }
+                }
+                if !alive.contains(&true) {
+                    break;
+                }
+                rng.gen(nmi);
+            }
+
+            if let Some(entropy) = GameEntropy::all().find(|e| alive[usize::from(e.to_inner())]) {
+                return Some(
+                    RandomBoardParam::new(rng_state, nmi_counter, NMI_TIMING, entropy).unwrap(),
+                );
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use samegame_core::{COL_2, ROW_4};
+
+    #[test]
+    fn test_diagnose_board() {
+        // 生成可能な盤面。
+        let param: RandomBoardParam = "0x0012,0x34,40,3".parse().unwrap();
+        let (board, _) = param.gen_legal_board().unwrap();
+        assert_eq!(diagnose_board(&board), []);
+        let found = find_generating_param(&board).unwrap();
+        assert_eq!(found.gen_board().0, board);
+
+        // 途中の盤面は生成可能性を問わない。
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+        assert_eq!(diagnose_board(&board), []);
+
+        // 浮いた駒。
+        let board: Board = indoc! {"
+            ........
+            ........
+            .3......
+            1.......
+            12.5....
+            21155...
+        "}
+        .parse()
+        .unwrap();
+        assert_eq!(
+            diagnose_board(&board),
+            [BoardAnomaly::FloatingPiece {
+                sq: Square::new(COL_2, ROW_4)
+            }]
+        );
+
+        // 同種駒が多すぎる。
+        let board: Board = indoc! {"
+            11111111
+            11111111
+            11111111
+            22222222
+            33333333
+            44444444
+        "}
+        .parse()
+        .unwrap();
+        let anomalies = diagnose_board(&board);
+        assert_eq!(
+            anomalies,
+            [BoardAnomaly::TooManyPieces {
+                piece: Piece::MIN,
+                count: 24
+            }]
+        );
+        assert!(anomalies[0].to_string().contains("24"));
+
+        // 列の隙間 (通常の構築手段では作れないので、左詰めしない消去で作る)。
+        let board: Board = indoc! {"
+            ........
+            ........
+            ........
+            ........
+            ........
+            1223....
+        "}
+        .parse()
+        .unwrap();
+        let mb = board.piece_mask(Piece::from_inner(2).unwrap());
+        let board = board.erase_without_compaction(&mb);
+        assert_eq!(
+            diagnose_board(&board),
+            [BoardAnomaly::ColumnGap { col: COL_2 }]
+        );
+    }
+
+    #[test]
+    #[ignore = "全パラメータを走査するので遅い"]
+    fn test_diagnose_board_not_generatable() {
+        let board: Board = indoc! {"
+            12345123
+            45123451
+            23451234
+            51234512
+            34512345
+            12345123
+        "}
+        .parse()
+        .unwrap();
+        assert_eq!(diagnose_board(&board), [BoardAnomaly::NotGeneratable]);
+    }
+}
@@ -0,0 +1,335 @@
+//! 全盤面の最適スコア分布関連。
+//!
+//! 全ての盤面生成パラメータの面を厳密に解き、最適スコアごとに面の数と代表となるパラメータをまとめた表を作る。
+//! 求解は `Pipeline` で行い、その結果ファイルから分布を集計するので、中断しても結果ファイルから再開できる。
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use anyhow::Context as _;
+use samegame_core::{CanonicalIndex, RandomBoardParam, Score};
+
+use crate::pipeline::{ExactConfig, ExportFormat, Pipeline, PipelineSummary};
+use crate::record::ResultRecord;
+use samegame_solver::{NullProgress, ProgressSink};
+
+/// 最適スコアが同じ面たちの集計。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScoreClass {
+    /// 相異なる盤面の数 (集計したレコード数)。
+    pub board_count: u64,
+    /// 盤面生成パラメータの数 (各レコードを同値なパラメータ数で重み付けしたもの)。
+    pub seed_count: u64,
+    /// 代表となるパラメータたち (昇順に最小のものから、最大で代表数の上限個)。
+    pub representatives: Vec<RandomBoardParam>,
+}
+
+/// 最適スコアの分布。
+///
+/// 各レコードは `CanonicalIndex` の代表元について厳密に解いた結果であるとみなし、
+/// 同値なパラメータ数 (`CanonicalIndex::multiplicity()`) で重み付けしてパラメータ数を数える。
+///
+/// 表形式はタブ区切りで、ヘッダ行 `score boards seeds representatives` に続けてスコア降順に 1 行ずつ出力する。
+/// `representatives` は代表パラメータを空白区切りで並べたもの。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScoreDistribution {
+    classes: BTreeMap<Score, ScoreClass>,
+    representative_count: usize,
+}
+
+impl ScoreDistribution {
+    /// 各スコアの代表パラメータを最大 `representative_count` 個持つ、空の分布を作る。
+    pub fn new(representative_count: usize) -> Self {
+        Self {
+            classes: BTreeMap::new(),
+            representative_count,
+        }
+    }
+
+    /// 集計したスコアの種類数を返す。
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    /// 何も集計していないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// スコア `score` の集計を返す。
+    pub fn get(&self, score: Score) -> Option<&ScoreClass> {
+        self.classes.get(&score)
+    }
+
+    /// (スコア, 集計) の組たちをスコア降順に列挙する。
+    pub fn iter(&self) -> impl Iterator<Item = (Score, &ScoreClass)> {
+        self.classes
+            .iter()
+            .rev()
+            .map(|(&score, class)| (score, class))
+    }
+
+    /// 集計した盤面の総数を返す。
+    pub fn board_count(&self) -> u64 {
+        self.classes.values().map(|class| class.board_count).sum()
+    }
+
+    /// 集計したパラメータの総数を返す。
+    pub fn seed_count(&self) -> u64 {
+        self.classes.values().map(|class| class.seed_count).sum()
+    }
+
+    /// 最適スコアが `score` である、`seed_count` 個のパラメータを代表するパラメータ `param` の面を 1 つ集計する。
+    pub fn add(&mut self, param: &RandomBoardParam, score: Score, seed_count: u64) {
+        let class = self.classes.entry(score).or_default();
+        class.board_count += 1;
+        class.seed_count += seed_count;
+        self.push_representative(score, param);
+    }
+
+    /// 結果レコード `record` を集計する。同値なパラメータ数は索引 `index` から求める。
+    pub fn add_record(&mut self, record: &ResultRecord, index: &CanonicalIndex) {
+        let seed_count = u64::from(index.multiplicity(&record.param));
+
+        self.add(&record.param, record.score, seed_count);
+    }
+
+    /// 分布 `other` を取り込む (分割して集計したものをまとめる)。同じ面を重複して集計しないこと。
+    pub fn merge(&mut self, other: &Self) {
+        for (&score, other_class) in &other.classes {
+            let class = self.classes.entry(score).or_default();
+            class.board_count += other_class.board_count;
+            class.seed_count += other_class.seed_count;
+            for param in &other_class.representatives {
+                self.push_representative(score, param);
+            }
+        }
+    }
+
+    /// 結果ファイルの内容から、同値なパラメータ数を索引 `index` から求めて分布を集計する。空行は無視する。
+    pub fn from_result_reader(
+        rdr: impl BufRead,
+        index: &CanonicalIndex,
+        representative_count: usize,
+    ) -> anyhow::Result<Self> {
+        let mut this = Self::new(representative_count);
+
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ResultRecord = line
+                .parse()
+                .with_context(|| format!("結果ファイルの {} 行目のパースに失敗", i + 1))?;
+            this.add_record(&record, index);
+        }
+
+        Ok(this)
+    }
+
+    /// 結果ファイル `path` から、同値なパラメータ数を索引 `index` から求めて分布を集計する。
+    pub fn from_result_file(
+        path: impl AsRef<Path>,
+        index: &CanonicalIndex,
+        representative_count: usize,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("結果ファイル '{}' を開けない", path.display()))?;
+
+        Self::from_result_reader(std::io::BufReader::new(file), index, representative_count)
+    }
+
+    /// 表形式で書き出す。
+    pub fn write_tsv(&self, mut wtr: impl Write) -> anyhow::Result<()> {
+        writeln!(wtr, "score\tboards\tseeds\trepresentatives")?;
+        for (score, class) in self.iter() {
+            write!(
+                wtr,
+                "{score}\t{}\t{}\t",
+                class.board_count, class.seed_count
+            )?;
+            for (i, param) in class.representatives.iter().enumerate() {
+                if i != 0 {
+                    wtr.write_all(b" ")?;
+                }
+                write!(wtr, "{param}")?;
+            }
+            writeln!(wtr)?;
+        }
+
+        Ok(())
+    }
+
+    /// スコア `score` の代表パラメータたちに `param` を加え、小さい方から上限個だけ残す。
+    fn push_representative(&mut self, score: Score, param: &RandomBoardParam) {
+        let reps = &mut self.classes.get_mut(&score).unwrap().representatives;
+        if let Err(i) = reps.binary_search(param) {
+            if i < self.representative_count {
+                reps.insert(i, param.clone());
+                reps.truncate(self.representative_count);
+            }
+        }
+    }
+}
+
+/// `export_score_distribution()` の設定。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DistributionConfig {
+    /// 各スコアの代表パラメータの最大個数。
+    pub representative_count: usize,
+    /// 並列に解くスレッド数。
+    pub thread_count: usize,
+}
+
+impl Default for DistributionConfig {
+    fn default() -> Self {
+        Self {
+            representative_count: 8,
+            thread_count: 1,
+        }
+    }
+}
+
+/// パラメータたち `params` の面を全て厳密に解いて最適スコアの分布を集計し、表を `output` に書き出す。
+///
+/// `params` は索引 `index` の代表元たち (`CanonicalIndex::params()` の部分列) を昇順に与えること。
+/// パラメータ数は `index` の同値なパラメータ数で重み付けする。
+/// 各面の最適解は結果ファイル `results` に `Pipeline` の形式で書き出す。`results` が既に存在すれば、
+/// 処理済みのパラメータをスキップして再開する (`Pipeline::resume()`)。
+/// 分布は再開前の分も含め `results` 全体から集計する。
+///
+/// 各面の枝刈り用スコア閾値は 0 に固定し、これまでの最大スコアで引き上げないので、全ての面で最適値が求まる
+/// (その分、最大スコアのみを求める全探索よりずっと重い)。
+pub fn export_score_distribution(
+    params: impl IntoIterator<Item = RandomBoardParam, IntoIter: Send + 'static>,
+    index: &CanonicalIndex,
+    results: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    config: &DistributionConfig,
+) -> anyhow::Result<(ScoreDistribution, PipelineSummary)> {
+    export_score_distribution_with_progress(params, index, results, output, config, NullProgress)
+}
+
+/// `export_score_distribution()` と同様だが、求解の進捗を `progress` に通知する。
+pub fn export_score_distribution_with_progress(
+    params: impl IntoIterator<Item = RandomBoardParam, IntoIter: Send + 'static>,
+    index: &CanonicalIndex,
+    results: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    config: &DistributionConfig,
+    progress: impl ProgressSink + Send + Sync + 'static,
+) -> anyhow::Result<(ScoreDistribution, PipelineSummary)> {
+    let results = results.as_ref();
+    let output = output.as_ref();
+
+    let summary = Pipeline::new()
+        .params(params)
+        .solve(ExactConfig {
+            prune_score_max: Score::ZERO,
+            raise_threshold: false,
+            ..Default::default()
+        })
+        .export(ExportFormat::Tsv, results)
+        .resume(true)
+        .threads(config.thread_count)
+        .progress(progress)
+        .run()?;
+
+    let distribution =
+        ScoreDistribution::from_result_file(results, index, config.representative_count)?;
+
+    let file = std::fs::File::create(output)
+        .with_context(|| format!("出力ファイル '{}' を作れない", output.display()))?;
+    let mut wtr = std::io::BufWriter::new(file);
+    distribution.write_tsv(&mut wtr)?;
+    wtr.flush()?;
+
+    Ok((distribution, summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use samegame_core::ActionHistory;
+
+    use super::*;
+
+    #[test]
+    fn test_score_distribution() {
+        let index = CanonicalIndex::new(0x1234..=0x1234);
+        let params: Vec<RandomBoardParam> =
+            index.params().take(5).map(|(param, _)| param).collect();
+        let record = |i: usize, score: u32| ResultRecord {
+            param: params[i].clone(),
+            score: Score::new(score),
+            solution: ActionHistory::new(),
+        };
+        let records = [
+            record(0, 500),
+            record(1, 700),
+            record(2, 500),
+            record(3, 500),
+            record(4, 600),
+        ];
+
+        let mut dist = ScoreDistribution::new(2);
+        for record in &records[..3] {
+            dist.add_record(record, &index);
+        }
+        let mut rest = ScoreDistribution::new(2);
+        for record in &records[3..] {
+            rest.add_record(record, &index);
+        }
+        dist.merge(&rest);
+
+        assert_eq!(dist.len(), 3);
+        assert_eq!(dist.board_count(), 5);
+        let class = dist.get(Score::new(500)).unwrap();
+        assert_eq!(class.board_count, 3);
+        assert_eq!(
+            class.representatives,
+            [params[0].clone(), params[2].clone()]
+        );
+        assert_eq!(
+            dist.iter()
+                .map(|(score, _)| score.to_inner())
+                .collect::<Vec<_>>(),
+            [700, 600, 500]
+        );
+
+        // パラメータ数は同値なパラメータ数で重み付けされる。
+        let seed_count: u64 = index.params().take(5).map(|(_, n)| u64::from(n)).sum();
+        assert_eq!(dist.seed_count(), seed_count);
+
+        // 結果ファイルが揃っていれば何も解かずに再開し、結果ファイル全体から集計する。
+        let dir = std::env::temp_dir();
+        let results = dir.join(format!("samegame-dist-test-{}.tsv", std::process::id()));
+        let output = dir.join(format!("samegame-dist-test-{}.out.tsv", std::process::id()));
+        let src: String = records.iter().map(|record| format!("{record}\n")).collect();
+        std::fs::write(&results, src).unwrap();
+        let (exported, summary) = export_score_distribution(
+            params.clone(),
+            &index,
+            &results,
+            &output,
+            &DistributionConfig {
+                representative_count: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(summary.param_count, 0);
+        assert_eq!(exported, dist);
+
+        let table = std::fs::read_to_string(&output).unwrap();
+        let mut lines = table.lines();
+        assert_eq!(lines.next(), Some("score\tboards\tseeds\trepresentatives"));
+        assert!(lines.next().unwrap().starts_with("700\t1\t"));
+        assert_eq!(lines.count(), 2);
+
+        std::fs::remove_file(&results).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+//! 公式記録 (ゲーム内に現れうる全ての面の中での最大スコア) の探索手順関連。
+//!
+//! 公開している最大スコアの主張を、サンプルプログラムを決まった順に動かすといった手作業ではなく、
+//! `record_hunt()` を 1 回呼ぶだけで再現できるようにする。手順は以下の通り:
+//!
+//! 1. 盤面生成パラメータを同値類の代表元 (`RandomBoardParam::all_canonical()`) のみ昇順に列挙する。
+//! 2. ゲーム内に現れない盤面と、先に列挙したパラメータと同一の盤面を除く。
+//! 3. 残りの面を `Pipeline` で厳密に解く。スレッド間で暫定最大スコアを共有し、それ未満の解は枝刈りする。
+//! 4. 結果ファイルの全レコードを盤面の再生成と手順の再生により検証する。
+//! 5. 最大スコアと、それを達成する面のパラメータたちを記録ファイルに書き出す。
+
+use std::collections::hash_map::Entry;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context as _};
+use samegame_core::{RandomBoardParam, Score};
+
+use crate::pipeline::{ExactConfig, ExportFormat, Pipeline, PipelineSummary};
+use crate::record::ResultRecord;
+use samegame_solver::Position;
+use samegame_solver::U64HashMap;
+use samegame_solver::{verify_batch, VerificationReport};
+use samegame_solver::{NullProgress, ProgressSink};
+
+const MAGIC: &str = "samegame-record";
+
+/// 結果ファイルのファイル名。
+pub const HUNT_RESULTS_FILE: &str = "results.tsv";
+
+/// 記録ファイルのファイル名。
+pub const HUNT_RECORD_FILE: &str = "record.txt";
+
+/// `record_hunt()` の設定。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HuntConfig {
+    /// 対象とする乱数生成器の内部状態の範囲。公式記録では全範囲 (`0..=0x7FFF`)。
+    pub rng_states: RangeInclusive<u16>,
+    /// 枝刈り用スコア閾値の初期値。真の最大スコア未満でなければ記録は得られない。
+    pub prune_score_max: Score,
+    /// 並列に解くスレッド数。
+    pub thread_count: usize,
+}
+
+impl Default for HuntConfig {
+    fn default() -> Self {
+        Self {
+            rng_states: 0..=0x7FFF,
+            prune_score_max: Score::ZERO,
+            thread_count: 1,
+        }
+    }
+}
+
+/// 記録ファイルの内容。
+///
+/// 文字列形式はタブ区切りの `キー 値` の行を並べたもの:
+///
+/// ```text
+/// samegame-record  1
+/// crate_version    0.1.0
+/// rng_states       0x0000-0x7FFF
+/// prune_score_max  800
+/// best_score       844
+/// best_param       0x....,0x..,40,2
+/// digest           0123456789ABCDEF
+/// ```
+///
+/// `best_score` は解が 1 つも得られなければ `-` となる。`best_param` は最大スコアを達成する面ごとに昇順に並ぶ。
+/// `digest` はそれより前の全ての行 (改行を含む) の FNV-1a 64bit ハッシュ値で、記録の改竄や転記ミスの検出用。
+/// 暗号学的な署名ではない。
+///
+/// スレッド数や中断の有無によらず同じ内容になるよう、実行順序に依存する値 (解の手順、解けた面の数など) は含めない。
+/// 最大スコアを達成する手順は結果ファイルにあり、記録ファイルを書き出す前に検証済みである。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HuntRecord {
+    /// 探索した乱数生成器の内部状態の範囲。
+    pub rng_states: RangeInclusive<u16>,
+    /// 枝刈り用スコア閾値の初期値。
+    pub prune_score_max: Score,
+    /// 最大スコア。
+    pub best_score: Option<Score>,
+    /// 最大スコアを達成する面のパラメータたち (昇順)。
+    pub best_params: Vec<RandomBoardParam>,
+}
+
+impl HuntRecord {
+    /// 記録ファイルの形式のバージョン。
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// 結果レコードたち `records` から最大スコアとそれを達成する面を集める。
+    pub fn from_results<'a>(
+        config: &HuntConfig,
+        records: impl IntoIterator<Item = &'a ResultRecord>,
+    ) -> Self {
+        let mut best_score = None;
+        let mut best_params = Vec::new();
+        for record in records {
+            if best_score.is_none_or(|best| best < record.score) {
+                best_score = Some(record.score);
+                best_params.clear();
+            }
+            if best_score == Some(record.score) {
+                best_params.push(record.param.clone());
+            }
+        }
+        best_params.sort_unstable();
+
+        Self {
+            rng_states: config.rng_states.clone(),
+            prune_score_max: config.prune_score_max,
+            best_score,
+            best_params,
+        }
+    }
+
+    /// `digest` 行より前の部分を返す。
+    fn body(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut s = String::new();
+        writeln!(s, "{MAGIC}\t{}", Self::FORMAT_VERSION).unwrap();
+        writeln!(s, "crate_version\t{}", env!("CARGO_PKG_VERSION")).unwrap();
+        writeln!(
+            s,
+            "rng_states\t0x{:04X}-0x{:04X}",
+            self.rng_states.start(),
+            self.rng_states.end()
+        )
+        .unwrap();
+        writeln!(s, "prune_score_max\t{}", self.prune_score_max).unwrap();
+        match self.best_score {
+            Some(score) => writeln!(s, "best_score\t{score}").unwrap(),
+            None => writeln!(s, "best_score\t-").unwrap(),
+        }
+        for param in &self.best_params {
+            writeln!(s, "best_param\t{param}").unwrap();
+        }
+
+        s
+    }
+
+    /// 記録のハッシュ値を返す。
+    pub fn digest(&self) -> u64 {
+        fnv1a64(self.body().as_bytes())
+    }
+}
+
+impl std::fmt::Display for HuntRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.body())?;
+        writeln!(f, "digest\t{:016X}", self.digest())
+    }
+}
+
+/// `record_hunt()` の実行結果。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HuntReport {
+    /// 今回の実行でのパイプラインの集計 (再開時にスキップした分は含まない)。
+    pub summary: PipelineSummary,
+    /// 結果ファイル全体の検証結果。
+    pub verification: VerificationReport,
+    /// 書き出した記録。
+    pub record: HuntRecord,
+}
+
+/// 公式記録の探索手順を一通り実行し、結果をディレクトリ `dir` に書き出す。
+///
+/// `dir` には結果ファイル `HUNT_RESULTS_FILE` (`Pipeline` の出力形式) と記録ファイル `HUNT_RECORD_FILE`
+/// (`HuntRecord` の文字列形式) を作る。結果ファイルが既に存在すれば、処理済みのパラメータをスキップして再開する。
+/// 検証に失敗したレコードがあればエラーを返し、記録ファイルは書き出さない。
+///
+/// 同一盤面の除外のため、列挙したパラメータの盤面のハッシュ値を全て保持する (全範囲なら 1000 万件程度)。
+pub fn record_hunt(dir: impl AsRef<Path>, config: &HuntConfig) -> anyhow::Result<HuntReport> {
+    record_hunt_with_progress(dir, config, NullProgress)
+}
+
+/// `record_hunt()` と同様だが、求解の進捗を `progress` に通知する。
+pub fn record_hunt_with_progress(
+    dir: impl AsRef<Path>,
+    config: &HuntConfig,
+    progress: impl ProgressSink + Send + Sync + 'static,
+) -> anyhow::Result<HuntReport> {
+    let params =
+        RandomBoardParam::all_canonical_in(config.rng_states.clone()).map(|(param, _)| param);
+
+    hunt(params, dir.as_ref(), config, progress)
+}
+
+fn hunt(
+    params: impl Iterator<Item = RandomBoardParam> + Send + 'static,
+    dir: &Path,
+    config: &HuntConfig,
+    progress: impl ProgressSink + Send + Sync + 'static,
+) -> anyhow::Result<HuntReport> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("出力ディレクトリ '{}' を作れない", dir.display()))?;
+    let results: PathBuf = dir.join(HUNT_RESULTS_FILE);
+
+    let summary = Pipeline::new()
+        .params(dedup_boards(params))
+        .solve(ExactConfig {
+            prune_score_max: config.prune_score_max,
+            raise_threshold: true,
+            ..Default::default()
+        })
+        .export(ExportFormat::Tsv, &results)
+        .resume(true)
+        .threads(config.thread_count)
+        .progress(progress)
+        .run()?;
+
+    let records: Vec<ResultRecord> = std::fs::read_to_string(&results)
+        .with_context(|| format!("結果ファイル '{}' を読めない", results.display()))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::parse)
+        .collect::<anyhow::Result<_>>()?;
+
+    let verification = verify_batch(
+        records
+            .iter()
+            .map(|record| (record.param.clone(), record.score, record.solution.clone())),
+    );
+    ensure!(
+        verification.is_ok(),
+        "結果ファイルの検証に失敗:\n{verification}"
+    );
+
+    let record = HuntRecord::from_results(config, &records);
+    let path = dir.join(HUNT_RECORD_FILE);
+    std::fs::write(&path, record.to_string())
+        .with_context(|| format!("記録ファイル '{}' を書き込めない", path.display()))?;
+
+    Ok(HuntReport {
+        summary,
+        verification,
+        record,
+    })
+}
+
+/// パラメータたちから、ゲーム内に現れない盤面と、先に列挙したパラメータと同一の盤面を生成するものを除く。
+fn dedup_boards(
+    params: impl Iterator<Item = RandomBoardParam>,
+) -> impl Iterator<Item = RandomBoardParam> {
+    let mut seen = U64HashMap::<u64, RandomBoardParam>::default();
+
+    params.filter(move |param| {
+        let Some((board, _)) = param.gen_legal_board() else {
+            return false;
+        };
+        match seen.entry(Position::new(board.clone()).key()) {
+            // ハッシュ値が衝突しただけなら別の盤面として扱う。
+            Entry::Occupied(entry) => entry.get().gen_board().0 != board,
+            Entry::Vacant(entry) => {
+                entry.insert(param.clone());
+                true
+            }
+        }
+    })
+}
+
+/// FNV-1a 64bit ハッシュ値を返す。
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_hunt() {
+        // テストを軽くするため、すぐ解ける面 1 つのみを対象とする。2 つ目は同一盤面として除かれる。
+        let param: RandomBoardParam = "0x1234,0x02,40,0".parse().unwrap();
+        let params = vec![param.clone(), param.clone()];
+        let config = HuntConfig {
+            rng_states: 0x1234..=0x1234,
+            ..Default::default()
+        };
+
+        let dir = std::env::temp_dir().join(format!("samegame-hunt-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let report = hunt(params.clone().into_iter(), &dir, &config, NullProgress).unwrap();
+        assert_eq!(report.summary.param_count, 1);
+        assert!(report.verification.is_ok());
+        let record = &report.record;
+        assert_eq!(record.best_score, Some(Score::new(181)));
+        assert_eq!(record.best_params, [param]);
+
+        let s = std::fs::read_to_string(dir.join(HUNT_RECORD_FILE)).unwrap();
+        assert_eq!(s, record.to_string());
+        assert!(s.starts_with("samegame-record\t1\n"));
+        assert!(s.ends_with(&format!("digest\t{:016X}\n", record.digest())));
+
+        // 再開時は何も解かず、同じ記録を書き出す。
+        let resumed = hunt(params.into_iter(), &dir, &config, NullProgress).unwrap();
+        assert_eq!(resumed.summary.param_count, 0);
+        assert_eq!(resumed.record, report.record);
+
+        // 結果ファイルが改竄されていれば検証に失敗する。
+        let results = dir.join(HUNT_RESULTS_FILE);
+        let mut records: Vec<ResultRecord> = std::fs::read_to_string(&results)
+            .unwrap()
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        records[0].score += Score::new(1);
+        let src: String = records.iter().map(|record| format!("{record}\n")).collect();
+        std::fs::write(&results, src).unwrap();
+        assert!(hunt(std::iter::empty(), &dir, &config, NullProgress).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
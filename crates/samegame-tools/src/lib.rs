@@ -0,0 +1,32 @@
+//! SFC『鮫亀』: さめがめ「かんたん」用のツール類。
+//!
+//! 全盤面の一括求解 (パイプライン)、結果ファイルやデータベース、索引、CLI `samegame` を含む。
+//! 探索自体は `samegame-solver` クレートにある。
+
+mod annotations;
+mod db;
+mod diagnose;
+mod distribution;
+mod hunt;
+mod par;
+mod pipeline;
+mod record;
+mod resolve;
+mod reverse_index;
+mod rta;
+mod schema;
+mod solution_index;
+
+pub use self::annotations::*;
+pub use self::db::*;
+pub use self::diagnose::*;
+pub use self::distribution::*;
+pub use self::hunt::*;
+pub use self::par::*;
+pub use self::pipeline::*;
+pub use self::record::*;
+pub use self::resolve::*;
+pub use self::reverse_index::*;
+pub use self::rta::*;
+pub use self::schema::*;
+pub use self::solution_index::*;
@@ -0,0 +1,287 @@
+//! 最適スコア (パー) に対する手順の評価関連。
+//!
+//! 走者が試走後に見返すための、各手の損失を注釈した評価を作る。
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+use anyhow::{ensure, Context as _};
+use samegame_core::{Action, ActionHistory, RandomBoardParam, Score, Square};
+
+use crate::record::ResultRecord;
+use samegame_solver::BeamSearch;
+use samegame_solver::Position;
+use samegame_solver::Solver;
+use samegame_solver::{GameTerminalEval, TerminalEval as _};
+
+/// `ParTable::rate_route()` の設定。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateConfig {
+    /// 各局面の最大追加獲得スコアの下界を得るビームサーチのビーム幅。
+    pub beam_width: usize,
+    /// 各局面を解き直す際の訪問ノード数の上限。`None` は無制限。
+    pub node_limit: Option<u64>,
+}
+
+impl Default for RateConfig {
+    fn default() -> Self {
+        Self {
+            beam_width: 64,
+            node_limit: Some(10_000_000),
+        }
+    }
+}
+
+/// 評価した 1 手。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RatedMove {
+    /// 着手 (最小マス)。
+    pub square: Square,
+    /// 着手による獲得スコア。
+    pub gain: Score,
+    /// 着手前の局面からの最大追加獲得スコア (終了局面評価を含む)。
+    pub best_before: Score,
+    /// この手による損失 (最大追加獲得スコアの減少分)。
+    pub loss: Score,
+    /// `best_before` および `loss` が正確かどうか。
+    ///
+    /// 偽の場合、解き直しが予算内に完了しなかったので `best_before` は下界、`loss` は概算となる。
+    pub exact: bool,
+}
+
+/// 手順の評価。`ParTable::rate_route()` の戻り値。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RouteRating {
+    /// パー (初期盤面の最適スコア)。
+    pub par: Score,
+    /// 手順の獲得スコア (完了していれば終了局面評価を含む)。
+    pub score: Score,
+    /// 手順が終了局面に達しているかどうか。
+    pub completed: bool,
+    /// 手順の後の局面からの最大追加獲得スコア (完了していれば 0)。
+    pub best_remaining: Score,
+    /// 各手の評価。
+    pub moves: Vec<RatedMove>,
+}
+
+impl RouteRating {
+    /// パーに対する損失の総和を返す。
+    ///
+    /// 手順の後も最善を尽くした場合の最終スコアとパーとの差。
+    pub fn total_loss(&self) -> Score {
+        self.par.saturating_sub(self.score + self.best_remaining)
+    }
+
+    /// 全ての手の評価が正確かどうかを返す。
+    pub fn is_exact(&self) -> bool {
+        self.moves.iter().all(|mv| mv.exact)
+    }
+}
+
+/// 盤面生成パラメータごとの最適スコアの表。
+///
+/// 全探索の結果ファイル (`ResultRecord` の列) から作る。記録されたスコアを最適値とみなす。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParTable {
+    pars: HashMap<RandomBoardParam, Score>,
+}
+
+impl ParTable {
+    /// 空の表を作る。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 結果レコードの列から表を作る。空行は無視する。
+    pub fn from_reader(rdr: impl BufRead) -> anyhow::Result<Self> {
+        let mut this = Self::new();
+
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ResultRecord = line
+                .parse()
+                .with_context(|| format!("結果ファイルの {} 行目のパースに失敗", i + 1))?;
+            this.insert(&record);
+        }
+
+        Ok(this)
+    }
+
+    /// 結果ファイルから表を作る。
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("結果ファイル '{}' を開けない", path.display()))?;
+
+        Self::from_reader(std::io::BufReader::new(file))
+    }
+
+    /// 結果レコードを 1 つ反映する。同じパラメータが複数あれば最大スコアを採る。
+    pub fn insert(&mut self, record: &ResultRecord) {
+        let par = self
+            .pars
+            .entry(record.param.clone())
+            .or_insert(record.score);
+        *par = (*par).max(record.score);
+    }
+
+    /// 記録されているパラメータ数を返す。
+    pub fn len(&self) -> usize {
+        self.pars.len()
+    }
+
+    /// 表が空かどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.pars.is_empty()
+    }
+
+    /// パラメータ `param` のパーを返す。
+    pub fn get(&self, param: &RandomBoardParam) -> Option<Score> {
+        self.pars.get(param).copied()
+    }
+
+    /// パラメータ `param` の盤面上の手順 `history` (途中まででもよい) をパーと比較して評価する。
+    ///
+    /// 各手の後の局面を予算内で解き直し、最大追加獲得スコアの減少分を各手の損失とする。
+    /// 解き直しは手数回行うので、局面によってはかなり重い。
+    ///
+    /// パーが記録されていない、盤面がゲーム内に現れない、手順が非合法手を含む場合はエラーを返す。
+    pub fn rate_route(
+        &self,
+        param: &RandomBoardParam,
+        history: &ActionHistory,
+        config: &RateConfig,
+    ) -> anyhow::Result<RouteRating> {
+        let par = self
+            .get(param)
+            .with_context(|| format!("パラメータ {param} のパーが記録されていない"))?;
+        let (board, _) = param
+            .gen_legal_board()
+            .with_context(|| format!("パラメータ {param} の盤面はゲーム内に現れない"))?;
+
+        let mut pos = Position::new(board);
+        let mut score = Score::ZERO;
+        let mut best_before = (par, true);
+        let mut moves = Vec::with_capacity(history.len());
+
+        for (i, &sq) in history.iter().enumerate() {
+            let action = Action::from_board_square(pos.board(), sq)
+                .with_context(|| format!("{} 手目 {sq} が合法手でない", i + 1))?;
+            pos = pos.do_action(&action);
+            score += action.gain();
+
+            let best_after = best_remaining(&pos, config);
+            let (best, exact) = best_before;
+            moves.push(RatedMove {
+                square: sq,
+                gain: action.gain(),
+                best_before: best,
+                loss: best.saturating_sub(action.gain() + best_after.0),
+                exact: exact && best_after.1,
+            });
+            best_before = best_after;
+        }
+
+        let completed = !pos.has_action();
+        let best_remaining = if completed {
+            score += GameTerminalEval.final_gain(&pos);
+            Score::ZERO
+        } else {
+            best_before.0
+        };
+        ensure!(
+            score + best_remaining <= par || !best_before.1,
+            "手順のスコアがパー {par} を超える (パーが最適値でない)"
+        );
+
+        Ok(RouteRating {
+            par,
+            score,
+            completed,
+            best_remaining,
+            moves,
+        })
+    }
+}
+
+/// 局面 `pos` からの最大追加獲得スコア (終了局面評価を含む) とそれが正確かどうかを返す。
+fn best_remaining(pos: &Position, config: &RateConfig) -> (Score, bool) {
+    if !pos.has_action() {
+        return (GameTerminalEval.final_gain(pos), true);
+    }
+
+    // ビームサーチの下界を閾値として、それより良い解を探す。
+    let (lb, _) = BeamSearch::new(config.beam_width).search(pos.board().clone());
+    let mut solver = Solver::new(lb);
+    solver.set_node_limit(config.node_limit);
+    let (res, stats) = solver.solve_with_stats(pos.board().clone());
+    let best = res.map_or(lb, |(score, _)| score.max(lb));
+
+    (best, !stats.node_limit_reached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use samegame_solver::benchmark_board;
+
+    #[test]
+    fn test_rate_route() {
+        let bb = benchmark_board("easiest").unwrap();
+        let mut table = ParTable::new();
+        table.insert(&ResultRecord {
+            param: bb.param.clone(),
+            score: bb.known_best,
+            solution: bb.solution.clone(),
+        });
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(&bb.param), Some(bb.known_best));
+
+        // 最適手順は損失なし。
+        let config = RateConfig::default();
+        let rating = table.rate_route(&bb.param, &bb.solution, &config).unwrap();
+        assert!(rating.completed);
+        assert!(rating.is_exact());
+        assert_eq!(rating.score, bb.known_best);
+        assert_eq!(rating.total_loss(), Score::ZERO);
+        assert!(rating.moves.iter().all(|mv| mv.loss == Score::ZERO));
+
+        // 最適でない初手を指した途中までの手順。
+        let pos = Position::new(bb.board.clone());
+        let action = pos
+            .actions()
+            .find(|action| action.least_square() != bb.solution.as_slice()[0])
+            .unwrap();
+        let mut history = ActionHistory::new();
+        history.push(action.least_square());
+        let rating = table.rate_route(&bb.param, &history, &config).unwrap();
+        assert!(!rating.completed);
+        assert_eq!(rating.moves.len(), 1);
+        assert_eq!(rating.moves[0].best_before, bb.known_best);
+        assert_eq!(rating.total_loss(), rating.moves[0].loss);
+        assert_eq!(rating.score, action.gain());
+
+        // パーの記録がないパラメータや非合法手はエラー。
+        let other: RandomBoardParam = "0x0000,0x00,40,0".parse().unwrap();
+        assert!(table.rate_route(&other, &history, &config).is_err());
+        let sq_illegal = Square::all()
+            .find(|&sq| Action::from_board_square(&bb.board, sq).is_err())
+            .unwrap();
+        let illegal = ActionHistory::from_slice(&[sq_illegal]).unwrap();
+        assert!(table.rate_route(&bb.param, &illegal, &config).is_err());
+
+        let src = format!(
+            "{}\n\n",
+            ResultRecord {
+                param: bb.param.clone(),
+                score: bb.known_best,
+                solution: bb.solution.clone(),
+            }
+        );
+        assert_eq!(ParTable::from_reader(src.as_bytes()).unwrap(), table);
+    }
+}
@@ -0,0 +1,536 @@
+//! 盤面の列挙・絞り込み・求解・出力をまとめて行うパイプライン関連。
+
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::Context as _;
+use samegame_core::{ActionHistory, Board, RandomBoardParam, Score};
+use samegame_solver::logging::info;
+use samegame_solver::{
+    AtomicScoreBest, BeamSearch, DpSpillConfig, NullProgress, Position, ProgressEvent,
+    ProgressSink, Solver,
+};
+
+use crate::record::{ResultRecord, ScanProgress};
+
+/// パイプラインで求解対象の盤面を絞り込むフィルタ。
+///
+/// `Fn(&RandomBoardParam, &Board) -> bool` を満たすクロージャはこのトレイトを実装している。
+pub trait BoardFilter {
+    /// 盤面を求解対象とするなら `true` を返す。
+    fn accepts(&self, param: &RandomBoardParam, board: &Board) -> bool;
+}
+
+impl<F: Fn(&RandomBoardParam, &Board) -> bool> BoardFilter for F {
+    fn accepts(&self, param: &RandomBoardParam, board: &Board) -> bool {
+        self(param, board)
+    }
+}
+
+/// 獲得スコア上界 (`Position::gain_upper_bound()`) が指定値を超える盤面のみを通すフィルタ。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UpperBoundFilter(pub Score);
+
+impl BoardFilter for UpperBoundFilter {
+    fn accepts(&self, _param: &RandomBoardParam, board: &Board) -> bool {
+        Position::new(board.clone()).gain_upper_bound() > self.0
+    }
+}
+
+/// パイプラインの厳密求解の設定。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExactConfig {
+    /// 枝刈り用スコア閾値の初期値。
+    pub prune_score_max: Score,
+    /// 面を解くたびに、それまでの最大スコア - 1 で閾値を chmax するかどうか (`solve_all` と同様)。
+    pub raise_threshold: bool,
+    /// 同スコアの解のうち手数が短いものを優先するかどうか。
+    pub prefer_shorter: bool,
+    /// DP テーブルのディスク退避設定。
+    pub dp_spill: Option<DpSpillConfig>,
+}
+
+impl Default for ExactConfig {
+    fn default() -> Self {
+        Self {
+            prune_score_max: Score::ZERO,
+            raise_threshold: true,
+            prefer_shorter: false,
+            dp_spill: None,
+        }
+    }
+}
+
+/// パイプラインの出力形式。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ExportFormat {
+    /// `ResultRecord` の文字列形式 (`solve_all` の出力と同じ) を 1 行 1 レコードで出力する。
+    #[default]
+    Tsv,
+}
+
+/// パイプラインの実行結果の集計。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PipelineSummary {
+    /// 処理したパラメータ数 (再開時にスキップしたものを除く)。
+    pub param_count: u64,
+    /// ゲーム内に現れない盤面を生成したパラメータ数。
+    pub illegal_count: u64,
+    /// フィルタで除外したパラメータ数。
+    pub filtered_count: u64,
+    /// 解が得られなかった (枝刈り用スコア閾値を超える解がなかった) パラメータ数。
+    pub unsolved_count: u64,
+    /// 解が得られたパラメータ数。
+    pub solved_count: u64,
+    /// 得られた解の最大スコア。
+    pub best_score: Option<Score>,
+}
+
+/// 盤面生成パラメータの列挙から結果の出力までを行うパイプライン。
+///
+/// ```no_run
+/// # use samegame_core::*;
+/// # use samegame_solver::*;
+/// # use samegame_tools::*;
+/// let summary = Pipeline::new()
+///     .filter(UpperBoundFilter(Score::new(1000)))
+///     .presolve(BeamSearch::new(100))
+///     .solve(ExactConfig::default())
+///     .export(ExportFormat::Tsv, "result.tsv")
+///     .threads(8)
+///     .run()?;
+/// # anyhow::Ok(())
+/// ```
+///
+/// パラメータはバッチ単位で並列に解き、バッチ内の結果をパラメータ順に並べてから出力する。
+/// よって出力ファイルは常にパラメータ順となり、`resume(true)` で中断した処理を再開できる (`ScanProgress` を参照)。
+pub struct Pipeline {
+    params: Box<dyn Iterator<Item = RandomBoardParam> + Send>,
+    filters: Vec<Box<dyn BoardFilter + Send + Sync>>,
+    presolve: Option<BeamSearch>,
+    exact: ExactConfig,
+    export: Option<(ExportFormat, PathBuf)>,
+    resume: bool,
+    thread_count: usize,
+    batch_size: usize,
+    progress: Arc<dyn ProgressSink + Send + Sync>,
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("filter_count", &self.filters.len())
+            .field("presolve", &self.presolve)
+            .field("exact", &self.exact)
+            .field("export", &self.export)
+            .field("resume", &self.resume)
+            .field("thread_count", &self.thread_count)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pipeline {
+    /// 全パラメータ (`RandomBoardParam::all()`) を対象とし、1 スレッドで解いて何も出力しないパイプラインを作る。
+    pub fn new() -> Self {
+        Self {
+            params: Box::new(RandomBoardParam::all()),
+            filters: Vec::new(),
+            presolve: None,
+            exact: ExactConfig::default(),
+            export: None,
+            resume: false,
+            thread_count: 1,
+            batch_size: 1024,
+            progress: Arc::new(NullProgress),
+        }
+    }
+
+    /// 対象とするパラメータたちを設定する。昇順でなければならない。
+    pub fn params(
+        mut self,
+        params: impl IntoIterator<Item = RandomBoardParam, IntoIter: Send + 'static>,
+    ) -> Self {
+        self.params = Box::new(params.into_iter());
+        self
+    }
+
+    /// 盤面フィルタを追加する。全てのフィルタを通過した盤面のみを解く。
+    pub fn filter(mut self, filter: impl BoardFilter + Send + Sync + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// 厳密求解の前にビームサーチで近似解を求め、そのスコアで枝刈り用スコア閾値を引き上げる。
+    pub fn presolve(mut self, beam: BeamSearch) -> Self {
+        self.presolve = Some(beam);
+        self
+    }
+
+    /// 厳密求解の設定を行う。
+    pub fn solve(mut self, exact: ExactConfig) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    /// 結果の出力先を設定する。
+    pub fn export(mut self, format: ExportFormat, path: impl Into<PathBuf>) -> Self {
+        self.export = Some((format, path.into()));
+        self
+    }
+
+    /// 出力ファイルが既に存在する場合、処理済みのパラメータをスキップして追記するかどうかを設定する。
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// 並列に解くスレッド数を設定する。`thread_count` は 1 以上でなければならない。
+    pub fn threads(mut self, thread_count: usize) -> Self {
+        assert!(thread_count >= 1, "スレッド数は 1 以上でなければならない");
+        self.thread_count = thread_count;
+        self
+    }
+
+    /// バッチサイズ (出力およびチェックポイントの単位) を設定する。`batch_size` は 1 以上でなければならない。
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size >= 1, "バッチサイズは 1 以上でなければならない");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// 進捗イベント (`ProgressEvent`) の通知先を設定する。
+    ///
+    /// パラメータの列挙・各面の求解の開始と終了・最良解の更新・出力ファイルのフラッシュを通知する。
+    pub fn progress(mut self, sink: impl ProgressSink + Send + Sync + 'static) -> Self {
+        self.progress = Arc::new(sink);
+        self
+    }
+
+    /// パイプラインを実行する。
+    pub fn run(self) -> anyhow::Result<PipelineSummary> {
+        let Self {
+            params,
+            filters,
+            presolve,
+            exact,
+            export,
+            resume,
+            thread_count,
+            batch_size,
+            progress,
+        } = self;
+
+        let total = match params.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(lower as u64),
+            _ => None,
+        };
+
+        let scan = match &export {
+            Some((ExportFormat::Tsv, path)) if resume => ScanProgress::from_path(path)?,
+            _ => ScanProgress::default(),
+        };
+
+        let mut wtr = export
+            .as_ref()
+            .map(|(_, path)| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(resume)
+                    .truncate(!resume)
+                    .open(path)
+                    .map(std::io::BufWriter::new)
+                    .with_context(|| format!("出力ファイル '{}' を開けない", path.display()))
+            })
+            .transpose()?;
+
+        let best = AtomicScoreBest::new(Score::ZERO);
+        if let Some(best_score) = scan.best_score {
+            info!("Resume: best score {best_score}");
+            best.chmax_score(best_score);
+        }
+
+        let worker = Worker {
+            filters: &filters,
+            presolve: presolve.as_ref(),
+            exact: &exact,
+            best: &best,
+            progress: progress.as_ref(),
+        };
+        let mut solvers: Vec<Solver> = (0..thread_count)
+            .map(|_| {
+                let mut solver = Solver::new(exact.prune_score_max);
+                solver.set_prefer_shorter(exact.prefer_shorter);
+                solver.set_dp_spill(exact.dp_spill.clone());
+                solver
+            })
+            .collect();
+
+        let mut summary = PipelineSummary::default();
+        let consumed = std::cell::Cell::new(0_u64);
+        let mut params = params
+            .inspect(|_| consumed.set(consumed.get() + 1))
+            .filter(|param| !scan.is_processed(param));
+
+        loop {
+            let batch: Vec<RandomBoardParam> = params.by_ref().take(batch_size).collect();
+            if batch.is_empty() {
+                break;
+            }
+
+            let outcomes = worker.run_batch(summary.param_count, &batch, &mut solvers);
+
+            for (param, outcome) in batch.into_iter().zip(outcomes) {
+                summary.param_count += 1;
+                match outcome {
+                    BoardOutcome::Illegal => summary.illegal_count += 1,
+                    BoardOutcome::Filtered => summary.filtered_count += 1,
+                    BoardOutcome::Unsolved => summary.unsolved_count += 1,
+                    BoardOutcome::Solved(score, solution) => {
+                        summary.solved_count += 1;
+                        if summary
+                            .best_score
+                            .is_none_or(|best_score| best_score < score)
+                        {
+                            summary.best_score = Some(score);
+                        }
+                        if let Some(wtr) = &mut wtr {
+                            let record = ResultRecord {
+                                param,
+                                score,
+                                solution,
+                            };
+                            writeln!(wtr, "{record}")?;
+                        }
+                    }
+                }
+            }
+
+            progress.on_event(&ProgressEvent::EnumerationProgress {
+                processed: consumed.get(),
+                total,
+            });
+
+            // バッチ単位でチェックポイントとする。
+            if let (Some(wtr), Some((_, path))) = (&mut wtr, &export) {
+                wtr.flush()?;
+                progress.on_event(&ProgressEvent::CheckpointWritten {
+                    path: path.clone(),
+                    processed: consumed.get(),
+                });
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// 1 つのパラメータの処理結果。
+#[derive(Debug)]
+enum BoardOutcome {
+    Illegal,
+    Filtered,
+    Unsolved,
+    Solved(Score, ActionHistory),
+}
+
+/// 各スレッドで共有するパイプラインの設定と状態。
+struct Worker<'a> {
+    filters: &'a [Box<dyn BoardFilter + Send + Sync>],
+    presolve: Option<&'a BeamSearch>,
+    exact: &'a ExactConfig,
+    best: &'a AtomicScoreBest,
+    progress: &'a (dyn ProgressSink + Send + Sync),
+}
+
+impl Worker<'_> {
+    /// バッチ内のパラメータたちを並列に処理し、パラメータ順に結果を返す。
+    ///
+    /// `index_start` はバッチ先頭のパラメータの通し番号。
+    fn run_batch(
+        &self,
+        index_start: u64,
+        batch: &[RandomBoardParam],
+        solvers: &mut [Solver],
+    ) -> Vec<BoardOutcome> {
+        let next = AtomicUsize::new(0);
+        let outcomes = Mutex::new(Vec::<(usize, BoardOutcome)>::with_capacity(batch.len()));
+
+        std::thread::scope(|s| {
+            for solver in solvers.iter_mut() {
+                let next = &next;
+                let outcomes = &outcomes;
+                s.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(param) = batch.get(i) else {
+                        break;
+                    };
+                    let outcome = self.process(index_start + i as u64, param, solver);
+                    outcomes.lock().unwrap().push((i, outcome));
+                });
+            }
+        });
+
+        let mut outcomes = outcomes.into_inner().unwrap();
+        outcomes.sort_unstable_by_key(|&(i, _)| i);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
+    fn process(&self, index: u64, param: &RandomBoardParam, solver: &mut Solver) -> BoardOutcome {
+        let Some((board, _)) = param.gen_legal_board() else {
+            return BoardOutcome::Illegal;
+        };
+
+        if !self
+            .filters
+            .iter()
+            .all(|filter| filter.accepts(param, &board))
+        {
+            return BoardOutcome::Filtered;
+        }
+
+        let mut threshold = self.exact.prune_score_max;
+        if self.exact.raise_threshold {
+            // 同点の解は全て列挙したいので -1 する。
+            threshold = threshold.max(self.best.score().saturating_sub(Score::new(1)));
+        }
+        if let Some(beam) = self.presolve {
+            // 近似解のスコア以上の解は必ず存在する。
+            let (score_beam, _) = beam.search(board.clone());
+            threshold = threshold.max(score_beam.saturating_sub(Score::new(1)));
+        }
+        solver.set_prune_score_max(threshold);
+
+        self.progress.on_event(&ProgressEvent::SolveStarted {
+            index,
+            param: Some(param.clone()),
+            prune_score_max: threshold,
+        });
+        let start = Instant::now();
+        let res = solver.solve(board);
+        self.progress.on_event(&ProgressEvent::SolveFinished {
+            index,
+            param: Some(param.clone()),
+            score: res.as_ref().map(|&(score, _)| score),
+            elapsed: start.elapsed(),
+        });
+
+        match res {
+            Some((score, solution)) => {
+                if self.best.offer(score, &solution) {
+                    self.progress.on_event(&ProgressEvent::Incumbent {
+                        index,
+                        param: Some(param.clone()),
+                        score,
+                        solution: solution.clone(),
+                    });
+                }
+                BoardOutcome::Solved(score, solution)
+            }
+            None => BoardOutcome::Unsolved,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline() {
+        let params: Vec<RandomBoardParam> = RandomBoardParam::all()
+            .skip(0x1234 * 256 * 5)
+            .take(8)
+            .collect();
+
+        let path =
+            std::env::temp_dir().join(format!("samegame-pipeline-test-{}.tsv", std::process::id()));
+
+        let run = |params: Vec<RandomBoardParam>, resume: bool| {
+            // テストを軽くするため、解が見つからない程度に閾値を高くしておく。
+            Pipeline::new()
+                .params(params)
+                .filter(UpperBoundFilter(Score::new(100)))
+                .filter(|param: &RandomBoardParam, _: &Board| param.entropy.to_inner() != 0)
+                .presolve(BeamSearch::new(10))
+                .solve(ExactConfig {
+                    prune_score_max: Score::new(500),
+                    ..Default::default()
+                })
+                .export(ExportFormat::Tsv, &path)
+                .resume(resume)
+                .threads(3)
+                .batch_size(3)
+                .run()
+                .unwrap()
+        };
+
+        let summary = run(params.clone(), false);
+        assert_eq!(summary.param_count, 8);
+        assert_eq!(
+            summary.illegal_count
+                + summary.filtered_count
+                + summary.unsolved_count
+                + summary.solved_count,
+            8
+        );
+        assert!(summary.filtered_count >= 1);
+
+        // 記録済みのパラメータ以前は処理済みとしてスキップされる。
+        let record = ResultRecord {
+            param: params[4].clone(),
+            score: Score::new(100),
+            solution: ActionHistory::new(),
+        };
+        std::fs::write(&path, format!("{record}\n")).unwrap();
+        let summary = run(params, true);
+        assert_eq!(summary.param_count, 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_pipeline_progress_events() {
+        // チャネル経由で列挙の進捗とチェックポイントが通知される。
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("samegame-progress-test-{}.tsv", std::process::id()));
+        let params: Vec<RandomBoardParam> = RandomBoardParam::all()
+            .skip(0x1234 * 256 * 5)
+            .take(4)
+            .collect();
+        let (tx, rx) = std::sync::mpsc::channel();
+        Pipeline::new()
+            .params(params)
+            .solve(ExactConfig {
+                prune_score_max: Score::new(10000),
+                ..Default::default()
+            })
+            .export(ExportFormat::Tsv, &path)
+            .batch_size(3)
+            .progress(tx)
+            .run()
+            .unwrap();
+        let events: Vec<ProgressEvent> = rx.iter().collect();
+        assert!(events.contains(&ProgressEvent::EnumerationProgress {
+            processed: 4,
+            total: Some(4),
+        }));
+        assert!(events.contains(&ProgressEvent::CheckpointWritten {
+            path: path.clone(),
+            processed: 3,
+        }));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
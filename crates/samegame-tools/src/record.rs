@@ -0,0 +1,288 @@
+//! 探索結果レコード関連。
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write as _};
+use std::path::Path;
+
+use anyhow::{ensure, Context as _};
+use samegame_core::{parse_uint, ActionHistory, GameEntropy, RandomBoardParam, Score, SeedId};
+
+use samegame_solver::chmax;
+use samegame_solver::{ProgressEvent, ProgressSink};
+
+/// 1 つの面の探索結果。
+///
+/// 文字列形式は `solve_all` の出力と同じタブ区切り:
+/// `rng_state nmi_counter nmi_timing entropy score solution` (`rng_state`, `nmi_counter` は 16 進)。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResultRecord {
+    pub param: RandomBoardParam,
+    pub score: Score,
+    pub solution: ActionHistory,
+}
+
+impl ResultRecord {
+    /// 盤面生成パラメータの識別子を返す。`SeedId` で表せないパラメータなら `None` を返す。
+    pub fn seed_id(&self) -> Option<SeedId> {
+        self.param.seed_id()
+    }
+}
+
+impl std::str::FromStr for ResultRecord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = s.split('\t').collect();
+        ensure!(
+            fields.len() == 6,
+            "結果レコードはタブ区切りの 6 フィールドでなければならない: '{s}'"
+        );
+
+        let rng_state: u16 = parse_uint(fields[0])
+            .with_context(|| format!("rng_state のパースに失敗: '{}'", fields[0]))?;
+        let nmi_counter: u8 = parse_uint(fields[1])
+            .with_context(|| format!("nmi_counter のパースに失敗: '{}'", fields[1]))?;
+        let nmi_timing: usize = fields[2]
+            .parse()
+            .with_context(|| format!("nmi_timing のパースに失敗: '{}'", fields[2]))?;
+        let entropy: GameEntropy = fields[3].parse()?;
+        let score: Score = fields[4].parse()?;
+        let solution: ActionHistory = fields[5]
+            .parse()
+            .with_context(|| format!("解のパースに失敗: '{}'", fields[5]))?;
+
+        Ok(Self {
+            param: RandomBoardParam::new(rng_state, nmi_counter, nmi_timing, entropy)?,
+            score,
+            solution,
+        })
+    }
+}
+
+impl std::fmt::Display for ResultRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let RandomBoardParam {
+            rng_state,
+            nmi_counter,
+            nmi_timing,
+            entropy,
+        } = self.param;
+
+        write!(
+            f,
+            "0x{rng_state:04X}\t0x{nmi_counter:02X}\t{nmi_timing}\t{entropy}\t{}\t{}",
+            self.score, self.solution
+        )
+    }
+}
+
+/// 既存の結果ファイルから読み取った、盤面生成パラメータ全探索の進捗。
+///
+/// 全探索はパラメータの列挙順に行われるので、記録されている最後のパラメータ以前は全て処理済みとみなせる。
+/// (解が見つからなかった面は結果ファイルに記録されないため、それ以降の処理済みパラメータは区別できない)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScanProgress {
+    /// 結果ファイルに記録されているパラメータたち。
+    pub recorded: HashSet<RandomBoardParam>,
+    /// 記録されている中で列挙順最後のパラメータ。
+    pub last: Option<RandomBoardParam>,
+    /// 記録されている中での最大スコア。
+    pub best_score: Option<Score>,
+}
+
+impl ScanProgress {
+    /// 結果レコードの列から進捗を求める。空行は無視する。
+    pub fn from_reader(rdr: impl BufRead) -> anyhow::Result<Self> {
+        let mut this = Self::default();
+
+        for (i, line) in rdr.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ResultRecord = line
+                .parse()
+                .with_context(|| format!("結果ファイルの {} 行目のパースに失敗", i + 1))?;
+            this.add(&record);
+        }
+
+        Ok(this)
+    }
+
+    /// 結果ファイルから進捗を求める。ファイルが存在しない場合、空の進捗を返す。
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("結果ファイル '{}' を開けない", path.display()))
+            }
+        };
+
+        Self::from_reader(std::io::BufReader::new(file))
+    }
+
+    /// 結果レコードを 1 つ反映する。
+    pub fn add(&mut self, record: &ResultRecord) {
+        if self.last.as_ref().is_none_or(|last| *last < record.param) {
+            self.last = Some(record.param.clone());
+        }
+        match &mut self.best_score {
+            Some(best_score) => {
+                chmax!(*best_score, record.score);
+            }
+            None => self.best_score = Some(record.score),
+        }
+        self.recorded.insert(record.param.clone());
+    }
+
+    /// 指定したパラメータが処理済みとみなせるかどうかを返す。
+    pub fn is_processed(&self, param: &RandomBoardParam) -> bool {
+        self.last.as_ref().is_some_and(|last| param <= last) || self.recorded.contains(param)
+    }
+}
+
+/// 分割して処理した結果ファイル (シャード) たち `shards` を、パラメータ順の 1 つの結果ファイル `output` に統合する。
+///
+/// 同じパラメータのレコードが複数あれば最大スコアのものを採る。空行は無視する。
+/// シャードを 1 つ読み込むたびに `progress` へ `ProgressEvent::ShardMerged` を通知する。
+/// 出力したレコード数を返す。
+pub fn merge_result_files(
+    shards: impl IntoIterator<Item = impl AsRef<Path>>,
+    output: impl AsRef<Path>,
+    progress: &dyn ProgressSink,
+) -> anyhow::Result<u64> {
+    let output = output.as_ref();
+
+    let mut records = Vec::<ResultRecord>::new();
+    for shard in shards {
+        let shard = shard.as_ref();
+        let s = std::fs::read_to_string(shard)
+            .with_context(|| format!("結果ファイル '{}' を読めない", shard.display()))?;
+
+        let len_before = records.len();
+        for (i, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ResultRecord = line.parse().with_context(|| {
+                format!(
+                    "結果ファイル '{}' の {} 行目のパースに失敗",
+                    shard.display(),
+                    i + 1
+                )
+            })?;
+            records.push(record);
+        }
+
+        progress.on_event(&ProgressEvent::ShardMerged {
+            path: shard.to_owned(),
+            record_count: (records.len() - len_before) as u64,
+        });
+    }
+
+    // パラメータ昇順、同パラメータ内ではスコア降順に並べ、各パラメータの先頭のみを残す。
+    records.sort_by(|lhs, rhs| {
+        lhs.param
+            .cmp(&rhs.param)
+            .then_with(|| rhs.score.cmp(&lhs.score))
+    });
+    records.dedup_by(|cur, prev| cur.param == prev.param);
+
+    let file = std::fs::File::create(output)
+        .with_context(|| format!("出力ファイル '{}' を作れない", output.display()))?;
+    let mut wtr = std::io::BufWriter::new(file);
+    for record in &records {
+        writeln!(wtr, "{record}")?;
+    }
+    wtr.flush()?;
+
+    Ok(records.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_io() {
+        let s = "0x0123\t0x45\t40\t2\t800\t1,1 2,3 8,6";
+        let record: ResultRecord = s.parse().unwrap();
+        assert_eq!(record.to_string(), s);
+        assert_eq!(record.seed_id().unwrap().to_param(), record.param);
+    }
+
+    #[test]
+    fn test_scan_progress() {
+        let src = "\
+0x0001\t0x00\t40\t1\t700\t1,1
+0x0002\t0x10\t40\t0\t750\t1,1
+
+0x0001\t0x05\t40\t4\t720\t1,1
+";
+        let progress = ScanProgress::from_reader(src.as_bytes()).unwrap();
+
+        assert_eq!(progress.recorded.len(), 3);
+        assert_eq!(progress.best_score, Some(Score::new(750)));
+        assert_eq!(progress.last, Some("0x0002,0x10,40,0".parse().unwrap()));
+
+        assert!(progress.is_processed(&"0x0000,0xFF,40,4".parse().unwrap()));
+        assert!(progress.is_processed(&"0x0002,0x10,40,0".parse().unwrap()));
+        assert!(!progress.is_processed(&"0x0002,0x10,40,1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_merge_result_files() {
+        // シャードごとに通知され、パラメータ順に統合される。
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("samegame-merge-test-{}.tsv", std::process::id()));
+        let record = |s: &str| s.parse::<ResultRecord>().unwrap();
+        let shards = [dir.join("a"), dir.join("b")].map(|name| {
+            let mut name = name.into_os_string();
+            name.push(format!("-samegame-merge-test-{}.tsv", std::process::id()));
+            std::path::PathBuf::from(name)
+        });
+        std::fs::write(
+            &shards[0],
+            "0x0002\t0x00\t40\t0\t700\t1,1\n0x0001\t0x00\t40\t0\t600\t1,1\n",
+        )
+        .unwrap();
+        std::fs::write(&shards[1], "0x0002\t0x00\t40\t0\t750\t1,2\n").unwrap();
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let sink = |event: &ProgressEvent| events.lock().unwrap().push(event.clone());
+        assert_eq!(merge_result_files(&shards, &path, &sink).unwrap(), 2);
+        assert_eq!(
+            events.into_inner().unwrap(),
+            [
+                ProgressEvent::ShardMerged {
+                    path: shards[0].clone(),
+                    record_count: 2,
+                },
+                ProgressEvent::ShardMerged {
+                    path: shards[1].clone(),
+                    record_count: 1,
+                },
+            ]
+        );
+        let merged: Vec<ResultRecord> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(record)
+            .collect();
+        assert_eq!(
+            merged,
+            [
+                record("0x0001\t0x00\t40\t0\t600\t1,1"),
+                record("0x0002\t0x00\t40\t0\t750\t1,2"),
+            ]
+        );
+
+        for path in shards.iter().chain([&path]) {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! 既存の結果の再探索関連。
+//!
+//! 枝刈りや上界を改良したら、弱い上界や探索予算で得た既存の結果を新しいソルバーで解き直し、
+//! 最適値が変わった面を洗い出す。
+
+use samegame_core::{RandomBoardParam, Score};
+
+use crate::record::ResultRecord;
+use samegame_solver::{Solver, SolverConfig};
+
+/// 再探索でスコアが改善した面。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScoreImprovement {
+    /// 既存の結果のスコア。
+    pub old_score: Score,
+    /// 再探索で得た結果。
+    pub record: ResultRecord,
+}
+
+/// 再探索で既存のスコアを確認できなかった理由。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResolveFailure {
+    /// 盤面生成パラメータがゲーム内に現れない盤面を生成する。
+    IllegalBoard,
+    /// 訪問ノード数の上限に達したか中断されたため、探索が完了しなかった。
+    Incomplete,
+    /// 探索は完了したが、既存のスコア以上の解が見つからなかった。
+    /// 既存の結果が誤っているか、新しいソルバーの枝刈りが正しくない。
+    NotReproduced,
+}
+
+impl std::fmt::Display for ResolveFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IllegalBoard => f.write_str("illegal board"),
+            Self::Incomplete => f.write_str("incomplete"),
+            Self::NotReproduced => f.write_str("not reproduced"),
+        }
+    }
+}
+
+/// 一括再探索の結果。`resolve_and_diff()` の戻り値。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeltaReport {
+    /// 再探索したレコード数。
+    pub record_count: usize,
+    /// スコアが改善した面たち (入力順)。
+    pub improvements: Vec<ScoreImprovement>,
+    /// 既存のスコアを確認できなかった面たち (入力順)。既存のスコアと組にする。
+    pub failures: Vec<(RandomBoardParam, Score, ResolveFailure)>,
+}
+
+impl DeltaReport {
+    /// スコアが変わらなかったレコード数を返す。
+    pub fn unchanged_count(&self) -> usize {
+        self.record_count - self.improvements.len() - self.failures.len()
+    }
+
+    /// 改善後の結果レコードたちを返す。既存の結果ファイルへの反映には `merge_result_files()` を使う。
+    pub fn improved_records(&self) -> impl Iterator<Item = &ResultRecord> {
+        self.improvements.iter().map(|imp| &imp.record)
+    }
+}
+
+impl std::fmt::Display for DeltaReport {
+    /// 1 行目に集計を、以降各行に改善した面と確認できなかった面を出力する。
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "records={} improved={} unchanged={} failed={}",
+            self.record_count,
+            self.improvements.len(),
+            self.unchanged_count(),
+            self.failures.len()
+        )?;
+        for imp in &self.improvements {
+            writeln!(
+                f,
+                "{}\t{} -> {} (+{})",
+                imp.record.param,
+                imp.old_score,
+                imp.record.score,
+                imp.record.score - imp.old_score
+            )?;
+        }
+        for (param, old_score, failure) in &self.failures {
+            writeln!(f, "{param}\t{old_score}\t{failure}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 既存の結果レコードたち `old` の面を設定 `config` のソルバーで解き直し、スコアの変化を集計する。
+///
+/// 各面は既存のスコア以上の解のみを探す (枝刈り用スコア閾値は既存のスコア未満に設定し、
+/// `config.prune_score_max` は無視する) ので、最適値を一から求めるより速い。
+/// 既存のスコアと同じ解しか見つからなければ変化なしとし、手順が異なっても報告しない。
+/// 改善の見込みがない面 (既に完全な探索で得た結果など) は呼び出し側で除いておくこと。
+pub fn resolve_and_diff<'a>(
+    old: impl IntoIterator<Item = &'a ResultRecord>,
+    config: &SolverConfig,
+) -> DeltaReport {
+    let mut solver = Solver::with_config(config);
+    let mut report = DeltaReport::default();
+
+    for record in old {
+        report.record_count += 1;
+
+        let Some((board, _)) = record.param.gen_legal_board() else {
+            report.failures.push((
+                record.param.clone(),
+                record.score,
+                ResolveFailure::IllegalBoard,
+            ));
+            continue;
+        };
+
+        solver.set_prune_score_max(record.score.saturating_sub(Score::new(1)));
+        let (res, stats) = solver.solve_with_stats(board);
+        match res {
+            Some((score, solution)) if score > record.score => {
+                report.improvements.push(ScoreImprovement {
+                    old_score: record.score,
+                    record: ResultRecord {
+                        param: record.param.clone(),
+                        score,
+                        solution,
+                    },
+                });
+            }
+            Some((score, _)) if score == record.score => {}
+            // 閾値以下の解が返ることもあるが、それは既存のスコアに届いていない。
+            _ => {
+                let failure = if stats.node_limit_reached || stats.cancelled {
+                    ResolveFailure::Incomplete
+                } else {
+                    ResolveFailure::NotReproduced
+                };
+                report
+                    .failures
+                    .push((record.param.clone(), record.score, failure));
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use samegame_core::ActionHistory;
+
+    use super::*;
+    use samegame_solver::verify_record;
+
+    #[test]
+    fn test_resolve_and_diff() {
+        let param: RandomBoardParam = "0x0123,0x45,40,2".parse().unwrap();
+        // この面の最大スコア。
+        let best = Score::new(290);
+
+        let record = |score: Score| ResultRecord {
+            param: param.clone(),
+            score,
+            solution: ActionHistory::new(),
+        };
+        let old = [
+            record(best),
+            record(best - Score::new(50)),
+            record(best + Score::new(1)),
+        ];
+
+        let report = resolve_and_diff(&old, &SolverConfig::default());
+        assert_eq!(report.record_count, 3);
+        assert_eq!(report.unchanged_count(), 1);
+        assert_eq!(report.improvements.len(), 1);
+        let imp = &report.improvements[0];
+        assert_eq!(imp.old_score, best - Score::new(50));
+        assert_eq!(imp.record.score, best);
+        assert_eq!(
+            verify_record(&param, imp.record.score, &imp.record.solution),
+            Ok(())
+        );
+        assert_eq!(
+            report.failures,
+            [(
+                param.clone(),
+                best + Score::new(1),
+                ResolveFailure::NotReproduced
+            )]
+        );
+        assert!(report
+            .to_string()
+            .starts_with("records=3 improved=1 unchanged=1 failed=1\n"));
+
+        // 探索予算が足りなければ確認できない。
+        let report = resolve_and_diff(
+            &old[..1],
+            &SolverConfig {
+                node_limit: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(report.failures, [(param, best, ResolveFailure::Incomplete)]);
+    }
+}
@@ -0,0 +1,233 @@
+//! 盤面から盤面生成パラメータを引く逆引き索引関連。
+//!
+//! 対話的なツールが「この盤面はどのシードか」を全パラメータの走査なしに答えるために使う。
+
+use std::fs::File;
+use std::io::{BufWriter, Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{ensure, Context as _};
+use samegame_core::{Board, RandomBoardParam, SeedId};
+
+use samegame_solver::Position;
+
+/// 索引ファイルの先頭のマジックナンバー (バージョンを含む)。
+const MAGIC: &[u8; 8] = b"SGRVIX01";
+
+/// バケット分けに用いるキーの上位ビット数。
+const BUCKET_BITS: u32 = 16;
+const BUCKET_COUNT: usize = 1 << BUCKET_BITS;
+
+/// 1 エントリのバイト数 (キー 8 バイト + `SeedId` 4 バイト)。
+const ENTRY_LEN: usize = 12;
+
+/// ヘッダ (マジックナンバー + エントリ数 + バケット境界) のバイト数。
+const HEADER_LEN: usize = MAGIC.len() + 8 + 4 * (BUCKET_COUNT + 1);
+
+/// 盤面の Zobrist ハッシュ値から、その盤面を生成する `SeedId` たちを引く、ファイル上の逆引き索引。
+///
+/// ファイル形式は、ヘッダの後に (キー, `SeedId`) のエントリを昇順に並べたもの。
+/// ヘッダにはキーの上位 16bit ごとのバケット境界 (エントリ番号) を持つので、
+/// `lookup()` はバケット 1 つ分 (平均数百バイト) を読むだけで済む (全体を読み込む必要はない)。
+///
+/// 全メソッドは `&self` を取り、内部で `Mutex` によりファイル読み込みを排他制御するので、
+/// `Arc<ReverseIndex>` を複数スレッドで共有できる。
+#[derive(Debug)]
+pub struct ReverseIndex {
+    file: Mutex<File>,
+    /// 各バケットの先頭のエントリ番号 (末尾に番兵としてエントリ数を持つ)。
+    buckets: Vec<u32>,
+}
+
+impl ReverseIndex {
+    /// パラメータたち `params` から索引ファイル `path` を作り、登録したエントリ数を返す。
+    ///
+    /// ゲーム内に現れない盤面を生成するパラメータは登録しない。
+    /// 全パラメータを対象とする場合、同じ盤面を生成するパラメータは代表元のみでよいので
+    /// `RandomBoardParam::all_canonical()` を渡すとよい。
+    /// エントリは全てメモリ上で整列するので、全パラメータ分では数百 MB のメモリを要する。
+    ///
+    /// `SeedId` で表せないパラメータがあればエラーを返す。
+    pub fn build(
+        path: impl AsRef<Path>,
+        params: impl IntoIterator<Item = RandomBoardParam>,
+    ) -> anyhow::Result<usize> {
+        let path = path.as_ref();
+
+        let mut entries = Vec::<(u64, SeedId)>::new();
+        for param in params {
+            let Some((board, _)) = param.gen_legal_board() else {
+                continue;
+            };
+            let seed = SeedId::try_from(&param)?;
+            entries.push((Position::new(board).key(), seed));
+        }
+        entries.sort_unstable();
+        ensure!(
+            u32::try_from(entries.len()).is_ok(),
+            "エントリ数が多すぎる: {}",
+            entries.len()
+        );
+
+        let mut buckets = vec![0_u32; BUCKET_COUNT + 1];
+        for &(key, _) in &entries {
+            buckets[bucket_of(key) + 1] += 1;
+        }
+        for i in 0..BUCKET_COUNT {
+            buckets[i + 1] += buckets[i];
+        }
+
+        let file = File::create(path)
+            .with_context(|| format!("索引ファイル '{}' を作れない", path.display()))?;
+        let mut wtr = BufWriter::new(file);
+        wtr.write_all(MAGIC)?;
+        wtr.write_all(&(entries.len() as u64).to_le_bytes())?;
+        for offset in &buckets {
+            wtr.write_all(&offset.to_le_bytes())?;
+        }
+        for (key, seed) in &entries {
+            wtr.write_all(&key.to_le_bytes())?;
+            wtr.write_all(&seed.to_inner().to_le_bytes())?;
+        }
+        wtr.flush()?;
+
+        Ok(entries.len())
+    }
+
+    /// 索引ファイル `path` を開く。読み込むのはヘッダのみ。
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+
+        let mut file = File::open(path)
+            .with_context(|| format!("索引ファイル '{}' を開けない", path.display()))?;
+
+        let mut header = vec![0; HEADER_LEN];
+        file.read_exact(&mut header)
+            .with_context(|| format!("索引ファイル '{}' のヘッダを読めない", path.display()))?;
+        ensure!(
+            header.starts_with(MAGIC),
+            "索引ファイル '{}' のマジックナンバーが一致しない",
+            path.display()
+        );
+
+        let len = u64::from_le_bytes(header[MAGIC.len()..][..8].try_into().unwrap());
+        let buckets: Vec<u32> = header[MAGIC.len() + 8..]
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        ensure!(
+            buckets.windows(2).all(|w| w[0] <= w[1]) && u64::from(buckets[BUCKET_COUNT]) == len,
+            "索引ファイル '{}' のバケット境界が壊れている",
+            path.display()
+        );
+
+        let file_len = file.metadata()?.len();
+        ensure!(
+            file_len == (HEADER_LEN + ENTRY_LEN * len as usize) as u64,
+            "索引ファイル '{}' のサイズが一致しない",
+            path.display()
+        );
+
+        Ok(Self {
+            file: Mutex::new(file),
+            buckets,
+        })
+    }
+
+    /// 登録されているエントリ数を返す。
+    pub fn len(&self) -> usize {
+        self.buckets[BUCKET_COUNT] as usize
+    }
+
+    /// 登録されているエントリがないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 盤面 `board` を生成する `SeedId` たちを昇順で返す。登録されていなければ空となる。
+    ///
+    /// ハッシュ値が衝突した別の盤面のエントリは、盤面を生成し直して除外する。
+    pub fn lookup(&self, board: &Board) -> anyhow::Result<Vec<SeedId>> {
+        let key = Position::new(board.clone()).key();
+        let bucket = bucket_of(key);
+        let (start, end) = (self.buckets[bucket], self.buckets[bucket + 1]);
+
+        let mut buf = vec![0; ENTRY_LEN * (end - start) as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(
+                (HEADER_LEN + ENTRY_LEN * start as usize) as u64,
+            ))?;
+            file.read_exact(&mut buf)?;
+        }
+
+        let mut seeds = Vec::new();
+        for entry in buf.chunks_exact(ENTRY_LEN) {
+            let entry_key = u64::from_le_bytes(entry[..8].try_into().unwrap());
+            if entry_key != key {
+                continue;
+            }
+            let inner = u32::from_le_bytes(entry[8..].try_into().unwrap());
+            let seed = SeedId::from_inner(inner)
+                .with_context(|| format!("索引ファイルの SeedId が無効: 0x{inner:08X}"))?;
+            if seed.to_param().gen_board().0 == *board {
+                seeds.push(seed);
+            }
+        }
+
+        Ok(seeds)
+    }
+}
+
+fn bucket_of(key: u64) -> usize {
+    (key >> (u64::BITS - BUCKET_BITS)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_index() {
+        let path = std::env::temp_dir().join(format!(
+            "samegame-reverse-index-test-{}.idx",
+            std::process::id()
+        ));
+
+        let params: Vec<(RandomBoardParam, u32)> =
+            RandomBoardParam::all_canonical_in(0x1230..=0x1231).collect();
+        let len =
+            ReverseIndex::build(&path, params.iter().map(|(param, _)| param.clone())).unwrap();
+        let index = ReverseIndex::open(&path).unwrap();
+        assert_eq!(index.len(), len);
+        assert!(len > 0 && len <= params.len());
+
+        // 登録した盤面は、それを生成する代表元のパラメータを全て引ける。
+        for (param, _) in params.iter().step_by(37) {
+            let Some((board, _)) = param.gen_legal_board() else {
+                continue;
+            };
+            let seeds = index.lookup(&board).unwrap();
+            assert!(seeds.contains(&param.seed_id().unwrap()));
+            assert!(seeds.windows(2).all(|w| w[0] < w[1]));
+            assert!(seeds
+                .iter()
+                .all(|seed| seed.to_param().gen_board().0 == board));
+        }
+
+        // 登録されていない盤面は引けない。
+        assert_eq!(index.lookup(&Board::empty()).unwrap(), []);
+        let (other, _) = "0x7000,0x00,40,0"
+            .parse::<RandomBoardParam>()
+            .unwrap()
+            .gen_legal_board()
+            .unwrap();
+        assert_eq!(index.lookup(&other).unwrap(), []);
+
+        std::fs::write(&path, b"SGRVIX00").unwrap();
+        assert!(ReverseIndex::open(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! 実機 RTA で到達可能な盤面生成パラメータ関連。
+//!
+//! `RandomBoardParam::all()` は TAS でしか狙えない内部状態も含む。
+//! ここでは電源投入からのフレーム経過を単純なモデルで追い、人間が実際に狙えるパラメータのみに絞り込む。
+
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+
+use samegame_core::{Board, GameEntropy, GameRng, RandomBoardParam};
+
+/// 電源投入後の乱数生成器および NMI カウンタの推移のモデル。
+///
+/// 毎フレーム NMI カウンタがインクリメントされ、その NMI カウンタの下で乱数生成器が
+/// `rng_calls_per_frame` 回呼ばれるとみなす。
+///
+/// NOTE: `Default` の値は仮のもので、実機で測定したものではない。
+/// 実測値が得られたらそれを与えること。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PowerOnModel {
+    /// 電源投入直後の乱数生成器の内部状態。
+    pub initial_rng_state: u16,
+    /// 電源投入直後の NMI カウンタ。
+    pub initial_nmi_counter: u8,
+    /// 1 フレームあたりの乱数生成器の呼び出し回数。
+    pub rng_calls_per_frame: u32,
+}
+
+impl Default for PowerOnModel {
+    fn default() -> Self {
+        Self {
+            initial_rng_state: 0,
+            initial_nmi_counter: 0,
+            rng_calls_per_frame: 1,
+        }
+    }
+}
+
+impl PowerOnModel {
+    /// 電源投入から `0..=max_frames` フレーム経過時点の (乱数生成器, NMI カウンタ) を順に列挙する。
+    pub fn states(
+        &self,
+        max_frames: u32,
+    ) -> impl std::iter::FusedIterator<Item = (GameRng, u8)> + Clone {
+        let calls = self.rng_calls_per_frame;
+
+        let init = (
+            GameRng::new(self.initial_rng_state),
+            self.initial_nmi_counter,
+        );
+        std::iter::successors(Some(init), move |&(mut rng, nmi_counter)| {
+            let nmi_counter = nmi_counter.wrapping_add(1);
+            for _ in 0..calls {
+                rng.gen(nmi_counter);
+            }
+            Some((rng, nmi_counter))
+        })
+        .take(max_frames as usize + 1)
+        .fuse()
+    }
+
+    /// 電源投入から `frames` フレーム経過時点の (乱数生成器, NMI カウンタ) を返す。
+    pub fn state_at(&self, frames: u32) -> (GameRng, u8) {
+        self.states(frames).last().unwrap()
+    }
+}
+
+/// 実機 RTA で到達可能な盤面生成パラメータの集合。
+///
+/// 電源投入から `max_frames` フレーム以内のいずれかの時点で乱数生成器の内部状態が確定し、
+/// その後のメニュー操作で (乱数生成器を呼ばずに) NMI カウンタのみが `menu_frames` フレーム分進んでから
+/// 盤面が生成されるとみなす。ゲーム内エントロピーは全ての値を取りうるとする。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RtaReachableParams {
+    /// 電源投入後の推移のモデル。
+    pub model: PowerOnModel,
+    /// 乱数生成器の内部状態が確定するまでの最大フレーム数。
+    pub max_frames: u32,
+    /// メニュー操作に要するフレーム数の範囲。
+    pub menu_frames: RangeInclusive<u8>,
+}
+
+impl Default for RtaReachableParams {
+    /// 電源投入から 1 分以内、メニュー操作 0..=60 フレーム。
+    fn default() -> Self {
+        Self {
+            model: PowerOnModel::default(),
+            max_frames: 60 * 60,
+            menu_frames: 0..=60,
+        }
+    }
+}
+
+impl RtaReachableParams {
+    /// 到達可能な (乱数生成器の内部状態, NMI カウンタ) の集合を返す。
+    ///
+    /// 内部状態の bit15 は無意味なので落としている (`RandomBoardParam::all()` と同じ範囲になる)。
+    fn rng_nmi_pairs(&self) -> BTreeSet<(u16, u8)> {
+        let mut pairs = BTreeSet::new();
+
+        for (rng, nmi_counter) in self.model.states(self.max_frames) {
+            for menu in self.menu_frames.clone() {
+                pairs.insert((rng.state() & 0x7FFF, nmi_counter.wrapping_add(menu)));
+            }
+        }
+
+        pairs
+    }
+
+    /// 到達可能な全パラメータを昇順で列挙する。
+    pub fn params(&self) -> impl std::iter::FusedIterator<Item = RandomBoardParam> + Clone {
+        self.rng_nmi_pairs()
+            .into_iter()
+            .flat_map(|(rng_state, nmi_counter)| {
+                GameEntropy::all().map(move |entropy| {
+                    RandomBoardParam::new(rng_state, nmi_counter, 40, entropy).unwrap()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .fuse()
+    }
+
+    /// 到達可能なパラメータの個数を返す。
+    pub fn param_count(&self) -> usize {
+        self.rng_nmi_pairs().len() * GameEntropy::NUM
+    }
+
+    /// パラメータ `param` が到達可能かどうかを返す。
+    ///
+    /// 多数のパラメータを判定する場合は `params()` の結果を集合に入れる方が速い。
+    pub fn contains(&self, param: &RandomBoardParam) -> bool {
+        param.nmi_timing() == 40
+            && self
+                .rng_nmi_pairs()
+                .contains(&(param.rng_state(), param.nmi_counter()))
+    }
+
+    /// 到達可能かつゲーム内に現れうる全ての盤面について (生成パラメータ, 盤面, 生成後の乱数生成器) を列挙する。
+    ///
+    /// `enumerate_all_legal_board()` の RTA 版。
+    pub fn legal_boards(
+        &self,
+    ) -> impl std::iter::FusedIterator<Item = (RandomBoardParam, Board, GameRng)> + Clone {
+        self.params().filter_map(|param| {
+            let (board, rng_after) = param.gen_legal_board()?;
+            Some((param, board, rng_after))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_on_model() {
+        let model = PowerOnModel {
+            initial_rng_state: 0x1234,
+            initial_nmi_counter: 0xFE,
+            rng_calls_per_frame: 2,
+        };
+
+        assert_eq!(model.state_at(0), (GameRng::new(0x1234), 0xFE));
+
+        let mut rng = GameRng::new(0x1234);
+        for nmi_counter in [0xFF, 0x00, 0x01] {
+            rng.gen(nmi_counter);
+            rng.gen(nmi_counter);
+        }
+        assert_eq!(model.state_at(3), (rng, 0x01));
+        assert_eq!(model.states(3).count(), 4);
+    }
+
+    #[test]
+    fn test_rta_reachable_params() {
+        let reach = RtaReachableParams {
+            model: PowerOnModel::default(),
+            max_frames: 10,
+            menu_frames: 0..=2,
+        };
+
+        let params: Vec<_> = reach.params().collect();
+        assert_eq!(params.len(), reach.param_count());
+        assert!(params.len() <= 11 * 3 * GameEntropy::NUM);
+        assert!(params.windows(2).all(|w| w[0] < w[1]));
+        assert!(params.iter().all(|param| reach.contains(param)));
+
+        // 電源投入直後にメニュー操作 0 フレームで生成した場合。
+        let (rng, nmi_counter) = reach.model.state_at(0);
+        let param = RandomBoardParam::new(rng.state(), nmi_counter, 40, GameEntropy::MIN).unwrap();
+        assert!(reach.contains(&param));
+
+        // 範囲外のメニュー操作フレーム数。
+        let param = RandomBoardParam::new(
+            rng.state(),
+            nmi_counter.wrapping_add(100),
+            40,
+            GameEntropy::MIN,
+        )
+        .unwrap();
+        assert!(!reach.contains(&param));
+
+        assert!(reach
+            .legal_boards()
+            .all(|(param, _, _)| reach.contains(&param)));
+    }
+}
@@ -0,0 +1,138 @@
+//! 結果レコード形式のスキーマ関連。
+//!
+//! 盤面文字列などの形式のスキーマは `samegame-core` にある (`Board::schema()` など)。
+//! ここではソルバーの出力である結果レコード形式のスキーマと、全形式の一覧を提供する。
+
+use samegame_core::{
+    ActionHistory, Board, FieldSchema, FieldType, FormatSchema, RandomBoardParam, PARAM_FIELDS,
+};
+
+use crate::record::ResultRecord;
+
+/// 全テキスト形式のスキーマを返す。
+pub fn all_schemas() -> [&'static FormatSchema; 4] {
+    [
+        Board::schema(),
+        ActionHistory::schema(),
+        RandomBoardParam::schema(),
+        ResultRecord::schema(),
+    ]
+}
+
+/// 全テキスト形式のスキーマを JSON 配列として返す。
+pub fn all_schemas_json() -> String {
+    let schemas: Vec<String> = all_schemas().iter().map(|s| s.to_json()).collect();
+    format!("[{}]", schemas.join(","))
+}
+
+static RECORD_SCHEMA: FormatSchema = FormatSchema {
+    name: "record",
+    version: ResultRecord::FORMAT_VERSION,
+    description: "1 面の探索結果 (solve_all の出力の 1 行)。6 フィールドをタブ区切りで並べる",
+    separator: "\t",
+    fields: &[
+        PARAM_FIELDS[0],
+        PARAM_FIELDS[1],
+        PARAM_FIELDS[2],
+        PARAM_FIELDS[3],
+        FieldSchema {
+            name: "score",
+            ty: FieldType::Uint {
+                max: u32::MAX as u64,
+                hex: false,
+            },
+            description: "最終スコア。10 進のみ",
+        },
+        FieldSchema {
+            name: "solution",
+            ty: FieldType::Format { format: "history" },
+            description: "解の手順",
+        },
+    ],
+    repeat: (1, 1),
+};
+
+impl ResultRecord {
+    /// 結果レコード形式のバージョン。
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// 結果レコード形式のスキーマを返す。
+    pub fn schema() -> &'static FormatSchema {
+        &RECORD_SCHEMA
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use samegame_core::Score;
+
+    /// 文字列 `s` がスキーマ `schema` のフィールド構成に従うことを (パターン以外について) 確かめる。
+    fn assert_conforms(schema: &FormatSchema, s: &str) {
+        let tokens: Vec<&str> = if s.is_empty() {
+            vec![]
+        } else {
+            s.split(schema.separator).collect()
+        };
+        let n = schema.fields.len();
+        assert_eq!(tokens.len() % n, 0, "{}: '{s}'", schema.name);
+        let repeat = tokens.len() / n;
+        assert!(
+            (schema.repeat.0..=schema.repeat.1).contains(&repeat),
+            "{}",
+            schema.name
+        );
+
+        for (token, field) in tokens.iter().zip(schema.fields.iter().cycle()) {
+            match field.ty {
+                FieldType::Uint { max, hex } => {
+                    let digits = if hex {
+                        u64::from_str_radix(token.strip_prefix("0x").unwrap(), 16)
+                    } else {
+                        token.parse()
+                    };
+                    assert!(digits.unwrap() <= max, "{}: '{token}'", field.name);
+                }
+                FieldType::Pattern { .. } => assert!(!token.is_empty()),
+                FieldType::Format { format } => {
+                    let sub = all_schemas()
+                        .into_iter()
+                        .find(|s| s.name == format)
+                        .unwrap();
+                    assert_conforms(sub, token);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_schema() {
+        let param: RandomBoardParam = "0x0012,0x34,40,3".parse().unwrap();
+        let board = param.gen_board().0;
+        let solution: ActionHistory = "1,1 2,3 8,6".parse().unwrap();
+        let record = ResultRecord {
+            param: param.clone(),
+            score: Score::new(1234),
+            solution: solution.clone(),
+        };
+
+        assert_conforms(Board::schema(), board.to_string().trim_end_matches('\n'));
+        assert_conforms(ActionHistory::schema(), &solution.to_string());
+        assert_conforms(ActionHistory::schema(), &ActionHistory::new().to_string());
+        assert_conforms(RandomBoardParam::schema(), &param.to_string());
+        assert_conforms(ResultRecord::schema(), &record.to_string());
+
+        assert!(Board::schema()
+            .ensure_version(Board::FORMAT_VERSION)
+            .is_ok());
+        assert!(Board::schema()
+            .ensure_version(Board::FORMAT_VERSION + 1)
+            .is_err());
+
+        let json = all_schemas_json();
+        assert!(json.starts_with("[{\"name\":\"board\",\"version\":1,"));
+        assert!(json.contains("\"separator\":\"\\t\""));
+        assert!(json.contains("\"type\":\"format\",\"format\":\"history\""));
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+    }
+}
@@ -0,0 +1,662 @@
+//! 盤面から最適解を引く索引関連。
+//!
+//! ヒントエンジンや Web サービスなどの対話的なツールが、事前計算した最適解を即座に返すために使う。
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use anyhow::{ensure, Context as _};
+use samegame_core::{ActionHistory, Board, Score, Square};
+use samegame_solver::convert::{decode_compact, encode_compact, COMPACT_LEN};
+use samegame_solver::logging::warn;
+use samegame_solver::{hashmap_memory_usage, Position, U64HashMap};
+
+/// 索引ファイルの先頭のマジックナンバー (バージョンを含む)。
+const MAGIC: &[u8; 8] = b"SGSIDX01";
+
+/// ハッシュ索引ファイルの先頭のマジックナンバー (バージョンを含む)。
+const MAGIC_HASH: &[u8; 8] = b"SGSHSH01";
+
+/// ハッシュ索引ファイルのヘッダのバイト数 (マジックナンバー 8 バイト + 対象レコードのバイト数 8 バイト)。
+const HASH_HEADER_LEN: usize = MAGIC_HASH.len() + 8;
+
+/// ハッシュ索引ファイルの 1 エントリのバイト数 (ハッシュ値 8 バイト + レコード位置 8 バイト)。
+const HASH_ENTRY_LEN: usize = 16;
+/// 1 レコードのバイト数。
+/// (盤面 18 バイト + スコア 4 バイト + 手数 1 バイト + 各手 1 バイト × `ActionHistory::CAPACITY`, 末尾は 0 詰め)
+const RECORD_LEN: usize = 48;
+
+const OFFSET_SCORE: usize = COMPACT_LEN;
+const OFFSET_LEN: usize = OFFSET_SCORE + 4;
+const OFFSET_SQUARES: usize = OFFSET_LEN + 1;
+
+const _: () = assert!(OFFSET_SQUARES + ActionHistory::CAPACITY <= RECORD_LEN);
+
+/// 盤面の最適解。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Solution {
+    /// 最終スコア。
+    pub score: Score,
+    /// 手順。
+    pub solution: ActionHistory,
+}
+
+/// 盤面をキーとして最適解を引く、ファイルに永続化された索引。
+///
+/// ファイル形式はマジックナンバーの後に固定長レコードを追記していくだけのもの。
+/// 同じ盤面のレコードが複数ある場合、最後のものが有効となる。
+///
+/// レコードの Zobrist ハッシュ値から位置への索引は、ハッシュ値順に並べて別ファイル (索引ファイルのパスに `.hash` を付けたもの)
+/// に保存でき (`compact()`, `save_hash_index()`)、`open()` 時にはそれを二分探索して引く。
+/// 保存した索引より後に追記されたレコードのみ、`open()` 時に読んでメモリ上のハッシュ表に載せる。
+/// feature `mmap` が有効なら両ファイルをメモリマップで開くので、`open()` はファイルサイズによらず速い。
+/// 無効ならファイル全体をメモリに読み込む (索引を作り直さないだけ速い)。
+///
+/// 全メソッドは `&self` を取り、内部で `RwLock` により排他制御するので、
+/// `Arc<SolutionIndex>` を複数スレッドで共有して並行に参照できる (参照同士はブロックしない)。
+/// 一方、プロセス間の排他制御はしないので、1 つの索引ファイルを同時に開く `SolutionIndex` は 1 つのみでなければならない
+/// (`open()` は末尾の不完全なレコードを切り詰め、`insert()` は追記するので、他のプロセスが読み書き中だと壊れる)。
+#[derive(Debug)]
+pub struct SolutionIndex {
+    inner: RwLock<Inner>,
+}
+
+/// ファイル内容。feature `mmap` が有効ならメモリマップ、さもなくばメモリに読み込んだもの。
+#[cfg(feature = "mmap")]
+type FileBytes = memmap2::Mmap;
+#[cfg(not(feature = "mmap"))]
+type FileBytes = Vec<u8>;
+
+#[derive(Debug)]
+struct Inner {
+    path: PathBuf,
+    file: File,
+    /// 開いた時点のファイル内容 (マジックナンバーを含む)。
+    base: FileBytes,
+    /// `base` の後に追記したレコード。
+    tail: Vec<u8>,
+    /// 保存済みのハッシュ索引。
+    saved: Option<SavedHashIndex>,
+    /// 保存済みのハッシュ索引に含まれないレコードについての、盤面のハッシュ値からレコード位置への索引。
+    /// `saved` より優先する。
+    index: U64HashMap<u64, usize>,
+    /// 登録されている盤面数。
+    len: usize,
+}
+
+/// ハッシュ索引ファイルの内容。
+///
+/// ヘッダの後に (ハッシュ値, レコード位置) をハッシュ値の昇順に並べたもの (いずれも u64 LE)。
+#[derive(Debug)]
+struct SavedHashIndex {
+    bytes: FileBytes,
+    /// 索引の対象となる先頭のレコード群のバイト数。
+    covered: usize,
+}
+
+impl SolutionIndex {
+    /// 索引ファイル `path` を開く。存在しなければ新規作成する。
+    ///
+    /// ハッシュ索引ファイルが無効なら警告を出し、それを用いずにハッシュ表を作る。
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let inner = Inner::load(path.as_ref())?;
+
+        Ok(Self {
+            inner: RwLock::new(inner),
+        })
+    }
+
+    /// 登録されている盤面数を返す。
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().len
+    }
+
+    /// 登録されている盤面がないかどうかを返す。
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// メモリ使用量の概算値 (バイト数) を返す。
+    ///
+    /// メモリに読み込んだファイル内容と、ハッシュ値からレコード位置への索引の分を数える。
+    /// メモリマップした分は含まない。
+    pub fn approx_memory_usage(&self) -> usize {
+        let inner = self.inner.read().unwrap();
+        #[cfg(feature = "mmap")]
+        let loaded = 0;
+        #[cfg(not(feature = "mmap"))]
+        let loaded = inner.base.capacity()
+            + inner
+                .saved
+                .as_ref()
+                .map_or(0, |saved| saved.bytes.capacity());
+
+        std::mem::size_of::<Self>()
+            + loaded
+            + inner.tail.capacity()
+            + hashmap_memory_usage(&inner.index)
+    }
+
+    /// 盤面 `board` の解を引く。登録されていなければ `None` を返す。
+    ///
+    /// 索引ファイルが壊れていてレコードが無効ならエラーを返す。
+    pub fn lookup(&self, board: &Board) -> anyhow::Result<Option<Solution>> {
+        let key = Position::new(board.clone()).key();
+        let inner = self.inner.read().unwrap();
+
+        let Some(offset) = inner.find(key) else {
+            return Ok(None);
+        };
+        let record = inner.record(offset);
+
+        // ハッシュ値が衝突しているなら別の盤面。
+        if record[..COMPACT_LEN] != encode_compact(board) {
+            return Ok(None);
+        }
+
+        decode_solution(record).map(Some)
+    }
+
+    /// 盤面 `board` の解 `solution` を登録する。
+    ///
+    /// 既に同じ盤面の解が登録されている場合、スコアが真に高いときのみ置き換える。
+    /// 登録 (置き換え) したかどうかを返す。レコードは直ちにファイルへ追記する。
+    /// 既存のレコードが壊れていて無効ならエラーを返す。
+    pub fn insert(&self, board: &Board, solution: &Solution) -> anyhow::Result<bool> {
+        let key = Position::new(board.clone()).key();
+        let board_bytes = encode_compact(board);
+        let mut inner = self.inner.write().unwrap();
+
+        let existing = inner.find(key);
+        if let Some(offset) = existing {
+            let record = inner.record(offset);
+            ensure!(
+                record[..COMPACT_LEN] == board_bytes,
+                "盤面のハッシュ値が衝突した:\n{board}"
+            );
+            if decode_solution(record)?.score >= solution.score {
+                return Ok(false);
+            }
+        }
+
+        let record = encode_record(&board_bytes, solution);
+        inner.file.write_all(&record)?;
+        inner.file.flush()?;
+
+        let offset = inner.records_len();
+        inner.tail.extend_from_slice(&record);
+        inner.index.insert(key, offset);
+        if existing.is_none() {
+            inner.len += 1;
+        }
+
+        Ok(true)
+    }
+
+    /// 同じ盤面の古いレコードを除いてファイルを書き直し、ハッシュ索引も保存する。
+    pub fn compact(&self) -> anyhow::Result<()> {
+        let mut inner = self.inner.write().unwrap();
+
+        // 元のレコード順を保って書き直す。
+        let mut entries: Vec<(usize, u64)> = inner
+            .entries()
+            .into_iter()
+            .map(|(key, offset)| (offset, key))
+            .collect();
+        entries.sort_unstable();
+
+        let mut records = Vec::with_capacity(MAGIC.len() + RECORD_LEN * entries.len());
+        records.extend_from_slice(MAGIC);
+        let mut hash_entries = Vec::with_capacity(entries.len());
+        for (offset, key) in entries {
+            hash_entries.push((key, records.len() - MAGIC.len()));
+            records.extend_from_slice(inner.record(offset));
+        }
+
+        // 途中で中断しても古いハッシュ索引を誤って使わないよう、先に消しておく。
+        let hash_path = hash_index_path(&inner.path);
+        match std::fs::remove_file(&hash_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        // メモリマップ中のファイルを切り詰めないよう、別ファイルに書いてから置き換える。
+        write_file_atomic(&inner.path, &records)?;
+        write_hash_index(&hash_path, records.len() - MAGIC.len(), hash_entries)?;
+
+        *inner = Inner::load(&inner.path)?;
+
+        Ok(())
+    }
+
+    /// 現在の全レコードを対象とするハッシュ索引をファイルに保存し、次回の `open()` を速くする。
+    pub fn save_hash_index(&self) -> anyhow::Result<()> {
+        let inner = self.inner.read().unwrap();
+
+        write_hash_index(
+            &hash_index_path(&inner.path),
+            inner.records_len(),
+            inner.entries(),
+        )
+    }
+}
+
+impl Inner {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("索引ファイル '{}' を開けない", path.display()))?;
+
+        if file.metadata()?.len() == 0 {
+            file.write_all(MAGIC)?;
+        }
+        let mut magic = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        (&mut file)
+            .take(MAGIC.len() as u64)
+            .read_to_end(&mut magic)?;
+        ensure!(
+            magic == MAGIC,
+            "索引ファイル '{}' の形式が無効 (マジックナンバー不一致)",
+            path.display()
+        );
+
+        // 書き込み途中で中断された末尾の不完全なレコードは捨てる (以後の追記がずれないよう、ファイルも切り詰める)。
+        let file_len = file.metadata()?.len() as usize;
+        let records_len = (file_len - MAGIC.len()) / RECORD_LEN * RECORD_LEN;
+        if MAGIC.len() + records_len != file_len {
+            file.set_len((MAGIC.len() + records_len) as u64)?;
+        }
+        let base = read_file_bytes(&mut file)?;
+
+        let hash_path = hash_index_path(path);
+        let saved = match SavedHashIndex::load(&hash_path, records_len) {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!(
+                    "ハッシュ索引ファイル '{}' を用いない: {e:#}",
+                    hash_path.display()
+                );
+                None
+            }
+        };
+
+        let mut this = Self {
+            path: path.to_owned(),
+            file,
+            base,
+            tail: Vec::new(),
+            saved,
+            index: U64HashMap::default(),
+            len: 0,
+        };
+
+        // 保存済みのハッシュ索引より後のレコードを索引に載せる。
+        let covered = this.saved.as_ref().map_or(0, |saved| saved.covered);
+        for offset in (covered..records_len).step_by(RECORD_LEN) {
+            let board = decode_compact(this.record(offset)[..COMPACT_LEN].try_into().unwrap())
+                .with_context(|| {
+                    format!(
+                        "索引ファイルのレコード #{} の盤面が無効",
+                        offset / RECORD_LEN
+                    )
+                })?;
+            let key = Position::new(board).key();
+            if this.find(key).is_none() {
+                this.len += 1;
+            }
+            this.index.insert(key, offset);
+        }
+        this.len += this.saved.as_ref().map_or(0, SavedHashIndex::len);
+
+        Ok(this)
+    }
+
+    /// レコードの総バイト数を返す。
+    fn records_len(&self) -> usize {
+        self.base.len() - MAGIC.len() + self.tail.len()
+    }
+
+    /// 位置 `offset` のレコードを返す。
+    fn record(&self, offset: usize) -> &[u8] {
+        let base = &self.base[MAGIC.len()..];
+        if offset < base.len() {
+            &base[offset..][..RECORD_LEN]
+        } else {
+            &self.tail[offset - base.len()..][..RECORD_LEN]
+        }
+    }
+
+    /// ハッシュ値 `key` の盤面の有効なレコードの位置を返す。
+    fn find(&self, key: u64) -> Option<usize> {
+        if let Some(&offset) = self.index.get(&key) {
+            return Some(offset);
+        }
+        self.saved.as_ref()?.find(key)
+    }
+
+    /// 全ての (ハッシュ値, 有効なレコードの位置) を返す。
+    fn entries(&self) -> Vec<(u64, usize)> {
+        let saved = self
+            .saved
+            .iter()
+            .flat_map(|saved| saved.entries())
+            .filter(|(key, _)| !self.index.contains_key(key));
+
+        saved
+            .chain(self.index.iter().map(|(&key, &offset)| (key, offset)))
+            .collect()
+    }
+}
+
+impl SavedHashIndex {
+    /// ハッシュ索引ファイル `path` を読み込む。ファイルがなければ `None` を返す。
+    ///
+    /// 索引ファイルのレコードのバイト数 `records_len` と矛盾するならエラーを返す。
+    fn load(path: &Path, records_len: usize) -> anyhow::Result<Option<Self>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let bytes = read_file_bytes(&mut file)?;
+
+        ensure!(
+            bytes.len() >= HASH_HEADER_LEN && bytes.starts_with(MAGIC_HASH),
+            "形式が無効 (マジックナンバー不一致)"
+        );
+        ensure!(
+            (bytes.len() - HASH_HEADER_LEN).is_multiple_of(HASH_ENTRY_LEN),
+            "形式が無効 (サイズ不正)"
+        );
+        let covered =
+            u64::from_le_bytes(bytes[MAGIC_HASH.len()..HASH_HEADER_LEN].try_into().unwrap());
+        let covered = usize::try_from(covered)?;
+        ensure!(
+            covered.is_multiple_of(RECORD_LEN) && covered <= records_len,
+            "索引ファイルと整合しない (対象レコードのバイト数: {covered}, 索引ファイルのレコードのバイト数: {records_len})"
+        );
+
+        Ok(Some(Self { bytes, covered }))
+    }
+
+    fn len(&self) -> usize {
+        (self.bytes.len() - HASH_HEADER_LEN) / HASH_ENTRY_LEN
+    }
+
+    fn entry(&self, i: usize) -> (u64, usize) {
+        let entry = &self.bytes[HASH_HEADER_LEN + HASH_ENTRY_LEN * i..][..HASH_ENTRY_LEN];
+        let key = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        let offset = u64::from_le_bytes(entry[8..].try_into().unwrap());
+
+        (key, offset as usize)
+    }
+
+    /// 全ての (ハッシュ値, レコード位置) を返す。範囲外のレコード位置は除く。
+    fn entries(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
+        (0..self.len())
+            .map(|i| self.entry(i))
+            .filter(|&(_, offset)| self.is_valid_offset(offset))
+    }
+
+    fn find(&self, key: u64) -> Option<usize> {
+        // 二分探索で key 以上の最初のエントリを探す。
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.entry(mid).0 < key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let (found, offset) = (lo < self.len()).then(|| self.entry(lo))?;
+        (found == key && self.is_valid_offset(offset)).then_some(offset)
+    }
+
+    fn is_valid_offset(&self, offset: usize) -> bool {
+        offset.is_multiple_of(RECORD_LEN) && offset < self.covered
+    }
+}
+
+/// 索引ファイル `path` に対するハッシュ索引ファイルのパスを返す。
+fn hash_index_path(path: &Path) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(".hash");
+    path.into()
+}
+
+/// 先頭 `covered` バイトのレコードに対するハッシュ索引 `entries` ((ハッシュ値, レコード位置) の列) をファイル `path` に書き込む。
+fn write_hash_index(
+    path: &Path,
+    covered: usize,
+    mut entries: Vec<(u64, usize)>,
+) -> anyhow::Result<()> {
+    entries.sort_unstable();
+
+    let mut bytes = Vec::with_capacity(HASH_HEADER_LEN + HASH_ENTRY_LEN * entries.len());
+    bytes.extend_from_slice(MAGIC_HASH);
+    bytes.extend_from_slice(&(covered as u64).to_le_bytes());
+    for (key, offset) in entries {
+        bytes.extend_from_slice(&key.to_le_bytes());
+        bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+    }
+
+    write_file_atomic(path, &bytes)
+}
+
+/// ファイル `path` の内容を `bytes` で置き換える。一時ファイルに書いてから名前を変えるので、途中で中断しても壊れない。
+fn write_file_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let mut tmp_path = OsString::from(path);
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = File::create(&tmp_path)
+        .with_context(|| format!("一時ファイル '{}' を作れない", tmp_path.display()))?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// ファイル `file` の内容全体を返す。
+#[cfg(feature = "mmap")]
+fn read_file_bytes(file: &mut File) -> std::io::Result<FileBytes> {
+    // SAFETY: 索引ファイルを同時に開く `SolutionIndex` は 1 つのみで、外部からも書き換えないものとする
+    // (`SolutionIndex` のドキュメントを参照)。その下で、ファイルの切り詰め (`Inner::load()`) はマップする前に行い、
+    // 以後は追記するか、別ファイルに書いてから置き換えるのみで、マップ済みの範囲を書き換えない。
+    unsafe { memmap2::Mmap::map(&*file) }
+}
+
+/// ファイル `file` の内容全体を返す。
+#[cfg(not(feature = "mmap"))]
+fn read_file_bytes(file: &mut File) -> std::io::Result<FileBytes> {
+    let mut buf = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut buf)?;
+
+    Ok(buf)
+}
+
+fn encode_record(board_bytes: &[u8; COMPACT_LEN], solution: &Solution) -> [u8; RECORD_LEN] {
+    let mut record = [0; RECORD_LEN];
+
+    record[..COMPACT_LEN].copy_from_slice(board_bytes);
+    record[OFFSET_SCORE..OFFSET_LEN].copy_from_slice(&solution.score.to_inner().to_le_bytes());
+    record[OFFSET_LEN] = solution.solution.len() as u8;
+    for (dst, sq) in record[OFFSET_SQUARES..].iter_mut().zip(&solution.solution) {
+        *dst = sq.to_inner();
+    }
+
+    record
+}
+
+/// レコードから解を読み取る。ファイルが壊れていて手数やマスが無効ならエラーを返す。
+fn decode_solution(record: &[u8]) -> anyhow::Result<Solution> {
+    let score = u32::from_le_bytes(record[OFFSET_SCORE..OFFSET_LEN].try_into().unwrap());
+    let len = usize::from(record[OFFSET_LEN]);
+    ensure!(
+        len <= ActionHistory::CAPACITY,
+        "索引ファイルのレコードの手数が無効: {len}"
+    );
+    let sqs = record[OFFSET_SQUARES..][..len]
+        .iter()
+        .map(|&inner| Square::from_inner(inner).context("索引ファイルのレコードのマスが無効"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Solution {
+        score: Score::new(score),
+        solution: ActionHistory::from_slice(&sqs)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use samegame_core::RandomBoardParam;
+
+    use super::*;
+
+    #[test]
+    fn test_solution_index() {
+        let path = std::env::temp_dir().join(format!(
+            "samegame-solution-index-test-{}.idx",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(hash_index_path(&path));
+
+        let boards: Vec<Board> = ["0x0012,0x34,40,3", "0x0123,0x45,40,2", "0x0234,0x56,40,1"]
+            .into_iter()
+            .map(|s| s.parse::<RandomBoardParam>().unwrap().gen_board().0)
+            .collect();
+        let sol = |score, s: &str| Solution {
+            score: Score::new(score),
+            solution: s.parse().unwrap(),
+        };
+
+        {
+            let index = SolutionIndex::open(&path).unwrap();
+            assert!(index.is_empty());
+            assert_eq!(index.lookup(&boards[0]).unwrap(), None);
+
+            assert!(index.insert(&boards[0], &sol(500, "1,1 2,3")).unwrap());
+            assert!(index.insert(&boards[1], &sol(600, "3,2")).unwrap());
+            // スコアが高くなければ置き換えない。
+            assert!(!index.insert(&boards[0], &sol(500, "4,4")).unwrap());
+            assert!(index.insert(&boards[0], &sol(700, "5,5 6,6 7,1")).unwrap());
+
+            assert_eq!(index.len(), 2);
+            assert_eq!(
+                index.lookup(&boards[0]).unwrap(),
+                Some(sol(700, "5,5 6,6 7,1"))
+            );
+        }
+
+        // 再度開いても内容が残っている。並行に参照できる。
+        let index = Arc::new(SolutionIndex::open(&path).unwrap());
+        assert_eq!(index.len(), 2);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let index = Arc::clone(&index);
+                let boards = &boards;
+                s.spawn(move || {
+                    assert_eq!(
+                        index.lookup(&boards[0]).unwrap(),
+                        Some(sol(700, "5,5 6,6 7,1"))
+                    );
+                    assert_eq!(index.lookup(&boards[1]).unwrap(), Some(sol(600, "3,2")));
+                    assert_eq!(index.lookup(&Board::empty()).unwrap(), None);
+                });
+            }
+        });
+
+        let len_before = std::fs::metadata(&path).unwrap().len();
+        index.compact().unwrap();
+        let len_after = std::fs::metadata(&path).unwrap().len();
+        assert_eq!(len_after, (MAGIC.len() + 2 * RECORD_LEN) as u64);
+        assert!(len_after < len_before);
+        #[cfg(not(feature = "mmap"))]
+        assert!(index.approx_memory_usage() >= 2 * RECORD_LEN);
+        assert_eq!(index.lookup(&boards[1]).unwrap(), Some(sol(600, "3,2")));
+        drop(index);
+
+        // 再度開くと、compact() で保存したハッシュ索引を用いる。
+        let hash_path = hash_index_path(&path);
+        let index = SolutionIndex::open(&path).unwrap();
+        assert!(index.inner.read().unwrap().saved.is_some());
+        assert!(index.inner.read().unwrap().index.is_empty());
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index.lookup(&boards[0]).unwrap(),
+            Some(sol(700, "5,5 6,6 7,1"))
+        );
+        assert!(index.insert(&boards[1], &sol(800, "2,2")).unwrap());
+        assert!(index.insert(&boards[2], &sol(900, "1,1")).unwrap());
+        assert_eq!(index.len(), 3);
+        drop(index);
+
+        // ハッシュ索引より後に追記したレコードも引ける。末尾の不完全なレコードは捨てる。
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[1; 5])
+            .unwrap();
+        let index = SolutionIndex::open(&path).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(
+            index.lookup(&boards[0]).unwrap(),
+            Some(sol(700, "5,5 6,6 7,1"))
+        );
+        assert_eq!(index.lookup(&boards[1]).unwrap(), Some(sol(800, "2,2")));
+        assert_eq!(index.lookup(&boards[2]).unwrap(), Some(sol(900, "1,1")));
+        index.save_hash_index().unwrap();
+        drop(index);
+
+        let index = SolutionIndex::open(&path).unwrap();
+        assert!(index.inner.read().unwrap().index.is_empty());
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.lookup(&boards[1]).unwrap(), Some(sol(800, "2,2")));
+        drop(index);
+
+        // ハッシュ索引が無効なら用いない。
+        std::fs::write(&hash_path, b"garbage!").unwrap();
+        let index = SolutionIndex::open(&path).unwrap();
+        assert!(index.inner.read().unwrap().saved.is_none());
+        assert_eq!(index.lookup(&boards[2]).unwrap(), Some(sol(900, "1,1")));
+        drop(index);
+
+        // レコードが壊れていれば、引く際にエラーを返す (boards[2] のレコードは末尾にある)。
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - RECORD_LEN;
+        bytes[last + OFFSET_LEN] = 200;
+        std::fs::write(&path, &bytes).unwrap();
+        let index = SolutionIndex::open(&path).unwrap();
+        assert!(index.lookup(&boards[2]).is_err());
+        assert!(index.insert(&boards[2], &sol(1000, "1,1")).is_err());
+        drop(index);
+        bytes[last + OFFSET_LEN] = 1;
+        bytes[last + OFFSET_SQUARES] = 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+        let index = SolutionIndex::open(&path).unwrap();
+        assert!(index.lookup(&boards[2]).is_err());
+        assert_eq!(index.lookup(&boards[1]).unwrap(), Some(sol(800, "2,2")));
+        drop(index);
+
+        std::fs::write(&path, b"garbage!").unwrap();
+        assert!(SolutionIndex::open(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&hash_path).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! 残り駒数ごとの分岐数統計のテーブルを生成する (`branching.rs` 内で `include!` する)。
+//!
+//! ゲーム内に現れる盤面たちからランダムプレイアウトを行い、訪問した各局面の合法手数などを集計する。
+
+use std::fs::File;
+use std::io::{BufWriter, Write as _};
+use std::path::Path;
+
+use rand::{rngs::StdRng, Rng, SeedableRng as _};
+
+use samegame_sfc_small_2::*;
+
+/// 標本とする盤面生成パラメータの間隔。
+const PARAM_STEP: usize = 97;
+
+/// 1 つの盤面あたりのプレイアウト回数。
+const PLAYOUT_COUNT: usize = 4;
+
+#[derive(Clone, Debug, Default)]
+struct Acc {
+    sample_count: u64,
+    branching_sum: u64,
+    max_branching: u32,
+    action_count: u64,
+    removed_sum: u64,
+}
+
+fn main() -> anyhow::Result<()> {
+    const PATH: &str = "branching_stats.in";
+
+    let mut rng = StdRng::seed_from_u64(2024);
+    let mut accs = vec![Acc::default(); Square::NUM + 1];
+
+    for param in RandomBoardParam::all().step_by(PARAM_STEP) {
+        let Some((board, _)) = param.gen_legal_board() else {
+            continue;
+        };
+        for _ in 0..PLAYOUT_COUNT {
+            playout(Position::new(board.clone()), &mut rng, &mut accs);
+        }
+    }
+
+    let mut wtr = create_file(PATH)?;
+    write!(wtr, "[")?;
+    for acc in &accs {
+        let avg_branching = if acc.sample_count == 0 {
+            0.0
+        } else {
+            acc.branching_sum as f64 / acc.sample_count as f64
+        };
+        let avg_removed = if acc.action_count == 0 {
+            0.0
+        } else {
+            acc.removed_sum as f64 / acc.action_count as f64
+        };
+        write!(
+            wtr,
+            "BranchingStats{{sample_count:{},avg_branching:{avg_branching:?},max_branching:{},avg_removed:{avg_removed:?}}},",
+            acc.sample_count, acc.max_branching,
+        )?;
+    }
+    write!(wtr, "]")?;
+
+    Ok(())
+}
+
+fn playout(mut pos: Position, rng: &mut impl Rng, accs: &mut [Acc]) {
+    loop {
+        let actions: Vec<Action> = pos.actions().collect();
+
+        let acc = &mut accs[pos.board().piece_count_total() as usize];
+        acc.sample_count += 1;
+        acc.branching_sum += actions.len() as u64;
+        acc.max_branching = acc.max_branching.max(actions.len() as u32);
+        acc.action_count += actions.len() as u64;
+        acc.removed_sum += actions
+            .iter()
+            .map(|action| u64::from(action.square_count()))
+            .sum::<u64>();
+
+        if actions.is_empty() {
+            break;
+        }
+        let action = &actions[rng.gen_range(0..actions.len())];
+        pos = pos.do_action(action);
+    }
+}
+
+fn create_file(path: impl AsRef<Path>) -> anyhow::Result<BufWriter<File>> {
+    let wtr = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+
+    Ok(BufWriter::new(wtr))
+}
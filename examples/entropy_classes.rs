@@ -0,0 +1,22 @@
+//! 各 (乱数生成器の内部状態, NMI カウンタ) について、エントロピー値たちの同値類分割を求める。
+//!
+//! 標準出力に各分割を、最後に標準エラー出力に分割ごとの出現数を出力する。
+
+use std::collections::BTreeMap;
+
+use samegame_sfc_small_2::*;
+
+fn main() -> anyhow::Result<()> {
+    let mut hist = BTreeMap::<EntropyPartition, u64>::new();
+
+    for (rng_state, nmi_counter, partition) in enumerate_entropy_partitions() {
+        println!("0x{rng_state:04X}\t0x{nmi_counter:02X}\t{partition}");
+        *hist.entry(partition).or_default() += 1;
+    }
+
+    for (partition, count) in hist {
+        eprintln!("{partition}\t{}\t{count}", partition.class_count());
+    }
+
+    Ok(())
+}
@@ -23,12 +23,7 @@ fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
 
-    let param = RandomBoardParam {
-        rng_state: cli.rng_state,
-        nmi_counter: cli.nmi_counter,
-        nmi_timing: cli.nmi_timing,
-        entropy: cli.entropy,
-    };
+    let param = RandomBoardParam::new(cli.rng_state, cli.nmi_counter, cli.nmi_timing, cli.entropy)?;
     let (board, legal, rng_after) = param.gen_board();
 
     if !legal {
@@ -0,0 +1,57 @@
+//! 組み込みの序盤定跡を生成する (`opening.rs` 内で `include_bytes!` する)。
+//!
+//! ゲーム内に現れる盤面たちを分類キー (最大の塊を消した後の局面の正規形) で分類し、
+//! 複数の盤面が属する分類の局面を頻度の降順に選んで厳密に解く。
+
+use std::collections::HashMap;
+use std::io::Write as _;
+
+use samegame_sfc_small_2::*;
+
+/// 標本とする盤面生成パラメータ (代表元) の間隔。
+const PARAM_STEP: usize = 37;
+
+/// 定跡に登録する局面数の上限。
+const ENTRY_COUNT_MAX: usize = 1024;
+
+fn main() -> anyhow::Result<()> {
+    const PATH: &str = "opening_book.bin";
+
+    // 分類キーごとの盤面数。
+    let mut counts = HashMap::<rules::Grid, u32>::new();
+    for (param, _) in RandomBoardParam::all_canonical().step_by(PARAM_STEP) {
+        let Some((board, _)) = param.gen_legal_board() else {
+            continue;
+        };
+        let Some(key) = opening_key(&board) else {
+            continue;
+        };
+        *counts.entry(key.board().to_grid()).or_default() += 1;
+    }
+
+    let mut frequent: Vec<(rules::Grid, u32)> = counts
+        .into_iter()
+        .filter(|&(_, count)| count >= 2)
+        .collect();
+    frequent.sort_unstable_by(|lhs, rhs| rhs.1.cmp(&lhs.1).then_with(|| lhs.0.cmp(&rhs.0)));
+    frequent.truncate(ENTRY_COUNT_MAX);
+    eprintln!("frequent clusters: {}", frequent.len());
+
+    let mut book = OpeningBook::new();
+    let mut solver = Solver::new(Score::ZERO);
+    for (grid, _) in frequent {
+        let board = Board::from_grid(&grid)?;
+        let (gain, continuation) = solver
+            .solve(board.clone())
+            .expect("閾値 0 なら解は必ず見つかる");
+        book.insert(&Position::new(board), BookEntry { gain, continuation });
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(PATH)?;
+    file.write_all(&book.to_bytes())?;
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! 全ての有効な盤面について、盤面から盤面生成パラメータ (`SeedId`) を引く逆引き索引ファイルを作る。
+//!
+//! 同じ盤面を生成するパラメータたちは代表元 (`RandomBoardParam::all_canonical()`) のみを登録する。
+//! 作った索引は `ReverseIndex::open()` で読み込む。
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use samegame_sfc_small_2::*;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    /// 出力する索引ファイル。
+    path: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let params = RandomBoardParam::all_canonical().map(|(param, _)| param);
+    let len = ReverseIndex::build(&cli.path, params)?;
+    eprintln!("{len} entries");
+
+    Ok(())
+}
@@ -10,9 +10,13 @@ use samegame_sfc_small_2::*;
 #[derive(Debug, Parser)]
 struct Cli {
     /// 最終スコアがこの値を超えないとわかったノードを枝刈りする。
-    #[arg(long, default_value_t = 0)]
+    #[arg(long, default_value_t = Score::ZERO)]
     prune_score_max: Score,
 
+    /// 同スコアの解のうち手数が短いものを優先する。
+    #[arg(long)]
+    prefer_shorter: bool,
+
     /// 盤面ファイル。
     path_board: PathBuf,
 }
@@ -29,6 +33,7 @@ fn main() -> anyhow::Result<()> {
         .with_context(|| format!("問題ファイル '{}' のパースに失敗", cli.path_board.display()))?;
 
     let mut solver = Solver::new(cli.prune_score_max);
+    solver.set_prefer_shorter(cli.prefer_shorter);
 
     if let Some((score, solution)) = solver.solve(board) {
         println!("{score}\t{solution}");
@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use clap::Parser;
 use log::info;
 
@@ -8,8 +11,26 @@ use samegame_sfc_small_2::*;
 struct Cli {
     /// 最終スコアがこの値を超えないとわかったノードを枝刈りする。
     /// 1 つの面を解き終えるたびに最大スコアで chmax される。
-    #[arg(long, default_value_t = 0)]
+    #[arg(long, default_value_t = Score::ZERO)]
     prune_score_max: Score,
+
+    /// この秒数ごとに探索状況 (ハートビート) をログ出力する。
+    #[arg(long)]
+    heartbeat_secs: Option<u64>,
+
+    /// 暫定解の更新を、前回のログ出力からこのスコア以上改善した場合のみ info レベルでログ出力する。
+    /// (最初の暫定解と最終的な解は常に出力する)
+    #[arg(long)]
+    incumbent_log_min_delta: Option<Score>,
+
+    /// 以前の実行の出力ファイル。指定した場合、処理済みのパラメータをスキップして再開する。
+    #[arg(long, conflicts_with = "db")]
+    resume: Option<PathBuf>,
+
+    /// 結果を標準出力に加えて書き込むデータベースファイル (`ResultDb`)。
+    /// 既に存在すれば、処理済みのパラメータをスキップして再開する。
+    #[arg(long)]
+    db: Option<PathBuf>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -17,8 +38,31 @@ fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
 
+    let mut db = cli.db.as_ref().map(ResultDb::open).transpose()?;
+
+    let progress = match (&cli.resume, &db) {
+        (Some(path), _) => ScanProgress::from_path(path)?,
+        (None, Some(db)) => db.progress().clone(),
+        (None, None) => ScanProgress::default(),
+    };
+
     let mut solver = Solver::new(cli.prune_score_max);
-    for (param, board, rng_after) in enumerate_all_legal_board() {
+    solver.set_heartbeat_interval(cli.heartbeat_secs.map(Duration::from_secs));
+    if let Some(min_delta) = cli.incumbent_log_min_delta {
+        solver.set_incumbent_log_policy(IncumbentLogPolicy::sparse(min_delta, Duration::ZERO));
+    }
+    if let Some(best_score) = progress.best_score {
+        info!("Resume: best score {best_score}");
+        solver.chmax_prune_score_max(best_score.saturating_sub(Score::new(1)));
+    }
+
+    let mut observer = HeartbeatLogger::default();
+
+    for (param_index, (param, board, rng_after)) in enumerate_all_legal_board().enumerate() {
+        if progress.is_processed(&param) {
+            continue;
+        }
+
         let RandomBoardParam {
             rng_state,
             nmi_counter,
@@ -31,10 +75,19 @@ fn main() -> anyhow::Result<()> {
             rng_after.state()
         );
 
-        if let Some((score, solution)) = solver.solve(board) {
-            println!("0x{rng_state:04X}\t0x{nmi_counter:02X}\t{nmi_timing}\t{entropy}\t{score}\t{solution}");
+        observer.param_index = Some(param_index as u64);
+        if let (Some((score, solution)), _) = solver.solve_with_observer(board, &mut observer) {
+            let record = ResultRecord {
+                param,
+                score,
+                solution,
+            };
+            println!("{record}");
+            if let Some(db) = &mut db {
+                db.insert(record)?;
+            }
             // 同点の解は全て列挙したいので -1 する。
-            solver.chmax_prune_score_max(score.saturating_sub(1));
+            solver.chmax_prune_score_max(score.saturating_sub(Score::new(1)));
         }
     }
 
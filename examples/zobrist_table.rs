@@ -12,10 +12,12 @@ use samegame_sfc_small_2::*;
 
 fn main() -> anyhow::Result<()> {
     const PATH_BOARD: &str = "zobrist_board.in";
+    const PATH_COLOR_COUNT: &str = "zobrist_color_count.in";
 
     let mut rng = StdRng::seed_from_u64(2024);
 
     make_table_board(PATH_BOARD, &mut rng)?;
+    make_table_color_count(PATH_COLOR_COUNT, &mut rng)?;
 
     Ok(())
 }
@@ -41,6 +43,21 @@ fn make_table_board(path: impl AsRef<Path>, rng: &mut impl Rng) -> anyhow::Resul
     Ok(())
 }
 
+fn make_table_color_count(path: impl AsRef<Path>, rng: &mut impl Rng) -> anyhow::Result<()> {
+    let mut wtr = create_file(path)?;
+
+    write!(wtr, "[")?;
+
+    for _ in 0..=Square::NUM {
+        let key: u64 = rng.gen();
+        write!(wtr, "0x{key:016x},")?;
+    }
+
+    write!(wtr, "]")?;
+
+    Ok(())
+}
+
 fn create_file(path: impl AsRef<Path>) -> anyhow::Result<BufWriter<File>> {
     let wtr = std::fs::OpenOptions::new()
         .write(true)
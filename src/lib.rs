@@ -1,29 +1,13 @@
 //! SFC『鮫亀』: さめがめ「かんたん」用ソルバーライブラリ。
+//!
+//! 互換性のため、以下のクレートの内容を全て再エクスポートしている:
+//!
+//! * `samegame-core`: 盤面、ゲームルール、乱数生成器。
+//! * `samegame-solver`: 局面と探索。
+//! * `samegame-tools`: 一括求解、結果ファイル、データベース、出力など。
+//!
+//! 組み込み用途などでソルバーやツール類が不要なら、`samegame-core` (または `samegame-rules`) を直接用いるとよい。
 
-mod action;
-mod array;
-mod asset;
-mod bitop;
-mod board;
-mod bounded;
-mod cmp;
-mod hash;
-mod hint;
-mod nonzero;
-mod piece;
-mod position;
-mod rng;
-mod score;
-mod solver;
-mod square;
-mod zobrist;
-
-pub use self::action::*;
-pub use self::board::*;
-pub use self::hash::*;
-pub use self::piece::*;
-pub use self::position::*;
-pub use self::rng::*;
-pub use self::score::*;
-pub use self::solver::*;
-pub use self::square::*;
+pub use samegame_core::*;
+pub use samegame_solver::*;
+pub use samegame_tools::*;